@@ -1,6 +1,8 @@
-use core::ops::{Add, Mul, Sub};
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::{Add, AddAssign, Mul, MulAssign, Sub, SubAssign};
 
-use p3_field::{ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra};
+use p3_field::{ExtensionField, Field, FieldAlgebra};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 
@@ -9,9 +11,94 @@ pub trait BaseAir<F>: Sync {
     /// The number of columns (a.k.a. registers) in this AIR.
     fn width(&self) -> usize;
 
+    /// The number of consecutive rows which constraints can access at once, i.e. the height of the
+    /// windows returned by `AirBuilder::main` and the other trace accessors. It must be at least two.
+    ///
+    /// Constraints involving rows past the first two should be guarded by
+    /// `AirBuilder::is_transition_window`, as the window wraps around at the end of the trace.
+    fn window_size(&self) -> usize {
+        2
+    }
+
     fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
         None
     }
+
+    /// How the prover may pad a main trace whose height isn't a power of two. By default, such
+    /// traces are rejected.
+    fn padding(&self) -> Padding<F> {
+        Padding::None
+    }
+
+    /// The index of a main trace column which the prover fills in when padding, with ones on the
+    /// trace's own rows and zeros on padding rows, so that constraints can tell them apart. Trace
+    /// generators can leave it zeroed.
+    fn real_row_selector(&self) -> Option<usize> {
+        None
+    }
+
+    /// Columns of constants which repeat down the trace, such as round constants, given by one
+    /// period each. Unlike preprocessed columns, they aren't committed to, as the verifier can
+    /// evaluate them on its own. Constraints can access them through `PeriodicAirBuilder`.
+    ///
+    /// Each period's length must be a power of two no larger than the trace's height.
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        vec![]
+    }
+
+    /// The number of extension field columns in the permutation trace, which is generated after the
+    /// main trace has been committed. See `PermutationAirBuilder`.
+    fn permutation_width(&self) -> usize {
+        0
+    }
+
+    /// The number of extension field challenges to sample before generating the permutation trace.
+    fn num_permutation_challenges(&self) -> usize {
+        0
+    }
+
+    /// Generate the permutation trace from the main trace and the sampled challenges.
+    ///
+    /// AIRs with a nonzero `permutation_width` must override this, and return a matrix of that width
+    /// with the same height as `main`.
+    fn permutation_trace<EF>(
+        &self,
+        _main: &RowMajorMatrix<F>,
+        _challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+    {
+        None
+    }
+
+    /// Whether the last permutation column is a running sum, such as that of a LogUp argument,
+    /// whose final value is the table's cumulative sum.
+    ///
+    /// If so, the prover sends the cumulative sum along with the proof, constraints can access it
+    /// through `MultiTableAirBuilder::cumulative_sum`, and the verifier checks that the cumulative
+    /// sums of all tables in a proof add up to zero.
+    fn has_cumulative_sum(&self) -> bool {
+        false
+    }
+}
+
+/// How a main trace is padded to the next power of two height. See `BaseAir::padding`.
+///
+/// Padding rows must satisfy the AIR's constraints just as the trace's own rows do, so the choice
+/// depends on the constraints; for instance, an AIR whose transition constraints hold between any
+/// row and a copy of itself can use `RepeatLastRow`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum Padding<F> {
+    /// Traces aren't padded, and must already have a power of two height.
+    None,
+    /// Pad with rows of zeros.
+    Zeros,
+    /// Pad with copies of the trace's last row.
+    RepeatLastRow,
+    /// Pad with copies of the given row.
+    Row(Vec<F>),
 }
 
 ///  An AIR with 0 or more public values.
@@ -129,14 +216,51 @@ pub trait AirBuilderWithPublicValues: AirBuilder {
     fn public_values(&self) -> &[Self::PublicVar];
 }
 
+/// An `AirBuilder` with access to the AIR's periodic columns. See `BaseAir::periodic_columns`.
+pub trait PeriodicAirBuilder: AirBuilder {
+    type PeriodicVar: Into<Self::Expr> + Copy;
+
+    /// The values of the periodic columns on the local row.
+    fn periodic_values(&self) -> &[Self::PeriodicVar];
+}
+
 pub trait PairBuilder: AirBuilder {
     fn preprocessed(&self) -> Self::M;
 }
 
+/// An algebra which expressions over `Expr` can be mixed into, such as the extension field
+/// expressions of an `ExtensionBuilder`.
+///
+/// This is `FieldExtensionAlgebra` without the conversions to and from base field coordinates,
+/// which symbolic expressions can't provide.
+pub trait ExtensionExpr<Expr>:
+    FieldAlgebra
+    + From<Expr>
+    + Add<Expr, Output = Self>
+    + AddAssign<Expr>
+    + Sub<Expr, Output = Self>
+    + SubAssign<Expr>
+    + Mul<Expr, Output = Self>
+    + MulAssign<Expr>
+{
+}
+
+impl<Expr, T> ExtensionExpr<Expr> for T where
+    T: FieldAlgebra
+        + From<Expr>
+        + Add<Expr, Output = Self>
+        + AddAssign<Expr>
+        + Sub<Expr, Output = Self>
+        + SubAssign<Expr>
+        + Mul<Expr, Output = Self>
+        + MulAssign<Expr>
+{
+}
+
 pub trait ExtensionBuilder: AirBuilder {
     type EF: ExtensionField<Self::F>;
 
-    type ExprEF: FieldExtensionAlgebra<Self::Expr, F = Self::EF>;
+    type ExprEF: ExtensionExpr<Self::Expr, F = Self::EF>;
 
     type VarEF: Into<Self::ExprEF> + Copy + Send + Sync;
 
@@ -170,6 +294,14 @@ pub trait PermutationAirBuilder: ExtensionBuilder {
     fn permutation_randomness(&self) -> &[Self::RandomVar];
 }
 
+/// A `PermutationAirBuilder` for tables which take part in a multi-table argument, such as LogUp.
+pub trait MultiTableAirBuilder: PermutationAirBuilder {
+    type Sum: Into<Self::ExprEF> + Copy;
+
+    /// The claimed final value of this table's running sum. See `BaseAir::has_cumulative_sum`.
+    fn cumulative_sum(&self) -> Self::Sum;
+}
+
 #[derive(Debug)]
 pub struct FilteredAirBuilder<'a, AB: AirBuilder> {
     pub inner: &'a mut AB,
@@ -209,6 +341,14 @@ impl<AB: AirBuilder> AirBuilder for FilteredAirBuilder<'_, AB> {
     }
 }
 
+impl<AB: PeriodicAirBuilder> PeriodicAirBuilder for FilteredAirBuilder<'_, AB> {
+    type PeriodicVar = AB::PeriodicVar;
+
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        self.inner.periodic_values()
+    }
+}
+
 impl<AB: ExtensionBuilder> ExtensionBuilder for FilteredAirBuilder<'_, AB> {
     type EF = AB::EF;
     type ExprEF = AB::ExprEF;
@@ -235,3 +375,11 @@ impl<AB: PermutationAirBuilder> PermutationAirBuilder for FilteredAirBuilder<'_,
         self.inner.permutation_randomness()
     }
 }
+
+impl<AB: MultiTableAirBuilder> MultiTableAirBuilder for FilteredAirBuilder<'_, AB> {
+    type Sum = AB::Sum;
+
+    fn cumulative_sum(&self) -> Self::Sum {
+        self.inner.cumulative_sum()
+    }
+}
@@ -0,0 +1,312 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::ops::Mul;
+
+use p3_field::{batch_multiplicative_inverse, ExtensionField, Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+
+use crate::{
+    Air, BaseAir, ExtensionExpr, MultiTableAirBuilder, Padding, PairBuilder, VirtualPairCol,
+};
+
+/// A bus on which tables send and receive tuples.
+///
+/// Buses are identified by their names, so tables interact on the same bus exactly when they name
+/// it the same, and no numbering of buses has to be agreed on.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Bus {
+    name: &'static str,
+}
+
+impl Bus {
+    pub const fn new(name: &'static str) -> Self {
+        Self { name }
+    }
+
+    pub const fn name(&self) -> &'static str {
+        self.name
+    }
+}
+
+/// A tuple of values sent or received on a bus, along with its multiplicity.
+#[derive(Clone, Debug)]
+pub struct Interaction<F: Field> {
+    pub values: Vec<VirtualPairCol<F>>,
+    pub multiplicity: VirtualPairCol<F>,
+    /// The bus on which the values are sent or received.
+    pub bus: Bus,
+}
+
+impl<F: Field> Interaction<F> {
+    pub const fn new(
+        values: Vec<VirtualPairCol<F>>,
+        multiplicity: VirtualPairCol<F>,
+        bus: Bus,
+    ) -> Self {
+        Self {
+            values,
+            multiplicity,
+            bus,
+        }
+    }
+
+    /// The LogUp denominator of this interaction,
+    /// `beta - (n + sum_j alpha^(j + 1) name[j] + sum_i alpha^(n + i + 1) values[i])`, where `name`
+    /// is the `n` bytes of the bus's name. Prefixing each tuple with its bus's name keeps the tuples
+    /// of different buses apart.
+    fn fingerprint<Expr, ExprEF, Var>(
+        &self,
+        preprocessed: &[Var],
+        main: &[Var],
+        alpha: ExprEF,
+        beta: ExprEF,
+    ) -> ExprEF
+    where
+        F: Into<Expr>,
+        Expr: FieldAlgebra + Mul<F, Output = Expr>,
+        ExprEF: ExtensionExpr<Expr>,
+        Var: Into<Expr> + Copy,
+    {
+        let name = self.bus.name().as_bytes();
+        let mut power = alpha.clone();
+        let mut combined = ExprEF::from_canonical_usize(name.len());
+        for &byte in name {
+            combined += power.clone() * Expr::from_canonical_u8(byte);
+            power *= alpha.clone();
+        }
+        for value in &self.values {
+            combined += power.clone() * value.apply::<Expr, Var>(preprocessed, main);
+            power *= alpha.clone();
+        }
+        beta - combined
+    }
+}
+
+/// An AIR which sends and receives values on buses.
+///
+/// Over all tables in a proof, the tuples sent on each bus must match the tuples received on it,
+/// counted with multiplicity. Wrap the AIR in a `LogUpAir` to have this enforced.
+pub trait InteractionAir<F: Field>: BaseAir<F> {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        vec![]
+    }
+
+    fn receives(&self) -> Vec<Interaction<F>> {
+        vec![]
+    }
+}
+
+/// Adds a LogUp argument for the interactions of an `InteractionAir` to its constraints.
+///
+/// The permutation trace has one column per interaction, holding `multiplicity / fingerprint` on
+/// each row, followed by a running sum of these columns in which receives are negated. The running
+/// sum ends at the table's cumulative sum, and the verifier checks that the cumulative sums of all
+/// tables balance to zero.
+///
+/// Two challenges are used: `alpha` to combine the values of a tuple, and `beta` to shift the
+/// combination.
+#[derive(Debug)]
+pub struct LogUpAir<F: Field, A> {
+    inner: A,
+    sends: Vec<Interaction<F>>,
+    receives: Vec<Interaction<F>>,
+}
+
+impl<F: Field, A: InteractionAir<F>> LogUpAir<F, A> {
+    pub fn new(inner: A) -> Self {
+        let sends = inner.sends();
+        let receives = inner.receives();
+        Self {
+            inner,
+            sends,
+            receives,
+        }
+    }
+}
+
+impl<F: Field, A> LogUpAir<F, A> {
+    pub const fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    fn num_interactions(&self) -> usize {
+        self.sends.len() + self.receives.len()
+    }
+
+    /// All interactions, each paired with whether it is a send.
+    fn interactions(&self) -> impl Iterator<Item = (&Interaction<F>, bool)> {
+        self.sends
+            .iter()
+            .map(|interaction| (interaction, true))
+            .chain(self.receives.iter().map(|interaction| (interaction, false)))
+    }
+}
+
+impl<F: Field, A: BaseAir<F>> BaseAir<F> for LogUpAir<F, A> {
+    fn width(&self) -> usize {
+        self.inner.width()
+    }
+
+    fn window_size(&self) -> usize {
+        self.inner.window_size()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.inner.preprocessed_trace()
+    }
+
+    fn padding(&self) -> Padding<F> {
+        self.inner.padding()
+    }
+
+    fn real_row_selector(&self) -> Option<usize> {
+        self.inner.real_row_selector()
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        self.inner.periodic_columns()
+    }
+
+    fn permutation_width(&self) -> usize {
+        self.num_interactions() + 1
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        2
+    }
+
+    fn permutation_trace<EF>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+    {
+        let (alpha, beta) = (challenges[0], challenges[1]);
+        let preprocessed = self.inner.preprocessed_trace();
+        let height = main.height();
+        let num_interactions = self.num_interactions();
+
+        let mut fingerprints = Vec::with_capacity(height * num_interactions);
+        let mut multiplicities = Vec::with_capacity(height * num_interactions);
+        for i in 0..height {
+            let preprocessed_row = preprocessed
+                .as_ref()
+                .map_or_else(Vec::new, |preprocessed| preprocessed.row_slice(i).to_vec());
+            let main_row = main.row_slice(i);
+            for (interaction, _) in self.interactions() {
+                fingerprints.push(interaction.fingerprint::<F, EF, F>(
+                    &preprocessed_row,
+                    &main_row,
+                    alpha,
+                    beta,
+                ));
+                multiplicities.push(
+                    interaction
+                        .multiplicity
+                        .apply::<F, F>(&preprocessed_row, &main_row),
+                );
+            }
+        }
+        let inverses = batch_multiplicative_inverse(&fingerprints);
+
+        let mut values = Vec::with_capacity(height * (num_interactions + 1));
+        let mut running_sum = EF::ZERO;
+        for i in 0..height {
+            let row = i * num_interactions..(i + 1) * num_interactions;
+            for ((&inverse, &multiplicity), (_, is_send)) in inverses[row.clone()]
+                .iter()
+                .zip(&multiplicities[row])
+                .zip(self.interactions())
+            {
+                let value = inverse * multiplicity;
+                values.push(value);
+                if is_send {
+                    running_sum += value;
+                } else {
+                    running_sum -= value;
+                }
+            }
+            values.push(running_sum);
+        }
+        Some(RowMajorMatrix::new(values, num_interactions + 1))
+    }
+
+    fn has_cumulative_sum(&self) -> bool {
+        true
+    }
+}
+
+impl<F: Field, A: InteractionAir<F>> InteractionAir<F> for LogUpAir<F, A> {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        self.sends.clone()
+    }
+
+    fn receives(&self) -> Vec<Interaction<F>> {
+        self.receives.clone()
+    }
+}
+
+impl<AB, A> Air<AB> for LogUpAir<AB::F, A>
+where
+    AB: MultiTableAirBuilder + PairBuilder,
+    A: Air<AB>,
+{
+    fn eval(&self, builder: &mut AB) {
+        self.inner.eval(builder);
+
+        let main = builder.main();
+        let main_local = main.row_slice(0);
+        let preprocessed = builder.preprocessed();
+        let preprocessed_local = preprocessed.row_slice(0);
+        let permutation = builder.permutation();
+        let permutation_local = permutation.row_slice(0);
+        let permutation_next = permutation.row_slice(1);
+
+        let challenges = builder.permutation_randomness();
+        let alpha: AB::ExprEF = challenges[0].into();
+        let beta: AB::ExprEF = challenges[1].into();
+        let cumulative_sum: AB::ExprEF = builder.cumulative_sum().into();
+
+        let mut sum_local = AB::ExprEF::ZERO;
+        let mut sum_next = AB::ExprEF::ZERO;
+        for (i, (interaction, is_send)) in self.interactions().enumerate() {
+            let value_local: AB::ExprEF = permutation_local[i].into();
+            let value_next: AB::ExprEF = permutation_next[i].into();
+
+            // Each row of the interaction's column holds `multiplicity / fingerprint`.
+            let fingerprint = interaction.fingerprint::<AB::Expr, AB::ExprEF, AB::Var>(
+                &preprocessed_local,
+                &main_local,
+                alpha.clone(),
+                beta.clone(),
+            );
+            let multiplicity = interaction
+                .multiplicity
+                .apply::<AB::Expr, AB::Var>(&preprocessed_local, &main_local);
+            builder.assert_eq_ext(value_local.clone() * fingerprint, multiplicity);
+
+            if is_send {
+                sum_local += value_local;
+                sum_next += value_next;
+            } else {
+                sum_local -= value_local;
+                sum_next -= value_next;
+            }
+        }
+        let running_sum_local: AB::ExprEF = permutation_local[self.num_interactions()].into();
+        let running_sum_next: AB::ExprEF = permutation_next[self.num_interactions()].into();
+        builder
+            .when_first_row()
+            .assert_eq_ext(running_sum_local.clone(), sum_local);
+        builder
+            .when_transition()
+            .assert_eq_ext(running_sum_next - running_sum_local.clone(), sum_next);
+        builder
+            .when_last_row()
+            .assert_eq_ext(running_sum_local, cumulative_sum);
+    }
+}
@@ -0,0 +1,34 @@
+/// The parameters of a Basefold opening proof.
+///
+/// Each sumcheck round folds the committed codeword in half, as a FRI commit phase round with arity
+/// two does, and commits to the result until a constant is left.
+#[derive(Debug)]
+pub struct BasefoldConfig<M> {
+    pub log_blowup: usize,
+    pub num_queries: usize,
+    /// The number of proof-of-work bits the prover grinds for before its queries are sampled.
+    pub proof_of_work_bits: usize,
+    pub mmcs: M,
+}
+
+impl<M> BasefoldConfig<M> {
+    pub const fn blowup(&self) -> usize {
+        1 << self.log_blowup
+    }
+
+    /// Returns the soundness bits of this Basefold instance based on the
+    /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture, as for FRI.
+    pub const fn conjectured_soundness_bits(&self) -> usize {
+        self.log_blowup * self.num_queries + self.proof_of_work_bits
+    }
+}
+
+/// Creates a minimal `BasefoldConfig` for testing purposes.
+pub const fn create_test_basefold_config<Mmcs>(mmcs: Mmcs) -> BasefoldConfig<Mmcs> {
+    BasefoldConfig {
+        log_blowup: 1,
+        num_queries: 2,
+        proof_of_work_bits: 1,
+        mmcs,
+    }
+}
@@ -0,0 +1,21 @@
+//! An implementation of Basefold, a multilinear PCS.
+//!
+//! Basefold, from [ZCF23](https://eprint.iacr.org/2023/1705), commits to a multilinear polynomial
+//! by the Reed-Solomon encoding of its coefficients, and proves an evaluation with a sumcheck
+//! whose rounds fold the encoding as FRI does, with the sumcheck's challenges.
+
+#![no_std]
+
+extern crate alloc;
+
+mod config;
+mod multilinear;
+mod pcs;
+mod proof;
+pub mod prover;
+pub mod verifier;
+
+pub use config::*;
+pub use multilinear::*;
+pub use pcs::*;
+pub use proof::*;
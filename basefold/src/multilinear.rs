@@ -0,0 +1,216 @@
+//! Operations on multilinear polynomials given by their evaluations over the boolean hypercube, and
+//! on their encodings.
+//!
+//! Throughout, bit `i` of an index into a table of evaluations is the value of the `i`th variable,
+//! so the variable which sumcheck rounds fix first is the one which tells adjacent entries apart.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::izip;
+use p3_field::{ExtensionField, Field, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_maybe_rayon::prelude::*;
+use p3_util::{log2_strict_usize, reverse_bits_len, reverse_slice_index_bits};
+
+/// Converts each column of evaluations of a multilinear polynomial into its coefficients, where the
+/// coefficient at index `s` is that of the monomial in the variables whose bits are set in `s`.
+pub fn multilinear_coeffs<F: Field>(mut evals: RowMajorMatrix<F>) -> RowMajorMatrix<F> {
+    let width = evals.width();
+    let mut half = 1;
+    while half < evals.height() {
+        // The inverse of the zeta transform: subtract the evaluations with each variable at zero
+        // from those with it at one.
+        evals
+            .values
+            .par_chunks_exact_mut(2 * half * width)
+            .for_each(|chunk| {
+                let (lo, hi) = chunk.split_at_mut(half * width);
+                izip!(hi, lo).for_each(|(hi, &mut lo)| *hi -= lo);
+            });
+        half *= 2;
+    }
+    evals
+}
+
+/// The table of `scale * eq(point, b)` for all `b` in `{0, 1}^n`, where `eq` is the multilinear
+/// extension of equality, so that a polynomial's value at `point` is its table's inner product
+/// with this one for `scale = 1`.
+pub fn eq_evals<F: Field>(point: &[F], scale: F) -> Vec<F> {
+    let mut evals = vec![scale];
+    for &z in point {
+        let hi = evals.iter().map(|&e| e * z).collect::<Vec<_>>();
+        izip!(&mut evals, &hi).for_each(|(lo, &hi)| *lo -= hi);
+        evals.extend(hi);
+    }
+    evals
+}
+
+/// Evaluates `eq(a, b)`, which is one when `a` and `b` are equal points of the hypercube and zero
+/// at all other pairs of them.
+pub fn eval_eq<F: Field>(a: &[F], b: &[F]) -> F {
+    assert_eq!(a.len(), b.len());
+    izip!(a, b)
+        .map(|(&a, &b)| a * b + (F::ONE - a) * (F::ONE - b))
+        .product()
+}
+
+/// Fixes the first variable of a multilinear polynomial to `r`.
+pub fn fold_evals<F: Field>(evals: &[F], r: F) -> Vec<F> {
+    evals
+        .par_chunks_exact(2)
+        .map(|pair| pair[0] + r * (pair[1] - pair[0]))
+        .collect()
+}
+
+/// The evaluations at 0, 1 and 2 of the sumcheck round polynomial
+/// `h(X) = sum_b f(X, b) w(X, b)`, which has degree at most two.
+pub fn sumcheck_round<F: Field>(f: &[F], w: &[F]) -> [F; 3] {
+    izip!(f.chunks_exact(2), w.chunks_exact(2)).fold([F::ZERO; 3], |[h0, h1, h2], (f, w)| {
+        let f2 = f[1].double() - f[0];
+        let w2 = w[1].double() - w[0];
+        [h0 + f[0] * w[0], h1 + f[1] * w[1], h2 + f2 * w2]
+    })
+}
+
+/// Evaluates at `r` the polynomial of degree at most two with the given evaluations at 0, 1 and 2.
+pub fn interpolate_round<F: Field>(evals: &[F; 3], r: F) -> F {
+    let [h0, h1, h2] = *evals;
+    // Lagrange interpolation over {0, 1, 2}.
+    let r1 = r - F::ONE;
+    let r2 = r - F::TWO;
+    (h0 * r1 * r2 + h2 * r * r1).halve() - h1 * r * r2
+}
+
+/// The point at which a codeword on `shift H`, for `H` of log size `log_size`, is evaluated at the
+/// first position of the pair `pair` in its bit-reversed order. The second position holds its
+/// negation.
+pub fn pair_point<F: TwoAdicField>(shift: F, log_size: usize, pair: usize) -> F {
+    shift * F::two_adic_generator(log_size).exp_u64(reverse_bits_len(pair, log_size - 1) as u64)
+}
+
+/// Folds the evaluations `lo` at `x` and `hi` at `-x` of a polynomial `p(X) = p_e(X^2) + X p_o(X^2)`
+/// into the evaluation of `p_e + r p_o` at `x^2`.
+pub fn fold_pair<Val: Field, F: ExtensionField<Val>>(lo: F, hi: F, r: F, x_inv: Val) -> F {
+    ((lo + hi) + r * (lo - hi) * x_inv).halve()
+}
+
+/// Folds a bit-reversed codeword on `shift H` with `fold_pair`, into the bit-reversed codeword on
+/// `shift^2 H^2` of the folded polynomial.
+pub fn fold_codeword<Val: TwoAdicField, F: ExtensionField<Val>>(
+    codeword: &[F],
+    shift: Val,
+    r: F,
+) -> Vec<F> {
+    let log_size = log2_strict_usize(codeword.len());
+    let mut x_invs = Val::two_adic_generator(log_size)
+        .inverse()
+        .shifted_powers(shift.inverse())
+        .take(codeword.len() / 2)
+        .collect::<Vec<_>>();
+    reverse_slice_index_bits(&mut x_invs);
+    codeword
+        .par_chunks_exact(2)
+        .zip(x_invs)
+        .map(|(pair, x_inv)| fold_pair(pair[0], pair[1], r, x_inv))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use itertools::Itertools;
+    use p3_baby_bear::BabyBear;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_field::{FieldAlgebra, FieldExtensionAlgebra};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<F, 4>;
+
+    #[test]
+    fn folding_evaluates_at_point() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let num_variables = 5;
+        let evals = RowMajorMatrix::<F>::rand(&mut rng, 1 << num_variables, 3);
+        let point: Vec<EF> = (0..num_variables).map(|_| rng.gen()).collect();
+        let values = evals.columnwise_dot_product(&eq_evals(&point, EF::ONE));
+
+        for (col, &value) in values.iter().enumerate() {
+            let mut folded = evals
+                .values
+                .iter()
+                .skip(col)
+                .step_by(3)
+                .map(|&x| EF::from_base(x))
+                .collect_vec();
+            for &r in &point {
+                folded = fold_evals(&folded, r);
+            }
+            assert_eq!(folded, vec![value]);
+        }
+        assert_eq!(
+            eq_evals(&point, EF::ONE)[0b10110],
+            eval_eq(&point, &[0, 1, 1, 0, 1].map(EF::from_canonical_u8))
+        );
+    }
+
+    #[test]
+    fn fold_codeword_matches_folded_encoding() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let num_variables = 4;
+        let log_size = num_variables + 2;
+        let evals = RowMajorMatrix::<F>::rand(&mut rng, 1 << num_variables, 1);
+        let r: EF = rng.gen();
+        let shift = F::GENERATOR.square();
+
+        let eval_at =
+            |coeffs: &[EF], x: F| coeffs.iter().rev().fold(EF::ZERO, |acc, &c| acc * x + c);
+        // The bit-reversed evaluations on `shift H` of the polynomial with the given coefficients.
+        let encode = |coeffs: &[EF], shift: F, log_size: usize| {
+            let mut codeword = F::two_adic_generator(log_size)
+                .shifted_powers(shift)
+                .take(1 << log_size)
+                .map(|x| eval_at(coeffs, x))
+                .collect_vec();
+            reverse_slice_index_bits(&mut codeword);
+            codeword
+        };
+        let to_ext = |m: RowMajorMatrix<F>| m.values.into_iter().map(EF::from_base).collect_vec();
+
+        let coeffs = to_ext(multilinear_coeffs(evals.clone()));
+        let codeword = encode(&coeffs, shift, log_size);
+        for pair in 0..1 << (log_size - 1) {
+            let x = pair_point(shift, log_size, pair);
+            assert_eq!(codeword[2 * pair], eval_at(&coeffs, x));
+            assert_eq!(codeword[2 * pair + 1], eval_at(&coeffs, -x));
+        }
+
+        let folded_evals = fold_evals(&to_ext(evals), r);
+        let folded_coeffs = multilinear_coeffs(RowMajorMatrix::new(folded_evals, 1)).values;
+        assert_eq!(
+            fold_codeword(&codeword, shift, r),
+            encode(&folded_coeffs, shift.square(), log_size - 1)
+        );
+    }
+
+    #[test]
+    fn round_polynomial_interpolation() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let f: Vec<EF> = (0..16).map(|_| rng.gen()).collect();
+        let w: Vec<EF> = (0..16).map(|_| rng.gen()).collect();
+        let r: EF = rng.gen();
+        let evals = sumcheck_round(&f, &w);
+        let folded_sum = izip!(fold_evals(&f, r), fold_evals(&w, r))
+            .map(|(f, w)| f * w)
+            .sum::<EF>();
+        assert_eq!(interpolate_round(&evals, r), folded_sum);
+        assert_eq!(
+            evals[0] + evals[1],
+            izip!(f, w).map(|(f, w)| f * w).sum::<EF>()
+        );
+    }
+}
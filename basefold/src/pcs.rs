@@ -0,0 +1,227 @@
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::{MultiOpeningMmcs, MultilinearPcs};
+use p3_dft::TwoAdicSubgroupDft;
+use p3_field::{ExtensionField, TwoAdicField};
+use p3_fri::BatchOpening;
+use p3_matrix::bitrev::BitReversableMatrix;
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
+use p3_matrix::{Dimensions, Matrix};
+use p3_util::log2_strict_usize;
+use tracing::info_span;
+
+use crate::verifier::{self, BasefoldError};
+use crate::{eq_evals, eval_eq, multilinear_coeffs, prover, BasefoldConfig, BasefoldProof};
+
+/// A multilinear PCS over two-adic fields, which proves its openings with Basefold.
+///
+/// The committed polynomials are encoded as the evaluations on `Val::GENERATOR H` of the univariate
+/// polynomials with the same coefficients, for `H` larger than the number of coefficients by the
+/// blowup. An opening combines the polynomials with powers of one random challenge and the points
+/// with powers of another, and reduces the combined claim to the batched polynomial's value at a
+/// random point with a sumcheck.
+#[derive(Debug)]
+pub struct TwoAdicBasefoldPcs<Val, Dft, InputMmcs, CommitMmcs> {
+    dft: Dft,
+    mmcs: InputMmcs,
+    basefold: BasefoldConfig<CommitMmcs>,
+    _phantom: PhantomData<Val>,
+}
+
+impl<Val, Dft, InputMmcs, CommitMmcs> TwoAdicBasefoldPcs<Val, Dft, InputMmcs, CommitMmcs> {
+    pub const fn new(dft: Dft, mmcs: InputMmcs, basefold: BasefoldConfig<CommitMmcs>) -> Self {
+        Self {
+            dft,
+            mmcs,
+            basefold,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+pub struct BasefoldProverData<Val, InputMmcs: MultiOpeningMmcs<Val>> {
+    /// The committed evaluations over the hypercube.
+    evals: RowMajorMatrix<Val>,
+    /// The committed encodings, with each row holding the evaluations of every polynomial at a
+    /// point and then at its negation.
+    encodings: InputMmcs::ProverData<RowMajorMatrix<Val>>,
+}
+
+pub type TwoAdicBasefoldPcsProof<Val, Challenge, InputMmcs, CommitMmcs> =
+    BasefoldProof<Challenge, CommitMmcs, Val, BatchOpening<Val, InputMmcs>>;
+
+impl<Val, Dft, InputMmcs, CommitMmcs, Challenge, Challenger> MultilinearPcs<Challenge, Challenger>
+    for TwoAdicBasefoldPcs<Val, Dft, InputMmcs, CommitMmcs>
+where
+    Val: TwoAdicField,
+    Dft: TwoAdicSubgroupDft<Val>,
+    InputMmcs: MultiOpeningMmcs<Val>,
+    CommitMmcs: MultiOpeningMmcs<Challenge>,
+    Challenge: ExtensionField<Val>,
+    Challenger: FieldChallenger<Val>
+        + CanObserve<CommitMmcs::Commitment>
+        + GrindingChallenger<Witness = Val>,
+{
+    type Val = Val;
+    type Commitment = InputMmcs::Commitment;
+    type ProverData = BasefoldProverData<Val, InputMmcs>;
+    type Proof = TwoAdicBasefoldPcsProof<Val, Challenge, InputMmcs, CommitMmcs>;
+    type Error = BasefoldError<CommitMmcs::Error, InputMmcs::Error>;
+
+    fn commit(&self, evals: RowMajorMatrix<Val>) -> (Self::Commitment, Self::ProverData) {
+        let num_variables = log2_strict_usize(evals.height());
+        assert!(
+            num_variables > 0,
+            "Basefold requires polynomials in at least one variable"
+        );
+        let width = evals.width();
+
+        let encodings = info_span!("encode", num_variables, width).in_scope(|| {
+            let mut coeffs = multilinear_coeffs(evals.clone());
+            coeffs.pad_to_height(1 << (num_variables + self.basefold.log_blowup), Val::ZERO);
+            self.dft
+                .coset_dft_batch(coeffs, Val::GENERATOR)
+                .bit_reverse_rows()
+                .to_row_major_matrix()
+        });
+        // Commit to the evaluations at each point and its negation, which are adjacent in
+        // bit-reversed order, in a single row.
+        let (commit, encodings) = self
+            .mmcs
+            .commit_matrix(RowMajorMatrix::new(encodings.values, 2 * width));
+
+        (commit, BasefoldProverData { evals, encodings })
+    }
+
+    fn open(
+        &self,
+        prover_data: &Self::ProverData,
+        points: &[Vec<Challenge>],
+        challenger: &mut Challenger,
+    ) -> (Vec<Vec<Challenge>>, Self::Proof) {
+        let evals = &prover_data.evals;
+        let values = info_span!("compute opened values").in_scope(|| {
+            points
+                .iter()
+                .map(|point| evals.columnwise_dot_product(&eq_evals(point, Challenge::ONE)))
+                .collect_vec()
+        });
+        for &value in values.iter().flatten() {
+            challenger.observe_ext_element(value);
+        }
+
+        // Batch combination challenges, for the polynomials and for the points.
+        let beta: Challenge = challenger.sample_ext_element();
+        let gamma: Challenge = challenger.sample_ext_element();
+
+        let batched_evals = evals.dot_ext_powers(beta).collect();
+        let mut weights = Challenge::zero_vec(evals.height());
+        for (point, gamma_pow) in izip!(points, gamma.powers()) {
+            izip!(&mut weights, eq_evals(point, gamma_pow)).for_each(|(w, eq)| *w += eq);
+        }
+        let encodings = self.mmcs.get_matrices(&prover_data.encodings)[0];
+        let batched_codeword = RowMajorMatrixView::new(&encodings.values, evals.width())
+            .dot_ext_powers(beta)
+            .collect();
+
+        let proof = prover::prove(
+            &self.basefold,
+            batched_evals,
+            weights,
+            batched_codeword,
+            challenger,
+            |indices| {
+                let (opened_values, opening_proof) =
+                    self.mmcs.open_batch_many(indices, &prover_data.encodings);
+                BatchOpening {
+                    opened_values,
+                    opening_proof,
+                }
+            },
+        );
+
+        (values, proof)
+    }
+
+    fn verify(
+        &self,
+        commit: &Self::Commitment,
+        claims: &[(Vec<Challenge>, Vec<Challenge>)],
+        proof: &Self::Proof,
+        challenger: &mut Challenger,
+    ) -> Result<(), Self::Error> {
+        let Some((first_point, first_values)) = claims.first() else {
+            return Err(BasefoldError::InvalidProofShape);
+        };
+        let num_variables = first_point.len();
+        let width = first_values.len();
+        if num_variables == 0
+            || claims
+                .iter()
+                .any(|(point, values)| point.len() != num_variables || values.len() != width)
+        {
+            return Err(BasefoldError::InvalidProofShape);
+        }
+
+        for &value in claims.iter().flat_map(|(_, values)| values) {
+            challenger.observe_ext_element(value);
+        }
+        let beta: Challenge = challenger.sample_ext_element();
+        let gamma: Challenge = challenger.sample_ext_element();
+
+        let claimed_sum: Challenge = izip!(claims, gamma.powers())
+            .map(|((_, values), gamma_pow)| {
+                gamma_pow
+                    * izip!(values, beta.powers())
+                        .map(|(&v, b)| v * b)
+                        .sum::<Challenge>()
+            })
+            .sum();
+        let eval_weights = |r: &[Challenge]| -> Challenge {
+            izip!(claims, gamma.powers())
+                .map(|((point, _), gamma_pow)| gamma_pow * eval_eq(point, r))
+                .sum()
+        };
+        let log_height = num_variables + self.basefold.log_blowup - 1;
+        let open_input = |indices: &[usize],
+                          input_proof: &BatchOpening<Val, InputMmcs>|
+         -> Result<Vec<(Challenge, Challenge)>, InputMmcs::Error> {
+            let dims = [Dimensions {
+                width: 2 * width,
+                height: 1 << log_height,
+            }];
+            self.mmcs.verify_batch_many(
+                commit,
+                &dims,
+                indices,
+                &input_proof.opened_values,
+                &input_proof.opening_proof,
+            )?;
+            // Combine the polynomials' evaluations at each point of the pair as the prover did.
+            let combine = |evals: &[Val]| -> Challenge {
+                izip!(evals, beta.powers()).map(|(&e, b)| b * e).sum()
+            };
+            Ok(input_proof
+                .opened_values
+                .iter()
+                .map(|opened_rows| {
+                    let (lo, hi) = opened_rows[0].split_at(width);
+                    (combine(lo), combine(hi))
+                })
+                .collect())
+        };
+
+        verifier::verify(
+            &self.basefold,
+            num_variables,
+            claimed_sum,
+            proof,
+            challenger,
+            eval_weights,
+            open_input,
+        )
+    }
+}
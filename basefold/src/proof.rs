@@ -0,0 +1,57 @@
+use alloc::vec::Vec;
+
+use p3_commit::MultiOpeningMmcs;
+use p3_field::{CanonicalEncoding, DecodingError, Field};
+use p3_fri::CommitPhaseProofStep;
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "Witness: Serialize, InputProof: Serialize",
+    deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
+))]
+pub struct BasefoldProof<F: Field, M: MultiOpeningMmcs<F>, Witness, InputProof> {
+    /// For each sumcheck round, the round polynomial's evaluations at 0, 1 and 2.
+    pub sumcheck_evals: Vec<[F; 3]>,
+    /// The commitments to the codewords folded by every sumcheck round but the last, in rows of
+    /// sibling pairs.
+    pub commits: Vec<M::Commitment>,
+    /// The value of the batched polynomial at the sumcheck's point, which is the constant the last
+    /// round folds the codeword into.
+    pub final_value: F,
+    pub pow_witness: Witness,
+    /// For each committed codeword, the openings at the queries.
+    pub query_proofs: Vec<CommitPhaseProofStep<F, M>>,
+    /// The openings of the committed codewords of the inputs at the queries.
+    pub input_proof: InputProof,
+}
+
+impl<F, M, Witness, InputProof> CanonicalEncoding for BasefoldProof<F, M, Witness, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::Commitment: CanonicalEncoding,
+    M::MultiProof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+    InputProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.sumcheck_evals.encode(out);
+        self.commits.encode(out);
+        self.final_value.encode(out);
+        self.pow_witness.encode(out);
+        self.query_proofs.encode(out);
+        self.input_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            sumcheck_evals: CanonicalEncoding::decode(input)?,
+            commits: CanonicalEncoding::decode(input)?,
+            final_value: CanonicalEncoding::decode(input)?,
+            pow_witness: CanonicalEncoding::decode(input)?,
+            query_proofs: CanonicalEncoding::decode(input)?,
+            input_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
@@ -0,0 +1,112 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::MultiOpeningMmcs;
+use p3_field::{ExtensionField, TwoAdicField};
+use p3_fri::CommitPhaseProofStep;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::log2_strict_usize;
+use tracing::{info_span, instrument};
+
+use crate::{fold_codeword, fold_evals, sumcheck_round, BasefoldConfig, BasefoldProof};
+
+/// Proves that `sum_b f(b) w(b) = sum_b evals[b] weights[b]`, over `b` in the boolean hypercube, for
+/// the multilinear polynomial `f` whose bit-reversed encoding on `Val::GENERATOR H` is `codeword`.
+///
+/// Each sumcheck round fixes the first variable of `f` and `w` to its challenge, and folds the
+/// codeword with the same challenge, so that the final constant is `f` at the sumcheck's point.
+/// `open_input` opens the inputs from which `codeword` was computed at the queried pairs, which
+/// index `codeword`'s pairs of adjacent evaluations.
+#[instrument(name = "Basefold prover", skip_all)]
+pub fn prove<Val, Challenge, M, Challenger, InputProof>(
+    config: &BasefoldConfig<M>,
+    mut evals: Vec<Challenge>,
+    mut weights: Vec<Challenge>,
+    mut codeword: Vec<Challenge>,
+    challenger: &mut Challenger,
+    open_input: impl FnOnce(&[usize]) -> InputProof,
+) -> BasefoldProof<Challenge, M, Challenger::Witness, InputProof>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val>,
+    M: MultiOpeningMmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    let num_variables = log2_strict_usize(evals.len());
+    let log_size = log2_strict_usize(codeword.len());
+    assert!(num_variables > 0);
+    assert_eq!(weights.len(), evals.len());
+    assert_eq!(log_size, num_variables + config.log_blowup);
+
+    let mut sumcheck_evals = Vec::with_capacity(num_variables);
+    let mut commits = Vec::with_capacity(num_variables - 1);
+    let mut layers = Vec::with_capacity(num_variables - 1);
+    for round in 0..num_variables {
+        let _span = info_span!("round", round).entered();
+        let round_evals = sumcheck_round(&evals, &weights);
+        for &eval in &round_evals {
+            challenger.observe_ext_element(eval);
+        }
+        sumcheck_evals.push(round_evals);
+
+        let r: Challenge = challenger.sample_ext_element();
+        evals = fold_evals(&evals, r);
+        weights = fold_evals(&weights, r);
+        codeword = fold_codeword(&codeword, Val::GENERATOR.exp_power_of_2(round), r);
+
+        // The last round folds the codeword into a constant, which is sent in the clear instead.
+        if round + 1 < num_variables {
+            let (commit, layer) = config
+                .mmcs
+                .commit_matrix(RowMajorMatrix::new(codeword.clone(), 2));
+            challenger.observe(commit.clone());
+            commits.push(commit);
+            layers.push(layer);
+        }
+    }
+
+    let final_value = evals[0];
+    debug_assert!(codeword.iter().all(|&x| x == final_value));
+    challenger.observe_ext_element(final_value);
+
+    let pow_witness = challenger.grind(config.proof_of_work_bits);
+    let indices = iter::repeat_with(|| challenger.sample_bits(log_size - 1))
+        .take(config.num_queries)
+        .collect_vec();
+
+    let input_proof = open_input(&indices);
+    let query_proofs = info_span!("open queries").in_scope(|| {
+        layers
+            .iter()
+            .enumerate()
+            .map(|(i, layer)| {
+                // Each query's pair in one codeword is its position in the next.
+                let positions = indices.iter().map(|&index| index >> i).collect_vec();
+                let rows = positions.iter().map(|&pos| pos >> 1).collect_vec();
+                let (opened_rows, opening_proof) = config.mmcs.open_batch_many(&rows, layer);
+                let sibling_values = izip!(positions, opened_rows)
+                    .map(|(pos, mut opened_rows)| {
+                        assert_eq!(opened_rows.len(), 1);
+                        vec![opened_rows.pop().unwrap()[(pos & 1) ^ 1]]
+                    })
+                    .collect();
+                CommitPhaseProofStep {
+                    sibling_values,
+                    opening_proof,
+                }
+            })
+            .collect()
+    });
+
+    BasefoldProof {
+        sumcheck_evals,
+        commits,
+        final_value,
+        pow_witness,
+        query_proofs,
+        input_proof,
+    }
+}
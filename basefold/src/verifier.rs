@@ -0,0 +1,139 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::MultiOpeningMmcs;
+use p3_field::{ExtensionField, TwoAdicField};
+use p3_matrix::Dimensions;
+
+use crate::{fold_pair, interpolate_round, pair_point, BasefoldConfig, BasefoldProof};
+
+#[derive(Debug)]
+pub enum BasefoldError<CommitMmcsErr, InputError> {
+    InvalidProofShape,
+    CommitMmcsError(CommitMmcsErr),
+    InputError(InputError),
+    /// A sumcheck round polynomial doesn't agree with the previous round's claim, or the final
+    /// value doesn't agree with the last one.
+    SumcheckMismatch,
+    /// A query doesn't fold to the final value.
+    FinalValueMismatch,
+    InvalidPowWitness,
+}
+
+/// Verifies a proof that `sum_b f(b) w(b) = claimed_sum`, over `b` in the boolean hypercube of
+/// dimension `num_variables`, for the multilinear polynomial `f` whose bit-reversed encoding on
+/// `Val::GENERATOR H` the prover committed to.
+///
+/// `eval_weights` evaluates the multilinear extension of `w` at the sumcheck's point, and
+/// `open_input` computes the pairs of adjacent evaluations of the encoding at the queried pairs.
+pub fn verify<Val, Challenge, M, Challenger, InputProof, InputError>(
+    config: &BasefoldConfig<M>,
+    num_variables: usize,
+    claimed_sum: Challenge,
+    proof: &BasefoldProof<Challenge, M, Challenger::Witness, InputProof>,
+    challenger: &mut Challenger,
+    eval_weights: impl FnOnce(&[Challenge]) -> Challenge,
+    open_input: impl FnOnce(&[usize], &InputProof) -> Result<Vec<(Challenge, Challenge)>, InputError>,
+) -> Result<(), BasefoldError<M::Error, InputError>>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val>,
+    M: MultiOpeningMmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    if num_variables == 0
+        || proof.sumcheck_evals.len() != num_variables
+        || proof.commits.len() != num_variables - 1
+        || proof.query_proofs.len() != num_variables - 1
+        || proof.query_proofs.iter().any(|step| {
+            step.sibling_values.len() != config.num_queries
+                || step.sibling_values.iter().any(|sibling| sibling.len() != 1)
+        })
+    {
+        return Err(BasefoldError::InvalidProofShape);
+    }
+    let log_size = num_variables + config.log_blowup;
+
+    // Replay the sumcheck, reducing the claim to one about the batched polynomial and the weights
+    // at the sumcheck's point.
+    let mut claim = claimed_sum;
+    let mut rs = Vec::with_capacity(num_variables);
+    for (round, round_evals) in proof.sumcheck_evals.iter().enumerate() {
+        if round_evals[0] + round_evals[1] != claim {
+            return Err(BasefoldError::SumcheckMismatch);
+        }
+        for &eval in round_evals {
+            challenger.observe_ext_element(eval);
+        }
+        let r: Challenge = challenger.sample_ext_element();
+        claim = interpolate_round(round_evals, r);
+        rs.push(r);
+        if let Some(commit) = proof.commits.get(round) {
+            challenger.observe(commit.clone());
+        }
+    }
+    challenger.observe_ext_element(proof.final_value);
+    if claim != proof.final_value * eval_weights(&rs) {
+        return Err(BasefoldError::SumcheckMismatch);
+    }
+
+    if !challenger.check_witness(config.proof_of_work_bits, proof.pow_witness) {
+        return Err(BasefoldError::InvalidPowWitness);
+    }
+    let indices = iter::repeat_with(|| challenger.sample_bits(log_size - 1))
+        .take(config.num_queries)
+        .collect_vec();
+
+    // Fold each query's pair of the encoding through the committed codewords.
+    let input_pairs =
+        open_input(&indices, &proof.input_proof).map_err(BasefoldError::InputError)?;
+    if input_pairs.len() != indices.len() {
+        return Err(BasefoldError::InvalidProofShape);
+    }
+    let mut folded = izip!(&indices, input_pairs)
+        .map(|(&index, (lo, hi))| {
+            let x = pair_point(Val::GENERATOR, log_size, index);
+            fold_pair(lo, hi, rs[0], x.inverse())
+        })
+        .collect_vec();
+
+    for (i, (commit, step)) in izip!(&proof.commits, &proof.query_proofs).enumerate() {
+        let layer_log_size = log_size - i - 1;
+        let positions = indices.iter().map(|&index| index >> i).collect_vec();
+        let rows = positions.iter().map(|&pos| pos >> 1).collect_vec();
+        let opened_rows = izip!(&positions, folded, &step.sibling_values)
+            .map(|(&pos, eval, sibling)| {
+                let mut row = vec![eval, sibling[0]];
+                if pos & 1 == 1 {
+                    row.swap(0, 1);
+                }
+                vec![row]
+            })
+            .collect_vec();
+        let dims = [Dimensions {
+            width: 2,
+            height: 1 << (layer_log_size - 1),
+        }];
+        config
+            .mmcs
+            .verify_batch_many(commit, &dims, &rows, &opened_rows, &step.opening_proof)
+            .map_err(BasefoldError::CommitMmcsError)?;
+
+        let shift = Val::GENERATOR.exp_power_of_2(i + 1);
+        folded = izip!(rows, opened_rows)
+            .map(|(row, opened)| {
+                let x = pair_point(shift, layer_log_size, row);
+                fold_pair(opened[0][0], opened[0][1], rs[i + 1], x.inverse())
+            })
+            .collect();
+    }
+
+    if folded.iter().any(|&eval| eval != proof.final_value) {
+        return Err(BasefoldError::FinalValueMismatch);
+    }
+
+    Ok(())
+}
@@ -0,0 +1,262 @@
+use itertools::Itertools;
+use p3_basefold::{BasefoldConfig, TwoAdicBasefoldPcs, TwoAdicBasefoldPcsProof};
+use p3_challenger::{CanObserve, DuplexChallenger, FieldChallenger};
+use p3_commit::{ExtensionMmcs, MultilinearPcs};
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use rand::distributions::{Distribution, Standard};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+fn seeded_rng() -> impl Rng {
+    ChaCha20Rng::seed_from_u64(0)
+}
+
+/// Commits to random multilinear polynomials in `num_variables` variables and opens them at
+/// `num_points` random points, then verifies the opened values and proof after passing them
+/// through `tamper`.
+fn do_test_basefold_pcs<Val, Challenge, Challenger, P>(
+    (pcs, challenger): &(P, Challenger),
+    num_variables: usize,
+    width: usize,
+    num_points: usize,
+    tamper: impl FnOnce(&mut Vec<Vec<Challenge>>, &mut P::Proof),
+) -> Result<(), P::Error>
+where
+    P: MultilinearPcs<Challenge, Challenger, Val = Val>,
+    Val: Field,
+    Standard: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+    Challenger: Clone + CanObserve<P::Commitment> + FieldChallenger<Val>,
+{
+    let mut rng = seeded_rng();
+    let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << num_variables, width);
+
+    let mut p_challenger = challenger.clone();
+    let (commit, data) = pcs.commit(evals);
+    p_challenger.observe(commit.clone());
+    let points = (0..num_points)
+        .map(|_| {
+            (0..num_variables)
+                .map(|_| p_challenger.sample_ext_element())
+                .collect_vec()
+        })
+        .collect_vec();
+    let (mut values, mut proof) = pcs.open(&data, &points, &mut p_challenger);
+    assert_eq!(values.len(), num_points);
+    assert!(values.iter().all(|values| values.len() == width));
+    tamper(&mut values, &mut proof);
+
+    let mut v_challenger = challenger.clone();
+    v_challenger.observe(commit.clone());
+    let verifier_points = (0..num_points)
+        .map(|_| {
+            (0..num_variables)
+                .map(|_| v_challenger.sample_ext_element())
+                .collect_vec()
+        })
+        .collect_vec();
+    assert_eq!(verifier_points, points);
+
+    let claims = points.into_iter().zip(values).collect_vec();
+    pcs.verify(&commit, &claims, &proof, &mut v_challenger)
+}
+
+/// Opens random multilinear polynomials at a point of the boolean hypercube, where their values
+/// are those in the committed evaluations.
+fn do_test_hypercube_point<Val, Challenge, Challenger, P>(
+    (pcs, challenger): &(P, Challenger),
+    num_variables: usize,
+    row: usize,
+) where
+    P: MultilinearPcs<Challenge, Challenger, Val = Val>,
+    Val: Field,
+    Standard: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+    Challenger: Clone,
+{
+    let mut rng = seeded_rng();
+    let evals = RowMajorMatrix::<Val>::rand(&mut rng, 1 << num_variables, 7);
+    let expected = evals.row(row).map(Challenge::from_base).collect_vec();
+
+    let (commit, data) = pcs.commit(evals);
+    let point = (0..num_variables)
+        .map(|i| Challenge::from_bool((row >> i) & 1 == 1))
+        .collect_vec();
+    let (values, proof) = pcs.open(&data, &[point.clone()], &mut challenger.clone());
+    assert_eq!(values, vec![expected]);
+
+    let claims = [(point, values[0].clone())];
+    pcs.verify(&commit, &claims, &proof, &mut challenger.clone())
+        .unwrap();
+}
+
+// Set it up so we create tests inside a module for each pcs, so we get nice error reports
+// specific to a failing PCS.
+macro_rules! make_tests_for_pcs {
+    ($p:expr) => {
+        fn verify(p: &PcsAndChallenger, num_variables: usize, width: usize, num_points: usize) {
+            $crate::do_test_basefold_pcs(p, num_variables, width, num_points, |_, _| {}).unwrap();
+        }
+
+        #[test]
+        fn single() {
+            let p = $p;
+            for num_variables in 1..10 {
+                verify(&p, num_variables, 1, 1);
+            }
+        }
+
+        #[test]
+        fn many_columns() {
+            let p = $p;
+            for width in [2, 5, 16] {
+                verify(&p, 6, width, 1);
+            }
+        }
+
+        #[test]
+        fn many_points() {
+            let p = $p;
+            for num_points in 2..5 {
+                verify(&p, 5, 3, num_points);
+            }
+        }
+
+        #[test]
+        fn hypercube_points() {
+            let p = $p;
+            for row in [0, 5, 31] {
+                $crate::do_test_hypercube_point(&p, 5, row);
+            }
+        }
+
+        #[test]
+        fn tampered_proofs_fail() {
+            let p = $p;
+            let tampered = |tamper: fn(&mut Vec<Vec<Challenge>>, &mut Proof)| {
+                $crate::do_test_basefold_pcs(&p, 8, 4, 2, tamper).is_err()
+            };
+            assert!(tampered(|values, _| values[1][2] += Challenge::ONE));
+            assert!(tampered(
+                |_, proof| proof.sumcheck_evals[0][0] += Challenge::ONE
+            ));
+            assert!(tampered(
+                |_, proof| proof.sumcheck_evals[3][2] += Challenge::ONE
+            ));
+            assert!(tampered(|_, proof| proof.final_value += Challenge::ONE));
+            assert!(tampered(|_, proof| {
+                proof.query_proofs[2].sibling_values[0][0] += Challenge::ONE
+            }));
+            assert!(tampered(|_, proof| {
+                proof.input_proof.opened_values[0][0][1] += Val::ONE
+            }));
+            assert!(tampered(|_, proof| proof.commits.swap(0, 1)));
+        }
+    };
+}
+
+mod babybear_basefold_pcs {
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_field::FieldAlgebra;
+
+    use super::*;
+
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    type Dft = Radix2DitParallel<Val>;
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    type MyPcs = TwoAdicBasefoldPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    type PcsAndChallenger = (MyPcs, Challenger);
+    type Proof = TwoAdicBasefoldPcsProof<Val, Challenge, ValMmcs, ChallengeMmcs>;
+
+    fn get_pcs(log_blowup: usize) -> PcsAndChallenger {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm.clone());
+
+        let val_mmcs = ValMmcs::new(hash, compress);
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+        let basefold_config = BasefoldConfig {
+            log_blowup,
+            num_queries: 10,
+            proof_of_work_bits: 8,
+            mmcs: challenge_mmcs,
+        };
+
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, basefold_config);
+        (pcs, Challenger::new(perm))
+    }
+
+    mod blowup_1 {
+        use super::*;
+        make_tests_for_pcs!(get_pcs(1));
+    }
+    mod blowup_2 {
+        use super::*;
+        make_tests_for_pcs!(get_pcs(2));
+    }
+}
+
+mod koalabear_basefold_pcs {
+    use p3_field::FieldAlgebra;
+    use p3_koala_bear::{KoalaBear, Poseidon2KoalaBear};
+
+    use super::*;
+
+    type Val = KoalaBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2KoalaBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    type Dft = Radix2DitParallel<Val>;
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    type MyPcs = TwoAdicBasefoldPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    type PcsAndChallenger = (MyPcs, Challenger);
+    type Proof = TwoAdicBasefoldPcsProof<Val, Challenge, ValMmcs, ChallengeMmcs>;
+
+    fn get_pcs(log_blowup: usize) -> PcsAndChallenger {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm.clone());
+
+        let val_mmcs = ValMmcs::new(hash, compress);
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+        let basefold_config = BasefoldConfig {
+            log_blowup,
+            num_queries: 10,
+            proof_of_work_bits: 8,
+            mmcs: challenge_mmcs,
+        };
+
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, basefold_config);
+        (pcs, Challenger::new(perm))
+    }
+
+    mod blowup_1 {
+        use super::*;
+        make_tests_for_pcs!(get_pcs(1));
+    }
+}
@@ -12,7 +12,10 @@ use ff::{Field as FFField, PrimeField as FFPrimeField};
 pub use halo2curves::bn256::Fr as FFBn254Fr;
 use halo2curves::serde::SerdeObject;
 use num_bigint::BigUint;
-use p3_field::{Field, FieldAlgebra, Packable, PrimeField, TwoAdicField};
+use p3_field::{
+    take_bytes, CanonicalEncoding, DecodingError, Field, FieldAlgebra, Packable, PrimeField,
+    TwoAdicField,
+};
 pub use poseidon2::Poseidon2Bn254;
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
@@ -55,6 +58,21 @@ impl<'de> Deserialize<'de> for Bn254Fr {
     }
 }
 
+impl CanonicalEncoding for Bn254Fr {
+    fn encode(&self, out: &mut Vec<u8>) {
+        out.extend_from_slice(self.value.to_repr().as_ref());
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        let mut repr = <FFBn254Fr as FFPrimeField>::Repr::default();
+        repr.as_mut()
+            .copy_from_slice(take_bytes(input, repr.as_ref().len())?);
+        Option::from(FFBn254Fr::from_repr(repr))
+            .map(Self::new)
+            .ok_or(DecodingError::NonCanonical)
+    }
+}
+
 impl Packable for Bn254Fr {}
 
 impl Hash for Bn254Fr {
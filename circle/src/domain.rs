@@ -2,15 +2,16 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::{iterate, Itertools};
-use p3_commit::{LagrangeSelectors, PolynomialSpace};
+use p3_commit::{LagrangeSelectors, PeriodicPolynomialSpace, PolynomialSpace};
 use p3_field::extension::ComplexExtendable;
-use p3_field::ExtensionField;
+use p3_field::{ExtensionField, FieldExtensionAlgebra};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 use p3_util::{log2_ceil_usize, log2_strict_usize};
 use tracing::instrument;
 
 use crate::point::Point;
+use crate::CircleEvaluations;
 
 /// A twin-coset of the circle group on F. It has a power-of-two size and an arbitrary shift.
 ///
@@ -217,6 +218,50 @@ impl<F: ComplexExtendable> PolynomialSpace for CircleDomain<F> {
     }
 }
 
+impl<F: ComplexExtendable> PeriodicPolynomialSpace for CircleDomain<F> {
+    // On a standard position domain of size n, scaling each point by n / p maps the point of row i
+    // to that of row i mod p of the standard position domain of size p. So a column with period p
+    // is its interpolant over the latter domain, composed with this scaling.
+    fn periodic_column_at_point<Ext: ExtensionField<Self::Val>>(
+        &self,
+        column: &[Self::Val],
+        point: Ext,
+    ) -> Ext {
+        assert!(self.is_standard());
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_n);
+        if log_period == 0 {
+            return <Ext as FieldExtensionAlgebra<F>>::from_base(column[0]);
+        }
+        let point = Point::from_projective_line(point) * (1 << (self.log_n - log_period));
+        CircleEvaluations::from_natural_order(
+            CircleDomain::standard(log_period),
+            RowMajorMatrix::new_col(column.to_vec()),
+        )
+        .evaluate_at_point(point)[0]
+    }
+
+    fn periodic_column_on_coset(&self, column: &[Self::Val], coset: Self) -> Vec<Self::Val> {
+        assert!(self.is_standard() && coset.is_standard());
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_n && self.log_n <= coset.log_n);
+        if log_period == 0 {
+            return vec![column[0]; coset.size()];
+        }
+        // The scaled points of the coset cycle through a smaller standard position domain.
+        let log_stride = self.log_n - log_period;
+        let evals = CircleEvaluations::from_natural_order(
+            CircleDomain::standard(log_period),
+            RowMajorMatrix::new_col(column.to_vec()),
+        )
+        .extrapolate(CircleDomain::standard(coset.log_n - log_stride))
+        .to_natural_order()
+        .to_row_major_matrix()
+        .values;
+        evals.into_iter().cycle().take(coset.size()).collect()
+    }
+}
+
 // 0 1 2 .. len-1 len len len-1 .. 1 0 0 1 ..
 fn forward_backward_index(mut i: usize, len: usize) -> usize {
     i %= 2 * len;
@@ -235,10 +280,9 @@ mod tests {
     use itertools::izip;
     use p3_field::{batch_multiplicative_inverse, FieldAlgebra};
     use p3_mersenne_31::Mersenne31;
-    use rand::thread_rng;
+    use rand::{thread_rng, Rng};
 
     use super::*;
-    use crate::CircleEvaluations;
 
     fn assert_is_twin_coset<F: ComplexExtendable>(d: CircleDomain<F>) {
         let pts = d.points().collect_vec();
@@ -328,6 +372,37 @@ mod tests {
         }
     }
 
+    #[test]
+    fn periodic_columns() {
+        type F = Mersenne31;
+        let log_n = 6;
+        let n = 1 << log_n;
+
+        let d = CircleDomain::<F>::standard(log_n);
+        let coset = d.create_disjoint_domain(4 * n);
+        for log_period in [0, 1, 3, log_n] {
+            let column: Vec<F> = (0..1 << log_period).map(|_| thread_rng().gen()).collect();
+
+            // The column repeats down the domain.
+            let mut pt = d.first_point();
+            for i in 0..n {
+                assert_eq!(
+                    d.periodic_column_at_point(&column, pt),
+                    column[i % column.len()]
+                );
+                pt = d.next_point(pt).unwrap();
+            }
+
+            // periodic_column_on_coset matches periodic_column_at_point
+            let evals = d.periodic_column_on_coset(&column, coset);
+            let mut pt = coset.first_point();
+            for eval in evals {
+                assert_eq!(eval, d.periodic_column_at_point(&column, pt));
+                pt = coset.next_point(pt).unwrap();
+            }
+        }
+    }
+
     #[test]
     fn selectors() {
         type F = Mersenne31;
@@ -5,11 +5,11 @@ use core::marker::PhantomData;
 
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs, PolynomialSpace};
+use p3_commit::{Mmcs, MmcsHashCount, OpenedValues, Pcs, PolynomialSpace};
 use p3_field::extension::ComplexExtendable;
-use p3_field::{ExtensionField, Field};
+use p3_field::{CanonicalEncoding, DecodingError, ExtensionField, Field};
 use p3_fri::verifier::FriError;
-use p3_fri::FriConfig;
+use p3_fri::{FriConfig, VerifierHashCount};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::{Dimensions, Matrix};
 use p3_maybe_rayon::prelude::*;
@@ -80,6 +80,119 @@ pub struct CirclePcsProof<
     >,
 }
 
+impl<Val, InputMmcs> CanonicalEncoding for BatchOpening<Val, InputMmcs>
+where
+    Val: Field + CanonicalEncoding,
+    InputMmcs: Mmcs<Val>,
+    InputMmcs::Proof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.opened_values.encode(out);
+        self.opening_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            opened_values: CanonicalEncoding::decode(input)?,
+            opening_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<Val, Challenge, InputMmcs, FriMmcs> CanonicalEncoding
+    for CircleInputProof<Val, Challenge, InputMmcs, FriMmcs>
+where
+    Val: Field + CanonicalEncoding,
+    Challenge: Field + CanonicalEncoding,
+    InputMmcs: Mmcs<Val>,
+    InputMmcs::Proof: CanonicalEncoding,
+    FriMmcs: Mmcs<Challenge>,
+    FriMmcs::Proof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.input_openings.encode(out);
+        self.first_layer_siblings.encode(out);
+        self.first_layer_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            input_openings: CanonicalEncoding::decode(input)?,
+            first_layer_siblings: CanonicalEncoding::decode(input)?,
+            first_layer_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<Val, Challenge, InputMmcs, FriMmcs, Witness> CanonicalEncoding
+    for CirclePcsProof<Val, Challenge, InputMmcs, FriMmcs, Witness>
+where
+    Val: Field + CanonicalEncoding,
+    Challenge: Field + CanonicalEncoding,
+    InputMmcs: Mmcs<Val>,
+    InputMmcs::Proof: CanonicalEncoding,
+    FriMmcs: Mmcs<Challenge>,
+    FriMmcs::Commitment: CanonicalEncoding,
+    FriMmcs::Proof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.first_layer_commitment.encode(out);
+        self.lambdas.encode(out);
+        self.fri_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            first_layer_commitment: CanonicalEncoding::decode(input)?,
+            lambdas: CanonicalEncoding::decode(input)?,
+            fri_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<Val: Field, InputMmcs: MmcsHashCount<Val>> VerifierHashCount for BatchOpening<Val, InputMmcs> {
+    fn verifier_hash_count(&self) -> usize {
+        InputMmcs::verify_batch_hash_count(&self.opening_proof)
+    }
+}
+
+impl<Val, Challenge, InputMmcs, FriMmcs> VerifierHashCount
+    for CircleInputProof<Val, Challenge, InputMmcs, FriMmcs>
+where
+    Val: Field,
+    Challenge: Field,
+    InputMmcs: MmcsHashCount<Val>,
+    FriMmcs: MmcsHashCount<Challenge>,
+{
+    fn verifier_hash_count(&self) -> usize {
+        self.input_openings.verifier_hash_count()
+            + FriMmcs::verify_batch_hash_count(&self.first_layer_proof)
+    }
+}
+
+impl<Val, Challenge, InputMmcs, FriMmcs, Witness>
+    CirclePcsProof<Val, Challenge, InputMmcs, FriMmcs, Witness>
+where
+    Val: Field,
+    Challenge: Field,
+    InputMmcs: Mmcs<Val>,
+    FriMmcs: Mmcs<Challenge>,
+{
+    /// The FRI proof, whose input proofs include the openings of the first layer. See
+    /// `CircleFriProof::size_report`.
+    pub const fn fri_proof(
+        &self,
+    ) -> &CircleFriProof<
+        Challenge,
+        FriMmcs,
+        Witness,
+        CircleInputProof<Val, Challenge, InputMmcs, FriMmcs>,
+    > {
+        &self.fri_proof
+    }
+}
+
 impl<Val, InputMmcs, FriMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
     for CirclePcs<Val, InputMmcs, FriMmcs>
 where
@@ -95,6 +208,14 @@ where
     type Proof = CirclePcsProof<Val, Challenge, InputMmcs, FriMmcs, Challenger::Witness>;
     type Error = FriError<FriMmcs::Error, InputError<InputMmcs::Error, FriMmcs::Error>>;
 
+    fn fingerprint(&self) -> Vec<u8> {
+        (
+            self.fri_config.fingerprint(),
+            (self.mmcs.fingerprint(), self.fri_config.mmcs.fingerprint()),
+        )
+            .to_bytes()
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         CircleDomain::standard(log2_strict_usize(degree))
     }
@@ -340,8 +461,10 @@ where
         let bivariate_beta: Challenge = challenger.sample_ext_element();
 
         // +1 to account for first layer
-        let log_global_max_height =
-            proof.fri_proof.commit_phase_commits.len() + self.fri_config.log_blowup + 1;
+        let log_global_max_height = proof.fri_proof.commit_phase_commits.len()
+            + self.fri_config.log_blowup
+            + self.fri_config.log_final_poly_len
+            + 1;
 
         let g: CircleFriConfig<Val, Challenge, InputMmcs, FriMmcs> =
             CircleFriGenericConfig(PhantomData);
@@ -362,18 +485,24 @@ where
                 } = input_proof;
 
                 for (batch_opening, (batch_commit, mats)) in izip!(input_openings, &rounds) {
-                    let batch_heights: Vec<usize> = mats
-                        .iter()
-                        .map(|(domain, _)| (domain.size() << self.fri_config.log_blowup))
-                        .collect_vec();
-                    let batch_dims: Vec<Dimensions> = batch_heights
+                    let batch_dims: Vec<Dimensions> = mats
                         .iter()
-                        // todo: mmcs doesn't really need width
-                        .map(|&height| Dimensions { width: 0, height })
+                        .enumerate()
+                        .map(|(mat, (domain, points_and_values))| Dimensions {
+                            // Each column is opened at every point, so the values at any point
+                            // give the width of the matrix. A matrix opened at no points
+                            // contributes nothing, so its width is taken from its opened row
+                            // instead, which leaves it unchecked; see `Pcs::verify`.
+                            width: points_and_values.first().map_or_else(
+                                || batch_opening.opened_values.get(mat).map_or(0, Vec::len),
+                                |(_, values)| values.len(),
+                            ),
+                            height: domain.size() << self.fri_config.log_blowup,
+                        })
                         .collect_vec();
 
                     let log_batch_max_height =
-                        log2_strict_usize(batch_heights.iter().max().copied().unwrap());
+                        log2_strict_usize(batch_dims.iter().map(|dims| dims.height).max().unwrap());
 
                     self.mmcs
                         .verify_batch(
@@ -443,7 +572,7 @@ where
                             );
 
                             let fl_dims = Dimensions {
-                                width: 0,
+                                width: 2,
                                 height: 1 << (log_height - 1),
                             };
 
@@ -511,7 +640,11 @@ mod tests {
 
         type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
 
-        let fri_config = create_test_fri_config(challenge_mmcs);
+        // Grind in each commit phase round as well as before the queries.
+        let fri_config = FriConfig {
+            commit_proof_of_work_bits: 2,
+            ..create_test_fri_config(challenge_mmcs)
+        };
 
         type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
         let pcs = Pcs {
@@ -536,6 +669,10 @@ mod tests {
 
         let mut chal = Challenger::from_hasher(vec![], byte_hash);
         let (values, proof) = pcs.open(vec![(&data, vec![vec![zeta]])], &mut chal);
+        assert_eq!(
+            proof.fri_proof.commit_pow_witnesses.len(),
+            proof.fri_proof.commit_phase_commits.len()
+        );
 
         let mut chal = Challenger::from_hasher(vec![], byte_hash);
         pcs.verify(
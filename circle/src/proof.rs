@@ -1,7 +1,8 @@
 use alloc::vec::Vec;
 
-use p3_commit::Mmcs;
-use p3_field::Field;
+use p3_commit::{Mmcs, MmcsHashCount};
+use p3_field::{CanonicalEncoding, DecodingError, Field};
+use p3_fri::{FriSizeReport, VerifierHashCount};
 use serde::{Deserialize, Serialize};
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -11,10 +12,11 @@ use serde::{Deserialize, Serialize};
 ))]
 pub struct CircleFriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
+    /// Like `FriProof::commit_pow_witnesses`.
+    pub commit_pow_witnesses: Vec<Witness>,
     pub query_proofs: Vec<CircleQueryProof<F, M, InputProof>>,
-    // This could become Vec<FC::Challenge> if this library was generalized to support non-constant
-    // final polynomials.
-    pub final_poly: F,
+    /// The circle coefficients of the final polynomial, in the order of `circle_basis`.
+    pub final_poly: Vec<F>,
     pub pow_witness: Witness,
 }
 
@@ -40,3 +42,103 @@ pub struct CircleCommitPhaseProofStep<F: Field, M: Mmcs<F>> {
 
     pub opening_proof: M::Proof,
 }
+
+impl<F, M, Witness, InputProof> CanonicalEncoding for CircleFriProof<F, M, Witness, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: Mmcs<F>,
+    M::Commitment: CanonicalEncoding,
+    M::Proof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+    InputProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.commit_phase_commits.encode(out);
+        self.commit_pow_witnesses.encode(out);
+        self.query_proofs.encode(out);
+        self.final_poly.encode(out);
+        self.pow_witness.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            commit_phase_commits: CanonicalEncoding::decode(input)?,
+            commit_pow_witnesses: CanonicalEncoding::decode(input)?,
+            query_proofs: CanonicalEncoding::decode(input)?,
+            final_poly: CanonicalEncoding::decode(input)?,
+            pow_witness: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<F, M, InputProof> CanonicalEncoding for CircleQueryProof<F, M, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: Mmcs<F>,
+    M::Proof: CanonicalEncoding,
+    InputProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.input_proof.encode(out);
+        self.commit_phase_openings.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            input_proof: CanonicalEncoding::decode(input)?,
+            commit_phase_openings: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<F, M> CanonicalEncoding for CircleCommitPhaseProofStep<F, M>
+where
+    F: Field + CanonicalEncoding,
+    M: Mmcs<F>,
+    M::Proof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.sibling_value.encode(out);
+        self.opening_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            sibling_value: CanonicalEncoding::decode(input)?,
+            opening_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<F, M, Witness, InputProof> CircleFriProof<F, M, Witness, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: MmcsHashCount<F>,
+    M::Commitment: CanonicalEncoding,
+    M::Proof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+    InputProof: CanonicalEncoding + VerifierHashCount,
+{
+    /// Like `FriProof::size_report`.
+    pub fn size_report(&self) -> FriSizeReport {
+        let mut report = FriSizeReport::new(
+            &self.commit_phase_commits,
+            &self.commit_pow_witnesses,
+            &self.final_poly,
+            &self.pow_witness,
+            self.to_bytes().len(),
+        );
+        for query_proof in &self.query_proofs {
+            report.add_query(
+                &query_proof.input_proof,
+                query_proof.commit_phase_openings.iter().map(|step| {
+                    (
+                        step.to_bytes().len(),
+                        M::verify_batch_hash_count(&step.opening_proof),
+                    )
+                }),
+            );
+        }
+        report
+    }
+}
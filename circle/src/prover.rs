@@ -5,13 +5,17 @@ use core::iter;
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
 use p3_commit::Mmcs;
+use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::{FriConfig, FriGenericConfig};
 use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
 use p3_util::log2_strict_usize;
-use tracing::{info_span, instrument};
+use tracing::{debug_span, info_span, instrument};
 
-use crate::{CircleCommitPhaseProofStep, CircleFriProof, CircleQueryProof};
+use crate::{
+    CircleCommitPhaseProofStep, CircleDomain, CircleEvaluations, CircleFriProof, CircleQueryProof,
+};
 
 #[instrument(name = "FRI prover", skip_all)]
 pub fn prove<G, Val, Challenge, M, Challenger>(
@@ -22,7 +26,7 @@ pub fn prove<G, Val, Challenge, M, Challenger>(
     open_input: impl Fn(usize) -> G::InputProof,
 ) -> CircleFriProof<Challenge, M, Challenger::Witness, G::InputProof>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
@@ -33,8 +37,17 @@ where
         .iter()
         .tuple_windows()
         .all(|(l, r)| l.len() >= r.len()));
+    assert_eq!(
+        config.log_folding_arity, 1,
+        "circle FRI only supports folding in pairs"
+    );
 
     let log_max_height = log2_strict_usize(inputs[0].len());
+    let log_min_height = log2_strict_usize(inputs.last().unwrap().len());
+    assert!(
+        log_min_height >= config.log_blowup + config.log_final_poly_len,
+        "inputs must be at least as large as the final polynomial's evaluations"
+    );
 
     let commit_phase_result = commit_phase(g, config, inputs, challenger);
 
@@ -56,16 +69,18 @@ where
 
     CircleFriProof {
         commit_phase_commits: commit_phase_result.commits,
+        commit_pow_witnesses: commit_phase_result.pow_witnesses,
         query_proofs,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
     }
 }
 
-struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
+struct CommitPhaseResult<F: Field, M: Mmcs<F>, Witness> {
     commits: Vec<M::Commitment>,
+    pow_witnesses: Vec<Witness>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
-    final_poly: F,
+    final_poly: Vec<F>,
 }
 
 #[instrument(name = "commit phase", skip_all)]
@@ -74,23 +89,27 @@ fn commit_phase<G, Val, Challenge, M, Challenger>(
     config: &FriConfig<M>,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
-) -> CommitPhaseResult<Challenge, M>
+) -> CommitPhaseResult<Challenge, M, Challenger::Witness>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
-    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
     let mut inputs_iter = inputs.into_iter().peekable();
     let mut folded = inputs_iter.next().unwrap();
     let mut commits = vec![];
+    let mut pow_witnesses = vec![];
     let mut data = vec![];
 
-    while folded.len() > config.blowup() {
+    while folded.len() > config.blowup() * config.final_poly_len() {
         let leaves = RowMajorMatrix::new(folded, 2);
         let (commit, prover_data) = config.mmcs.commit_matrix(leaves);
         challenger.observe(commit.clone());
+        if config.commit_proof_of_work_bits > 0 {
+            pow_witnesses.push(challenger.grind(config.commit_proof_of_work_bits));
+        }
 
         let beta: Challenge = challenger.sample_ext_element();
         // We passed ownership of `current` to the MMCS, so get a reference to it
@@ -105,16 +124,46 @@ where
         }
     }
 
-    // We should be left with `blowup` evaluations of a constant polynomial.
-    assert_eq!(folded.len(), config.blowup());
-    let final_poly = folded[0];
-    for x in folded {
-        assert_eq!(x, final_poly);
+    // We should be left with `blowup * final_poly_len` evaluations, in CFFT order over the
+    // standard domain of that size, of a polynomial with `final_poly_len` circle coefficients.
+    assert_eq!(folded.len(), config.blowup() * config.final_poly_len());
+    let mut final_poly = debug_span!("interpolate final poly").in_scope(|| {
+        let log_final_height = log2_strict_usize(folded.len());
+        // Interpolate each base field coordinate of the evaluations separately.
+        let flat = folded
+            .iter()
+            .flat_map(|x| x.as_base_slice().iter().copied())
+            .collect();
+        let coeffs = CircleEvaluations::from_cfft_order(
+            CircleDomain::standard(log_final_height),
+            RowMajorMatrix::new(flat, Challenge::D),
+        )
+        .interpolate();
+        coeffs
+            .rows()
+            .map(|row| Challenge::from_base_slice(&row.collect_vec()))
+            .collect_vec()
+    });
+
+    // The evaluation domain is "blown-up" relative to the size of `final_poly`, so all
+    // coefficients after the first `final_poly_len` should be zero.
+    debug_assert!(
+        final_poly
+            .iter()
+            .skip(config.final_poly_len())
+            .all(|x| x.is_zero()),
+        "All coefficients beyond final_poly_len must be zero"
+    );
+    final_poly.truncate(config.final_poly_len());
+
+    // Observe all coefficients of the final polynomial.
+    for &x in &final_poly {
+        challenger.observe_ext_element(x);
     }
-    challenger.observe_ext_element(final_poly);
 
     CommitPhaseResult {
         commits,
+        pow_witnesses,
         data,
         final_poly,
     }
@@ -4,12 +4,15 @@ use alloc::vec::Vec;
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
 use p3_commit::Mmcs;
+use p3_field::extension::ComplexExtendable;
 use p3_field::{ExtensionField, Field};
 use p3_fri::verifier::FriError;
 use p3_fri::{FriConfig, FriGenericConfig};
 use p3_matrix::Dimensions;
 
-use crate::{CircleCommitPhaseProofStep, CircleFriProof};
+use crate::{
+    cfft_permute_index, circle_basis, CircleCommitPhaseProofStep, CircleDomain, CircleFriProof,
+};
 
 pub fn verify<G, Val, Challenge, M, Challenger>(
     g: &G,
@@ -19,23 +22,50 @@ pub fn verify<G, Val, Challenge, M, Challenger>(
     open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
 ) -> Result<(), FriError<M::Error, G::InputError>>
 where
-    Val: Field,
+    Val: ComplexExtendable,
     Challenge: ExtensionField<Val>,
     M: Mmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
+    assert_eq!(
+        config.log_folding_arity, 1,
+        "circle FRI only supports folding in pairs"
+    );
+
+    let num_commit_pow_witnesses = if config.commit_proof_of_work_bits > 0 {
+        proof.commit_phase_commits.len()
+    } else {
+        0
+    };
+    if proof.commit_pow_witnesses.len() != num_commit_pow_witnesses {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    let mut commit_pow_witnesses = proof.commit_pow_witnesses.iter();
     let betas: Vec<Challenge> = proof
         .commit_phase_commits
         .iter()
         .map(|comm| {
             challenger.observe(comm.clone());
-            challenger.sample_ext_element()
+            if let Some(&witness) = commit_pow_witnesses.next() {
+                if !challenger.check_witness(config.commit_proof_of_work_bits, witness) {
+                    return Err(FriError::InvalidCommitPhasePowWitness);
+                }
+            }
+            Ok(challenger.sample_ext_element())
         })
-        .collect();
-    challenger.observe_ext_element(proof.final_poly);
+        .collect::<Result<_, _>>()?;
 
-    if proof.query_proofs.len() != config.num_queries {
+    // Observe all coefficients of the final polynomial.
+    proof
+        .final_poly
+        .iter()
+        .for_each(|x| challenger.observe_ext_element(*x));
+
+    if proof.query_proofs.len() != config.num_queries
+        || proof.final_poly.len() != config.final_poly_len()
+    {
         return Err(FriError::InvalidProofShape);
     }
 
@@ -44,7 +74,8 @@ where
         return Err(FriError::InvalidPowWitness);
     }
 
-    let log_max_height = proof.commit_phase_commits.len() + config.log_blowup;
+    let log_final_height = config.log_blowup + config.log_final_poly_len;
+    let log_max_height = proof.commit_phase_commits.len() + log_final_height;
 
     for qp in &proof.query_proofs {
         let index = challenger.sample_bits(log_max_height + g.extra_query_index_bits());
@@ -68,7 +99,18 @@ where
             log_max_height,
         )?;
 
-        if folded_eval != proof.final_poly {
+        // The final polynomial's evaluations are in CFFT order over the standard domain of size
+        // `blowup * final_poly_len`, so we find the point of the queried one and evaluate the
+        // polynomial's circle coefficients there.
+        let final_poly_index =
+            (index >> g.extra_query_index_bits()) >> (log_max_height - log_final_height);
+        let point = CircleDomain::<Val>::standard(log_final_height)
+            .nth_point(cfft_permute_index(final_poly_index, log_final_height));
+        let eval: Challenge = izip!(&proof.final_poly, circle_basis(point, log_final_height))
+            .map(|(&coeff, basis)| coeff * basis)
+            .sum();
+
+        if folded_eval != eval {
             return Err(FriError::FinalPolyMismatch);
         }
     }
@@ -129,7 +171,11 @@ where
         folded_eval = g.fold_row(index, log_folded_height, beta, evals.into_iter());
     }
 
-    debug_assert!(index < config.blowup(), "index was {}", index);
+    debug_assert!(
+        index < config.blowup() * config.final_poly_len(),
+        "index was {}",
+        index
+    );
     debug_assert!(
         ro_iter.next().is_none(),
         "verifier reduced_openings were not in descending order?"
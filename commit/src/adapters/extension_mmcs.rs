@@ -6,7 +6,7 @@ use p3_field::{ExtensionField, Field};
 use p3_matrix::extension::FlatMatrixView;
 use p3_matrix::{Dimensions, Matrix};
 
-use crate::Mmcs;
+use crate::{Mmcs, MmcsHashCount, MultiOpeningMmcs};
 
 #[derive(Clone, Debug)]
 pub struct ExtensionMmcs<F, EF, InnerMmcs> {
@@ -34,6 +34,10 @@ where
     type Proof = InnerMmcs::Proof;
     type Error = InnerMmcs::Error;
 
+    fn fingerprint(&self) -> Vec<u8> {
+        self.inner.fingerprint()
+    }
+
     fn commit<M: Matrix<EF>>(&self, inputs: Vec<M>) -> (Self::Commitment, Self::ProverData<M>) {
         self.inner
             .commit(inputs.into_iter().map(FlatMatrixView::new).collect())
@@ -45,10 +49,7 @@ where
         prover_data: &Self::ProverData<M>,
     ) -> (Vec<Vec<EF>>, Self::Proof) {
         let (opened_base_values, proof) = self.inner.open_batch(index, prover_data);
-        let opened_ext_values = opened_base_values
-            .into_iter()
-            .map(|row| row.chunks(EF::D).map(EF::from_base_slice).collect())
-            .collect();
+        let opened_ext_values = opened_base_values.into_iter().map(to_ext_row).collect();
         (opened_ext_values, proof)
     }
 
@@ -68,23 +69,93 @@ where
         opened_values: &[Vec<EF>],
         proof: &Self::Proof,
     ) -> Result<(), Self::Error> {
-        let opened_base_values: Vec<Vec<F>> = opened_values
-            .iter()
-            .map(|row| {
-                row.iter()
-                    .flat_map(|el| el.as_base_slice())
-                    .copied()
-                    .collect()
-            })
+        let opened_base_values: Vec<Vec<F>> =
+            opened_values.iter().map(|row| to_base_row(row)).collect();
+        self.inner.verify_batch(
+            commit,
+            &base_dimensions::<F, EF>(dimensions),
+            index,
+            &opened_base_values,
+            proof,
+        )
+    }
+}
+
+impl<F, EF, InnerMmcs> MultiOpeningMmcs<EF> for ExtensionMmcs<F, EF, InnerMmcs>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    InnerMmcs: MultiOpeningMmcs<F>,
+{
+    type MultiProof = InnerMmcs::MultiProof;
+
+    fn open_batch_many<M: Matrix<EF>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<EF>>>, Self::MultiProof) {
+        let (opened_base_values, proof) = self.inner.open_batch_many(indices, prover_data);
+        let opened_ext_values = opened_base_values
+            .into_iter()
+            .map(|rows| rows.into_iter().map(to_ext_row).collect())
             .collect();
-        let base_dimensions = dimensions
+        (opened_ext_values, proof)
+    }
+
+    fn verify_batch_many(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<EF>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        let opened_base_values: Vec<Vec<Vec<F>>> = opened_values
             .iter()
-            .map(|dim| Dimensions {
-                width: dim.width * EF::D,
-                height: dim.height,
-            })
-            .collect::<Vec<_>>();
-        self.inner
-            .verify_batch(commit, &base_dimensions, index, &opened_base_values, proof)
+            .map(|rows| rows.iter().map(|row| to_base_row(row)).collect())
+            .collect();
+        self.inner.verify_batch_many(
+            commit,
+            &base_dimensions::<F, EF>(dimensions),
+            indices,
+            &opened_base_values,
+            proof,
+        )
     }
+
+    fn verify_batch_many_hash_count(num_indices: usize, proof: &Self::MultiProof) -> usize {
+        InnerMmcs::verify_batch_many_hash_count(num_indices, proof)
+    }
+}
+
+impl<F, EF, InnerMmcs> MmcsHashCount<EF> for ExtensionMmcs<F, EF, InnerMmcs>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    InnerMmcs: MmcsHashCount<F>,
+{
+    fn verify_batch_hash_count(proof: &Self::Proof) -> usize {
+        InnerMmcs::verify_batch_hash_count(proof)
+    }
+}
+
+fn to_ext_row<F: Field, EF: ExtensionField<F>>(row: Vec<F>) -> Vec<EF> {
+    row.chunks(EF::D).map(EF::from_base_slice).collect()
+}
+
+fn to_base_row<F: Field, EF: ExtensionField<F>>(row: &[EF]) -> Vec<F> {
+    row.iter()
+        .flat_map(|el| el.as_base_slice())
+        .copied()
+        .collect()
+}
+
+fn base_dimensions<F: Field, EF: ExtensionField<F>>(dimensions: &[Dimensions]) -> Vec<Dimensions> {
+    dimensions
+        .iter()
+        .map(|dim| Dimensions {
+            width: dim.width * EF::D,
+            height: dim.height,
+        })
+        .collect()
 }
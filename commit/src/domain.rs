@@ -54,6 +54,23 @@ pub trait PolynomialSpace: Copy {
     fn selectors_on_coset(&self, coset: Self) -> LagrangeSelectors<Vec<Self::Val>>;
 }
 
+/// A `PolynomialSpace` which can evaluate periodic columns, i.e. columns whose values repeat with a
+/// power of two period, for AIRs with periodic columns.
+pub trait PeriodicPolynomialSpace: PolynomialSpace {
+    /// Evaluate at `point` the polynomial of degree less than this domain's size which takes the
+    /// values of `column`, repeated cyclically, on this domain's points in order.
+    ///
+    /// The length of `column` must be a power of two no larger than this domain's size.
+    fn periodic_column_at_point<Ext: ExtensionField<Self::Val>>(
+        &self,
+        column: &[Self::Val],
+        point: Ext,
+    ) -> Ext;
+
+    /// Like `periodic_column_at_point`, but evaluates at every point of `coset`, in order.
+    fn periodic_column_on_coset(&self, column: &[Self::Val], coset: Self) -> Vec<Self::Val>;
+}
+
 #[derive(Copy, Clone, Debug)]
 pub struct TwoAdicMultiplicativeCoset<Val: TwoAdicField> {
     pub log_n: usize,
@@ -169,3 +186,54 @@ impl<Val: TwoAdicField> PolynomialSpace for TwoAdicMultiplicativeCoset<Val> {
         }
     }
 }
+
+impl<Val: TwoAdicField> PeriodicPolynomialSpace for TwoAdicMultiplicativeCoset<Val> {
+    // A column with period p is given by Q((x / shift)^(n / p)), where Q interpolates the column
+    // over the subgroup of order p. Periods are expected to be short, so Q is handled naively.
+    fn periodic_column_at_point<Ext: ExtensionField<Val>>(
+        &self,
+        column: &[Val],
+        point: Ext,
+    ) -> Ext {
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_n);
+        let y = (point * self.shift.inverse()).exp_power_of_2(self.log_n - log_period);
+        subgroup_coeffs(column)
+            .iter()
+            .rfold(Ext::ZERO, |acc, &coeff| acc * y + coeff)
+    }
+
+    fn periodic_column_on_coset(&self, column: &[Val], coset: Self) -> Vec<Val> {
+        let log_period = log2_strict_usize(column.len());
+        assert!(log_period <= self.log_n && self.log_n <= coset.log_n);
+        // The powers (x / shift)^(n / p) of the coset's points cycle through a smaller coset.
+        let log_stride = self.log_n - log_period;
+        let ys = cyclic_subgroup_coset_known_order(
+            Val::two_adic_generator(coset.log_n - log_stride),
+            (coset.shift * self.shift.inverse()).exp_power_of_2(log_stride),
+            1 << (coset.log_n - log_stride),
+        );
+        let coeffs = subgroup_coeffs(column);
+        let evals = ys
+            .map(|y| {
+                coeffs
+                    .iter()
+                    .rfold(Val::ZERO, |acc, &coeff| acc * y + coeff)
+            })
+            .collect_vec();
+        evals.into_iter().cycle().take(1 << coset.log_n).collect()
+    }
+}
+
+/// The coefficients of the polynomial of degree less than `values.len()` which takes `values` on
+/// the subgroup of that order, in order, computed with a naive inverse DFT.
+fn subgroup_coeffs<F: TwoAdicField>(values: &[F]) -> Vec<F> {
+    let n = values.len();
+    let n_inv = F::from_canonical_usize(n).inverse();
+    F::two_adic_generator(log2_strict_usize(n))
+        .inverse()
+        .powers()
+        .take(n)
+        .map(|w| values.iter().rfold(F::ZERO, |acc, &v| acc * w + v) * n_inv)
+        .collect()
+}
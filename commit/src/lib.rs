@@ -7,6 +7,7 @@ extern crate alloc;
 mod adapters;
 mod domain;
 mod mmcs;
+mod multilinear_pcs;
 mod pcs;
 
 #[cfg(any(test, feature = "test-utils"))]
@@ -15,4 +16,5 @@ pub mod testing;
 pub use adapters::*;
 pub use domain::*;
 pub use mmcs::*;
+pub use multilinear_pcs::*;
 pub use pcs::*;
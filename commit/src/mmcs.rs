@@ -22,6 +22,13 @@ pub trait Mmcs<T: Send + Sync>: Clone {
     type Proof: Clone + Serialize + DeserializeOwned;
     type Error: Debug;
 
+    /// A summary of the parameters of this MMCS which affect the shape of its commitments and
+    /// proofs, such as a Merkle tree's arity, for use in a PCS's `fingerprint`. MMCSs without such
+    /// parameters may leave this empty.
+    fn fingerprint(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
     fn commit<M: Matrix<T>>(&self, inputs: Vec<M>) -> (Self::Commitment, Self::ProverData<M>);
 
     fn commit_matrix<M: Matrix<T>>(&self, input: M) -> (Self::Commitment, Self::ProverData<M>) {
@@ -67,7 +74,7 @@ pub trait Mmcs<T: Send + Sync>: Clone {
     /// `index` is the row index we're opening for each matrix, following the same
     /// semantics as `open_batch`.
     /// `dimensions` is a slice whose ith element is the dimensions of the matrix being opened
-    /// in the ith opening
+    /// in the ith opening. Openings which don't match these dimensions are rejected.
     fn verify_batch(
         &self,
         commit: &Self::Commitment,
@@ -77,3 +84,42 @@ pub trait Mmcs<T: Send + Sync>: Clone {
         proof: &Self::Proof,
     ) -> Result<(), Self::Error>;
 }
+
+/// An MMCS which can count the hashing its verifier does, for proof size reports.
+pub trait MmcsHashCount<T: Send + Sync>: Mmcs<T> {
+    /// The number of hash and compression function calls `verify_batch` makes to check `proof`,
+    /// when all opened matrices have the same height. This is a measure of the verifier's cost.
+    fn verify_batch_hash_count(proof: &Self::Proof) -> usize;
+}
+
+/// An MMCS which can open rows at several indices with a single proof, sharing the parts of their
+/// proofs that coincide.
+pub trait MultiOpeningMmcs<T: Send + Sync>: Mmcs<T> {
+    /// A proof of openings at several indices at once, which shares the parts of their proofs
+    /// that coincide, such as the upper nodes of Merkle paths.
+    type MultiProof: Clone + Serialize + DeserializeOwned;
+
+    /// Opens a batch of rows at each of several indices, with the semantics of `open_batch`.
+    /// Returns the openings at each index, in order, and a single proof for all of them.
+    fn open_batch_many<M: Matrix<T>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<T>>>, Self::MultiProof);
+
+    /// Verify the openings at several indices made by `open_batch_many`, where the `i`th element
+    /// of `opened_values` is the openings at `indices[i]`.
+    fn verify_batch_many(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<T>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error>;
+
+    /// The number of hash and compression function calls `verify_batch_many` makes to check
+    /// `proof` of openings at `num_indices` indices, which may repeat, when all opened matrices
+    /// have the same height.
+    fn verify_batch_many_hash_count(num_indices: usize, proof: &Self::MultiProof) -> usize;
+}
@@ -0,0 +1,63 @@
+//! Traits for multilinear polynomial commitment schemes.
+
+use alloc::vec::Vec;
+use core::fmt::Debug;
+
+use p3_field::{ExtensionField, Field};
+use p3_matrix::dense::RowMajorMatrix;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+
+/// A (not necessarily hiding) commitment scheme for batches of multilinear polynomials, which are
+/// committed to by their evaluations over the boolean hypercube and opened at arbitrary points.
+///
+/// Unlike `Pcs`, there's no `PolynomialSpace`: a polynomial in `n` variables is given by its `2^n`
+/// evaluations on `{0, 1}^n`, and is opened at points in `Challenge^n`.
+pub trait MultilinearPcs<Challenge, Challenger>
+where
+    Challenge: ExtensionField<Self::Val>,
+{
+    type Val: Field;
+
+    /// The commitment that's sent to the verifier.
+    type Commitment: Clone + Serialize + DeserializeOwned;
+
+    /// Data that the prover stores for committed polynomials, to help the prover with opening.
+    type ProverData;
+
+    /// The opening argument.
+    type Proof: Clone + Serialize + DeserializeOwned;
+
+    type Error: Debug;
+
+    /// Commits to the columns of `evaluations`, each the evaluations of a multilinear polynomial
+    /// in `log2(evaluations.height())` variables, where bit `i` of a row's index is the value of
+    /// the `i`th variable.
+    fn commit(
+        &self,
+        evaluations: RowMajorMatrix<Self::Val>,
+    ) -> (Self::Commitment, Self::ProverData);
+
+    /// Opens every committed polynomial at each of `points`, returning for each point the values
+    /// of the polynomials there, in the order of their columns.
+    fn open(
+        &self,
+        prover_data: &Self::ProverData,
+        points: &[Vec<Challenge>],
+        challenger: &mut Challenger,
+    ) -> (Vec<Vec<Challenge>>, Self::Proof);
+
+    fn verify(
+        &self,
+        commit: &Self::Commitment,
+        // For each point:
+        claims: &[(
+            // the point,
+            Vec<Challenge>,
+            // the values of the committed polynomials at the point.
+            Vec<Challenge>,
+        )],
+        proof: &Self::Proof,
+        challenger: &mut Challenger,
+    ) -> Result<(), Self::Error>;
+}
@@ -32,6 +32,22 @@ where
 
     type Error: Debug;
 
+    /// Whether this PCS is hiding, so that commitments and opening arguments reveal nothing beyond
+    /// the opened values.
+    ///
+    /// Provers such as `p3_uni_stark::prove` use this to decide whether to run in zero-knowledge
+    /// mode, in which they commit to their traces with `commit_randomized`, commit to their
+    /// preprocessed traces with `commit_public`, commit to their quotients with `commit_quotient`,
+    /// and add a polynomial from `commit_randomizer`.
+    const ZK: bool = false;
+
+    /// A summary of the parameters of this PCS which affect its proofs, such as its rate and number
+    /// of queries, so that proofs made under one configuration can be told apart from proofs made
+    /// under another. PCSs without such parameters may leave this empty.
+    fn fingerprint(&self) -> Vec<u8> {
+        Vec::new()
+    }
+
     /// This should return a coset domain (s.t. Domain::next_point returns Some)
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain;
 
@@ -41,6 +57,61 @@ where
         evaluations: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>)>,
     ) -> (Self::Commitment, Self::ProverData);
 
+    /// Commit to randomized versions of the given polynomials, for use in zero-knowledge mode.
+    ///
+    /// Each polynomial is given by its evaluations over a domain `H`. A hiding PCS replaces it with a
+    /// random polynomial of degree less than `2 |H|` which agrees with it on `H`, so that openings
+    /// outside of `H` reveal nothing about it; such commitments are verified against domains of size
+    /// `2 |H|`. Other PCSs commit to the polynomials unchanged.
+    #[allow(clippy::type_complexity)]
+    fn commit_randomized(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        self.commit(evaluations)
+    }
+
+    /// Commit to public polynomials, such as preprocessed traces, so that they can be opened
+    /// alongside those committed with `commit_randomized`.
+    ///
+    /// A hiding PCS commits to each polynomial unchanged, but over the domain of size `2 |H|` which
+    /// `commit_randomized` would have used, so the committed polynomials depend on the evaluations
+    /// alone. Other PCSs commit to the polynomials as `commit` does.
+    #[allow(clippy::type_complexity)]
+    fn commit_public(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        self.commit(evaluations)
+    }
+
+    /// Commit to the chunks of a quotient polynomial, given by their evaluations over the domains
+    /// produced by `PolynomialSpace::split_domains`.
+    ///
+    /// A hiding PCS adds a random multiple of its domain's vanishing polynomial to each chunk, with
+    /// the random polynomials chosen so that recombining the chunks still yields the quotient. The
+    /// randomized chunks have degree less than twice their domain's size, and are verified against
+    /// domains of that size. Other PCSs commit to the chunks unchanged.
+    #[allow(clippy::type_complexity)]
+    fn commit_quotient(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val<Self::Domain>>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        self.commit(evaluations)
+    }
+
+    /// Commit to a random extension field polynomial over `domain`, given as `Challenge::D` base
+    /// field columns, whose inclusion in the opening argument masks the other committed
+    /// polynomials.
+    ///
+    /// Returns `None` if this PCS is not hiding.
+    fn commit_randomizer(
+        &self,
+        _domain: Self::Domain,
+    ) -> Option<(Self::Commitment, Self::ProverData)> {
+        None
+    }
+
     fn get_evaluations_on_domain<'a>(
         &self,
         prover_data: &'a Self::ProverData,
@@ -62,6 +133,12 @@ where
         challenger: &mut Challenger,
     ) -> (OpenedValues<Challenge>, Self::Proof);
 
+    /// Verify the claimed evaluations of each round's matrices against the round's commitment.
+    ///
+    /// The width of each matrix is read from its claimed values. A matrix opened at no points has
+    /// none, so implementations take its width from the rows opened in `proof` instead, and don't
+    /// check it against the width that was committed. Its values never enter the opening argument,
+    /// but callers which rely on the shape of every committed matrix should open each at a point.
     #[allow(clippy::type_complexity)]
     fn verify(
         &self,
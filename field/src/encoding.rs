@@ -0,0 +1,257 @@
+use alloc::vec::Vec;
+use core::mem::size_of;
+
+use crate::extension::BinomialExtensionField;
+
+/// An error in decoding bytes with `CanonicalEncoding`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum DecodingError {
+    /// The input ended before the value was complete.
+    UnexpectedEnd,
+    /// A field element, tag or length was outside its canonical range.
+    NonCanonical,
+    /// Input remained after the value was decoded.
+    TrailingBytes,
+}
+
+/// A canonical binary encoding, in which every value has exactly one encoding, and decoding rejects
+/// anything which isn't the encoding of some value.
+///
+/// The encoding is as follows:
+/// - Integers are little-endian and fixed-width, with `usize` encoded as a `u64`, and `bool` as a
+///   byte which is either 0 or 1.
+/// - Prime field elements are encoded as their canonical representative, in a `u32` for fields of
+///   at most 32 bits, in a `u64` for fields of at most 64 bits, and in little-endian bytes
+///   otherwise. Extension field elements are encoded as their coefficients over the base field.
+/// - Arrays and tuples are encoded as their elements, in order, and vectors likewise, after their
+///   length as a `u32`.
+/// - `Option`s are encoded as a byte, 0 for `None` and 1 for `Some`, followed by the value if any.
+///
+/// Structs are encoded as their fields in order of declaration.
+pub trait CanonicalEncoding: Sized {
+    /// Appends the encoding of `self` to `out`.
+    fn encode(&self, out: &mut Vec<u8>);
+
+    /// Decodes a value from the start of `input`, advancing it past the value's encoding.
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError>;
+
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        self.encode(&mut out);
+        out
+    }
+
+    /// Decodes a value from `bytes`, which must contain nothing but its encoding.
+    fn from_bytes(mut bytes: &[u8]) -> Result<Self, DecodingError> {
+        let value = Self::decode(&mut bytes)?;
+        if bytes.is_empty() {
+            Ok(value)
+        } else {
+            Err(DecodingError::TrailingBytes)
+        }
+    }
+}
+
+/// Splits off the first `len` bytes of `input`.
+pub fn take_bytes<'a>(input: &mut &'a [u8], len: usize) -> Result<&'a [u8], DecodingError> {
+    if input.len() < len {
+        return Err(DecodingError::UnexpectedEnd);
+    }
+    let (bytes, rest) = input.split_at(len);
+    *input = rest;
+    Ok(bytes)
+}
+
+macro_rules! impl_canonical_encoding_for_uint {
+    ($($t:ty),*) => {
+        $(
+            impl CanonicalEncoding for $t {
+                fn encode(&self, out: &mut Vec<u8>) {
+                    out.extend_from_slice(&self.to_le_bytes());
+                }
+
+                fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+                    let bytes = take_bytes(input, size_of::<$t>())?;
+                    Ok(<$t>::from_le_bytes(bytes.try_into().unwrap()))
+                }
+            }
+        )*
+    };
+}
+
+impl_canonical_encoding_for_uint!(u8, u16, u32, u64);
+
+impl CanonicalEncoding for usize {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u64).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        u64::decode(input)?
+            .try_into()
+            .map_err(|_| DecodingError::NonCanonical)
+    }
+}
+
+impl CanonicalEncoding for bool {
+    fn encode(&self, out: &mut Vec<u8>) {
+        (*self as u8).encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        match u8::decode(input)? {
+            0 => Ok(false),
+            1 => Ok(true),
+            _ => Err(DecodingError::NonCanonical),
+        }
+    }
+}
+
+impl CanonicalEncoding for () {
+    fn encode(&self, _out: &mut Vec<u8>) {}
+
+    fn decode(_input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(())
+    }
+}
+
+impl<T: CanonicalEncoding, const N: usize> CanonicalEncoding for [T; N] {
+    fn encode(&self, out: &mut Vec<u8>) {
+        for x in self {
+            x.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        let values = (0..N)
+            .map(|_| T::decode(input))
+            .collect::<Result<Vec<_>, _>>()?;
+        Ok(values.try_into().unwrap_or_else(|_| unreachable!()))
+    }
+}
+
+impl<T: CanonicalEncoding> CanonicalEncoding for Vec<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        u32::try_from(self.len())
+            .expect("vector too long to encode")
+            .encode(out);
+        for x in self {
+            x.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        let len = u32::decode(input)? as usize;
+        // Don't trust the length to allocate more than the input could possibly hold.
+        let mut values = Vec::with_capacity(len.min(input.len()));
+        for _ in 0..len {
+            values.push(T::decode(input)?);
+        }
+        Ok(values)
+    }
+}
+
+impl<T: CanonicalEncoding> CanonicalEncoding for Option<T> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.is_some().encode(out);
+        if let Some(x) = self {
+            x.encode(out);
+        }
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        if bool::decode(input)? {
+            T::decode(input).map(Some)
+        } else {
+            Ok(None)
+        }
+    }
+}
+
+impl<A: CanonicalEncoding, B: CanonicalEncoding> CanonicalEncoding for (A, B) {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.0.encode(out);
+        self.1.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok((A::decode(input)?, B::decode(input)?))
+    }
+}
+
+impl<F: CanonicalEncoding, const D: usize> CanonicalEncoding for BinomialExtensionField<F, D> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.value.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            value: <[F; D]>::decode(input)?,
+        })
+    }
+}
+
+/// Decodes the canonical representative of an element of a prime field of at most 32 bits, which
+/// must be less than the field's `order`.
+pub fn decode_canonical_u32(input: &mut &[u8], order: u32) -> Result<u32, DecodingError> {
+    let value = u32::decode(input)?;
+    if value < order {
+        Ok(value)
+    } else {
+        Err(DecodingError::NonCanonical)
+    }
+}
+
+/// Like `decode_canonical_u32`, for prime fields of more than 32 bits.
+pub fn decode_canonical_u64(input: &mut &[u8], order: u64) -> Result<u64, DecodingError> {
+    let value = u64::decode(input)?;
+    if value < order {
+        Ok(value)
+    } else {
+        Err(DecodingError::NonCanonical)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use alloc::vec;
+
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let value = (vec![Some([1u32, 2]), None], (true, 3usize));
+        let bytes = value.to_bytes();
+        assert_eq!(bytes.len(), 4 + (1 + 8) + 1 + 1 + 8);
+        assert_eq!(
+            <(Vec<Option<[u32; 2]>>, (bool, usize))>::from_bytes(&bytes),
+            Ok(value)
+        );
+    }
+
+    #[test]
+    fn test_strict_decoding() {
+        assert_eq!(
+            u32::from_bytes(&[1, 2, 3]),
+            Err(DecodingError::UnexpectedEnd)
+        );
+        assert_eq!(
+            u32::from_bytes(&[1, 2, 3, 4, 5]),
+            Err(DecodingError::TrailingBytes)
+        );
+        assert_eq!(bool::from_bytes(&[2]), Err(DecodingError::NonCanonical));
+        assert_eq!(
+            Option::<u8>::from_bytes(&[2, 0]),
+            Err(DecodingError::NonCanonical)
+        );
+        // A huge length prefix fails on the missing elements rather than allocating.
+        assert_eq!(
+            Vec::<u64>::from_bytes(&[255, 255, 255, 255, 0]),
+            Err(DecodingError::UnexpectedEnd)
+        );
+        assert_eq!(
+            decode_canonical_u32(&mut &7u32.to_le_bytes()[..], 7),
+            Err(DecodingError::NonCanonical)
+        );
+    }
+}
@@ -6,6 +6,7 @@ use core::ops::Mul;
 
 use num_bigint::BigUint;
 use p3_maybe_rayon::prelude::{IntoParallelRefMutIterator, ParallelIterator};
+use p3_util::log2_f64;
 
 use crate::field::Field;
 use crate::{FieldAlgebra, PackedValue, PrimeField, PrimeField32, TwoAdicField};
@@ -38,6 +39,17 @@ pub fn cyclic_subgroup_coset_known_order<F: Field>(
     generator.shifted_powers(shift).take(order)
 }
 
+/// Computes `log_2` of the order of `F`, which may not fit in a `u64`.
+#[must_use]
+pub fn log2_field_order<F: Field>() -> f64 {
+    let order = F::order()
+        .to_u64_digits()
+        .iter()
+        .rev()
+        .fold(0.0, |acc, &digit| acc * (1u128 << 64) as f64 + digit as f64);
+    log2_f64(order)
+}
+
 #[must_use]
 pub fn add_vecs<F: Field>(v: Vec<F>, w: Vec<F>) -> Vec<F> {
     assert_eq!(v.len(), w.len());
@@ -1,16 +1,24 @@
 use alloc::vec::Vec;
 use core::fmt::Debug;
 
-use p3_field::Field;
+use p3_field::{CanonicalEncoding, Field};
 use p3_matrix::Matrix;
 
 #[derive(Debug)]
 pub struct FriConfig<M> {
     pub log_blowup: usize,
-    // TODO: This parameter and FRI early stopping are not yet implemented in `CirclePcs`.
     pub log_final_poly_len: usize,
+    /// The log of the maximum number of evaluations folded into one in each commit phase round.
+    /// Each round commits to its evaluations in rows of this many, so that a query opens them all
+    /// with a single Merkle proof. Rounds fold fewer when needed to stop at each input's height
+    /// and at the final polynomial's. `CirclePcs` only supports 1, i.e. folding in pairs.
+    pub log_folding_arity: usize,
     pub num_queries: usize,
     pub proof_of_work_bits: usize,
+    /// The number of proof-of-work bits the prover grinds for in each commit phase round, after
+    /// committing to the round's evaluations and before sampling its folding challenge. Zero
+    /// disables this grinding, and proofs then carry no witnesses for it.
+    pub commit_proof_of_work_bits: usize,
     pub mmcs: M,
 }
 
@@ -23,14 +31,44 @@ impl<M> FriConfig<M> {
         1 << self.log_final_poly_len
     }
 
+    /// The log of the folding arity of the commit phase round which starts with evaluations of log
+    /// height `log_height`, where `log_next_input_height` is the log height of the next input to
+    /// be rolled in, if any. This is the largest arity allowed which doesn't fold past that input's
+    /// height, or past the final polynomial's evaluations.
+    pub fn round_log_arity(
+        &self,
+        log_height: usize,
+        log_next_input_height: Option<usize>,
+    ) -> usize {
+        let log_stop_height =
+            (self.log_blowup + self.log_final_poly_len).max(log_next_input_height.unwrap_or(0));
+        self.log_folding_arity.min(log_height - log_stop_height)
+    }
+
     /// Returns the soundness bits of this FRI instance based on the
     /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture.
     ///
-    /// Certain users may instead want to look at proven soundness, a more complex calculation which
-    /// isn't currently supported by this crate.
+    /// Certain users may instead want to look at proven soundness, a more complex calculation
+    /// given by `proven_soundness`.
     pub fn conjectured_soundness_bits(&self) -> usize {
         self.log_blowup * self.num_queries + self.proof_of_work_bits
     }
+
+    /// The canonical encoding of the parameters which affect the shape and validity of proofs, for
+    /// use in a PCS's `fingerprint`.
+    pub fn fingerprint(&self) -> Vec<u8> {
+        (
+            (self.log_blowup, self.log_final_poly_len),
+            (
+                self.log_folding_arity,
+                (
+                    self.num_queries,
+                    (self.proof_of_work_bits, self.commit_proof_of_work_bits),
+                ),
+            ),
+        )
+            .to_bytes()
+    }
 }
 
 /// Whereas `FriConfig` encompasses parameters the end user can set, `FriGenericConfig` is
@@ -44,8 +82,8 @@ pub trait FriGenericConfig<F: Field> {
     fn extra_query_index_bits(&self) -> usize;
 
     /// Fold a row, returning a single column.
-    /// Right now the input row will always be 2 columns wide,
-    /// but we may support higher folding arity in the future.
+    /// The input row is always 2 columns wide; FRI folds rows of higher arity by folding pairs
+    /// repeatedly, as set by `FriConfig::log_folding_arity`.
     fn fold_row(
         &self,
         index: usize,
@@ -64,8 +102,10 @@ pub fn create_test_fri_config<Mmcs>(mmcs: Mmcs) -> FriConfig<Mmcs> {
     FriConfig {
         log_blowup: 1,
         log_final_poly_len: 0,
+        log_folding_arity: 1,
         num_queries: 2,
         proof_of_work_bits: 1,
+        commit_proof_of_work_bits: 0,
         mmcs,
     }
 }
@@ -76,8 +116,10 @@ pub fn create_benchmark_fri_config<Mmcs>(mmcs: Mmcs) -> FriConfig<Mmcs> {
     FriConfig {
         log_blowup: 1,
         log_final_poly_len: 0,
+        log_folding_arity: 1,
         num_queries: 100,
         proof_of_work_bits: 16,
+        commit_proof_of_work_bits: 0,
         mmcs,
     }
 }
@@ -1,11 +1,15 @@
+use alloc::vec;
 use alloc::vec::Vec;
 use core::cell::RefCell;
 use core::fmt::Debug;
 
+use itertools::Itertools;
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs, TwoAdicMultiplicativeCoset};
+use p3_commit::{MultiOpeningMmcs, OpenedValues, Pcs, PolynomialSpace, TwoAdicMultiplicativeCoset};
 use p3_dft::TwoAdicSubgroupDft;
-use p3_field::{ExtensionField, Field, TwoAdicField};
+use p3_field::{
+    CanonicalEncoding, ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra, TwoAdicField,
+};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::horizontally_truncated::HorizontallyTruncated;
 use p3_matrix::Matrix;
@@ -48,8 +52,8 @@ where
     Val: TwoAdicField,
     Standard: Distribution<Val>,
     Dft: TwoAdicSubgroupDft<Val>,
-    InputMmcs: Mmcs<Val>,
-    FriMmcs: Mmcs<Challenge>,
+    InputMmcs: MultiOpeningMmcs<Val>,
+    FriMmcs: MultiOpeningMmcs<Challenge>,
     Challenge: TwoAdicField + ExtensionField<Val>,
     Challenger:
         FieldChallenger<Val> + CanObserve<FriMmcs::Commitment> + GrindingChallenger<Witness = Val>,
@@ -66,6 +70,17 @@ where
     );
     type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
 
+    const ZK: bool = true;
+
+    fn fingerprint(&self) -> Vec<u8> {
+        let mut fingerprint = <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<
+            Challenge,
+            Challenger,
+        >>::fingerprint(&self.inner);
+        self.num_random_codewords.encode(&mut fingerprint);
+        fingerprint
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         <TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> as Pcs<Challenge, Challenger>>::natural_domain_for_degree(
             &self.inner, degree)
@@ -90,6 +105,140 @@ where
         )
     }
 
+    fn commit_randomized(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let randomized_evaluations = evaluations
+            .into_iter()
+            .map(|(domain, mat)| {
+                // The domain of twice the size with the same shift has `domain` as its even points,
+                // and its odd points form a coset of `domain` on which the vanishing polynomial of
+                // `domain` is the constant -2. Filling the odd rows with random values thus gives
+                // the evaluations of `p + Z_H r` for a uniformly random `r` of degree below |H|.
+                let width = mat.width();
+                let mut randomized = add_random_cols(mat, width, &mut *self.rng.borrow_mut());
+                randomized.width = width;
+                let extended_domain = TwoAdicMultiplicativeCoset {
+                    log_n: domain.log_n + 1,
+                    shift: domain.shift,
+                };
+                (extended_domain, randomized)
+            })
+            .collect();
+        <Self as Pcs<Challenge, Challenger>>::commit(self, randomized_evaluations)
+    }
+
+    fn commit_public(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let extended_evaluations = evaluations
+            .into_iter()
+            .map(|(domain, mat)| {
+                // Evaluate the same polynomials over the domain which `commit_randomized` would
+                // use, instead of filling its odd rows with random values.
+                let extended_domain = TwoAdicMultiplicativeCoset {
+                    log_n: domain.log_n + 1,
+                    shift: domain.shift,
+                };
+                let extended = self.inner.dft.lde_batch(mat, 1).to_row_major_matrix();
+                (extended_domain, extended)
+            })
+            .collect();
+        <Self as Pcs<Challenge, Challenger>>::commit(self, extended_evaluations)
+    }
+
+    fn commit_quotient(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let domains = evaluations.iter().map(|(domain, _)| *domain).collect_vec();
+        // The quotient is recombined as `sum_i q_i(X) prod_{j != i} Z_j(X) / c_i`, where `Z_j` is the
+        // vanishing polynomial of the `j`th chunk domain and `c_i = prod_{j != i} Z_j(s_i)` for the
+        // first point `s_i` of the `i`th chunk domain. Replacing each `q_i` by `q_i + Z_i r_i` adds
+        // `Z(X) sum_i r_i(X) / c_i` to the recombination, where `Z` vanishes on the whole quotient
+        // domain, so we pick random `r_i` subject to `sum_i r_i / c_i = 0`.
+        let inv_cs = domains
+            .iter()
+            .enumerate()
+            .map(|(i, domain_i)| {
+                domains
+                    .iter()
+                    .enumerate()
+                    .filter(|&(j, _)| j != i)
+                    .map(|(_, domain_j)| domain_j.zp_at_point(domain_i.first_point()))
+                    .product::<Val>()
+                    .inverse()
+            })
+            .collect_vec();
+
+        // Sample `r_i` in coefficient form for all but the last chunk, and derive the last one as
+        // `r_last = -c_last sum_i r_i / c_i`.
+        let num_chunks = evaluations.len();
+        let (height, width) = (evaluations[0].1.height(), evaluations[0].1.width());
+        let mut rs: Vec<Vec<Val>> = {
+            let mut rng = self.rng.borrow_mut();
+            (1..num_chunks)
+                .map(|_| (0..height * width).map(|_| rng.gen()).collect())
+                .collect()
+        };
+        let mut r_last = Val::zero_vec(height * width);
+        for (r, &inv_c) in rs.iter().zip(&inv_cs) {
+            let scale = -inv_c / inv_cs[num_chunks - 1];
+            for (acc, &x) in r_last.iter_mut().zip(r) {
+                *acc += scale * x;
+            }
+        }
+        rs.push(r_last);
+
+        let randomized_evaluations = evaluations
+            .into_iter()
+            .zip(rs)
+            .map(|((domain, evals), r)| {
+                // In coefficient form, `q + Z r = (q - r) + s^(-n) X^n r` for the shift `s`.
+                let mut coeffs = self.inner.dft.coset_idft_batch(evals, domain.shift);
+                for (c, &x) in coeffs.values.iter_mut().zip(&r) {
+                    *c -= x;
+                }
+                let shift_pow_n_inv = domain.shift.exp_power_of_2(domain.log_n).inverse();
+                coeffs
+                    .values
+                    .extend(r.into_iter().map(|x| x * shift_pow_n_inv));
+
+                let extended_domain = TwoAdicMultiplicativeCoset {
+                    log_n: domain.log_n + 1,
+                    shift: domain.shift,
+                };
+                let extended_evals = self
+                    .inner
+                    .dft
+                    .coset_dft_batch(coeffs, domain.shift)
+                    .to_row_major_matrix();
+                (extended_domain, extended_evals)
+            })
+            .collect();
+        <Self as Pcs<Challenge, Challenger>>::commit(self, randomized_evaluations)
+    }
+
+    fn commit_randomizer(
+        &self,
+        domain: Self::Domain,
+    ) -> Option<(Self::Commitment, Self::ProverData)> {
+        let width = <Challenge as FieldExtensionAlgebra<Val>>::D;
+        let randomizer = {
+            let mut rng = self.rng.borrow_mut();
+            RowMajorMatrix::new(
+                (0..domain.size() * width).map(|_| rng.gen()).collect(),
+                width,
+            )
+        };
+        Some(<Self as Pcs<Challenge, Challenger>>::commit(
+            self,
+            vec![(domain, randomizer)],
+        ))
+    }
+
     fn get_evaluations_on_domain<'a>(
         &self,
         prover_data: &'a Self::ProverData,
@@ -1,40 +1,236 @@
+use alloc::vec;
 use alloc::vec::Vec;
 
-use p3_commit::Mmcs;
-use p3_field::Field;
+use p3_commit::MultiOpeningMmcs;
+use p3_field::{CanonicalEncoding, DecodingError, Field};
+use p3_util::log2_strict_usize;
 use serde::{Deserialize, Serialize};
 
+use crate::FriConfig;
+
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(bound(
     serialize = "Witness: Serialize, InputProof: Serialize",
     deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
 ))]
-pub struct FriProof<F: Field, M: Mmcs<F>, Witness, InputProof> {
+pub struct FriProof<F: Field, M: MultiOpeningMmcs<F>, Witness, InputProof> {
     pub commit_phase_commits: Vec<M::Commitment>,
-    pub query_proofs: Vec<QueryProof<F, M, InputProof>>,
-    pub final_poly: Vec<F>,
-    pub pow_witness: Witness,
-}
-
-#[derive(Serialize, Deserialize, Clone)]
-#[serde(bound(
-    serialize = "InputProof: Serialize",
-    deserialize = "InputProof: Deserialize<'de>",
-))]
-pub struct QueryProof<F: Field, M: Mmcs<F>, InputProof> {
+    /// The proof-of-work witness of each commit phase round, ground after its commitment, or none
+    /// if `FriConfig::commit_proof_of_work_bits` is zero.
+    pub commit_pow_witnesses: Vec<Witness>,
+    /// The openings of the inputs at every queried location.
     pub input_proof: InputProof,
-    /// For each commit phase commitment, this contains openings of a commit phase codeword at the
-    /// queried location, along with an opening proof.
+    /// For each commit phase commitment, this contains openings of a commit phase codeword at
+    /// every queried location, along with a single opening proof for all of them.
     pub commit_phase_openings: Vec<CommitPhaseProofStep<F, M>>,
+    pub final_poly: Vec<F>,
+    pub pow_witness: Witness,
 }
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct CommitPhaseProofStep<F: Field, M: Mmcs<F>> {
-    /// The opening of the commit phase codeword at the sibling location.
-    // This may change to Vec<FC::Challenge> if the library is generalized to support other FRI
-    // folding arities besides 2, meaning that there can be multiple siblings.
-    pub sibling_value: F,
+pub struct CommitPhaseProofStep<F: Field, M: MultiOpeningMmcs<F>> {
+    /// For each query, the openings of the commit phase codeword at the sibling locations, i.e.
+    /// the rest of the queried row, which has as many evaluations as the round's folding arity.
+    pub sibling_values: Vec<Vec<F>>,
+
+    /// A proof of the queried rows, in which the Merkle paths of different queries share their
+    /// common nodes.
+    pub opening_proof: M::MultiProof,
+}
+
+impl<F: Field, M: MultiOpeningMmcs<F>, Witness, InputProof> FriProof<F, M, Witness, InputProof> {
+    /// The log height of the largest input, as implied by the folding arity of each commit phase
+    /// round, which is one more than the number of siblings the first query opens in that round.
+    /// Returns `None` if there are no queries, or if the first query's openings aren't those of a
+    /// valid schedule of rounds.
+    pub fn log_max_height(&self, config: &FriConfig<M>) -> Option<usize> {
+        if self.commit_phase_openings.len() != self.commit_phase_commits.len() {
+            return None;
+        }
+        let log_folded_height: usize = self
+            .commit_phase_openings
+            .iter()
+            .map(|step| {
+                let arity = step.sibling_values.first()?.len() + 1;
+                arity.is_power_of_two().then(|| log2_strict_usize(arity))
+            })
+            .sum::<Option<usize>>()?;
+        Some(log_folded_height + config.log_blowup + config.log_final_poly_len)
+    }
+}
+
+impl<F, M, Witness, InputProof> CanonicalEncoding for FriProof<F, M, Witness, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::Commitment: CanonicalEncoding,
+    M::MultiProof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+    InputProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.commit_phase_commits.encode(out);
+        self.commit_pow_witnesses.encode(out);
+        self.input_proof.encode(out);
+        self.commit_phase_openings.encode(out);
+        self.final_poly.encode(out);
+        self.pow_witness.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            commit_phase_commits: CanonicalEncoding::decode(input)?,
+            commit_pow_witnesses: CanonicalEncoding::decode(input)?,
+            input_proof: CanonicalEncoding::decode(input)?,
+            commit_phase_openings: CanonicalEncoding::decode(input)?,
+            final_poly: CanonicalEncoding::decode(input)?,
+            pow_witness: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<F, M> CanonicalEncoding for CommitPhaseProofStep<F, M>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::MultiProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.sibling_values.encode(out);
+        self.opening_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            sibling_values: CanonicalEncoding::decode(input)?,
+            opening_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+/// Proof components whose verification makes hash or compression function calls, such as the
+/// input proofs of a FRI proof.
+pub trait VerifierHashCount {
+    /// The number of hash and compression function calls made to verify `self`, as counted by
+    /// `MmcsHashCount::verify_batch_hash_count` or `MultiOpeningMmcs::verify_batch_many_hash_count`.
+    fn verifier_hash_count(&self) -> usize;
+}
+
+impl<T: VerifierHashCount> VerifierHashCount for Vec<T> {
+    fn verifier_hash_count(&self) -> usize {
+        self.iter().map(T::verifier_hash_count).sum()
+    }
+}
+
+/// A breakdown of the size of a FRI proof in its canonical encoding, and of the hashing its
+/// verifier does. See `FriProof::size_report`.
+///
+/// Sizes are in bytes, and summed over all queries. They don't include the length prefixes of
+/// vectors, so they add up to slightly less than `total`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct FriSizeReport {
+    /// The commitments of the commit phase rounds.
+    pub commit_phase_commits: usize,
+    /// The proof-of-work witnesses of the commit phase rounds.
+    pub commit_pow_witnesses: usize,
+    /// The openings of the committed input polynomials.
+    pub input_proofs: usize,
+    /// For each commit phase round, the opened sibling values and their Merkle paths.
+    pub commit_phase_openings: Vec<usize>,
+    pub final_poly: usize,
+    pub pow_witness: usize,
+    /// The size of the whole proof.
+    pub total: usize,
+    /// Hash and compression function calls made in verifying the input proofs.
+    pub input_hashes: usize,
+    /// Hash and compression function calls made in verifying the commit phase openings.
+    pub commit_phase_hashes: usize,
+}
+
+impl FriSizeReport {
+    /// Starts a report on a FRI proof with the given components, to which `add_query` adds each
+    /// query. This is shared by `FriProof` and the proofs of other FRI variants.
+    pub fn new<C: CanonicalEncoding, W: CanonicalEncoding>(
+        commit_phase_commits: &[C],
+        commit_pow_witnesses: &[W],
+        final_poly: &impl CanonicalEncoding,
+        pow_witness: &impl CanonicalEncoding,
+        total: usize,
+    ) -> Self {
+        Self {
+            commit_phase_commits: commit_phase_commits
+                .iter()
+                .map(|commit| commit.to_bytes().len())
+                .sum(),
+            commit_pow_witnesses: commit_pow_witnesses
+                .iter()
+                .map(|witness| witness.to_bytes().len())
+                .sum(),
+            commit_phase_openings: vec![0; commit_phase_commits.len()],
+            final_poly: final_poly.to_bytes().len(),
+            pow_witness: pow_witness.to_bytes().len(),
+            total,
+            ..Self::default()
+        }
+    }
+
+    /// Adds a query, or a batch of queries opened together, with the given input proof, and commit
+    /// phase openings given by their sizes and the number of hash and compression function calls
+    /// made in verifying them.
+    pub fn add_query(
+        &mut self,
+        input_proof: &(impl CanonicalEncoding + VerifierHashCount),
+        commit_phase_openings: impl IntoIterator<Item = (usize, usize)>,
+    ) {
+        self.input_proofs += input_proof.to_bytes().len();
+        self.input_hashes += input_proof.verifier_hash_count();
+        for (total, (size, hashes)) in self
+            .commit_phase_openings
+            .iter_mut()
+            .zip(commit_phase_openings)
+        {
+            *total += size;
+            self.commit_phase_hashes += hashes;
+        }
+    }
+
+    /// The number of hash and compression function calls made in verifying the proof's Merkle
+    /// openings. This excludes the hashing of the Fiat-Shamir transcript.
+    pub fn verifier_hashes(&self) -> usize {
+        self.input_hashes + self.commit_phase_hashes
+    }
+}
 
-    pub opening_proof: M::Proof,
+impl<F, M, Witness, InputProof> FriProof<F, M, Witness, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::Commitment: CanonicalEncoding,
+    M::MultiProof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+    InputProof: CanonicalEncoding + VerifierHashCount,
+{
+    /// Breaks down the size of this proof in its canonical encoding by component, and counts the
+    /// hashing its verifier does.
+    pub fn size_report(&self) -> FriSizeReport {
+        let mut report = FriSizeReport::new(
+            &self.commit_phase_commits,
+            &self.commit_pow_witnesses,
+            &self.final_poly,
+            &self.pow_witness,
+            self.to_bytes().len(),
+        );
+        // All queries are opened together.
+        report.add_query(
+            &self.input_proof,
+            self.commit_phase_openings.iter().map(|step| {
+                (
+                    step.to_bytes().len(),
+                    M::verify_batch_many_hash_count(step.sibling_values.len(), &step.opening_proof),
+                )
+            }),
+        );
+        report
+    }
 }
@@ -4,14 +4,14 @@ use core::iter;
 
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::Mmcs;
+use p3_commit::MultiOpeningMmcs;
 use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
 use p3_field::{ExtensionField, Field, TwoAdicField};
-use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
 use p3_util::{log2_strict_usize, reverse_slice_index_bits};
 use tracing::{debug_span, info_span, instrument};
 
-use crate::{CommitPhaseProofStep, FriConfig, FriGenericConfig, FriProof, QueryProof};
+use crate::{CommitPhaseProofStep, FriConfig, FriGenericConfig, FriProof};
 
 #[instrument(name = "FRI prover", skip_all)]
 pub fn prove<G, Val, Challenge, M, Challenger>(
@@ -19,12 +19,12 @@ pub fn prove<G, Val, Challenge, M, Challenger>(
     config: &FriConfig<M>,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize) -> G::InputProof,
+    open_input: impl Fn(&[usize]) -> G::InputProof,
 ) -> FriProof<Challenge, M, Challenger::Witness, G::InputProof>
 where
     Val: Field,
     Challenge: ExtensionField<Val> + TwoAdicField,
-    M: Mmcs<Challenge>,
+    M: MultiOpeningMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
@@ -37,6 +37,11 @@ where
         "Inputs are not sorted in descending order of length."
     );
 
+    assert!(
+        config.log_folding_arity > 0,
+        "FRI must fold at least pairs of evaluations"
+    );
+
     let log_max_height = log2_strict_usize(inputs[0].len());
     let log_min_height = log2_strict_usize(inputs.last().unwrap().len());
     if config.log_final_poly_len > 0 {
@@ -47,31 +52,40 @@ where
 
     let pow_witness = challenger.grind(config.proof_of_work_bits);
 
-    let query_proofs = info_span!("query phase").in_scope(|| {
-        iter::repeat_with(|| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
-            .take(config.num_queries)
-            .map(|index| QueryProof {
-                input_proof: open_input(index),
-                commit_phase_openings: answer_query(
-                    config,
-                    &commit_phase_result.data,
-                    index >> g.extra_query_index_bits(),
-                ),
-            })
-            .collect()
+    let (input_proof, commit_phase_openings) = info_span!("query phase").in_scope(|| {
+        let indices = iter::repeat_with(|| {
+            challenger.sample_bits(log_max_height + g.extra_query_index_bits())
+        })
+        .take(config.num_queries)
+        .collect_vec();
+        let input_proof = open_input(&indices);
+        let commit_phase_openings = answer_queries(
+            config,
+            &commit_phase_result.data,
+            &commit_phase_result.log_arities,
+            indices
+                .iter()
+                .map(|index| index >> g.extra_query_index_bits())
+                .collect(),
+        );
+        (input_proof, commit_phase_openings)
     });
 
     FriProof {
         commit_phase_commits: commit_phase_result.commits,
-        query_proofs,
+        commit_pow_witnesses: commit_phase_result.pow_witnesses,
+        input_proof,
+        commit_phase_openings,
         final_poly: commit_phase_result.final_poly,
         pow_witness,
     }
 }
 
-struct CommitPhaseResult<F: Field, M: Mmcs<F>> {
+struct CommitPhaseResult<F: Field, M: MultiOpeningMmcs<F>, Witness> {
     commits: Vec<M::Commitment>,
+    pow_witnesses: Vec<Witness>,
     data: Vec<M::ProverData<RowMajorMatrix<F>>>,
+    log_arities: Vec<usize>,
     final_poly: Vec<F>,
 }
 
@@ -81,31 +95,48 @@ fn commit_phase<G, Val, Challenge, M, Challenger>(
     config: &FriConfig<M>,
     inputs: Vec<Vec<Challenge>>,
     challenger: &mut Challenger,
-) -> CommitPhaseResult<Challenge, M>
+) -> CommitPhaseResult<Challenge, M, Challenger::Witness>
 where
     Val: Field,
     Challenge: ExtensionField<Val> + TwoAdicField,
-    M: Mmcs<Challenge>,
-    Challenger: FieldChallenger<Val> + CanObserve<M::Commitment>,
+    M: MultiOpeningMmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
     let mut inputs_iter = inputs.into_iter().peekable();
     let mut folded = inputs_iter.next().unwrap();
     let mut commits = vec![];
+    let mut pow_witnesses = vec![];
     let mut data = vec![];
+    let mut log_arities = vec![];
 
     while folded.len() > config.blowup() * config.final_poly_len() {
-        let leaves = RowMajorMatrix::new(folded, 2);
+        let log_arity = config.round_log_arity(
+            log2_strict_usize(folded.len()),
+            inputs_iter.peek().map(|v| log2_strict_usize(v.len())),
+        );
+        let leaves = RowMajorMatrix::new(folded, 1 << log_arity);
         let (commit, prover_data) = config.mmcs.commit_matrix(leaves);
         challenger.observe(commit.clone());
+        if config.commit_proof_of_work_bits > 0 {
+            pow_witnesses.push(challenger.grind(config.commit_proof_of_work_bits));
+        }
 
         let beta: Challenge = challenger.sample_ext_element();
         // We passed ownership of `current` to the MMCS, so get a reference to it
         let leaves = config.mmcs.get_matrices(&prover_data).pop().unwrap();
-        folded = g.fold_matrix(beta, leaves.as_view());
+        // Since evaluations are in bit-reversed order, each row folds to a single evaluation by
+        // folding pairs `log_arity` times, which we do with `beta`, `beta^2`, `beta^4`, etc.
+        folded = g.fold_matrix(beta, RowMajorMatrixView::new(&leaves.values, 2));
+        let mut beta_pow = beta;
+        for _ in 1..log_arity {
+            beta_pow = beta_pow.square();
+            folded = g.fold_matrix(beta_pow, RowMajorMatrix::new(folded, 2));
+        }
 
         commits.push(commit);
         data.push(prover_data);
+        log_arities.push(log_arity);
 
         if let Some(v) = inputs_iter.next_if(|v| v.len() == folded.len()) {
             izip!(&mut folded, v).for_each(|(c, x)| *c += x);
@@ -141,36 +172,45 @@ where
 
     CommitPhaseResult {
         commits,
+        pow_witnesses,
         data,
+        log_arities,
         final_poly,
     }
 }
 
-fn answer_query<F, M>(
+fn answer_queries<F, M>(
     config: &FriConfig<M>,
     commit_phase_commits: &[M::ProverData<RowMajorMatrix<F>>],
-    index: usize,
+    log_arities: &[usize],
+    mut indices: Vec<usize>,
 ) -> Vec<CommitPhaseProofStep<F, M>>
 where
     F: Field,
-    M: Mmcs<F>,
+    M: MultiOpeningMmcs<F>,
 {
-    commit_phase_commits
-        .iter()
-        .enumerate()
-        .map(|(i, commit)| {
-            let index_i = index >> i;
-            let index_i_sibling = index_i ^ 1;
-            let index_pair = index_i >> 1;
-
-            let (mut opened_rows, opening_proof) = config.mmcs.open_batch(index_pair, commit);
-            assert_eq!(opened_rows.len(), 1);
-            let opened_row = opened_rows.pop().unwrap();
-            assert_eq!(opened_row.len(), 2, "Committed data should be in pairs");
-            let sibling_value = opened_row[index_i_sibling % 2];
+    izip!(commit_phase_commits, log_arities)
+        .map(|(commit, &log_arity)| {
+            let row_indices = indices.iter().map(|index| index >> log_arity).collect_vec();
+
+            let (opened_rows, opening_proof) = config.mmcs.open_batch_many(&row_indices, commit);
+            let sibling_values = izip!(&indices, opened_rows)
+                .map(|(index, mut opened_rows)| {
+                    assert_eq!(opened_rows.len(), 1);
+                    let mut sibling_values = opened_rows.pop().unwrap();
+                    assert_eq!(
+                        sibling_values.len(),
+                        1 << log_arity,
+                        "Committed data should be in rows of the round's arity"
+                    );
+                    sibling_values.remove(index & ((1 << log_arity) - 1));
+                    sibling_values
+                })
+                .collect();
+            indices = row_indices;
 
             CommitPhaseProofStep {
-                sibling_value,
+                sibling_values,
                 opening_proof,
             }
         })
@@ -0,0 +1,294 @@
+//! Proven round-by-round soundness bounds for FRI.
+//!
+//! The commit phase bounds are the proximity gaps for Reed-Solomon codes of
+//! [BCIKS20](https://eprint.iacr.org/2020/654), applied to each round which samples a challenge, as
+//! in the round-by-round analysis of Block et al., "Fiat-Shamir Security of FRI and Related SNARKs".
+//! The [ethSTARK](https://eprint.iacr.org/2021/582) documentation states the same bounds for a
+//! whole STARK. Soundness is given in bits, i.e. as `-log2` of an error probability.
+
+use alloc::vec::Vec;
+
+use p3_field::{log2_field_order, Field};
+use p3_util::log2_f64;
+
+use crate::FriConfig;
+
+/// The range of proximity parameters in which soundness is proven, where `rho` is the rate of the
+/// code, i.e. the inverse of the blowup.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum SoundnessRegime {
+    /// Distances within the unique decoding radius `(1 - rho) / 2`.
+    UniqueDecoding,
+    /// Distances within `1 - sqrt(rho) (1 + 1 / 2m)`, which approaches the Johnson bound
+    /// `1 - sqrt(rho)` as the multiplicity parameter `m`, which must be at least 3, grows. Larger
+    /// values of `m` weaken the proximity gaps of the commit phase.
+    Johnson { m: usize },
+}
+
+impl SoundnessRegime {
+    /// `log2` of the probability that a single query accepts a function which is far from the code.
+    pub fn log_query_error(self, log_blowup: usize) -> f64 {
+        match self {
+            Self::UniqueDecoding => log2_f64((1.0 + rate(log_blowup)) / 2.0),
+            Self::Johnson { m } => log2_f64(1.0 + 0.5 / m as f64) - log_blowup as f64 / 2.0,
+        }
+    }
+
+    /// `log2` of the probability that a random combination of two functions on a domain of size
+    /// `2^log_n`, one of which is far from the code, is close to it. The combination's coefficient
+    /// is sampled from a field of size `2^log_field_size`.
+    pub fn log_proximity_gap_error(
+        self,
+        log_blowup: usize,
+        log_n: usize,
+        log_field_size: f64,
+    ) -> f64 {
+        match self {
+            // n / |F|
+            Self::UniqueDecoding => log_n as f64 - log_field_size,
+            // (m + 1/2)^7 n^2 / (2 rho^(3/2) |F|)
+            Self::Johnson { m } => {
+                7.0 * log2_f64(m as f64 + 0.5) + 2.0 * log_n as f64 + 1.5 * log_blowup as f64
+                    - 1.0
+                    - log_field_size
+            }
+        }
+    }
+
+    /// A bound on the number of codewords within the proximity parameter of any function on a
+    /// domain of size `2^log_n`, or infinity if there is no useful bound.
+    pub fn list_size(self, log_blowup: usize, log_n: usize) -> f64 {
+        match self {
+            Self::UniqueDecoding => 1.0,
+            Self::Johnson { m } => {
+                let m = m as f64;
+                let slack = rate(log_blowup) - 2.0 * m / (1u64 << log_n) as f64;
+                if slack > 0.0 {
+                    m / slack
+                } else {
+                    f64::INFINITY
+                }
+            }
+        }
+    }
+
+    fn check(self) {
+        if let Self::Johnson { m } = self {
+            assert!(m >= 3, "the multiplicity parameter must be at least 3");
+        }
+    }
+}
+
+/// Proven round-by-round soundness of FRI, for each of its rounds which sample challenges.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FriSoundness {
+    /// The round which samples the challenge batching the input functions into one.
+    pub batching_bits: f64,
+    /// Each commit phase round, which samples a folding challenge, including its proof of work.
+    pub commit_phase_bits: Vec<f64>,
+    /// The query phase, including its proof of work.
+    pub query_bits: f64,
+}
+
+impl FriSoundness {
+    /// The round-by-round soundness of the whole protocol, which is that of its weakest round.
+    pub fn bits(&self) -> f64 {
+        self.commit_phase_bits
+            .iter()
+            .fold(self.batching_bits.min(self.query_bits), |a, &b| a.min(b))
+    }
+}
+
+impl<M> FriConfig<M> {
+    /// Returns the proven round-by-round soundness of this FRI instance, with challenges sampled
+    /// from `F`, when testing `num_functions` batched functions for proximity to polynomials of
+    /// degree below `2^log_degree`.
+    ///
+    /// In a PCS, each column of each committed matrix counts as one function per point it is
+    /// opened at.
+    pub fn proven_soundness<F: Field>(
+        &self,
+        regime: SoundnessRegime,
+        log_degree: usize,
+        num_functions: usize,
+    ) -> FriSoundness {
+        regime.check();
+        let log_field_size = log2_field_order::<F>();
+        let gap = |log_n| regime.log_proximity_gap_error(self.log_blowup, log_n, log_field_size);
+
+        // The batching challenge's powers combine the functions along a curve of degree
+        // `num_functions - 1`, whose proximity gap is that many times a line's.
+        let log_max_height = log_degree + self.log_blowup;
+        let batching_bits = curve_bits(num_functions.saturating_sub(1), gap(log_max_height));
+
+        // Likewise, folding by `2^log_arity` uses a curve of degree `2^log_arity - 1` in beta. Each
+        // round's proof of work multiplies the cost of resampling beta.
+        let mut commit_phase_bits = Vec::new();
+        let mut log_height = log_max_height;
+        while log_height > self.log_blowup + self.log_final_poly_len {
+            let log_arity = self.round_log_arity(log_height, None);
+            commit_phase_bits.push(
+                curve_bits((1 << log_arity) - 1, gap(log_height))
+                    + self.commit_proof_of_work_bits as f64,
+            );
+            log_height -= log_arity;
+        }
+
+        let query_bits = -(self.num_queries as f64) * regime.log_query_error(self.log_blowup)
+            + self.proof_of_work_bits as f64;
+
+        FriSoundness {
+            batching_bits,
+            commit_phase_bits,
+            query_bits,
+        }
+    }
+
+    /// Returns the least number of queries for which `proven_soundness` reaches `security_bits`,
+    /// or `None` if a round before the query phase falls short of it.
+    pub fn min_num_queries<F: Field>(
+        &self,
+        regime: SoundnessRegime,
+        log_degree: usize,
+        num_functions: usize,
+        security_bits: f64,
+    ) -> Option<usize> {
+        let soundness = self.proven_soundness::<F>(regime, log_degree, num_functions);
+        let commit_bits = FriSoundness {
+            query_bits: f64::INFINITY,
+            ..soundness
+        }
+        .bits();
+        if commit_bits < security_bits {
+            return None;
+        }
+
+        let needed = (security_bits - self.proof_of_work_bits as f64)
+            / -regime.log_query_error(self.log_blowup);
+        // The cast saturates negative values to zero.
+        let num_queries = needed as usize;
+        Some(if (num_queries as f64) < needed {
+            num_queries + 1
+        } else {
+            num_queries
+        })
+    }
+}
+
+fn rate(log_blowup: usize) -> f64 {
+    1.0 / (1u64 << log_blowup) as f64
+}
+
+/// The soundness bits of sampling a point on a curve of the given degree, given `log2` of the
+/// proximity gap error of a line. A degree of zero means there is nothing to sample.
+fn curve_bits(degree: usize, log_line_error: f64) -> f64 {
+    if degree == 0 {
+        f64::INFINITY
+    } else {
+        -(log2_f64(degree as f64) + log_line_error).min(0.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::extension::BinomialExtensionField;
+
+    use super::*;
+
+    type Challenge = BinomialExtensionField<BabyBear, 4>;
+
+    fn config(log_blowup: usize, log_folding_arity: usize, num_queries: usize) -> FriConfig<()> {
+        FriConfig {
+            log_blowup,
+            log_final_poly_len: 0,
+            log_folding_arity,
+            num_queries,
+            proof_of_work_bits: 16,
+            commit_proof_of_work_bits: 0,
+            mmcs: (),
+        }
+    }
+
+    #[test]
+    fn unique_decoding() {
+        let soundness =
+            config(1, 1, 100).proven_soundness::<Challenge>(SoundnessRegime::UniqueDecoding, 20, 1);
+        // A single function needs no batching.
+        assert_eq!(soundness.batching_bits, f64::INFINITY);
+        assert_eq!(soundness.commit_phase_bits.len(), 20);
+        // Each round folds in pairs over a domain half the size of the last.
+        let log_field_size = log2_field_order::<Challenge>();
+        for (i, &bits) in soundness.commit_phase_bits.iter().enumerate() {
+            assert!((bits - (log_field_size - (21 - i) as f64)).abs() < 1e-9);
+        }
+        // Each query rejects with probability 1/4.
+        let query_bits = 100.0 * log2_f64(4.0 / 3.0) + 16.0;
+        assert!((soundness.query_bits - query_bits).abs() < 1e-9);
+        assert_eq!(soundness.bits(), soundness.query_bits);
+    }
+
+    #[test]
+    fn johnson() {
+        let regime = SoundnessRegime::Johnson { m: 3 };
+        let config = config(2, 2, 60);
+        let soundness = config.proven_soundness::<Challenge>(regime, 20, 300);
+        // Each round folds by 4.
+        assert_eq!(soundness.commit_phase_bits.len(), 10);
+        assert!(soundness.batching_bits < soundness.commit_phase_bits[0]);
+        assert!(soundness
+            .commit_phase_bits
+            .windows(2)
+            .all(|bits| bits[0] < bits[1]));
+        // Proven soundness is weaker than conjectured.
+        assert!(soundness.query_bits < config.conjectured_soundness_bits() as f64);
+
+        // The unique decoding radius is smaller, so needs more queries for the same soundness.
+        let unique = config.proven_soundness::<Challenge>(SoundnessRegime::UniqueDecoding, 20, 300);
+        assert!(unique.query_bits < soundness.query_bits);
+        assert!(unique.batching_bits > soundness.batching_bits);
+    }
+
+    #[test]
+    fn commit_phase_pow() {
+        let regime = SoundnessRegime::Johnson { m: 3 };
+        let base = config(2, 2, 60).proven_soundness::<Challenge>(regime, 20, 300);
+        let ground = FriConfig {
+            commit_proof_of_work_bits: 20,
+            ..config(2, 2, 60)
+        }
+        .proven_soundness::<Challenge>(regime, 20, 300);
+        assert_eq!(ground.commit_phase_bits.len(), base.commit_phase_bits.len());
+        for (&bits, &ground_bits) in base.commit_phase_bits.iter().zip(&ground.commit_phase_bits) {
+            assert!((ground_bits - bits - 20.0).abs() < 1e-9);
+        }
+        assert_eq!(ground.query_bits, base.query_bits);
+    }
+
+    #[test]
+    fn min_num_queries() {
+        let regime = SoundnessRegime::Johnson { m: 3 };
+        let soundness_with = |num_queries| {
+            config(3, 1, num_queries)
+                .proven_soundness::<Challenge>(regime, 16, 100)
+                .bits()
+        };
+        let config = config(3, 1, 0);
+        let num_queries = config
+            .min_num_queries::<Challenge>(regime, 16, 100, 60.0)
+            .unwrap();
+        assert!(soundness_with(num_queries) >= 60.0);
+        assert!(soundness_with(num_queries - 1) < 60.0);
+
+        // The proof of work alone suffices for a low enough target.
+        assert_eq!(
+            config.min_num_queries::<Challenge>(regime, 16, 100, 10.0),
+            Some(0)
+        );
+        // Challenges from the base field alone can't reach 60 bits however many queries are made.
+        assert_eq!(
+            config.min_num_queries::<BabyBear>(regime, 16, 100, 60.0),
+            None
+        );
+    }
+}
@@ -6,11 +6,13 @@ use core::marker::PhantomData;
 
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::{Mmcs, OpenedValues, Pcs, PolynomialSpace, TwoAdicMultiplicativeCoset};
+use p3_commit::{
+    Mmcs, MultiOpeningMmcs, OpenedValues, Pcs, PolynomialSpace, TwoAdicMultiplicativeCoset,
+};
 use p3_dft::TwoAdicSubgroupDft;
 use p3_field::{
-    batch_multiplicative_inverse, cyclic_subgroup_coset_known_order, dot_product, ExtensionField,
-    Field, TwoAdicField,
+    batch_multiplicative_inverse, cyclic_subgroup_coset_known_order, dot_product,
+    CanonicalEncoding, DecodingError, ExtensionField, Field, TwoAdicField,
 };
 use p3_interpolation::interpolate_coset;
 use p3_matrix::bitrev::{BitReversableMatrix, BitReversalPerm};
@@ -23,11 +25,11 @@ use serde::{Deserialize, Serialize};
 use tracing::{info_span, instrument};
 
 use crate::verifier::{self, FriError};
-use crate::{prover, FriConfig, FriGenericConfig, FriProof};
+use crate::{prover, FriConfig, FriGenericConfig, FriProof, VerifierHashCount};
 
 #[derive(Debug)]
 pub struct TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
-    dft: Dft,
+    pub(crate) dft: Dft,
     mmcs: InputMmcs,
     fri: FriConfig<FriMmcs>,
     _phantom: PhantomData<Val>,
@@ -46,9 +48,37 @@ impl<Val, Dft, InputMmcs, FriMmcs> TwoAdicFriPcs<Val, Dft, InputMmcs, FriMmcs> {
 
 #[derive(Serialize, Deserialize, Clone)]
 #[serde(bound = "")]
-pub struct BatchOpening<Val: Field, InputMmcs: Mmcs<Val>> {
-    pub opened_values: Vec<Vec<Val>>,
-    pub opening_proof: <InputMmcs as Mmcs<Val>>::Proof,
+pub struct BatchOpening<Val: Field, InputMmcs: MultiOpeningMmcs<Val>> {
+    /// For each query, the opened row of each matrix.
+    pub opened_values: Vec<Vec<Vec<Val>>>,
+    pub opening_proof: <InputMmcs as MultiOpeningMmcs<Val>>::MultiProof,
+}
+
+impl<Val, InputMmcs> CanonicalEncoding for BatchOpening<Val, InputMmcs>
+where
+    Val: Field + CanonicalEncoding,
+    InputMmcs: MultiOpeningMmcs<Val>,
+    InputMmcs::MultiProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.opened_values.encode(out);
+        self.opening_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            opened_values: CanonicalEncoding::decode(input)?,
+            opening_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<Val: Field, InputMmcs: MultiOpeningMmcs<Val>> VerifierHashCount
+    for BatchOpening<Val, InputMmcs>
+{
+    fn verifier_hash_count(&self) -> usize {
+        InputMmcs::verify_batch_many_hash_count(self.opened_values.len(), &self.opening_proof)
+    }
 }
 
 pub struct TwoAdicFriGenericConfig<InputProof, InputError>(
@@ -134,8 +164,8 @@ impl<Val, Dft, InputMmcs, FriMmcs, Challenge, Challenger> Pcs<Challenge, Challen
 where
     Val: TwoAdicField,
     Dft: TwoAdicSubgroupDft<Val>,
-    InputMmcs: Mmcs<Val>,
-    FriMmcs: Mmcs<Challenge>,
+    InputMmcs: MultiOpeningMmcs<Val>,
+    FriMmcs: MultiOpeningMmcs<Challenge>,
     Challenge: TwoAdicField + ExtensionField<Val>,
     Challenger:
         FieldChallenger<Val> + CanObserve<FriMmcs::Commitment> + GrindingChallenger<Witness = Val>,
@@ -146,6 +176,14 @@ where
     type Proof = FriProof<Challenge, FriMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>;
     type Error = FriError<FriMmcs::Error, InputMmcs::Error>;
 
+    fn fingerprint(&self) -> Vec<u8> {
+        (
+            self.fri.fingerprint(),
+            (self.mmcs.fingerprint(), self.fri.mmcs.fingerprint()),
+        )
+            .to_bytes()
+    }
+
     fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
         let log_n = log2_strict_usize(degree);
         TwoAdicMultiplicativeCoset {
@@ -323,14 +361,18 @@ where
         let g: TwoAdicFriGenericConfigForMmcs<Val, InputMmcs> =
             TwoAdicFriGenericConfig(PhantomData);
 
-        let fri_proof = prover::prove(&g, &self.fri, fri_input, challenger, |index| {
+        let fri_proof = prover::prove(&g, &self.fri, fri_input, challenger, |indices| {
             rounds
                 .iter()
                 .map(|(data, _)| {
                     let log_max_height = log2_strict_usize(self.mmcs.get_max_height(data));
                     let bits_reduced = log_global_max_height - log_max_height;
-                    let reduced_index = index >> bits_reduced;
-                    let (opened_values, opening_proof) = self.mmcs.open_batch(reduced_index, data);
+                    let reduced_indices = indices
+                        .iter()
+                        .map(|index| index >> bits_reduced)
+                        .collect_vec();
+                    let (opened_values, opening_proof) =
+                        self.mmcs.open_batch_many(&reduced_indices, data);
                     BatchOpening {
                         opened_values,
                         opening_proof,
@@ -366,90 +408,123 @@ where
         // Batch combination challenge
         let alpha: Challenge = challenger.sample_ext_element();
 
-        let log_global_max_height =
-            proof.commit_phase_commits.len() + self.fri.log_blowup + self.fri.log_final_poly_len;
+        let log_global_max_height = match proof.log_max_height(&self.fri) {
+            Some(log_max_height) => log_max_height,
+            // Without queries, no inputs are opened.
+            None if self.fri.num_queries == 0 => 0,
+            None => return Err(FriError::InvalidProofShape),
+        };
 
         let g: TwoAdicFriGenericConfigForMmcs<Val, InputMmcs> =
             TwoAdicFriGenericConfig(PhantomData);
 
-        verifier::verify(&g, &self.fri, proof, challenger, |index, input_proof| {
+        verifier::verify(&g, &self.fri, proof, challenger, |indices, input_proof| {
             // TODO: separate this out into functions
 
-            // log_height -> (alpha_pow, reduced_opening)
-            let mut reduced_openings = BTreeMap::<usize, (Challenge, Challenge)>::new();
+            // For each query, log_height -> (alpha_pow, reduced_opening)
+            let mut reduced_openings =
+                vec![BTreeMap::<usize, (Challenge, Challenge)>::new(); indices.len()];
 
             for (batch_opening, (batch_commit, mats)) in izip!(input_proof, &rounds) {
-                let batch_heights = mats
-                    .iter()
-                    .map(|(domain, _)| domain.size() << self.fri.log_blowup)
-                    .collect_vec();
-                let batch_dims = batch_heights
+                let batch_dims = mats
                     .iter()
-                    // TODO: MMCS doesn't really need width; we put 0 for now.
-                    .map(|&height| Dimensions { width: 0, height })
+                    .enumerate()
+                    .map(|(mat, (domain, points_and_values))| Dimensions {
+                        // Each column is opened at every point, so the values at any point give the
+                        // width of the matrix. A matrix opened at no points contributes nothing, so
+                        // its width is taken from the opened rows instead, which leaves it
+                        // unchecked; see `Pcs::verify`.
+                        width: points_and_values.first().map_or_else(
+                            || {
+                                batch_opening
+                                    .opened_values
+                                    .first()
+                                    .and_then(|rows| rows.get(mat))
+                                    .map_or(0, Vec::len)
+                            },
+                            |(_, values)| values.len(),
+                        ),
+                        height: domain.size() << self.fri.log_blowup,
+                    })
                     .collect_vec();
 
-                let batch_max_height = batch_heights.iter().max().expect("Empty batch?");
-                let log_batch_max_height = log2_strict_usize(*batch_max_height);
+                let batch_max_height = batch_dims
+                    .iter()
+                    .map(|dims| dims.height)
+                    .max()
+                    .expect("Empty batch?");
+                let log_batch_max_height = log2_strict_usize(batch_max_height);
                 let bits_reduced = log_global_max_height - log_batch_max_height;
-                let reduced_index = index >> bits_reduced;
+                let reduced_indices = indices
+                    .iter()
+                    .map(|index| index >> bits_reduced)
+                    .collect_vec();
 
-                self.mmcs.verify_batch(
+                self.mmcs.verify_batch_many(
                     batch_commit,
                     &batch_dims,
-                    reduced_index,
+                    &reduced_indices,
                     &batch_opening.opened_values,
                     &batch_opening.opening_proof,
                 )?;
-                for (mat_opening, (mat_domain, mat_points_and_values)) in
-                    izip!(&batch_opening.opened_values, mats)
+                for (&index, opened_values, reduced_openings) in
+                    izip!(indices, &batch_opening.opened_values, &mut reduced_openings)
                 {
-                    let log_height = log2_strict_usize(mat_domain.size()) + self.fri.log_blowup;
+                    for (mat_opening, (mat_domain, mat_points_and_values)) in
+                        izip!(opened_values, mats)
+                    {
+                        let log_height = log2_strict_usize(mat_domain.size()) + self.fri.log_blowup;
 
-                    let bits_reduced = log_global_max_height - log_height;
-                    let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
+                        let bits_reduced = log_global_max_height - log_height;
+                        let rev_reduced_index = reverse_bits_len(index >> bits_reduced, log_height);
 
-                    // todo: this can be nicer with domain methods?
+                        // todo: this can be nicer with domain methods?
 
-                    let x = Val::GENERATOR
-                        * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
+                        let x = Val::GENERATOR
+                            * Val::two_adic_generator(log_height).exp_u64(rev_reduced_index as u64);
 
-                    let (alpha_pow, ro) = reduced_openings
-                        .entry(log_height)
-                        .or_insert((Challenge::ONE, Challenge::ZERO));
+                        let (alpha_pow, ro) = reduced_openings
+                            .entry(log_height)
+                            .or_insert((Challenge::ONE, Challenge::ZERO));
 
-                    for (z, ps_at_z) in mat_points_and_values {
-                        for (&p_at_x, &p_at_z) in izip!(mat_opening, ps_at_z) {
-                            let quotient = (-p_at_z + p_at_x) / (-*z + x);
-                            *ro += *alpha_pow * quotient;
-                            *alpha_pow *= alpha;
+                        for (z, ps_at_z) in mat_points_and_values {
+                            for (&p_at_x, &p_at_z) in izip!(mat_opening, ps_at_z) {
+                                let quotient = (-p_at_z + p_at_x) / (-*z + x);
+                                *ro += *alpha_pow * quotient;
+                                *alpha_pow *= alpha;
+                            }
                         }
                     }
                 }
             }
 
-            // `reduced_openings` would have a log_height = log_blowup entry only if there was a
-            // trace matrix of height 1. In this case the reduced opening can be skipped as it will
-            // not be checked against any commit phase commit.
-            if let Some((_alpha_pow, ro)) = reduced_openings.remove(&self.fri.log_blowup) {
-                debug_assert!(ro.is_zero());
-            }
-
-            // Return reduced openings descending by log_height.
             Ok(reduced_openings
                 .into_iter()
-                .rev()
-                .map(|(log_height, (_alpha_pow, ro))| (log_height, ro))
+                .map(|mut reduced_openings| {
+                    // `reduced_openings` would have a log_height = log_blowup entry only if there
+                    // was a trace matrix of height 1. In this case the reduced opening can be
+                    // skipped as it will not be checked against any commit phase commit.
+                    if let Some((_alpha_pow, ro)) = reduced_openings.remove(&self.fri.log_blowup) {
+                        debug_assert!(ro.is_zero());
+                    }
+
+                    // Return reduced openings descending by log_height.
+                    reduced_openings
+                        .into_iter()
+                        .rev()
+                        .map(|(log_height, (_alpha_pow, ro))| (log_height, ro))
+                        .collect()
+                })
                 .collect())
         })
-        .expect("fri err");
-
-        Ok(())
     }
 }
 
+/// For each opening point `z`, computes `1 / (z - x)` for every `x` in the bit-reversed coset
+/// `coset_shift H` with `H` the largest subgroup on which a matrix is opened at `z`. Matrices on
+/// smaller subgroups use a prefix of these, since their bit-reversed cosets are prefixes of it.
 #[instrument(skip_all)]
-fn compute_inverse_denominators<F: TwoAdicField, EF: ExtensionField<F>, M: Matrix<F>>(
+pub fn compute_inverse_denominators<F: TwoAdicField, EF: ExtensionField<F>, M: Matrix<F>>(
     mats_and_points: &[(Vec<M>, &Vec<Vec<EF>>)],
     coset_shift: F,
 ) -> LinearMap<EF, Vec<EF>> {
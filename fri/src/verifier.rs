@@ -1,9 +1,10 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::iter;
 
 use itertools::{izip, Itertools};
 use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
-use p3_commit::Mmcs;
+use p3_commit::{Mmcs, MultiOpeningMmcs};
 use p3_field::{ExtensionField, Field, TwoAdicField};
 use p3_matrix::Dimensions;
 use p3_util::reverse_bits_len;
@@ -17,6 +18,7 @@ pub enum FriError<CommitMmcsErr, InputError> {
     InputError(InputError),
     FinalPolyMismatch,
     InvalidPowWitness,
+    InvalidCommitPhasePowWitness,
 }
 
 pub fn verify<G, Val, Challenge, M, Challenger>(
@@ -24,23 +26,38 @@ pub fn verify<G, Val, Challenge, M, Challenger>(
     config: &FriConfig<M>,
     proof: &FriProof<Challenge, M, Challenger::Witness, G::InputProof>,
     challenger: &mut Challenger,
-    open_input: impl Fn(usize, &G::InputProof) -> Result<Vec<(usize, Challenge)>, G::InputError>,
+    open_input: impl Fn(&[usize], &G::InputProof) -> Result<Vec<Vec<(usize, Challenge)>>, G::InputError>,
 ) -> Result<(), FriError<M::Error, G::InputError>>
 where
     Val: Field,
     Challenge: ExtensionField<Val> + TwoAdicField,
-    M: Mmcs<Challenge>,
+    M: MultiOpeningMmcs<Challenge>,
     Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
     G: FriGenericConfig<Challenge>,
 {
+    let num_commit_pow_witnesses = if config.commit_proof_of_work_bits > 0 {
+        proof.commit_phase_commits.len()
+    } else {
+        0
+    };
+    if proof.commit_pow_witnesses.len() != num_commit_pow_witnesses {
+        return Err(FriError::InvalidProofShape);
+    }
+
+    let mut commit_pow_witnesses = proof.commit_pow_witnesses.iter();
     let betas: Vec<Challenge> = proof
         .commit_phase_commits
         .iter()
         .map(|comm| {
             challenger.observe(comm.clone());
-            challenger.sample_ext_element()
+            if let Some(&witness) = commit_pow_witnesses.next() {
+                if !challenger.check_witness(config.commit_proof_of_work_bits, witness) {
+                    return Err(FriError::InvalidCommitPhasePowWitness);
+                }
+            }
+            Ok(challenger.sample_ext_element())
         })
-        .collect();
+        .collect::<Result<_, _>>()?;
 
     // Observe all coefficients of the final polynomial.
     proof
@@ -48,7 +65,12 @@ where
         .iter()
         .for_each(|x| challenger.observe_ext_element(*x));
 
-    if proof.query_proofs.len() != config.num_queries {
+    if proof.commit_phase_openings.len() != proof.commit_phase_commits.len()
+        || proof
+            .commit_phase_openings
+            .iter()
+            .any(|step| step.sibling_values.len() != config.num_queries)
+    {
         return Err(FriError::InvalidProofShape);
     }
 
@@ -57,32 +79,46 @@ where
         return Err(FriError::InvalidPowWitness);
     }
 
-    let log_max_height =
-        proof.commit_phase_commits.len() + config.log_blowup + config.log_final_poly_len;
+    if config.num_queries == 0 {
+        return Ok(());
+    }
+    let log_max_height = proof
+        .log_max_height(config)
+        .ok_or(FriError::InvalidProofShape)?;
+    let log_final_height = config.log_blowup + config.log_final_poly_len;
 
-    for qp in &proof.query_proofs {
-        let index = challenger.sample_bits(log_max_height + g.extra_query_index_bits());
-        let ro = open_input(index, &qp.input_proof).map_err(FriError::InputError)?;
+    let indices =
+        iter::repeat_with(|| challenger.sample_bits(log_max_height + g.extra_query_index_bits()))
+            .take(config.num_queries)
+            .collect_vec();
+    let reduced_openings =
+        open_input(&indices, &proof.input_proof).map_err(FriError::InputError)?;
 
-        debug_assert!(
-            ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r),
-            "reduced openings sorted by height descending"
-        );
+    debug_assert!(
+        reduced_openings
+            .iter()
+            .all(|ro| ro.iter().tuple_windows().all(|((l, _), (r, _))| l > r)),
+        "reduced openings sorted by height descending"
+    );
+
+    let folded_evals = verify_queries(
+        g,
+        config,
+        indices
+            .iter()
+            .map(|index| index >> g.extra_query_index_bits())
+            .collect(),
+        izip!(
+            &betas,
+            &proof.commit_phase_commits,
+            &proof.commit_phase_openings
+        ),
+        reduced_openings,
+        log_max_height,
+    )?;
 
-        let folded_eval = verify_query(
-            g,
-            config,
-            index >> g.extra_query_index_bits(),
-            izip!(
-                &betas,
-                &proof.commit_phase_commits,
-                &qp.commit_phase_openings
-            ),
-            ro,
-            log_max_height,
-        )?;
-
-        let final_poly_index = index >> (proof.commit_phase_commits.len());
+    for (index, folded_eval) in izip!(indices, folded_evals) {
+        let final_poly_index = index >> (log_max_height - log_final_height);
 
         let mut eval = Challenge::ZERO;
 
@@ -113,62 +149,122 @@ type CommitStep<'a, F, M> = (
     &'a CommitPhaseProofStep<F, M>,
 );
 
-fn verify_query<'a, G, F, M>(
+/// Folds the reduced openings at each query index through the commit phase rounds, checking each
+/// round's openings at all queries together, and returns the final folded evaluation of each
+/// query.
+fn verify_queries<'a, G, F, M>(
     g: &G,
     config: &FriConfig<M>,
-    mut index: usize,
+    mut indices: Vec<usize>,
     steps: impl Iterator<Item = CommitStep<'a, F, M>>,
-    reduced_openings: Vec<(usize, F)>,
+    reduced_openings: Vec<Vec<(usize, F)>>,
     log_max_height: usize,
-) -> Result<F, FriError<M::Error, G::InputError>>
+) -> Result<Vec<F>, FriError<M::Error, G::InputError>>
 where
     F: Field,
-    M: Mmcs<F> + 'a,
+    M: MultiOpeningMmcs<F> + 'a,
     G: FriGenericConfig<F>,
 {
-    let mut folded_eval = F::ZERO;
-    let mut ro_iter = reduced_openings.into_iter().peekable();
+    if reduced_openings.len() != indices.len() {
+        return Err(FriError::InvalidProofShape);
+    }
+    let mut folded_evals = vec![F::ZERO; indices.len()];
+    let mut ro_iters = reduced_openings
+        .into_iter()
+        .map(|ro| ro.into_iter().peekable())
+        .collect_vec();
+    let mut log_height = log_max_height;
 
-    for (log_folded_height, (&beta, comm, opening)) in izip!((0..log_max_height).rev(), steps) {
-        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_folded_height + 1) {
-            folded_eval += ro;
+    for (&beta, comm, opening) in steps {
+        for (folded_eval, ro_iter) in izip!(&mut folded_evals, &mut ro_iters) {
+            if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_height) {
+                *folded_eval += ro;
+            }
         }
 
-        let index_sibling = index ^ 1;
-        let index_pair = index >> 1;
+        // The prover must use the same schedule of arities as `prover::prove`, which only depends
+        // on the heights of the inputs. These are the same for every query, as any query whose
+        // reduced openings are at other heights is left with some over at the end.
+        let log_next_input_height = ro_iters[0].peek().map(|(lh, _)| *lh);
+        let log_arity = config.round_log_arity(log_height, log_next_input_height);
+        if log_arity == 0
+            || opening
+                .sibling_values
+                .iter()
+                .any(|siblings| siblings.len() != (1 << log_arity) - 1)
+        {
+            return Err(FriError::InvalidProofShape);
+        }
 
-        let mut evals = vec![folded_eval; 2];
-        evals[index_sibling % 2] = opening.sibling_value;
+        let row_indices = indices.iter().map(|index| index >> log_arity).collect_vec();
+        let opened_rows = izip!(&indices, &folded_evals, &opening.sibling_values)
+            .map(|(index, &folded_eval, siblings)| {
+                let mut evals = siblings.clone();
+                evals.insert(index & ((1 << log_arity) - 1), folded_eval);
+                vec![evals]
+            })
+            .collect_vec();
 
         let dims = &[Dimensions {
-            width: 2,
-            height: 1 << log_folded_height,
+            width: 1 << log_arity,
+            height: 1 << (log_height - log_arity),
         }];
         config
             .mmcs
-            .verify_batch(
+            .verify_batch_many(
                 comm,
                 dims,
-                index_pair,
-                &[evals.clone()],
+                &row_indices,
+                &opened_rows,
                 &opening.opening_proof,
             )
             .map_err(FriError::CommitPhaseMmcsError)?;
 
-        index = index_pair;
-
-        folded_eval = g.fold_row(index, log_folded_height, beta, evals.into_iter());
+        // Fold each row in pairs with `beta`, `beta^2`, `beta^4`, etc., as the prover did.
+        for (folded_eval, &row_index, mut evals) in
+            izip!(&mut folded_evals, &row_indices, opened_rows)
+        {
+            let mut evals = evals.pop().unwrap();
+            let mut beta_pow = beta;
+            let mut log_folded_height = log_height;
+            for i in (0..log_arity).rev() {
+                log_folded_height -= 1;
+                evals = evals
+                    .chunks_exact(2)
+                    .enumerate()
+                    .map(|(j, pair)| {
+                        g.fold_row(
+                            (row_index << i) + j,
+                            log_folded_height,
+                            beta_pow,
+                            pair.iter().copied(),
+                        )
+                    })
+                    .collect();
+                beta_pow = beta_pow.square();
+            }
+            *folded_eval = evals[0];
+        }
+        log_height -= log_arity;
+        indices = row_indices;
     }
 
-    debug_assert!(
-        index < config.blowup() * config.final_poly_len(),
-        "index was {}",
-        index,
-    );
-    debug_assert!(
-        ro_iter.next().is_none(),
-        "verifier reduced_openings were not in descending order?"
-    );
+    for (folded_eval, index, ro_iter) in izip!(&mut folded_evals, &indices, &mut ro_iters) {
+        // An input of the final polynomial's height would be rolled in after the last round.
+        if let Some((_, ro)) = ro_iter.next_if(|(lh, _)| *lh == log_height) {
+            *folded_eval += ro;
+        }
+
+        debug_assert!(
+            *index < config.blowup() * config.final_poly_len(),
+            "index was {}",
+            index,
+        );
+        // Any reduced openings left over weren't at the start of any round.
+        if ro_iter.next().is_some() {
+            return Err(FriError::InvalidProofShape);
+        }
+    }
 
-    Ok(folded_eval)
+    Ok(folded_evals)
 }
@@ -7,7 +7,8 @@ use p3_commit::ExtensionMmcs;
 use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
 use p3_field::extension::BinomialExtensionField;
 use p3_field::{Field, FieldAlgebra};
-use p3_fri::{prover, verifier, FriConfig, TwoAdicFriGenericConfig};
+use p3_fri::verifier::FriError;
+use p3_fri::{prover, verifier, FriConfig, FriProof, TwoAdicFriGenericConfig};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::util::reverse_matrix_index_bits;
 use p3_matrix::Matrix;
@@ -29,7 +30,12 @@ type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
 type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
 type MyFriConfig = FriConfig<ChallengeMmcs>;
 
-fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm, MyFriConfig) {
+fn get_ldt_for_testing<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    log_folding_arity: usize,
+    commit_proof_of_work_bits: usize,
+) -> (Perm, MyFriConfig) {
     let perm = Perm::new_from_rng_128(rng);
     let hash = MyHash::new(perm.clone());
     let compress = MyCompress::new(perm.clone());
@@ -37,21 +43,35 @@ fn get_ldt_for_testing<R: Rng>(rng: &mut R, log_final_poly_len: usize) -> (Perm,
     let fri_config = FriConfig {
         log_blowup: 1,
         log_final_poly_len,
+        log_folding_arity,
         num_queries: 10,
         proof_of_work_bits: 8,
+        commit_proof_of_work_bits,
         mmcs,
     };
     (perm, fri_config)
 }
 
-fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
-    let (perm, fc) = get_ldt_for_testing(rng, log_final_poly_len);
+fn do_test_fri_ldt<R: Rng>(
+    rng: &mut R,
+    log_final_poly_len: usize,
+    log_folding_arity: usize,
+    commit_proof_of_work_bits: usize,
+    deg_bits: &[usize],
+) -> FriProof<Challenge, ChallengeMmcs, Val, Vec<Vec<(usize, Challenge)>>> {
+    let (perm, fc) = get_ldt_for_testing(
+        rng,
+        log_final_poly_len,
+        log_folding_arity,
+        commit_proof_of_work_bits,
+    );
     let dft = Radix2Dit::default();
 
     let shift = Val::GENERATOR;
 
-    let ldes: Vec<RowMajorMatrix<Val>> = (5..10)
-        .map(|deg_bits| {
+    let ldes: Vec<RowMajorMatrix<Val>> = deg_bits
+        .iter()
+        .map(|&deg_bits| {
             let evals = RowMajorMatrix::<Val>::rand_nonzero(rng, 1 << deg_bits, 16);
             let mut lde = dft.coset_lde_batch(evals, 1, shift);
             reverse_matrix_index_bits(&mut lde);
@@ -90,19 +110,24 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
         let log_max_height = log2_strict_usize(input[0].len());
 
         let proof = prover::prove(
-            &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+            &TwoAdicFriGenericConfig::<Vec<Vec<(usize, Challenge)>>, ()>(PhantomData),
             &fc,
             input.clone(),
             &mut chal,
-            |idx| {
+            |indices| {
                 // As our "input opening proof", just pass through the literal reduced openings.
-                let mut ro = vec![];
-                for v in &input {
-                    let log_height = log2_strict_usize(v.len());
-                    ro.push((log_height, v[idx >> (log_max_height - log_height)]));
-                }
-                ro.sort_by_key(|(lh, _)| Reverse(*lh));
-                ro
+                indices
+                    .iter()
+                    .map(|idx| {
+                        let mut ro = vec![];
+                        for v in &input {
+                            let log_height = log2_strict_usize(v.len());
+                            ro.push((log_height, v[idx >> (log_max_height - log_height)]));
+                        }
+                        ro.sort_by_key(|(lh, _)| Reverse(*lh));
+                        ro
+                    })
+                    .collect()
             },
         );
 
@@ -112,11 +137,11 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
     let mut v_challenger = Challenger::new(perm);
     let _alpha: Challenge = v_challenger.sample_ext_element();
     verifier::verify(
-        &TwoAdicFriGenericConfig::<Vec<(usize, Challenge)>, ()>(PhantomData),
+        &TwoAdicFriGenericConfig::<Vec<Vec<(usize, Challenge)>>, ()>(PhantomData),
         &fc,
         &proof,
         &mut v_challenger,
-        |_index, proof| Ok(proof.clone()),
+        |_indices, proof| Ok(proof.clone()),
     )
     .unwrap();
 
@@ -125,6 +150,8 @@ fn do_test_fri_ldt<R: Rng>(rng: &mut R, log_final_poly_len: usize) {
         v_challenger.sample_bits(8),
         "prover and verifier transcript have same state after FRI"
     );
+
+    proof
 }
 
 #[test]
@@ -132,10 +159,94 @@ fn test_fri_ldt() {
     // FRI is kind of flaky depending on indexing luck
     for i in 0..4 {
         let mut rng = ChaCha20Rng::seed_from_u64(i as u64);
-        do_test_fri_ldt(&mut rng, i + 1);
+        do_test_fri_ldt(&mut rng, i + 1, 1, 0, &[5, 6, 7, 8, 9]);
+    }
+}
+
+#[test]
+fn test_fri_ldt_higher_arity() {
+    for log_folding_arity in 2..5 {
+        let mut rng = ChaCha20Rng::seed_from_u64(log_folding_arity as u64);
+        let proof = do_test_fri_ldt(&mut rng, 1, log_folding_arity, 0, &[4, 9, 10]);
+
+        // Inputs have log heights 11, 10 and 5, and the final polynomial's evaluations have log
+        // height 2, so the rounds stop at each of those heights.
+        let log_arities = proof
+            .commit_phase_openings
+            .iter()
+            .map(|step| log2_strict_usize(step.sibling_values[0].len() + 1))
+            .collect::<Vec<_>>();
+        let expected = match log_folding_arity {
+            2 => vec![1, 2, 2, 1, 2, 1],
+            3 => vec![1, 3, 2, 3],
+            _ => vec![1, 4, 1, 3],
+        };
+        assert_eq!(log_arities, expected);
+    }
+}
+
+#[test]
+fn test_fri_shared_paths() {
+    let mut rng = ChaCha20Rng::seed_from_u64(0);
+    let proof = do_test_fri_ldt(&mut rng, 1, 1, 0, &[4, 5]);
+    assert!(proof.commit_pow_witnesses.is_empty());
+
+    // The rounds fold from log height 6 down to the final polynomial's evaluations at log height
+    // 2, committing to pairs, so their trees have 5, 4, 3 and 2 layers below the root.
+    assert_eq!(proof.commit_phase_openings.len(), 4);
+    for (step, num_layers) in proof.commit_phase_openings.iter().zip((2..6).rev()) {
+        assert_eq!(step.sibling_values.len(), 10);
+        assert_eq!(step.opening_proof.len(), num_layers);
+        // The ten queries' paths share nodes at least in the layers of fewer than ten nodes, so
+        // give fewer siblings than ten separate paths would.
+        let num_siblings: usize = step.opening_proof.iter().map(Vec::len).sum();
+        assert!(num_siblings < 10 * num_layers);
     }
 }
 
+#[test]
+fn test_fri_commit_pow() {
+    let proof = do_test_fri_ldt(&mut ChaCha20Rng::seed_from_u64(0), 1, 2, 8, &[4, 9]);
+    assert_eq!(
+        proof.commit_pow_witnesses.len(),
+        proof.commit_phase_commits.len()
+    );
+
+    // Seeding the RNG the same way gives the same permutation and config.
+    let (perm, fc) = get_ldt_for_testing(&mut ChaCha20Rng::seed_from_u64(0), 1, 2, 8);
+    let verify = |proof: &FriProof<_, _, _, _>| {
+        let mut challenger = Challenger::new(perm.clone());
+        let _alpha: Challenge = challenger.sample_ext_element();
+        verifier::verify(
+            &TwoAdicFriGenericConfig::<Vec<Vec<(usize, Challenge)>>, ()>(PhantomData),
+            &fc,
+            proof,
+            &mut challenger,
+            |_indices, proof| Ok(proof.clone()),
+        )
+    };
+    verify(&proof).expect("expected verification to succeed");
+
+    // The witness of a round no longer passes once it is changed, or once the commitment it was
+    // ground against is.
+    let mut tampered = proof.clone();
+    tampered.commit_pow_witnesses[1] += Val::ONE;
+    assert!(matches!(
+        verify(&tampered),
+        Err(FriError::InvalidCommitPhasePowWitness)
+    ));
+    let mut tampered = proof.clone();
+    tampered.commit_phase_commits.swap(0, 1);
+    assert!(verify(&tampered).is_err());
+
+    let mut tampered = proof;
+    tampered.commit_pow_witnesses.pop();
+    assert!(matches!(
+        verify(&tampered),
+        Err(FriError::InvalidProofShape)
+    ));
+}
+
 // This test is expected to panic because the polynomial degree is less than the final_poly_degree in the config.
 #[test]
 #[should_panic]
@@ -143,6 +254,6 @@ fn test_fri_ldt_should_panic() {
     // FRI is kind of flaky depending on indexing luck
     for i in 0..4 {
         let mut rng = ChaCha20Rng::seed_from_u64(i);
-        do_test_fri_ldt(&mut rng, 5);
+        do_test_fri_ldt(&mut rng, 5, 1, 0, &[5, 6, 7, 8, 9]);
     }
 }
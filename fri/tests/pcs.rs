@@ -18,8 +18,25 @@ fn seeded_rng() -> impl Rng {
 }
 
 fn do_test_fri_pcs<Val, Challenge, Challenger, P>(
+    pcs_and_challenger: &(P, Challenger),
+    log_degrees_by_round: &[&[usize]],
+) where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Val: Field,
+    Standard: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+    Challenger: Clone + CanObserve<P::Commitment> + FieldChallenger<Val>,
+{
+    do_test_fri_pcs_with_unopened(pcs_and_challenger, log_degrees_by_round, &[]);
+}
+
+/// Like `do_test_fri_pcs`, but doesn't open the matrices whose `(round, index)` is in `unopened`
+/// at any point.
+fn do_test_fri_pcs_with_unopened<Val, Challenge, Challenger, P>(
     (pcs, challenger): &(P, Challenger),
     log_degrees_by_round: &[&[usize]],
+    unopened: &[(usize, usize)],
 ) where
     P: Pcs<Challenge, Challenger>,
     P::Domain: PolynomialSpace<Val = Val>,
@@ -63,7 +80,18 @@ fn do_test_fri_pcs<Val, Challenge, Challenger, P>(
 
     let points_by_round = log_degrees_by_round
         .iter()
-        .map(|log_degrees| vec![vec![zeta]; log_degrees.len()])
+        .enumerate()
+        .map(|(round, log_degrees)| {
+            (0..log_degrees.len())
+                .map(|mat| {
+                    if unopened.contains(&(round, mat)) {
+                        vec![]
+                    } else {
+                        vec![zeta]
+                    }
+                })
+                .collect_vec()
+        })
         .collect_vec();
     let data_and_points = data_by_round.iter().zip(points_by_round).collect();
     let (opening_by_round, proof) = pcs.open(data_and_points, &mut p_challenger);
@@ -84,7 +112,13 @@ fn do_test_fri_pcs<Val, Challenge, Challenger, P>(
         let claims = domains_and_polys
             .iter()
             .zip(openings)
-            .map(|((domain, _), mat_openings)| (*domain, vec![(zeta, mat_openings[0].clone())]))
+            .map(|((domain, _), mat_openings)| {
+                let points_and_values = mat_openings
+                    .into_iter()
+                    .map(|values| (zeta, values))
+                    .collect_vec();
+                (*domain, points_and_values)
+            })
             .collect_vec();
         (commit, claims)
     })
@@ -147,6 +181,13 @@ macro_rules! make_tests_for_pcs {
             $crate::do_test_fri_pcs(&p, &[&[3, 3], &[2, 2]]);
             $crate::do_test_fri_pcs(&p, &[&[2], &[3, 3]]);
         }
+
+        #[test]
+        fn unopened_matrices() {
+            let p = $p;
+            $crate::do_test_fri_pcs_with_unopened(&p, &[&[3, 3]], &[(0, 1)]);
+            $crate::do_test_fri_pcs_with_unopened(&p, &[&[4, 3], &[3]], &[(0, 0)]);
+        }
     };
 }
 
@@ -168,19 +209,25 @@ mod babybear_fri_pcs {
     type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
     type MyPcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
 
-    fn get_pcs(log_blowup: usize) -> (MyPcs, Challenger) {
+    fn get_pcs(
+        log_blowup: usize,
+        log_folding_arity: usize,
+        cap_height: usize,
+    ) -> (MyPcs, Challenger) {
         let perm = Perm::new_from_rng_128(&mut seeded_rng());
         let hash = MyHash::new(perm.clone());
         let compress = MyCompress::new(perm.clone());
 
-        let val_mmcs = ValMmcs::new(hash, compress);
+        let val_mmcs = ValMmcs::new(hash, compress).with_cap_height(cap_height);
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
 
         let fri_config = FriConfig {
             log_blowup,
             log_final_poly_len: 0,
+            log_folding_arity,
             num_queries: 10,
             proof_of_work_bits: 8,
+            commit_proof_of_work_bits: 0,
             mmcs: challenge_mmcs,
         };
 
@@ -189,10 +236,22 @@ mod babybear_fri_pcs {
     }
 
     mod blowup_1 {
-        make_tests_for_pcs!(super::get_pcs(1));
+        make_tests_for_pcs!(super::get_pcs(1, 1, 0));
     }
     mod blowup_2 {
-        make_tests_for_pcs!(super::get_pcs(2));
+        make_tests_for_pcs!(super::get_pcs(2, 1, 0));
+    }
+    mod arity_4 {
+        make_tests_for_pcs!(super::get_pcs(1, 2, 0));
+    }
+    mod arity_8 {
+        make_tests_for_pcs!(super::get_pcs(2, 3, 0));
+    }
+    mod arity_16 {
+        make_tests_for_pcs!(super::get_pcs(1, 4, 0));
+    }
+    mod cap_height_3 {
+        make_tests_for_pcs!(super::get_pcs(1, 1, 3));
     }
 }
 
@@ -223,7 +282,7 @@ mod m31_fri_pcs {
 
     type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
 
-    fn get_pcs(log_blowup: usize) -> (Pcs, Challenger) {
+    fn get_pcs(log_blowup: usize, log_final_poly_len: usize) -> (Pcs, Challenger) {
         let byte_hash = ByteHash {};
         let field_hash = FieldHash::new(byte_hash);
         let compress = MyCompress::new(byte_hash);
@@ -231,9 +290,11 @@ mod m31_fri_pcs {
         let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
         let fri_config = FriConfig {
             log_blowup,
-            log_final_poly_len: 0,
+            log_final_poly_len,
+            log_folding_arity: 1,
             num_queries: 10,
             proof_of_work_bits: 8,
+            commit_proof_of_work_bits: 0,
             mmcs: challenge_mmcs,
         };
         let pcs = Pcs {
@@ -245,9 +306,28 @@ mod m31_fri_pcs {
     }
 
     mod blowup_1 {
-        make_tests_for_pcs!(super::get_pcs(1));
+        make_tests_for_pcs!(super::get_pcs(1, 0));
     }
     mod blowup_2 {
-        make_tests_for_pcs!(super::get_pcs(2));
+        make_tests_for_pcs!(super::get_pcs(2, 0));
+    }
+
+    // The generic tests commit to polynomials of degree 4, whose first layer folds leave inputs
+    // too small to reach larger final polynomials, so these use larger degrees.
+    #[test]
+    fn final_poly() {
+        for (log_blowup, log_final_poly_len) in [(1, 1), (1, 2), (2, 1), (1, 3)] {
+            let p = get_pcs(log_blowup, log_final_poly_len);
+            let log_min_degree = log_final_poly_len + 1;
+            for i in log_min_degree..log_min_degree + 3 {
+                do_test_fri_pcs(&p, &[&[i]]);
+            }
+            let degrees = (log_min_degree..log_min_degree + 4).collect::<Vec<_>>();
+            do_test_fri_pcs(&p, &[&degrees]);
+            do_test_fri_pcs(
+                &p,
+                &[&[log_min_degree + 2], &[log_min_degree, log_min_degree + 1]],
+            );
+        }
     }
 }
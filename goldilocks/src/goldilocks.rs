@@ -9,8 +9,9 @@ use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use num_bigint::BigUint;
 use p3_field::{
-    exp_10540996611094048183, exp_u64_by_squaring, halve_u64, Field, FieldAlgebra, Packable,
-    PrimeField, PrimeField64, TwoAdicField,
+    decode_canonical_u64, exp_10540996611094048183, exp_u64_by_squaring, halve_u64,
+    CanonicalEncoding, DecodingError, Field, FieldAlgebra, Packable, PrimeField, PrimeField64,
+    TwoAdicField,
 };
 use p3_util::{assume, branch_hint};
 use rand::distributions::{Distribution, Standard};
@@ -249,6 +250,16 @@ impl Field for Goldilocks {
     }
 }
 
+impl CanonicalEncoding for Goldilocks {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_canonical_u64().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        decode_canonical_u64(input, P).map(Self::new)
+    }
+}
+
 impl PrimeField for Goldilocks {
     fn as_canonical_biguint(&self) -> BigUint {
         <Self as PrimeField64>::as_canonical_u64(self).into()
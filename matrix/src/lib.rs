@@ -209,6 +209,29 @@ pub trait Matrix<T: Send + Sync>: Send + Sync {
             .collect_vec()
     }
 
+    /// Pack together a window of `num_rows` rows, each `step` apart, in the same way as
+    /// `vertically_packed_row_pair`.
+    ///
+    /// The result contains `width * num_rows` packed values, the `j`th group of `width` of which
+    /// packs rows `r + j * step` through `r + j * step + P::WIDTH - 1`, wrapping around as needed.
+    #[inline]
+    fn vertically_packed_row_window<P>(&self, r: usize, step: usize, num_rows: usize) -> Vec<P>
+    where
+        T: Copy,
+        P: PackedValue<Value = T>,
+    {
+        (0..num_rows)
+            .flat_map(|j| {
+                let rows = (0..P::WIDTH)
+                    .map(|c| self.row_slice((r + c + j * step) % self.height()))
+                    .collect_vec();
+                (0..self.width())
+                    .map(|c| P::from_fn(|i| rows[i][c]))
+                    .collect_vec()
+            })
+            .collect()
+    }
+
     fn vertically_strided(self, stride: usize, offset: usize) -> VerticallyStridedMatrixView<Self>
     where
         Self: Sized,
@@ -312,6 +335,29 @@ mod tests {
         assert_eq!(m.columnwise_dot_product(&v), expected);
     }
 
+    #[test]
+    fn test_vertically_packed_row_window() {
+        type F = BabyBear;
+        type P = <F as Field>::Packing;
+
+        let m = RowMajorMatrix::<F>::rand(&mut thread_rng(), 1 << 4, 3);
+        assert_eq!(
+            m.vertically_packed_row_window::<P>(5, 3, 2),
+            m.vertically_packed_row_pair::<P>(5, 3)
+        );
+
+        let window = m.vertically_packed_row_window::<P>(5, 3, 4);
+        assert_eq!(window.len(), 4 * 3);
+        for (j, c) in (0..4).flat_map(|j| (0..3).map(move |c| (j, c))) {
+            for i in 0..P::WIDTH {
+                assert_eq!(
+                    window[j * 3 + c].as_slice()[i],
+                    m.get((5 + i + j * 3) % m.height(), c)
+                );
+            }
+        }
+    }
+
     // Mock implementation for testing purposes
     struct MockMatrix {
         data: Vec<Vec<u32>>,
@@ -2,12 +2,12 @@ use alloc::vec::Vec;
 use core::cell::RefCell;
 
 use itertools::Itertools;
-use p3_commit::Mmcs;
-use p3_field::PackedValue;
+use p3_commit::{Mmcs, MmcsHashCount, MultiOpeningMmcs};
+use p3_field::{CanonicalEncoding, PackedValue};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::stack::HorizontalPair;
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
 use serde::de::DeserializeOwned;
@@ -34,15 +34,24 @@ use crate::{MerkleTree, MerkleTreeError, MerkleTreeMmcs};
 /// - `H`: the leaf hasher
 /// - `C`: the digest compression function
 /// - `R`: a random number generator for blinding leaves
+/// - `ARITY`: the number of children of each node of the tree, a power of two
 #[derive(Clone, Debug)]
-pub struct MerkleTreeHidingMmcs<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-{
-    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>,
+pub struct MerkleTreeHidingMmcs<
+    P,
+    PW,
+    H,
+    C,
+    R,
+    const DIGEST_ELEMS: usize,
+    const SALT_ELEMS: usize,
+    const ARITY: usize = 2,
+> {
+    inner: MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>,
     rng: RefCell<R>,
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
-    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
 {
     pub fn new(hash: H, compress: C, rng: R) -> Self {
         let inner = MerkleTreeMmcs::new(hash, compress);
@@ -51,10 +60,19 @@ impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize>
             rng: rng.into(),
         }
     }
+
+    /// Commit to Merkle caps of height `cap_height`; see `MerkleTreeMmcs::with_cap_height`.
+    #[must_use]
+    pub fn with_cap_height(self, cap_height: usize) -> Self {
+        Self {
+            inner: self.inner.with_cap_height(cap_height),
+            ..self
+        }
+    }
 }
 
-impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS>
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    Mmcs<P::Value> for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
 where
     P: PackedValue,
     P::Value: Serialize + DeserializeOwned,
@@ -62,21 +80,30 @@ where
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
     H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
     H: Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
     C: Sync,
     R: Rng + Clone,
-    PW::Value: Eq,
+    PW::Value: Eq + Default,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
     Standard: Distribution<P::Value>,
 {
-    type ProverData<M> =
-        MerkleTree<P::Value, PW::Value, HorizontalPair<M, RowMajorMatrix<P::Value>>, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<
+        P::Value,
+        PW::Value,
+        HorizontalPair<M, RowMajorMatrix<P::Value>>,
+        DIGEST_ELEMS,
+        ARITY,
+    >;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
     /// The first item is salts; the second is the usual Merkle proof (sibling digests).
     type Proof = (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>);
     type Error = MerkleTreeError;
 
+    fn fingerprint(&self) -> Vec<u8> {
+        (self.inner.fingerprint(), SALT_ELEMS).to_bytes()
+    }
+
     fn commit<M: Matrix<P::Value>>(
         &self,
         inputs: Vec<M>,
@@ -101,13 +128,7 @@ where
         (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>),
     ) {
         let (salted_openings, siblings) = self.inner.open_batch(index, prover_data);
-        let (openings, salts): (Vec<_>, Vec<_>) = salted_openings
-            .into_iter()
-            .map(|row| {
-                let (a, b) = row.split_at(row.len() - SALT_ELEMS);
-                (a.to_vec(), b.to_vec())
-            })
-            .unzip();
+        let (openings, salts) = unsalt::<_, SALT_ELEMS>(salted_openings);
         (openings, (salts, siblings))
     }
 
@@ -128,15 +149,163 @@ where
     ) -> Result<(), Self::Error> {
         let (salts, siblings) = proof;
 
+        check_salts::<_, SALT_ELEMS>(dimensions, salts)?;
+        let opened_salted_values = salt(opened_values, salts);
+
+        self.inner.verify_batch(
+            commit,
+            &salted_dimensions::<SALT_ELEMS>(dimensions),
+            index,
+            &opened_salted_values,
+            siblings,
+        )
+    }
+}
+
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    MultiOpeningMmcs<P::Value>
+    for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
+where
+    P: PackedValue,
+    P::Value: Serialize + DeserializeOwned,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
+    H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
+    H: Sync,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+    C: Sync,
+    R: Rng + Clone,
+    PW::Value: Eq + Default,
+    [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
+    Standard: Distribution<P::Value>,
+{
+    /// The first item is the salts at each index; the second is the usual Merkle multi-proof.
+    type MultiProof = (Vec<Vec<Vec<P::Value>>>, Vec<Vec<[PW::Value; DIGEST_ELEMS]>>);
+
+    fn open_batch_many<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<P::Value>>>, Self::MultiProof) {
+        let (salted_openings, proof) = self.inner.open_batch_many(indices, prover_data);
+        let (openings, salts) = salted_openings
+            .into_iter()
+            .map(unsalt::<_, SALT_ELEMS>)
+            .unzip();
+        (openings, (salts, proof))
+    }
+
+    fn verify_batch_many(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<P::Value>>],
+        (salts, proof): &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        if salts.len() != opened_values.len() {
+            return Err(MerkleTreeError::WrongBatchSize);
+        }
+        for salts in salts {
+            check_salts::<_, SALT_ELEMS>(dimensions, salts)?;
+        }
         let opened_salted_values = opened_values
             .iter()
-            .zip(salts.iter())
-            .map(|(opened, salt)| opened.iter().chain(salt.iter()).copied().collect_vec())
+            .zip(salts)
+            .map(|(opened, salts)| salt(opened, salts))
             .collect_vec();
 
-        self.inner
-            .verify_batch(commit, dimensions, index, &opened_salted_values, siblings)
+        self.inner.verify_batch_many(
+            commit,
+            &salted_dimensions::<SALT_ELEMS>(dimensions),
+            indices,
+            &opened_salted_values,
+            proof,
+        )
     }
+
+    fn verify_batch_many_hash_count(
+        num_indices: usize,
+        (_salts, proof): &Self::MultiProof,
+    ) -> usize {
+        MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS, ARITY>::verify_batch_many_hash_count(
+            num_indices,
+            proof,
+        )
+    }
+}
+
+impl<P, PW, H, C, R, const DIGEST_ELEMS: usize, const SALT_ELEMS: usize, const ARITY: usize>
+    MmcsHashCount<P::Value>
+    for MerkleTreeHidingMmcs<P, PW, H, C, R, DIGEST_ELEMS, SALT_ELEMS, ARITY>
+where
+    P: PackedValue,
+    P::Value: Serialize + DeserializeOwned,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
+    H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
+    H: Sync,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+    C: Sync,
+    R: Rng + Clone,
+    PW::Value: Eq + Default,
+    [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
+    Standard: Distribution<P::Value>,
+{
+    fn verify_batch_hash_count((_salts, siblings): &Self::Proof) -> usize {
+        MerkleTreeMmcs::<P, PW, H, C, DIGEST_ELEMS, ARITY>::verify_batch_hash_count(siblings)
+    }
+}
+
+/// Splits each salted row into its values and its salt.
+fn unsalt<T: Clone, const SALT_ELEMS: usize>(
+    salted_rows: Vec<Vec<T>>,
+) -> (Vec<Vec<T>>, Vec<Vec<T>>) {
+    salted_rows
+        .into_iter()
+        .map(|row| {
+            let (a, b) = row.split_at(row.len() - SALT_ELEMS);
+            (a.to_vec(), b.to_vec())
+        })
+        .unzip()
+}
+
+/// Checks that there is a salt of `SALT_ELEMS` elements for each matrix.
+fn check_salts<T, const SALT_ELEMS: usize>(
+    dimensions: &[Dimensions],
+    salts: &[Vec<T>],
+) -> Result<(), MerkleTreeError> {
+    if salts.len() != dimensions.len() {
+        return Err(MerkleTreeError::WrongBatchSize);
+    }
+    match salts.iter().position(|salt| salt.len() != SALT_ELEMS) {
+        Some(matrix) => Err(MerkleTreeError::WrongSaltSize {
+            matrix,
+            salt_len: salts[matrix].len(),
+        }),
+        None => Ok(()),
+    }
+}
+
+/// The dimensions of the salted matrices committed to by the inner MMCS.
+fn salted_dimensions<const SALT_ELEMS: usize>(dimensions: &[Dimensions]) -> Vec<Dimensions> {
+    dimensions
+        .iter()
+        .map(|dims| Dimensions {
+            width: dims.width + SALT_ELEMS,
+            height: dims.height,
+        })
+        .collect()
+}
+
+/// Appends each row's salt to it.
+fn salt<T: Copy>(rows: &[Vec<T>], salts: &[Vec<T>]) -> Vec<Vec<T>> {
+    rows.iter()
+        .zip(salts.iter())
+        .map(|(opened, salt)| opened.iter().chain(salt.iter()).copied().collect_vec())
+        .collect_vec()
 }
 
 #[cfg(test)]
@@ -208,4 +377,31 @@ mod tests {
         let (opened_values, proof) = mmcs.open_batch(17, &prover_data);
         mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
     }
+
+    #[test]
+    fn wrong_salt_size() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress, thread_rng());
+
+        let mats = (0..2)
+            .map(|i| RowMajorMatrix::<F>::rand(&mut thread_rng(), 16, i + 2))
+            .collect_vec();
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+        let (mut opened_values, (mut salts, siblings)) = mmcs.open_batch(5, &prover_data);
+
+        // Moving a value from a row into its salt keeps the salted row intact.
+        let value = opened_values[1].pop().unwrap();
+        salts[1].insert(0, value);
+        assert!(matches!(
+            mmcs.verify_batch(&commit, &dims, 5, &opened_values, &(salts, siblings)),
+            Err(MerkleTreeError::WrongSaltSize {
+                matrix: 1,
+                salt_len: 5
+            })
+        ));
+    }
 }
@@ -8,17 +8,18 @@ use itertools::Itertools;
 use p3_field::PackedValue;
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::*;
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
+use p3_symmetric::{CryptographicHasher, Hash, MerkleCap, PseudoCompressionFunction};
+use p3_util::{log2_ceil_usize, log2_strict_usize};
 use serde::{Deserialize, Serialize};
 use tracing::instrument;
 
-/// A binary Merkle tree for packed data. It has leaves of type `F` and digests of type
-/// `[W; DIGEST_ELEMS]`.
+/// A Merkle tree for packed data. It has leaves of type `F` and digests of type
+/// `[W; DIGEST_ELEMS]`, and each node has `ARITY` children, which must be a power of two.
 ///
 /// This generally shouldn't be used directly. If you're using a Merkle tree as an MMCS,
 /// see `MerkleTreeMmcs`.
 #[derive(Debug, Serialize, Deserialize)]
-pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize> {
+pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize, const ARITY: usize = 2> {
     pub(crate) leaves: Vec<M>,
     // Enable serialization for this type whenever the underlying array type supports it (len 1-32).
     #[serde(bound(serialize = "[W; DIGEST_ELEMS]: Serialize"))]
@@ -28,11 +29,20 @@ pub struct MerkleTree<F, W, M, const DIGEST_ELEMS: usize> {
     _phantom: PhantomData<F>,
 }
 
-impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
-    MerkleTree<F, W, M, DIGEST_ELEMS>
+impl<
+        F: Clone + Send + Sync,
+        W: Clone,
+        M: Matrix<F>,
+        const DIGEST_ELEMS: usize,
+        const ARITY: usize,
+    > MerkleTree<F, W, M, DIGEST_ELEMS, ARITY>
 {
     /// Matrix heights need not be powers of two. However, if the heights of two given matrices
     /// round up to the same power of two, they must be equal.
+    ///
+    /// Each layer of the tree is `ARITY` times shorter than the one below it, and a matrix is
+    /// injected into the layer whose padded length is its padded height. So the padded height of
+    /// each matrix must be that of the tallest divided by a power of `ARITY`, or one.
     #[instrument(name = "build merkle tree", level = "debug", skip_all,
                  fields(dimensions = alloc::format!("{:?}", leaves.iter().map(|l| l.dimensions()).collect::<Vec<_>>())))]
     pub fn new<P, PW, H, C>(h: &H, c: &C, leaves: Vec<M>) -> Self
@@ -42,14 +52,20 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
         H: CryptographicHasher<F, [W; DIGEST_ELEMS]>,
         H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
         H: Sync,
-        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], 2>,
-        C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+        C: PseudoCompressionFunction<[W; DIGEST_ELEMS], ARITY>,
+        C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
         C: Sync,
     {
         assert!(!leaves.is_empty(), "No matrices given?");
 
         assert_eq!(P::WIDTH, PW::WIDTH, "Packing widths must match");
 
+        assert!(
+            ARITY >= 2 && ARITY.is_power_of_two(),
+            "tree arity must be a power of two"
+        );
+        let log_arity = log2_strict_usize(ARITY);
+
         let mut leaves_largest_first = leaves
             .iter()
             .sorted_by_key(|l| Reverse(l.height()))
@@ -67,27 +83,37 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
         );
 
         let max_height = leaves_largest_first.peek().unwrap().height();
+        let log_max_height = log2_ceil_usize(max_height);
+        assert!(
+            leaves
+                .iter()
+                .all(|m| is_injectable::<ARITY>(log_max_height, m.height())),
+            "matrix heights must round up to the tallest height over a power of the arity"
+        );
+
         let tallest_matrices = leaves_largest_first
             .peeking_take_while(|m| m.height() == max_height)
             .collect_vec();
 
-        let mut digest_layers = vec![first_digest_layer::<P, PW, H, M, DIGEST_ELEMS>(
+        let mut digest_layers = vec![first_digest_layer::<P, PW, H, M, DIGEST_ELEMS, ARITY>(
             h,
             tallest_matrices,
         )];
+        // The log of the padded length of the current layer.
+        let mut log_layer_len = log_max_height;
         loop {
             let prev_layer = digest_layers.last().unwrap().as_slice();
             if prev_layer.len() == 1 {
                 break;
             }
-            let next_layer_len = (prev_layer.len() / 2).next_power_of_two();
+            log_layer_len = log_layer_len.saturating_sub(log_arity);
 
             // The matrices that get injected at this layer.
             let matrices_to_inject = leaves_largest_first
-                .peeking_take_while(|m| m.height().next_power_of_two() == next_layer_len)
+                .peeking_take_while(|m| log2_ceil_usize(m.height()) == log_layer_len)
                 .collect_vec();
 
-            let next_digests = compress_and_inject::<P, PW, H, C, M, DIGEST_ELEMS>(
+            let next_digests = compress_and_inject::<P, PW, H, C, M, DIGEST_ELEMS, ARITY>(
                 prev_layer,
                 matrices_to_inject,
                 h,
@@ -110,10 +136,57 @@ impl<F: Clone + Send + Sync, W: Clone, M: Matrix<F>, const DIGEST_ELEMS: usize>
     {
         self.digest_layers.last().unwrap()[0].into()
     }
+
+    /// The rows of each matrix at `index`, as opened by `MerkleTreeMmcs::open_batch`.
+    pub(crate) fn open_rows(&self, index: usize) -> Vec<Vec<F>> {
+        let max_height = self.leaves.iter().map(|m| m.height()).max().unwrap();
+        let log_max_height = log2_ceil_usize(max_height);
+        self.leaves
+            .iter()
+            .map(|matrix| {
+                let log2_height = log2_ceil_usize(matrix.height());
+                let bits_reduced = log_max_height - log2_height;
+                let reduced_index = index >> bits_reduced;
+                matrix.row(reduced_index).collect()
+            })
+            .collect()
+    }
+
+    /// The number of layers in a path from a leaf to the cap of height at most `cap_height`, each
+    /// of which contributes `ARITY - 1` siblings to an opening proof.
+    pub(crate) fn path_len(&self, cap_height: usize) -> usize {
+        let min_height = self.leaves.iter().map(|m| m.height()).min().unwrap();
+        self.digest_layers.len() - 1 - effective_cap_height::<ARITY>(cap_height, min_height)
+    }
+
+    /// Returns the cap of the tree at height at most `cap_height`, padded with default digests to
+    /// `ARITY^h` digests where `h` is its actual height. See `effective_cap_height`.
+    #[must_use]
+    pub fn cap(&self, cap_height: usize) -> MerkleCap<F, W, DIGEST_ELEMS>
+    where
+        W: Copy + Default,
+    {
+        let path_len = self.path_len(cap_height);
+        let mut cap = self.digest_layers[path_len].clone();
+        cap.resize(
+            ARITY.pow((self.digest_layers.len() - 1 - path_len) as u32),
+            [W::default(); DIGEST_ELEMS],
+        );
+        cap.into()
+    }
+}
+
+/// The height of the cap actually committed to for a requested `cap_height`, given the height of
+/// the shortest committed matrix and the arity of the tree.
+///
+/// Each matrix is injected into the tree at the layer whose padded length is its padded height,
+/// so the cap can be no wider than the shortest matrix for every matrix to lie below it.
+pub fn effective_cap_height<const ARITY: usize>(cap_height: usize, min_height: usize) -> usize {
+    cap_height.min(log2_ceil_usize(min_height).div_ceil(log2_strict_usize(ARITY)))
 }
 
 #[instrument(name = "first digest layer", level = "debug", skip_all)]
-fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize>(
+fn first_digest_layer<P, PW, H, M, const DIGEST_ELEMS: usize, const ARITY: usize>(
     h: &H,
     tallest_matrices: Vec<&M>,
 ) -> Vec<[PW::Value; DIGEST_ELEMS]>
@@ -127,11 +200,11 @@ where
 {
     let width = PW::WIDTH;
     let max_height = tallest_matrices[0].height();
-    // we always want to return an even number of digests, except when it's the root.
+    // we always want to return a multiple of `ARITY` digests, except when it's the root.
     let max_height_padded = if max_height == 1 {
         1
     } else {
-        max_height + max_height % 2
+        max_height.next_multiple_of(ARITY)
     };
 
     let default_digest: [PW::Value; DIGEST_ELEMS] = [PW::Value::default(); DIGEST_ELEMS];
@@ -163,9 +236,9 @@ where
     digests
 }
 
-/// Compress `n` digests from the previous layer into `n/2` digests, while potentially mixing in
-/// some leaf data, if there are input matrices with (padded) height `n/2`.
-fn compress_and_inject<P, PW, H, C, M, const DIGEST_ELEMS: usize>(
+/// Compress `n` digests from the previous layer into `n/ARITY` digests, while potentially mixing
+/// in some leaf data, if there are input matrices with (padded) height `n/ARITY`.
+fn compress_and_inject<P, PW, H, C, M, const DIGEST_ELEMS: usize, const ARITY: usize>(
     prev_layer: &[[PW::Value; DIGEST_ELEMS]],
     matrices_to_inject: Vec<&M>,
     h: &H,
@@ -177,40 +250,42 @@ where
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
     H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
     H: Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
     C: Sync,
     M: Matrix<P::Value>,
 {
     if matrices_to_inject.is_empty() {
-        return compress::<PW, C, DIGEST_ELEMS>(prev_layer, c);
+        return compress::<PW, C, DIGEST_ELEMS, ARITY>(prev_layer, c);
     }
 
     let width = PW::WIDTH;
     let next_len = matrices_to_inject[0].height();
-    // We always want to return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
-        1
-    } else {
-        (prev_layer.len() / 2 + 1) & !1
-    };
+    let next_len_padded = next_layer_len_padded::<ARITY>(prev_layer.len());
 
     let default_digest: [PW::Value; DIGEST_ELEMS] = [PW::Value::default(); DIGEST_ELEMS];
+    let packed_default_digest: [PW; DIGEST_ELEMS] = default_digest.map(|x| PW::from_fn(|_| x));
     let mut next_digests = vec![default_digest; next_len_padded];
     next_digests[0..next_len]
         .par_chunks_exact_mut(width)
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| PW::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let mut packed_digest = c.compress([left, right]);
+            let children = array::from_fn(|child| {
+                array::from_fn(|j| PW::from_fn(|k| prev_layer[ARITY * (first_row + k) + child][j]))
+            });
+            let packed_digest = c.compress(children);
             let tallest_digest = h.hash_iter(
                 matrices_to_inject
                     .iter()
                     .flat_map(|m| m.vertically_packed_row(first_row)),
             );
-            packed_digest = c.compress([packed_digest, tallest_digest]);
+            let packed_digest = inject::<_, _, DIGEST_ELEMS, ARITY>(
+                c,
+                packed_digest,
+                tallest_digest,
+                packed_default_digest,
+            );
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
@@ -219,45 +294,38 @@ where
     // If our packing width did not divide next_len, fall back to single-threaded scalar code
     // for the last bit.
     for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
+        let digest = c.compress(array::from_fn(|child| prev_layer[ARITY * i + child]));
         let rows_digest = h.hash_iter(matrices_to_inject.iter().flat_map(|m| m.row(i)));
-        next_digests[i] = c.compress([digest, rows_digest]);
+        next_digests[i] =
+            inject::<_, _, DIGEST_ELEMS, ARITY>(c, digest, rows_digest, default_digest);
     }
 
     // At this point, we've exceeded the height of the matrices to inject, so we continue the
     // process above except with default_digest in place of an input digest.
-    // We only need go as far as half the length of the previous layer.
-    for i in next_len..(prev_layer.len() / 2) {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        let digest = c.compress([left, right]);
-        next_digests[i] = c.compress([digest, default_digest]);
+    // We only need go as far as the length of the previous layer divided by `ARITY`.
+    for i in next_len..(prev_layer.len() / ARITY) {
+        let digest = c.compress(array::from_fn(|child| prev_layer[ARITY * i + child]));
+        next_digests[i] =
+            inject::<_, _, DIGEST_ELEMS, ARITY>(c, digest, default_digest, default_digest);
     }
 
     next_digests
 }
 
-/// Compress `n` digests from the previous layer into `n/2` digests.
-fn compress<P, C, const DIGEST_ELEMS: usize>(
+/// Compress `n` digests from the previous layer into `n/ARITY` digests.
+fn compress<P, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
     prev_layer: &[[P::Value; DIGEST_ELEMS]],
     c: &C,
 ) -> Vec<[P::Value; DIGEST_ELEMS]>
 where
     P: PackedValue,
-    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[P; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[P::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[P; DIGEST_ELEMS], ARITY>,
     C: Sync,
 {
     let width = P::WIDTH;
-    // Always return an even number of digests, except when it's the root.
-    let next_len_padded = if prev_layer.len() == 2 {
-        1
-    } else {
-        (prev_layer.len() / 2 + 1) & !1
-    };
-    let next_len = prev_layer.len() / 2;
+    let next_len_padded = next_layer_len_padded::<ARITY>(prev_layer.len());
+    let next_len = prev_layer.len() / ARITY;
 
     let default_digest: [P::Value; DIGEST_ELEMS] = [P::Value::default(); DIGEST_ELEMS];
     let mut next_digests = vec![default_digest; next_len_padded];
@@ -267,9 +335,10 @@ where
         .enumerate()
         .for_each(|(i, digests_chunk)| {
             let first_row = i * width;
-            let left = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k)][j]));
-            let right = array::from_fn(|j| P::from_fn(|k| prev_layer[2 * (first_row + k) + 1][j]));
-            let packed_digest = c.compress([left, right]);
+            let children = array::from_fn(|child| {
+                array::from_fn(|j| P::from_fn(|k| prev_layer[ARITY * (first_row + k) + child][j]))
+            });
+            let packed_digest = c.compress(children);
             for (dst, src) in digests_chunk.iter_mut().zip(unpack_array(packed_digest)) {
                 *dst = src;
             }
@@ -278,15 +347,50 @@ where
     // If our packing width did not divide next_len, fall back to single-threaded scalar code
     // for the last bit.
     for i in (next_len / width * width)..next_len {
-        let left = prev_layer[2 * i];
-        let right = prev_layer[2 * i + 1];
-        next_digests[i] = c.compress([left, right]);
+        next_digests[i] = c.compress(array::from_fn(|child| prev_layer[ARITY * i + child]));
     }
 
     // Everything has been initialized so we can safely cast.
     next_digests
 }
 
+/// The padded length of the layer above one of length `prev_len`, which is always a multiple of
+/// `ARITY`, except when it's the root.
+const fn next_layer_len_padded<const ARITY: usize>(prev_len: usize) -> usize {
+    if prev_len == ARITY {
+        1
+    } else {
+        (prev_len / ARITY).next_multiple_of(ARITY)
+    }
+}
+
+/// Mixes the digest of some injected rows into the digest of a node, by compressing the two with
+/// `ARITY - 2` default digests.
+#[inline]
+pub(crate) fn inject<T, C, const DIGEST_ELEMS: usize, const ARITY: usize>(
+    c: &C,
+    digest: [T; DIGEST_ELEMS],
+    rows_digest: [T; DIGEST_ELEMS],
+    default_digest: [T; DIGEST_ELEMS],
+) -> [T; DIGEST_ELEMS]
+where
+    T: Copy,
+    C: PseudoCompressionFunction<[T; DIGEST_ELEMS], ARITY>,
+{
+    c.compress(array::from_fn(|i| match i {
+        0 => digest,
+        1 => rows_digest,
+        _ => default_digest,
+    }))
+}
+
+/// Whether a matrix of the given height can be injected into a tree of arity `ARITY` whose tallest
+/// matrix has height at most `2^log_max_height`.
+pub(crate) fn is_injectable<const ARITY: usize>(log_max_height: usize, height: usize) -> bool {
+    let log_height = log2_ceil_usize(height);
+    log_height == 0 || (log_max_height - log_height) % log2_strict_usize(ARITY) == 0
+}
+
 /// Converts a packed array `[P; N]` into its underlying `P::WIDTH` scalar arrays.
 #[inline]
 fn unpack_array<P: PackedValue, const N: usize>(
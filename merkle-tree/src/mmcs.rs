@@ -1,102 +1,159 @@
+use alloc::collections::btree_map::Entry;
+use alloc::collections::{BTreeMap, BTreeSet};
 use alloc::vec::Vec;
+use core::array;
 use core::cmp::Reverse;
 use core::marker::PhantomData;
 
-use itertools::Itertools;
-use p3_commit::Mmcs;
-use p3_field::PackedValue;
+use itertools::{izip, Itertools};
+use p3_commit::{Mmcs, MmcsHashCount, MultiOpeningMmcs};
+use p3_field::{CanonicalEncoding, PackedValue};
 use p3_matrix::{Dimensions, Matrix};
-use p3_symmetric::{CryptographicHasher, Hash, PseudoCompressionFunction};
-use p3_util::log2_ceil_usize;
+use p3_symmetric::{CryptographicHasher, MerkleCap, PseudoCompressionFunction};
+use p3_util::{log2_ceil_usize, log2_strict_usize};
 use serde::{Deserialize, Serialize};
 
-use crate::MerkleTree;
-use crate::MerkleTreeError::{RootMismatch, WrongBatchSize, WrongHeight};
+use crate::MerkleTreeError::{
+    IncompatibleHeight, IndexOutOfBounds, RootMismatch, WrongBatchSize, WrongCapSize, WrongHeight,
+    WrongWidth,
+};
+use crate::{effective_cap_height, inject, is_injectable, MerkleTree};
 
 /// A vector commitment scheme backed by a `MerkleTree`.
 ///
+/// Commitments are caps of the tree rather than its root; see `MerkleTree::cap`. The cap height
+/// is zero, i.e. the cap is the root, unless set with `with_cap_height`.
+///
 /// Generics:
 /// - `P`: a leaf value
 /// - `PW`: an element of a digest
 /// - `H`: the leaf hasher
-/// - `C`: the digest compression function
+/// - `C`: the digest compression function, which compresses `ARITY` digests into one
+/// - `ARITY`: the number of children of each node of the tree, a power of two
 #[derive(Copy, Clone, Debug)]
-pub struct MerkleTreeMmcs<P, PW, H, C, const DIGEST_ELEMS: usize> {
+pub struct MerkleTreeMmcs<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize = 2> {
     hash: H,
     compress: C,
+    cap_height: usize,
     _phantom: PhantomData<(P, PW)>,
 }
 
-#[derive(Debug)]
+#[derive(Clone, Debug)]
 pub enum MerkleTreeError {
+    /// The number of opened rows differs from the number of matrices, or from the number of
+    /// indices, or no matrices were given.
     WrongBatchSize,
-    WrongWidth,
+    /// The opened row of a matrix doesn't have the width of the matrix.
+    WrongWidth {
+        matrix: usize,
+        width: usize,
+        row_len: usize,
+    },
+    /// A matrix couldn't have been committed to alongside the others: its height is zero, it rounds
+    /// up to the same power of two as a matrix of another height, or it falls between two layers
+    /// of the tree.
+    IncompatibleHeight {
+        matrix: usize,
+        height: usize,
+    },
+    /// An opened index is beyond the height of the tallest matrix.
+    IndexOutOfBounds {
+        index: usize,
+        max_height: usize,
+    },
+    /// The proof doesn't have the number of siblings of a tree with the given tallest matrix.
     WrongHeight {
         max_height: usize,
         num_siblings: usize,
     },
     RootMismatch,
+    WrongCapSize {
+        cap_height: usize,
+        cap_len: usize,
+    },
+    /// The salt opened with a matrix by `MerkleTreeHidingMmcs` doesn't have `SALT_ELEMS` elements.
+    WrongSaltSize {
+        matrix: usize,
+        salt_len: usize,
+    },
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS> {
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize>
+    MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
+{
     pub const fn new(hash: H, compress: C) -> Self {
         Self {
             hash,
             compress,
+            cap_height: 0,
             _phantom: PhantomData,
         }
     }
+
+    /// Commit to the cap of `ARITY^cap_height` digests below the root rather than the root
+    /// itself, which shortens each opening proof by `cap_height` layers of siblings. The cap is
+    /// lower if the shortest committed matrix is shorter than the cap; see `effective_cap_height`.
+    #[must_use]
+    pub const fn with_cap_height(mut self, cap_height: usize) -> Self {
+        self.cap_height = cap_height;
+        self
+    }
+
+    pub const fn cap_height(&self) -> usize {
+        self.cap_height
+    }
 }
 
-impl<P, PW, H, C, const DIGEST_ELEMS: usize> Mmcs<P::Value>
-    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS>
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize> Mmcs<P::Value>
+    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
 where
     P: PackedValue,
     PW: PackedValue,
     H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
     H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
     H: Sync,
-    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], 2>,
-    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], 2>,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
     C: Sync,
-    PW::Value: Eq,
+    PW::Value: Eq + Default,
     [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
 {
-    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>;
-    type Commitment = Hash<P::Value, PW::Value, DIGEST_ELEMS>;
+    type ProverData<M> = MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY>;
+    type Commitment = MerkleCap<P::Value, PW::Value, DIGEST_ELEMS>;
+    /// For each layer of the tree below the cap, bottom first, the `ARITY - 1` siblings of the
+    /// node on the opened path, in order of position.
     type Proof = Vec<[PW::Value; DIGEST_ELEMS]>;
     type Error = MerkleTreeError;
 
+    fn fingerprint(&self) -> Vec<u8> {
+        (ARITY, self.cap_height).to_bytes()
+    }
+
     fn commit<M: Matrix<P::Value>>(
         &self,
         inputs: Vec<M>,
     ) -> (Self::Commitment, Self::ProverData<M>) {
         let tree = MerkleTree::new::<P, PW, H, C>(&self.hash, &self.compress, inputs);
-        let root = tree.root();
-        (root, tree)
+        let cap = tree.cap(self.cap_height);
+        (cap, tree)
     }
 
     fn open_batch<M: Matrix<P::Value>>(
         &self,
         index: usize,
-        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS>,
+        prover_data: &MerkleTree<P::Value, PW::Value, M, DIGEST_ELEMS, ARITY>,
     ) -> (Vec<Vec<P::Value>>, Vec<[PW::Value; DIGEST_ELEMS]>) {
-        let max_height = self.get_max_height(prover_data);
-        let log_max_height = log2_ceil_usize(max_height);
-
-        let openings = prover_data
-            .leaves
-            .iter()
-            .map(|matrix| {
-                let log2_height = log2_ceil_usize(matrix.height());
-                let bits_reduced = log_max_height - log2_height;
-                let reduced_index = index >> bits_reduced;
-                matrix.row(reduced_index).collect()
+        let openings = prover_data.open_rows(index);
+
+        let log_arity = log2_strict_usize(ARITY);
+        let proof: Vec<_> = (0..prover_data.path_len(self.cap_height))
+            .flat_map(|i| {
+                let pos = index >> (i * log_arity);
+                let first_child = pos / ARITY * ARITY;
+                (first_child..first_child + ARITY)
+                    .filter(move |&child| child != pos)
+                    .map(move |child| prover_data.digest_layers[i][child])
             })
-            .collect_vec();
-
-        let proof: Vec<_> = (0..log_max_height)
-            .map(|i| prover_data.digest_layers[i][(index >> i) ^ 1])
             .collect();
 
         (openings, proof)
@@ -118,21 +175,22 @@ where
         proof: &Self::Proof,
     ) -> Result<(), Self::Error> {
         // Check that the openings have the correct shape.
-        if dimensions.len() != opened_values.len() {
-            return Err(WrongBatchSize);
-        }
+        let max_height = check_dimensions::<ARITY>(dimensions)?;
+        check_widths(dimensions, opened_values)?;
+        check_index(index, max_height)?;
 
-        // TODO: Disabled for now since TwoAdicFriPcs and CirclePcs currently pass 0 for width.
-        // for (dims, opened_vals) in dimensions.iter().zip(opened_values) {
-        //     if opened_vals.len() != dims.width {
-        //         return Err(WrongWidth);
-        //     }
-        // }
-
-        // TODO: Disabled for now, CirclePcs sometimes passes a height that's off by 1 bit.
-        let max_height = dimensions.iter().map(|dim| dim.height).max().unwrap();
+        let log_arity = log2_strict_usize(ARITY);
         let log_max_height = log2_ceil_usize(max_height);
-        if proof.len() != log_max_height {
+        let min_height = dimensions.iter().map(|dim| dim.height).min().unwrap();
+        let cap_height = effective_cap_height::<ARITY>(self.cap_height, min_height);
+        if commit.len() != ARITY.pow(cap_height as u32) {
+            return Err(WrongCapSize {
+                cap_height,
+                cap_len: commit.len(),
+            });
+        }
+        let num_layers = log_max_height.div_ceil(log_arity);
+        if proof.len() != (ARITY - 1) * (num_layers - cap_height) {
             return Err(WrongHeight {
                 max_height,
                 num_siblings: proof.len(),
@@ -145,36 +203,32 @@ where
             .sorted_by_key(|(_, dims)| Reverse(dims.height))
             .peekable();
 
-        let mut curr_height_padded = heights_tallest_first
-            .peek()
-            .unwrap()
-            .1
-            .height
-            .next_power_of_two();
+        let mut curr_log_height = log2_ceil_usize(heights_tallest_first.peek().unwrap().1.height);
 
         let mut root = self.hash.hash_iter_slices(
             heights_tallest_first
-                .peeking_take_while(|(_, dims)| {
-                    dims.height.next_power_of_two() == curr_height_padded
-                })
+                .peeking_take_while(|(_, dims)| log2_ceil_usize(dims.height) == curr_log_height)
                 .map(|(i, _)| opened_values[i].as_slice()),
         );
-
-        for &sibling in proof.iter() {
-            let (left, right) = if index & 1 == 0 {
-                (root, sibling)
-            } else {
-                (sibling, root)
-            };
-
-            root = self.compress.compress([left, right]);
-            index >>= 1;
-            curr_height_padded >>= 1;
+        let default_digest = [PW::Value::default(); DIGEST_ELEMS];
+
+        for siblings in proof.chunks_exact(ARITY - 1) {
+            let mut siblings = siblings.iter();
+            let pos = index % ARITY;
+            root = self.compress.compress(array::from_fn(|child| {
+                if child == pos {
+                    root
+                } else {
+                    *siblings.next().unwrap()
+                }
+            }));
+            index /= ARITY;
+            curr_log_height = curr_log_height.saturating_sub(log_arity);
 
             let next_height = heights_tallest_first
                 .peek()
                 .map(|(_, dims)| dims.height)
-                .filter(|h| h.next_power_of_two() == curr_height_padded);
+                .filter(|&h| log2_ceil_usize(h) == curr_log_height);
             if let Some(next_height) = next_height {
                 let next_height_openings_digest = self.hash.hash_iter_slices(
                     heights_tallest_first
@@ -182,34 +236,334 @@ where
                         .map(|(i, _)| opened_values[i].as_slice()),
                 );
 
-                root = self.compress.compress([root, next_height_openings_digest]);
+                root = inject::<_, _, DIGEST_ELEMS, ARITY>(
+                    &self.compress,
+                    root,
+                    next_height_openings_digest,
+                    default_digest,
+                );
+            }
+        }
+
+        // After the siblings, `index` is the position of the computed digest in the cap.
+        if commit.digests().get(index) == Some(&root) {
+            Ok(())
+        } else {
+            Err(RootMismatch)
+        }
+    }
+}
+
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize> MultiOpeningMmcs<P::Value>
+    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
+where
+    P: PackedValue,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
+    H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
+    H: Sync,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+    C: Sync,
+    PW::Value: Eq + Default,
+    [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
+{
+    /// For each layer of the tree below the cap, bottom first, the siblings of the nodes on the
+    /// opened paths which aren't themselves on an opened path, in order of position.
+    type MultiProof = Vec<Vec<[PW::Value; DIGEST_ELEMS]>>;
+
+    fn open_batch_many<M: Matrix<P::Value>>(
+        &self,
+        indices: &[usize],
+        prover_data: &Self::ProverData<M>,
+    ) -> (Vec<Vec<Vec<P::Value>>>, Self::MultiProof) {
+        let openings = indices
+            .iter()
+            .map(|&index| prover_data.open_rows(index))
+            .collect();
+
+        // The positions of the nodes on the opened paths in the current layer.
+        let mut positions: BTreeSet<usize> = indices.iter().copied().collect();
+        let proof = (0..prover_data.path_len(self.cap_height))
+            .map(|i| {
+                let siblings: Vec<_> = positions
+                    .iter()
+                    .map(|&pos| pos / ARITY)
+                    .dedup()
+                    .flat_map(|parent| parent * ARITY..(parent + 1) * ARITY)
+                    .filter(|child| !positions.contains(child))
+                    .map(|child| prover_data.digest_layers[i][child])
+                    .collect();
+                positions = positions.iter().map(|&pos| pos / ARITY).collect();
+                siblings
+            })
+            .collect();
+
+        (openings, proof)
+    }
+
+    fn verify_batch_many(
+        &self,
+        commit: &Self::Commitment,
+        dimensions: &[Dimensions],
+        indices: &[usize],
+        opened_values: &[Vec<Vec<P::Value>>],
+        proof: &Self::MultiProof,
+    ) -> Result<(), Self::Error> {
+        // Check that the openings have the correct shape.
+        let max_height = check_dimensions::<ARITY>(dimensions)?;
+        if indices.len() != opened_values.len() {
+            return Err(WrongBatchSize);
+        }
+        for (&index, opened) in izip!(indices, opened_values) {
+            check_widths(dimensions, opened)?;
+            check_index(index, max_height)?;
+        }
+
+        let log_arity = log2_strict_usize(ARITY);
+        let log_max_height = log2_ceil_usize(max_height);
+        let wrong_height = WrongHeight {
+            max_height,
+            num_siblings: proof.iter().map(Vec::len).sum(),
+        };
+        let min_height = dimensions.iter().map(|dim| dim.height).min().unwrap();
+        let cap_height = effective_cap_height::<ARITY>(self.cap_height, min_height);
+        if commit.len() != ARITY.pow(cap_height as u32) {
+            return Err(WrongCapSize {
+                cap_height,
+                cap_len: commit.len(),
+            });
+        }
+        if proof.len() != log_max_height.div_ceil(log_arity) - cap_height {
+            return Err(wrong_height);
+        }
+
+        let mut heights_tallest_first = dimensions
+            .iter()
+            .enumerate()
+            .sorted_by_key(|(_, dims)| Reverse(dims.height))
+            .peekable();
+
+        let mut curr_log_height = log2_ceil_usize(heights_tallest_first.peek().unwrap().1.height);
+
+        let tallest = heights_tallest_first
+            .peeking_take_while(|(_, dims)| log2_ceil_usize(dims.height) == curr_log_height)
+            .map(|(i, _)| i)
+            .collect_vec();
+        // The digests of the nodes on the opened paths in the current layer, by position.
+        let mut nodes = BTreeMap::new();
+        for (&index, opened) in izip!(indices, opened_values) {
+            let digest = self
+                .hash
+                .hash_iter_slices(tallest.iter().map(|&i| opened[i].as_slice()));
+            insert_node(&mut nodes, index, digest)?;
+        }
+        let default_digest = [PW::Value::default(); DIGEST_ELEMS];
+
+        for (layer, siblings) in proof.iter().enumerate() {
+            let mut siblings = siblings.iter();
+            let mut parents = BTreeMap::new();
+            let mut nodes_iter = nodes.into_iter().peekable();
+            while let Some(&(pos, _)) = nodes_iter.peek() {
+                let parent = pos / ARITY;
+                let mut children = [default_digest; ARITY];
+                for (i, child) in children.iter_mut().enumerate() {
+                    // A node's siblings are only given if they aren't on an opened path themselves.
+                    *child =
+                        match nodes_iter.next_if(|&(next_pos, _)| next_pos == parent * ARITY + i) {
+                            Some((_, digest)) => digest,
+                            None => *siblings.next().ok_or_else(|| wrong_height.clone())?,
+                        };
+                }
+                parents.insert(parent, self.compress.compress(children));
+            }
+            if siblings.next().is_some() {
+                return Err(wrong_height);
+            }
+            nodes = parents;
+            curr_log_height = curr_log_height.saturating_sub(log_arity);
+
+            let next_height = heights_tallest_first
+                .peek()
+                .map(|(_, dims)| dims.height)
+                .filter(|&h| log2_ceil_usize(h) == curr_log_height);
+            if let Some(next_height) = next_height {
+                let injected = heights_tallest_first
+                    .peeking_take_while(|(_, dims)| dims.height == next_height)
+                    .map(|(i, _)| i)
+                    .collect_vec();
+                let mut injected_nodes = BTreeMap::new();
+                for (&index, opened) in izip!(indices, opened_values) {
+                    let pos = index >> ((layer + 1) * log_arity);
+                    let next_height_openings_digest = self
+                        .hash
+                        .hash_iter_slices(injected.iter().map(|&i| opened[i].as_slice()));
+                    let digest = inject::<_, _, DIGEST_ELEMS, ARITY>(
+                        &self.compress,
+                        nodes[&pos],
+                        next_height_openings_digest,
+                        default_digest,
+                    );
+                    insert_node(&mut injected_nodes, pos, digest)?;
+                }
+                nodes = injected_nodes;
             }
         }
 
-        if commit == &root {
+        if nodes
+            .into_iter()
+            .all(|(pos, digest)| commit.digests().get(pos) == Some(&digest))
+        {
             Ok(())
         } else {
             Err(RootMismatch)
         }
     }
+
+    fn verify_batch_many_hash_count(num_indices: usize, proof: &Self::MultiProof) -> usize {
+        // One hash of the opened rows at each index, and one compression per node on the opened
+        // paths above the leaves. Each of such a node's `ARITY` children is either on an opened
+        // path, or given as a sibling, so a layer with `n` nodes on the opened paths has
+        // `ARITY * n` minus its siblings below it. Repeated indices share their nodes, so we count
+        // down from the top rather than up from the indices. The proof doesn't say how many cap
+        // nodes are on the opened paths, so we take the fewest consistent with the siblings, which
+        // is exact when the cap is the root.
+        let nodes_by_layer = |num_top_nodes: usize| {
+            proof
+                .iter()
+                .rev()
+                .scan(num_top_nodes, |num_nodes, siblings| {
+                    let num_parents = *num_nodes;
+                    // Every node on an opened path has a child on it too.
+                    *num_nodes = (ARITY * num_parents).checked_sub(siblings.len())?;
+                    (*num_nodes >= num_parents).then_some(num_parents)
+                })
+                .collect_vec()
+        };
+        let num_compressions: usize = (1..)
+            .map(nodes_by_layer)
+            .find(|nodes| nodes.len() == proof.len())
+            .unwrap()
+            .into_iter()
+            .sum();
+        num_indices + num_compressions
+    }
+}
+
+impl<P, PW, H, C, const DIGEST_ELEMS: usize, const ARITY: usize> MmcsHashCount<P::Value>
+    for MerkleTreeMmcs<P, PW, H, C, DIGEST_ELEMS, ARITY>
+where
+    P: PackedValue,
+    PW: PackedValue,
+    H: CryptographicHasher<P::Value, [PW::Value; DIGEST_ELEMS]>,
+    H: CryptographicHasher<P, [PW; DIGEST_ELEMS]>,
+    H: Sync,
+    C: PseudoCompressionFunction<[PW::Value; DIGEST_ELEMS], ARITY>,
+    C: PseudoCompressionFunction<[PW; DIGEST_ELEMS], ARITY>,
+    C: Sync,
+    PW::Value: Eq + Default,
+    [PW::Value; DIGEST_ELEMS]: Serialize + for<'de> Deserialize<'de>,
+{
+    fn verify_batch_hash_count(proof: &Self::Proof) -> usize {
+        // One hash of the opened rows, and one compression per layer of siblings.
+        1 + proof.len() / (ARITY - 1)
+    }
+}
+
+/// Checks that matrices of the given dimensions could have been committed to together, and returns
+/// the height of the tallest.
+fn check_dimensions<const ARITY: usize>(
+    dimensions: &[Dimensions],
+) -> Result<usize, MerkleTreeError> {
+    let max_height = dimensions
+        .iter()
+        .map(|dims| dims.height)
+        .max()
+        .ok_or(WrongBatchSize)?;
+    let log_max_height = log2_ceil_usize(max_height);
+    for (matrix, dims) in dimensions.iter().enumerate() {
+        let log_height = log2_ceil_usize(dims.height);
+        // Matrices whose heights round up to the same power of two are hashed into the same
+        // leaves, so must have the same height.
+        let compatible = dims.height > 0
+            && is_injectable::<ARITY>(log_max_height, dims.height)
+            && dimensions[..matrix].iter().all(|other| {
+                other.height == dims.height || log2_ceil_usize(other.height) != log_height
+            });
+        if !compatible {
+            return Err(IncompatibleHeight {
+                matrix,
+                height: dims.height,
+            });
+        }
+    }
+    Ok(max_height)
+}
+
+/// Checks that there is an opened row for each matrix, of the width of the matrix.
+fn check_widths<T>(
+    dimensions: &[Dimensions],
+    opened_values: &[Vec<T>],
+) -> Result<(), MerkleTreeError> {
+    if opened_values.len() != dimensions.len() {
+        return Err(WrongBatchSize);
+    }
+    for (matrix, (dims, row)) in izip!(dimensions, opened_values).enumerate() {
+        if row.len() != dims.width {
+            return Err(WrongWidth {
+                matrix,
+                width: dims.width,
+                row_len: row.len(),
+            });
+        }
+    }
+    Ok(())
+}
+
+const fn check_index(index: usize, max_height: usize) -> Result<(), MerkleTreeError> {
+    if index < max_height {
+        Ok(())
+    } else {
+        Err(IndexOutOfBounds { index, max_height })
+    }
+}
+
+/// Records the digest of the node at `pos`, as computed from the openings at some index. Openings
+/// at several indices may share a node, and must then agree on its digest.
+fn insert_node<D: Eq>(
+    nodes: &mut BTreeMap<usize, D>,
+    pos: usize,
+    digest: D,
+) -> Result<(), MerkleTreeError> {
+    match nodes.entry(pos) {
+        Entry::Vacant(entry) => {
+            entry.insert(digest);
+        }
+        Entry::Occupied(entry) if *entry.get() == digest => {}
+        Entry::Occupied(_) => return Err(RootMismatch),
+    }
+    Ok(())
 }
 
 #[cfg(test)]
 mod tests {
     use alloc::vec;
+    use core::array;
 
     use itertools::Itertools;
     use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
-    use p3_commit::Mmcs;
+    use p3_commit::{Mmcs, MmcsHashCount, MultiOpeningMmcs};
     use p3_field::{Field, FieldAlgebra};
     use p3_matrix::dense::RowMajorMatrix;
     use p3_matrix::{Dimensions, Matrix};
     use p3_symmetric::{
-        CryptographicHasher, PaddingFreeSponge, PseudoCompressionFunction, TruncatedPermutation,
+        CompressionFunctionFromHasher, CryptographicHasher, PaddingFreeSponge,
+        PseudoCompressionFunction, TruncatedPermutation,
     };
     use rand::thread_rng;
 
     use super::MerkleTreeMmcs;
+    use crate::MerkleTreeError;
 
     type F = BabyBear;
 
@@ -219,6 +573,13 @@ mod tests {
     type MyMmcs =
         MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress, 8>;
 
+    type MyCompress4 = CompressionFunctionFromHasher<MyHash, 4, 8>;
+    type MyMmcs4 =
+        MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress4, 8, 4>;
+    type MyCompress8 = CompressionFunctionFromHasher<MyHash, 8, 8>;
+    type MyMmcs8 =
+        MerkleTreeMmcs<<F as Field>::Packing, <F as Field>::Packing, MyHash, MyCompress8, 8, 8>;
+
     #[test]
     fn commit_single_1x8() {
         let perm = Perm::new_from_rng_128(&mut thread_rng());
@@ -558,4 +919,550 @@ mod tests {
         mmcs.verify_batch(&commit, &dims, 17, &opened_values, &proof)
             .expect("expected verification to succeed");
     }
+
+    #[test]
+    fn commit_caps() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 32, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 16, 5),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (root, _) = MyMmcs::new(hash.clone(), compress.clone()).commit(mats.clone());
+
+        for cap_height in 0..=4 {
+            let mmcs = MyMmcs::new(hash.clone(), compress.clone()).with_cap_height(cap_height);
+            let (cap, prover_data) = mmcs.commit(mats.clone());
+            assert_eq!(cap.len(), 1 << cap_height);
+
+            // Compressing the cap's digests in pairs gives back the root.
+            let mut layer = cap.digests().to_vec();
+            while layer.len() > 1 {
+                layer = layer
+                    .chunks(2)
+                    .map(|pair| compress.compress([pair[0], pair[1]]))
+                    .collect();
+            }
+            assert_eq!(root, layer[0]);
+
+            for index in [0, 13, 31] {
+                let (opened_values, proof) = mmcs.open_batch(index, &prover_data);
+                assert_eq!(proof.len(), 5 - cap_height);
+                mmcs.verify_batch(&cap, &dims, index, &opened_values, &proof)
+                    .expect("expected verification to succeed");
+            }
+        }
+    }
+
+    #[test]
+    fn cap_below_shortest_matrix() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress).with_cap_height(3);
+
+        // The matrix of 2 rows is injected one layer below the root, so the cap is that layer.
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 32, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 2, 5),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (cap, prover_data) = mmcs.commit(mats);
+        assert_eq!(cap.len(), 2);
+
+        let (opened_values, proof) = mmcs.open_batch(21, &prover_data);
+        assert_eq!(proof.len(), 4);
+        mmcs.verify_batch(&cap, &dims, 21, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        // The opening is checked against the cap's second digest only.
+        let mut digests = cap.digests().to_vec();
+        digests[0][0] += F::ONE;
+        mmcs.verify_batch(&digests.clone().into(), &dims, 21, &opened_values, &proof)
+            .expect("expected verification to succeed");
+        digests[1][0] += F::ONE;
+        assert!(matches!(
+            mmcs.verify_batch(&digests.clone().into(), &dims, 21, &opened_values, &proof),
+            Err(MerkleTreeError::RootMismatch)
+        ));
+
+        digests.truncate(1);
+        assert!(matches!(
+            mmcs.verify_batch(&digests.into(), &dims, 21, &opened_values, &proof),
+            Err(MerkleTreeError::WrongCapSize {
+                cap_height: 1,
+                cap_len: 1
+            })
+        ));
+    }
+
+    #[test]
+    fn open_many() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 1000, 8),
+            RowMajorMatrix::<F>::rand(&mut rng, 70, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 5),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        // Indices may repeat, share a parent, or share a row of a shorter matrix.
+        let indices = [6, 7, 500, 6, 999, 0, 501];
+
+        for cap_height in [0, 2] {
+            let mmcs = MyMmcs::new(hash.clone(), compress.clone()).with_cap_height(cap_height);
+            let (commit, prover_data) = mmcs.commit(mats.clone());
+            let (opened_values, proof) = mmcs.open_batch_many(&indices, &prover_data);
+
+            for (&index, opened) in indices.iter().zip(&opened_values) {
+                assert_eq!(opened, &mmcs.open_batch(index, &prover_data).0);
+            }
+            mmcs.verify_batch_many(&commit, &dims, &indices, &opened_values, &proof)
+                .expect("expected verification to succeed");
+
+            // There are fewer siblings than in the paths of the six distinct indices, as each
+            // layer's siblings are given at most once, and none which are on another opened path.
+            let num_siblings: usize = proof.iter().map(Vec::len).sum();
+            assert_eq!(proof.len(), 10 - cap_height);
+            assert!(num_siblings < 6 * (10 - cap_height));
+        }
+    }
+
+    #[test]
+    fn open_many_hash_count() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let (_, prover_data) = mmcs.commit(vec![RowMajorMatrix::<F>::rand(&mut rng, 32, 4)]);
+        // The paths of indices 12 and 13 meet above the leaves, and those of 9 and 12 two layers
+        // higher.
+        let (_, proof) = mmcs.open_batch_many(&[9, 12, 13], &prover_data);
+        let num_siblings = proof.iter().map(Vec::len).collect_vec();
+        assert_eq!(num_siblings, [1, 2, 0, 1, 1]);
+
+        // Three leaf hashes, and the compressions of 2, 2, 1, 1 and 1 nodes above each layer.
+        assert_eq!(MyMmcs::verify_batch_many_hash_count(3, &proof), 3 + 7);
+
+        // Repeated indices are each hashed, but share the compressions above them.
+        let (_, prover_data) = mmcs.commit(vec![RowMajorMatrix::<F>::rand(&mut rng, 4, 4)]);
+        let indices = (0..50).map(|i| i % 3).collect_vec();
+        let (_, proof) = mmcs.open_batch_many(&indices, &prover_data);
+        assert_eq!(MyMmcs::verify_batch_many_hash_count(50, &proof), 50 + 3);
+    }
+
+    #[test]
+    fn verify_many_tampered_fails() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 64, 2),
+            RowMajorMatrix::<F>::rand(&mut rng, 16, 3),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+        let indices = [5, 21, 5, 7];
+        let (opened_values, proof) = mmcs.open_batch_many(&indices, &prover_data);
+        let verify = |opened_values: &[Vec<Vec<F>>], proof: &Vec<Vec<[F; 8]>>| {
+            mmcs.verify_batch_many(&commit, &dims, &indices, opened_values, proof)
+        };
+        verify(&opened_values, &proof).expect("expected verification to succeed");
+
+        // A repeated index must be opened to the same values each time.
+        let mut tampered = opened_values.clone();
+        tampered[2][0][1] += F::ONE;
+        assert!(matches!(
+            verify(&tampered, &proof),
+            Err(MerkleTreeError::RootMismatch)
+        ));
+
+        // Indices 5 and 7 open the same row of the shorter matrix.
+        let mut tampered = opened_values.clone();
+        tampered[3][1][0] += F::ONE;
+        assert!(matches!(
+            verify(&tampered, &proof),
+            Err(MerkleTreeError::RootMismatch)
+        ));
+
+        let mut tampered = proof.clone();
+        tampered[3][0][0] += F::ONE;
+        assert!(matches!(
+            verify(&opened_values, &tampered),
+            Err(MerkleTreeError::RootMismatch)
+        ));
+
+        let mut tampered = proof.clone();
+        tampered[1].pop();
+        assert!(matches!(
+            verify(&opened_values, &tampered),
+            Err(MerkleTreeError::WrongHeight { .. })
+        ));
+        let mut tampered = proof;
+        tampered[0].push(tampered[0][0]);
+        assert!(matches!(
+            verify(&opened_values, &tampered),
+            Err(MerkleTreeError::WrongHeight { .. })
+        ));
+    }
+
+    #[test]
+    fn verify_wrong_shapes_fails() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 64, 2),
+            RowMajorMatrix::<F>::rand(&mut rng, 16, 3),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+        let (opened_values, proof) = mmcs.open_batch(37, &prover_data);
+        let verify = |dims: &[Dimensions], index: usize, opened_values: &[Vec<F>]| {
+            mmcs.verify_batch(&commit, dims, index, opened_values, &proof)
+        };
+        verify(&dims, 37, &opened_values).expect("expected verification to succeed");
+
+        assert!(matches!(
+            verify(&dims[..1], 37, &opened_values),
+            Err(MerkleTreeError::WrongBatchSize)
+        ));
+        assert!(matches!(
+            verify(&[], 37, &[]),
+            Err(MerkleTreeError::WrongBatchSize)
+        ));
+
+        // Widths must match exactly, including the placeholder width of zero.
+        for width in [0, 2, 4] {
+            let mut wrong_dims = dims.clone();
+            wrong_dims[1].width = width;
+            assert!(matches!(
+                verify(&wrong_dims, 37, &opened_values),
+                Err(MerkleTreeError::WrongWidth {
+                    matrix: 1,
+                    width: w,
+                    row_len: 3
+                }) if w == width
+            ));
+        }
+        let mut tampered = opened_values.clone();
+        tampered[0].push(F::ZERO);
+        assert!(matches!(
+            verify(&dims, 37, &tampered),
+            Err(MerkleTreeError::WrongWidth {
+                matrix: 0,
+                width: 2,
+                row_len: 3
+            })
+        ));
+
+        // Heights which round up to the same power of two as another, or which are zero, can't
+        // have been committed to.
+        for height in [0, 60] {
+            let mut wrong_dims = dims.clone();
+            wrong_dims.push(Dimensions { width: 0, height });
+            let mut opened_values = opened_values.clone();
+            opened_values.push(vec![]);
+            assert!(matches!(
+                verify(&wrong_dims, 37, &opened_values),
+                Err(MerkleTreeError::IncompatibleHeight { matrix: 2, height: h }) if h == height
+            ));
+        }
+
+        // A tallest height that's off by one bit changes the number of siblings.
+        let mut wrong_dims = dims.clone();
+        wrong_dims[0].height = 128;
+        assert!(matches!(
+            verify(&wrong_dims, 37, &opened_values),
+            Err(MerkleTreeError::WrongHeight {
+                max_height: 128,
+                num_siblings: 6
+            })
+        ));
+        let mut wrong_dims = dims.clone();
+        wrong_dims[0].height = 32;
+        assert!(matches!(
+            verify(&wrong_dims, 5, &opened_values),
+            Err(MerkleTreeError::WrongHeight {
+                max_height: 32,
+                num_siblings: 6
+            })
+        ));
+
+        // A shorter matrix injected at the wrong layer gives another root.
+        let mut wrong_dims = dims.clone();
+        wrong_dims[1].height = 32;
+        assert!(matches!(
+            verify(&wrong_dims, 37, &opened_values),
+            Err(MerkleTreeError::RootMismatch)
+        ));
+
+        assert!(matches!(
+            verify(&dims, 64, &opened_values),
+            Err(MerkleTreeError::IndexOutOfBounds {
+                index: 64,
+                max_height: 64
+            })
+        ));
+    }
+
+    #[test]
+    fn verify_many_wrong_shapes_fails() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm);
+        let mmcs = MyMmcs::new(hash, compress);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 32, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 4, 1),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+        let indices = [3, 30, 17];
+        let (opened_values, proof) = mmcs.open_batch_many(&indices, &prover_data);
+        let verify = |dims: &[Dimensions], indices: &[usize], opened_values: &[Vec<Vec<F>>]| {
+            mmcs.verify_batch_many(&commit, dims, indices, opened_values, &proof)
+        };
+        verify(&dims, &indices, &opened_values).expect("expected verification to succeed");
+
+        assert!(matches!(
+            verify(&dims, &indices[..2], &opened_values),
+            Err(MerkleTreeError::WrongBatchSize)
+        ));
+        let mut tampered = opened_values.clone();
+        tampered[1].pop();
+        assert!(matches!(
+            verify(&dims, &indices, &tampered),
+            Err(MerkleTreeError::WrongBatchSize)
+        ));
+
+        // The openings at every index are checked against the widths.
+        let mut tampered = opened_values.clone();
+        tampered[2][1].push(F::ONE);
+        assert!(matches!(
+            verify(&dims, &indices, &tampered),
+            Err(MerkleTreeError::WrongWidth {
+                matrix: 1,
+                width: 1,
+                row_len: 2
+            })
+        ));
+
+        let mut wrong_dims = dims.clone();
+        wrong_dims[1].height = 24;
+        assert!(matches!(
+            verify(&wrong_dims, &indices, &opened_values),
+            Err(MerkleTreeError::IncompatibleHeight {
+                matrix: 1,
+                height: 24
+            })
+        ));
+        let mut wrong_dims = dims.clone();
+        wrong_dims.swap(0, 1);
+        assert!(matches!(
+            verify(&wrong_dims, &indices, &opened_values),
+            Err(MerkleTreeError::WrongWidth { matrix: 0, .. })
+        ));
+
+        assert!(matches!(
+            verify(&dims, &[3, 30, 32], &opened_values),
+            Err(MerkleTreeError::IndexOutOfBounds {
+                index: 32,
+                max_height: 32
+            })
+        ));
+    }
+
+    #[test]
+    fn commit_arity_4() {
+        let perm = Perm::new_from_rng_128(&mut thread_rng());
+        let hash = MyHash::new(perm);
+        let compress = MyCompress4::new(hash.clone());
+        let mmcs = MyMmcs4::new(hash.clone(), compress.clone());
+        let default_digest = [F::ZERO; 8];
+
+        // With 8 leaves, the root has two children and two default digests.
+        let v = (0..8).map(F::from_canonical_usize).collect_vec();
+        let (commit, prover_data) = mmcs.commit_vec(v.clone());
+
+        let leaves = v.iter().map(|&x| hash.hash_item(x)).collect_vec();
+        let children = [
+            compress.compress([leaves[0], leaves[1], leaves[2], leaves[3]]),
+            compress.compress([leaves[4], leaves[5], leaves[6], leaves[7]]),
+        ];
+        let expected_result =
+            compress.compress([children[0], children[1], default_digest, default_digest]);
+        assert_eq!(commit, expected_result);
+
+        // Each layer gives the three siblings of the node on the path, in order of position.
+        let (opened_values, proof) = mmcs.open_batch(6, &prover_data);
+        assert_eq!(opened_values, vec![vec![v[6]]]);
+        assert_eq!(
+            proof,
+            vec![
+                leaves[4],
+                leaves[5],
+                leaves[7],
+                children[0],
+                default_digest,
+                default_digest
+            ]
+        );
+        let dims = [Dimensions {
+            width: 1,
+            height: 8,
+        }];
+        mmcs.verify_batch(&commit, &dims, 6, &opened_values, &proof)
+            .expect("expected verification to succeed");
+        assert_eq!(MyMmcs4::verify_batch_hash_count(&proof), 3);
+    }
+
+    #[test]
+    fn commit_arity_4_mixed() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm);
+        let compress = MyCompress4::new(hash.clone());
+        let mmcs = MyMmcs4::new(hash.clone(), compress.clone());
+        let default_digest = [F::ZERO; 8];
+
+        // The matrix of 1 row is injected at the root, along with two default digests.
+        let mat_1 = RowMajorMatrix::<F>::rand(&mut rng, 4, 2);
+        let mat_2 = RowMajorMatrix::<F>::rand(&mut rng, 1, 3);
+        let (commit, _) = mmcs.commit(vec![mat_1.clone(), mat_2.clone()]);
+
+        let expected_result = compress.compress([
+            compress.compress(array::from_fn(|i| hash.hash_iter(mat_1.row(i)))),
+            hash.hash_iter(mat_2.row(0)),
+            default_digest,
+            default_digest,
+        ]);
+        assert_eq!(commit, expected_result);
+    }
+
+    #[test]
+    #[should_panic]
+    fn arity_4_skipped_layer() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm);
+        let compress = MyCompress4::new(hash.clone());
+        let mmcs = MyMmcs4::new(hash, compress);
+
+        // A matrix of 8 rows would be injected between the layers of 16 and 4 digests.
+        let _ = mmcs.commit(vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 16, 1),
+            RowMajorMatrix::<F>::rand(&mut rng, 8, 1),
+        ]);
+    }
+
+    #[test]
+    fn arity_8_size_gaps() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm);
+        let compress = MyCompress8::new(hash.clone());
+
+        // The layers below the root have padded lengths 128, 16 and 2, so matrices of 100, 9 and
+        // 2 rows are injected into each of them.
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 100, 4),
+            RowMajorMatrix::<F>::rand(&mut rng, 9, 3),
+            RowMajorMatrix::<F>::rand(&mut rng, 2, 5),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+
+        for cap_height in [0, 1] {
+            let mmcs = MyMmcs8::new(hash.clone(), compress.clone()).with_cap_height(cap_height);
+            let (commit, prover_data) = mmcs.commit(mats.clone());
+            assert_eq!(commit.len(), 8_usize.pow(cap_height as u32));
+
+            for index in [0, 17, 99] {
+                let (opened_values, proof) = mmcs.open_batch(index, &prover_data);
+                assert_eq!(proof.len(), 7 * (3 - cap_height));
+                mmcs.verify_batch(&commit, &dims, index, &opened_values, &proof)
+                    .expect("expected verification to succeed");
+            }
+        }
+
+        // The cap is no lower than the layer of 2 digests.
+        let mmcs = MyMmcs8::new(hash, compress).with_cap_height(2);
+        let (commit, prover_data) = mmcs.commit(mats);
+        assert_eq!(commit.len(), 8);
+        let (opened_values, proof) = mmcs.open_batch(42, &prover_data);
+        mmcs.verify_batch(&commit, &dims, 42, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        // A matrix of 4 rows can't be injected into the tree.
+        let mut wrong_dims = dims;
+        wrong_dims[2].height = 4;
+        assert!(matches!(
+            mmcs.verify_batch(&commit, &wrong_dims, 42, &opened_values, &proof),
+            Err(MerkleTreeError::IncompatibleHeight {
+                matrix: 2,
+                height: 4
+            })
+        ));
+    }
+
+    #[test]
+    fn open_many_arity_4() {
+        let mut rng = thread_rng();
+        let perm = Perm::new_from_rng_128(&mut rng);
+        let hash = MyHash::new(perm);
+        let compress = MyCompress4::new(hash.clone());
+        let mmcs = MyMmcs4::new(hash, compress);
+
+        let mats = vec![
+            RowMajorMatrix::<F>::rand(&mut rng, 64, 2),
+            RowMajorMatrix::<F>::rand(&mut rng, 16, 3),
+        ];
+        let dims = mats.iter().map(|m| m.dimensions()).collect_vec();
+        let (commit, prover_data) = mmcs.commit(mats);
+
+        // Indices 4, 6 and 7 share a parent, and 4 and 21 a node two layers higher.
+        let indices = [21, 4, 7, 6, 7];
+        let (opened_values, proof) = mmcs.open_batch_many(&indices, &prover_data);
+        for (&index, opened) in indices.iter().zip(&opened_values) {
+            assert_eq!(opened, &mmcs.open_batch(index, &prover_data).0);
+        }
+        let num_siblings = proof.iter().map(Vec::len).collect_vec();
+        assert_eq!(num_siblings, [4, 6, 2]);
+        mmcs.verify_batch_many(&commit, &dims, &indices, &opened_values, &proof)
+            .expect("expected verification to succeed");
+
+        // Five leaf hashes, and the compressions of 2, 2 and 1 nodes above each layer.
+        assert_eq!(MyMmcs4::verify_batch_many_hash_count(5, &proof), 5 + 5);
+
+        let mut tampered = proof.clone();
+        tampered[1][5][0] += F::ONE;
+        assert!(matches!(
+            mmcs.verify_batch_many(&commit, &dims, &indices, &opened_values, &tampered),
+            Err(MerkleTreeError::RootMismatch)
+        ));
+        let mut tampered = proof;
+        tampered[2].pop();
+        assert!(matches!(
+            mmcs.verify_batch_many(&commit, &dims, &indices, &opened_values, &tampered),
+            Err(MerkleTreeError::WrongHeight { .. })
+        ));
+    }
 }
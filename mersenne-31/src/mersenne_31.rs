@@ -9,8 +9,8 @@ use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use num_bigint::BigUint;
 use p3_field::{
-    exp_1717986917, exp_u64_by_squaring, halve_u32, Field, FieldAlgebra, Packable, PrimeField,
-    PrimeField32, PrimeField64,
+    decode_canonical_u32, exp_1717986917, exp_u64_by_squaring, halve_u32, CanonicalEncoding,
+    DecodingError, Field, FieldAlgebra, Packable, PrimeField, PrimeField32, PrimeField64,
 };
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
@@ -110,6 +110,16 @@ impl<'a> Deserialize<'a> for Mersenne31 {
     }
 }
 
+impl CanonicalEncoding for Mersenne31 {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_canonical_u32().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        decode_canonical_u32(input, P).map(Self::new)
+    }
+}
+
 impl FieldAlgebra for Mersenne31 {
     type F = Self;
 
@@ -452,13 +462,25 @@ pub const fn to_mersenne31_array<const N: usize>(input: [u32; N]) -> [Mersenne31
 
 #[cfg(test)]
 mod tests {
-    use p3_field::{Field, FieldAlgebra, PrimeField32};
+    use p3_field::{CanonicalEncoding, DecodingError, Field, FieldAlgebra, PrimeField32};
     use p3_field_testing::test_field;
 
     use crate::Mersenne31;
 
     type F = Mersenne31;
 
+    #[test]
+    fn canonical_encoding() {
+        let x = F::new(12345);
+        assert_eq!(F::from_bytes(&x.to_bytes()), Ok(x));
+        // Zero has two internal representations, but only one encoding.
+        assert_eq!(F::new(F::ORDER_U32).to_bytes(), F::ZERO.to_bytes());
+        assert_eq!(
+            F::from_bytes(&F::ORDER_U32.to_le_bytes()),
+            Err(DecodingError::NonCanonical)
+        );
+    }
+
     #[test]
     fn add() {
         assert_eq!(F::ONE + F::ONE, F::TWO);
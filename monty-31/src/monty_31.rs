@@ -11,7 +11,8 @@ use core::ops::{Add, AddAssign, Div, Mul, MulAssign, Neg, Sub, SubAssign};
 
 use num_bigint::BigUint;
 use p3_field::{
-    Field, FieldAlgebra, Packable, PrimeField, PrimeField32, PrimeField64, TwoAdicField,
+    decode_canonical_u32, CanonicalEncoding, DecodingError, Field, FieldAlgebra, Packable,
+    PrimeField, PrimeField32, PrimeField64, TwoAdicField,
 };
 use rand::distributions::{Distribution, Standard};
 use rand::Rng;
@@ -167,6 +168,16 @@ impl<'de, FP: FieldParameters> Deserialize<'de> for MontyField31<FP> {
     }
 }
 
+impl<FP: FieldParameters> CanonicalEncoding for MontyField31<FP> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.as_canonical_u32().encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        decode_canonical_u32(input, FP::PRIME).map(Self::from_canonical_u32)
+    }
+}
+
 impl<FP: FieldParameters> Packable for MontyField31<FP> {}
 
 impl<FP: FieldParameters> FieldAlgebra for MontyField31<FP> {
@@ -93,7 +93,10 @@ fn main() -> Result<(), impl Debug> {
 
     let dft = Dft::default();
 
-    let fri_config = create_benchmark_fri_config(challenge_mmcs);
+    let mut fri_config = create_benchmark_fri_config(challenge_mmcs);
+    // Zero-knowledge mode doubles the degree of the committed trace, so the degree 3 constraints
+    // need a quotient domain of 8 times the trace height, and thus a blowup of 4.
+    fri_config.log_blowup = 2;
     type Pcs = HidingFriPcs<Val, Dft, ValMmcs, ChallengeMmcs, StdRng>;
     let pcs = Pcs::new(dft, val_mmcs, fri_config, 4, StdRng::from_entropy());
 
@@ -0,0 +1,152 @@
+use alloc::vec::Vec;
+
+use p3_field::{CanonicalEncoding, TwoAdicField};
+
+/// The parameters of a STIR low-degree test.
+///
+/// Each round folds the function being tested by `2^log_folding_factor`, and evaluates the result
+/// on a domain half the size of the previous one, so the rate improves by
+/// `log_folding_factor - 1` bits per round. Later rounds therefore need fewer queries than the
+/// first for the same soundness.
+#[derive(Debug)]
+pub struct StirConfig<M> {
+    pub log_blowup: usize,
+    /// Rounds stop folding once the degree bound would fall below `2^log_final_poly_len`, after
+    /// which the prover folds once more and sends the result in the clear.
+    pub log_final_poly_len: usize,
+    /// The log of the number of evaluations folded into one in each round. Each round commits to
+    /// its evaluations in rows of this many, so that a query opens them all with a single Merkle
+    /// proof.
+    pub log_folding_factor: usize,
+    /// The number of queries of the first round. Later rounds make as many as are needed for the
+    /// same conjectured soundness at their improved rate; see `StirConfig::rounds`.
+    pub num_queries: usize,
+    /// The number of proof-of-work bits the prover grinds for in each round, before its queries
+    /// are sampled.
+    pub proof_of_work_bits: usize,
+    pub mmcs: M,
+}
+
+/// The shape of one round of a STIR proof, as given by `StirConfig::rounds`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct StirRound {
+    /// The log size of the domain on which the round's oracle is committed.
+    pub log_domain_size: usize,
+    /// The log of the degree bound of the function the round folds.
+    pub log_degree: usize,
+    /// The log of the number of evaluations the round folds into one.
+    pub log_folding_factor: usize,
+    pub num_queries: usize,
+}
+
+impl StirRound {
+    /// The log of the inverse rate of the round's oracle.
+    pub const fn log_inv_rate(&self) -> usize {
+        self.log_domain_size - self.log_degree
+    }
+}
+
+impl<M> StirConfig<M> {
+    pub const fn blowup(&self) -> usize {
+        1 << self.log_blowup
+    }
+
+    /// The rounds of a STIR proof that a function on a domain of log size `log_domain_size` has
+    /// degree less than `2^(log_domain_size - log_blowup)`. The last round is the final one, whose
+    /// folded function is sent in the clear, and all others fold by the full folding factor.
+    pub fn rounds(&self, log_domain_size: usize) -> Vec<StirRound> {
+        assert!(
+            self.log_folding_factor > 0,
+            "STIR must fold at least pairs of evaluations"
+        );
+        assert!(self.log_blowup > 0, "STIR requires a rate below one");
+
+        let mut round = StirRound {
+            log_domain_size,
+            log_degree: log_domain_size - self.log_blowup,
+            log_folding_factor: self.log_folding_factor,
+            num_queries: self.num_queries,
+        };
+        let mut rounds = Vec::new();
+        while round.log_degree >= self.log_final_poly_len + self.log_folding_factor {
+            rounds.push(round);
+            round.log_domain_size -= 1;
+            round.log_degree -= self.log_folding_factor;
+            round.num_queries = self.num_queries_at_rate(round.log_inv_rate());
+        }
+        round.log_folding_factor = self.log_folding_factor.min(round.log_degree);
+        rounds.push(round);
+        rounds
+    }
+
+    /// The number of queries which gives an oracle of the given rate the conjectured soundness of
+    /// the first round's queries.
+    pub fn num_queries_at_rate(&self, log_inv_rate: usize) -> usize {
+        (self.num_queries * self.log_blowup).div_ceil(log_inv_rate)
+    }
+
+    /// Returns the soundness bits of this STIR instance based on the
+    /// [ethSTARK](https://eprint.iacr.org/2021/582) conjecture, as for FRI, which every round
+    /// achieves.
+    pub fn conjectured_soundness_bits(&self) -> usize {
+        self.log_blowup * self.num_queries + self.proof_of_work_bits
+    }
+
+    /// The canonical encoding of the parameters which affect the shape and validity of proofs, for
+    /// use in a PCS's `fingerprint`.
+    pub fn fingerprint(&self) -> Vec<u8> {
+        (
+            (self.log_blowup, self.log_final_poly_len),
+            (
+                self.log_folding_factor,
+                (self.num_queries, self.proof_of_work_bits),
+            ),
+        )
+            .to_bytes()
+    }
+}
+
+/// Checks that each round's new domain is disjoint from the points at which the previous round's
+/// folded function is queried, so that the verifier never divides by zero.
+///
+/// All domains are cosets of `F::GENERATOR`, so this fails only for folding factors `k` such that
+/// the odd part of the multiplicative group's order divides `k - 1`, on very large domains.
+pub(crate) fn assert_disjoint_domains<F: TwoAdicField>(rounds: &[StirRound]) {
+    for (round, next) in rounds.iter().zip(&rounds[1..]) {
+        // The next domain is `g H` and the queried points are in `g^k H'`, for `H'` a subgroup of
+        // `H`, so they meet exactly when `g^(k - 1)` is in `H`.
+        let ratio = F::GENERATOR.exp_u64((1 << round.log_folding_factor) - 1);
+        assert_ne!(
+            ratio.exp_power_of_2(next.log_domain_size),
+            F::ONE,
+            "STIR domains of log size {} with log folding factor {} overlap",
+            next.log_domain_size,
+            round.log_folding_factor
+        );
+    }
+}
+
+/// Creates a minimal `StirConfig` for testing purposes.
+pub const fn create_test_stir_config<Mmcs>(mmcs: Mmcs) -> StirConfig<Mmcs> {
+    StirConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        log_folding_factor: 2,
+        num_queries: 2,
+        proof_of_work_bits: 1,
+        mmcs,
+    }
+}
+
+/// Creates a `StirConfig` suitable for benchmarking, with the same conjectured soundness as
+/// `p3_fri::create_benchmark_fri_config`.
+pub const fn create_benchmark_stir_config<Mmcs>(mmcs: Mmcs) -> StirConfig<Mmcs> {
+    StirConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        log_folding_factor: 4,
+        num_queries: 100,
+        proof_of_work_bits: 16,
+        mmcs,
+    }
+}
@@ -0,0 +1,21 @@
+//! An implementation of the STIR low-degree test (LDT), and of a PCS based on it.
+//!
+//! STIR, from [ACFY24](https://eprint.iacr.org/2024/390), is an alternative to FRI which folds by
+//! a larger factor in each round while only halving the evaluation domain, so that the rate of
+//! each round's oracle improves and later rounds need fewer queries.
+
+#![no_std]
+
+extern crate alloc;
+
+mod config;
+mod pcs;
+mod polynomial;
+mod proof;
+pub mod prover;
+pub mod verifier;
+
+pub use config::*;
+pub use pcs::*;
+pub use polynomial::*;
+pub use proof::*;
@@ -0,0 +1,373 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::marker::PhantomData;
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::{MultiOpeningMmcs, OpenedValues, Pcs, PolynomialSpace, TwoAdicMultiplicativeCoset};
+use p3_dft::TwoAdicSubgroupDft;
+use p3_field::{dot_product, CanonicalEncoding, ExtensionField, TwoAdicField};
+use p3_fri::{compute_inverse_denominators, BatchOpening};
+use p3_interpolation::interpolate_coset;
+use p3_matrix::bitrev::{BitReversableMatrix, BitReversalPerm};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::{Dimensions, Matrix};
+use p3_maybe_rayon::prelude::*;
+use p3_util::{log2_strict_usize, reverse_bits_len, reverse_slice_index_bits, VecExt};
+use tracing::info_span;
+
+use crate::verifier::{self, StirError};
+use crate::{prover, StirConfig, StirProof};
+
+/// A PCS over two-adic fields like `p3_fri::TwoAdicFriPcs`, which proves its openings with STIR
+/// in place of FRI.
+///
+/// As in `TwoAdicFriPcs`, the quotients `(p(X) - p(z)) / (X - z)` of the opened polynomials are
+/// combined with powers of a random challenge. Quotients of polynomials of each degree are tested
+/// by a separate STIR proof, as STIR's domains after the first round don't line up with the
+/// domains of smaller inputs.
+#[derive(Debug)]
+pub struct TwoAdicStirPcs<Val, Dft, InputMmcs, StirMmcs> {
+    dft: Dft,
+    mmcs: InputMmcs,
+    stir: StirConfig<StirMmcs>,
+    _phantom: PhantomData<Val>,
+}
+
+impl<Val, Dft, InputMmcs, StirMmcs> TwoAdicStirPcs<Val, Dft, InputMmcs, StirMmcs> {
+    pub const fn new(dft: Dft, mmcs: InputMmcs, stir: StirConfig<StirMmcs>) -> Self {
+        Self {
+            dft,
+            mmcs,
+            stir,
+            _phantom: PhantomData,
+        }
+    }
+}
+
+/// The STIR proofs of a `TwoAdicStirPcs` opening, one for each height of the opened matrices,
+/// from the tallest to the shortest.
+pub type TwoAdicStirPcsProof<Val, Challenge, InputMmcs, StirMmcs> =
+    Vec<StirProof<Challenge, StirMmcs, Val, Vec<BatchOpening<Val, InputMmcs>>>>;
+
+impl<Val, Dft, InputMmcs, StirMmcs, Challenge, Challenger> Pcs<Challenge, Challenger>
+    for TwoAdicStirPcs<Val, Dft, InputMmcs, StirMmcs>
+where
+    Val: TwoAdicField,
+    Dft: TwoAdicSubgroupDft<Val>,
+    InputMmcs: MultiOpeningMmcs<Val>,
+    StirMmcs: MultiOpeningMmcs<Challenge>,
+    Challenge: TwoAdicField + ExtensionField<Val>,
+    Challenger:
+        FieldChallenger<Val> + CanObserve<StirMmcs::Commitment> + GrindingChallenger<Witness = Val>,
+{
+    type Domain = TwoAdicMultiplicativeCoset<Val>;
+    type Commitment = InputMmcs::Commitment;
+    type ProverData = InputMmcs::ProverData<RowMajorMatrix<Val>>;
+    type Proof = TwoAdicStirPcsProof<Val, Challenge, InputMmcs, StirMmcs>;
+    type Error = StirError<StirMmcs::Error, InputMmcs::Error>;
+
+    fn fingerprint(&self) -> Vec<u8> {
+        (
+            self.stir.fingerprint(),
+            (self.mmcs.fingerprint(), self.stir.mmcs.fingerprint()),
+        )
+            .to_bytes()
+    }
+
+    fn natural_domain_for_degree(&self, degree: usize) -> Self::Domain {
+        let log_n = log2_strict_usize(degree);
+        TwoAdicMultiplicativeCoset {
+            log_n,
+            shift: Val::ONE,
+        }
+    }
+
+    fn commit(
+        &self,
+        evaluations: Vec<(Self::Domain, RowMajorMatrix<Val>)>,
+    ) -> (Self::Commitment, Self::ProverData) {
+        let ldes: Vec<_> = evaluations
+            .into_iter()
+            .map(|(domain, evals)| {
+                assert_eq!(domain.size(), evals.height());
+                let shift = Val::GENERATOR / domain.shift;
+                // Commit to the bit-reversed LDE, on the same coset as STIR's first domain.
+                self.dft
+                    .coset_lde_batch(evals, self.stir.log_blowup, shift)
+                    .bit_reverse_rows()
+                    .to_row_major_matrix()
+            })
+            .collect();
+
+        self.mmcs.commit(ldes)
+    }
+
+    fn get_evaluations_on_domain<'a>(
+        &self,
+        prover_data: &'a Self::ProverData,
+        idx: usize,
+        domain: Self::Domain,
+    ) -> impl Matrix<Val> + 'a {
+        assert_eq!(domain.shift, Val::GENERATOR);
+        let lde = self.mmcs.get_matrices(prover_data)[idx];
+        assert!(lde.height() >= domain.size());
+        lde.split_rows(domain.size()).0.bit_reverse_rows()
+    }
+
+    fn open(
+        &self,
+        // For each round,
+        rounds: Vec<(
+            &Self::ProverData,
+            // for each matrix,
+            Vec<
+                // points to open
+                Vec<Challenge>,
+            >,
+        )>,
+        challenger: &mut Challenger,
+    ) -> (OpenedValues<Challenge>, Self::Proof) {
+        // This reduces the openings as `TwoAdicFriPcs::open` does; see there for the details.
+
+        // Batch combination challenge
+        let alpha: Challenge = challenger.sample_ext_element();
+
+        let mats_and_points = rounds
+            .iter()
+            .map(|(data, points)| {
+                (
+                    self.mmcs
+                        .get_matrices(data)
+                        .into_iter()
+                        .map(|m| m.as_view())
+                        .collect_vec(),
+                    points,
+                )
+            })
+            .collect_vec();
+
+        let inv_denoms = compute_inverse_denominators(&mats_and_points, Val::GENERATOR);
+
+        let mut all_opened_values: OpenedValues<Challenge> = vec![];
+
+        let mut reduced_openings: [_; 32] = core::array::from_fn(|_| None);
+        let mut num_reduced = [0; 32];
+
+        for (mats, points) in mats_and_points {
+            let opened_values_for_round = all_opened_values.pushed_mut(vec![]);
+            for (mat, points_for_mat) in izip!(mats, points) {
+                let log_height = log2_strict_usize(mat.height());
+                let reduced_opening_for_log_height = reduced_openings[log_height]
+                    .get_or_insert_with(|| vec![Challenge::ZERO; mat.height()]);
+
+                let opened_values_for_mat = opened_values_for_round.pushed_mut(vec![]);
+                for &point in points_for_mat {
+                    let _guard =
+                        info_span!("reduce matrix quotient", dims = %mat.dimensions()).entered();
+
+                    // Use Barycentric interpolation to evaluate the matrix at the given point.
+                    let ys = info_span!("compute opened values with Lagrange interpolation")
+                        .in_scope(|| {
+                            let h = mat.height() >> self.stir.log_blowup;
+                            let (low_coset, _) = mat.split_rows(h);
+                            let mut inv_denoms = inv_denoms.get(&point).unwrap()[..h].to_vec();
+                            reverse_slice_index_bits(&mut inv_denoms);
+                            interpolate_coset(
+                                &BitReversalPerm::new_view(low_coset),
+                                Val::GENERATOR,
+                                point,
+                                Some(&inv_denoms),
+                            )
+                        });
+
+                    let alpha_pow_offset = alpha.exp_u64(num_reduced[log_height] as u64);
+                    let reduced_ys: Challenge = dot_product(alpha.powers(), ys.iter().copied());
+
+                    info_span!("reduce rows").in_scope(|| {
+                        mat.dot_ext_powers(alpha)
+                            .zip(reduced_opening_for_log_height.par_iter_mut())
+                            .zip(inv_denoms.get(&point).unwrap().par_iter())
+                            .for_each(|((reduced_row, ro), &inv_denom)| {
+                                *ro += alpha_pow_offset * (reduced_ys - reduced_row) * inv_denom
+                            });
+                    });
+
+                    num_reduced[log_height] += mat.width();
+                    opened_values_for_mat.push(ys);
+                }
+            }
+        }
+
+        let proofs = reduced_openings
+            .into_iter()
+            .enumerate()
+            .rev()
+            .filter_map(|(log_height, reduced_opening)| Some((log_height, reduced_opening?)))
+            .map(|(log_height, reduced_opening)| {
+                prover::prove(&self.stir, reduced_opening, challenger, |indices| {
+                    // Open every batch with a matrix of this height.
+                    rounds
+                        .iter()
+                        .filter(|(data, _)| {
+                            self.mmcs
+                                .get_matrix_heights(data)
+                                .contains(&(1 << log_height))
+                        })
+                        .map(|(data, _)| {
+                            let log_max_height = log2_strict_usize(self.mmcs.get_max_height(data));
+                            let batch_indices = indices
+                                .iter()
+                                .map(|index| index << (log_max_height - log_height))
+                                .collect_vec();
+                            let (opened_values, opening_proof) =
+                                self.mmcs.open_batch_many(&batch_indices, data);
+                            BatchOpening {
+                                opened_values,
+                                opening_proof,
+                            }
+                        })
+                        .collect()
+                })
+            })
+            .collect();
+
+        (all_opened_values, proofs)
+    }
+
+    fn verify(
+        &self,
+        // For each round:
+        rounds: Vec<(
+            Self::Commitment,
+            // for each matrix:
+            Vec<(
+                // its domain,
+                Self::Domain,
+                // for each point:
+                Vec<(
+                    // the point,
+                    Challenge,
+                    // values at the point
+                    Vec<Challenge>,
+                )>,
+            )>,
+        )>,
+        proof: &Self::Proof,
+        challenger: &mut Challenger,
+    ) -> Result<(), Self::Error> {
+        // Batch combination challenge
+        let alpha: Challenge = challenger.sample_ext_element();
+
+        let log_lde_height = |domain: &Self::Domain| domain.log_n + self.stir.log_blowup;
+        let log_heights = rounds
+            .iter()
+            .flat_map(|(_, mats)| mats.iter().map(|(domain, _)| log_lde_height(domain)))
+            .sorted()
+            .dedup()
+            .rev()
+            .collect_vec();
+        if proof.len() != log_heights.len() {
+            return Err(StirError::InvalidProofShape);
+        }
+
+        for (log_height, stir_proof) in izip!(log_heights, proof) {
+            let batches = rounds
+                .iter()
+                .filter(|(_, mats)| {
+                    mats.iter()
+                        .any(|(domain, _)| log_lde_height(domain) == log_height)
+                })
+                .collect_vec();
+            let num_queries = self.stir.num_queries;
+            if stir_proof.input_proof.len() != batches.len()
+                || stir_proof
+                    .input_proof
+                    .iter()
+                    .any(|batch_opening| batch_opening.opened_values.len() != num_queries)
+            {
+                return Err(StirError::InvalidProofShape);
+            }
+
+            verifier::verify(
+                &self.stir,
+                log_height,
+                stir_proof,
+                challenger,
+                |indices, input_proof| -> Result<Vec<Challenge>, InputMmcs::Error> {
+                    // For each query, the next power of alpha and the reduced opening.
+                    let mut reduced_openings =
+                        vec![(Challenge::ONE, Challenge::ZERO); indices.len()];
+
+                    for (batch_opening, (batch_commit, mats)) in izip!(input_proof, &batches) {
+                        let batch_dims = mats
+                            .iter()
+                            .enumerate()
+                            .map(|(mat, (domain, points_and_values))| Dimensions {
+                                // Each column is opened at every point, so the values at any point
+                                // give the width of the matrix. A matrix opened at no points
+                                // contributes nothing, so its width is taken from the opened rows
+                                // instead, which leaves it unchecked; see `Pcs::verify`.
+                                width: points_and_values.first().map_or_else(
+                                    || {
+                                        batch_opening
+                                            .opened_values
+                                            .first()
+                                            .and_then(|rows| rows.get(mat))
+                                            .map_or(0, Vec::len)
+                                    },
+                                    |(_, values)| values.len(),
+                                ),
+                                height: domain.size() << self.stir.log_blowup,
+                            })
+                            .collect_vec();
+                        let log_batch_max_height = mats
+                            .iter()
+                            .map(|(domain, _)| log_lde_height(domain))
+                            .max()
+                            .expect("Empty batch?");
+                        let batch_indices = indices
+                            .iter()
+                            .map(|index| index << (log_batch_max_height - log_height))
+                            .collect_vec();
+
+                        self.mmcs.verify_batch_many(
+                            batch_commit,
+                            &batch_dims,
+                            &batch_indices,
+                            &batch_opening.opened_values,
+                            &batch_opening.opening_proof,
+                        )?;
+
+                        for (&index, opened_values, (alpha_pow, ro)) in
+                            izip!(indices, &batch_opening.opened_values, &mut reduced_openings)
+                        {
+                            let x = Val::GENERATOR
+                                * Val::two_adic_generator(log_height)
+                                    .exp_u64(reverse_bits_len(index, log_height) as u64);
+
+                            for (mat_opening, (mat_domain, mat_points_and_values)) in
+                                izip!(opened_values, mats)
+                            {
+                                if log_lde_height(mat_domain) != log_height {
+                                    continue;
+                                }
+                                for (z, ps_at_z) in mat_points_and_values {
+                                    for (&p_at_x, &p_at_z) in izip!(mat_opening, ps_at_z) {
+                                        let quotient = (-p_at_z + p_at_x) / (-*z + x);
+                                        *ro += *alpha_pow * quotient;
+                                        *alpha_pow *= alpha;
+                                    }
+                                }
+                            }
+                        }
+                    }
+
+                    Ok(reduced_openings.into_iter().map(|(_, ro)| ro).collect())
+                },
+            )?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,192 @@
+//! Operations on polynomials given by their coefficients, and on the rows of committed oracles.
+
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_field::{batch_multiplicative_inverse, ExtensionField, Field, TwoAdicField};
+use p3_util::{reverse_bits_len, reverse_slice_index_bits};
+
+/// Folds a polynomial by `2^log_factor` with randomness `alpha`: writing
+/// `f(X) = sum_j X^j f_j(X^k)` for `j < k = 2^log_factor`, this returns the coefficients of
+/// `sum_j alpha^j f_j(X)`.
+pub fn fold_polynomial<F: Field>(coeffs: &[F], log_factor: usize, alpha: F) -> Vec<F> {
+    coeffs
+        .chunks(1 << log_factor)
+        .map(|chunk| eval_polynomial(chunk, alpha))
+        .collect()
+}
+
+pub fn eval_polynomial<F: Field>(coeffs: &[F], x: F) -> F {
+    coeffs
+        .iter()
+        .rev()
+        .fold(F::ZERO, |acc, &coeff| acc * x + coeff)
+}
+
+/// Divides a polynomial by the vanishing polynomial of `points`, discarding the remainder. For a
+/// polynomial which agrees with `ans` on `points`, this is the quotient of the polynomial minus
+/// the interpolant of `ans`.
+pub fn divide_by_vanishing<F: Field>(mut coeffs: Vec<F>, points: &[F]) -> Vec<F> {
+    for &point in points {
+        // Synthetic division by `X - point`.
+        let mut acc = F::ZERO;
+        for coeff in coeffs.iter_mut().rev() {
+            let next = *coeff + point * acc;
+            *coeff = acc;
+            acc = next;
+        }
+        // The last coefficient is now zero, and `acc` is the remainder.
+        coeffs.pop();
+    }
+    coeffs
+}
+
+/// Multiplies a polynomial by `sum_{i <= e} (comb X)^i`, which raises its degree bound by `e`, and
+/// returns the first `len` coefficients of the product.
+pub fn degree_correct<F: Field>(coeffs: &[F], comb: F, e: usize, len: usize) -> Vec<F> {
+    let comb_pow = comb.exp_u64(e as u64 + 1);
+    let coeff = |i: usize| coeffs.get(i).copied().unwrap_or(F::ZERO);
+    let mut result = vec![F::ZERO; len];
+    let mut prev = F::ZERO;
+    for (i, out) in result.iter_mut().enumerate() {
+        // The product's coefficients satisfy `h_i = q_i + comb h_{i-1} - comb^(e+1) q_{i-e-1}`.
+        let mut h = coeff(i) + comb * prev;
+        if i > e {
+            h -= comb_pow * coeff(i - e - 1);
+        }
+        *out = h;
+        prev = h;
+    }
+    result
+}
+
+/// Evaluates `sum_{i <= e} (comb x)^i` as a geometric sum.
+pub fn degree_correction_factor<F: Field>(comb_x: F, e: usize) -> F {
+    if comb_x == F::ONE {
+        F::from_canonical_usize(e + 1)
+    } else {
+        (F::ONE - comb_x.exp_u64(e as u64 + 1)) / (F::ONE - comb_x)
+    }
+}
+
+/// The first point of row `row` of an oracle committed on `F::GENERATOR H`, for `H` of log size
+/// `log_domain_size`, with its bit-reversed evaluations in rows of `2^log_factor`. The row holds
+/// the evaluations at the `2^log_factor`-th roots of this point's `2^log_factor`-th power.
+pub fn row_start<F: TwoAdicField>(log_domain_size: usize, log_factor: usize, row: usize) -> F {
+    F::GENERATOR
+        * F::two_adic_generator(log_domain_size)
+            .exp_u64(reverse_bits_len(row, log_domain_size - log_factor) as u64)
+}
+
+/// The points of the row of an oracle starting at `start`, as given by `row_start`, in the order
+/// of their evaluations.
+pub fn row_points<F: TwoAdicField>(start: F, log_factor: usize) -> Vec<F> {
+    let mut points = F::two_adic_generator(log_factor)
+        .shifted_powers(start)
+        .take(1 << log_factor)
+        .collect_vec();
+    reverse_slice_index_bits(&mut points);
+    points
+}
+
+/// Folds the evaluations of a function on the row of an oracle starting at `start` into the
+/// evaluation of its fold by `alpha`, as defined by `fold_polynomial`, at `start^(2^log_factor)`.
+///
+/// This is the evaluation at `alpha` of the polynomial interpolating the row.
+pub fn fold_row<F: TwoAdicField, EF: ExtensionField<F>>(
+    start: F,
+    log_factor: usize,
+    alpha: EF,
+    evals: &[EF],
+) -> EF {
+    let points = row_points(start, log_factor);
+    debug_assert_eq!(points.len(), evals.len());
+    if let Some(i) = points.iter().position(|&x| alpha == EF::from_base(x)) {
+        return evals[i];
+    }
+
+    // The row's points are the roots of `Z(X) = X^k - start^k`, so the Lagrange basis polynomial
+    // of `x` is `Z(X) x / (k start^k (X - x))`.
+    let start_pow = start.exp_power_of_2(log_factor);
+    let scale = (alpha.exp_power_of_2(log_factor) - start_pow)
+        / EF::from_base(start_pow * F::from_canonical_usize(1 << log_factor));
+    let inv_diffs = batch_multiplicative_inverse(&points.iter().map(|&x| alpha - x).collect_vec());
+    let sum: EF = points
+        .iter()
+        .zip(evals)
+        .zip(inv_diffs)
+        .map(|((&x, &eval), inv_diff)| eval * inv_diff * x)
+        .sum();
+    sum * scale
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_baby_bear::BabyBear;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_field::{FieldAlgebra, FieldExtensionAlgebra};
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha20Rng;
+
+    use super::*;
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<F, 4>;
+
+    #[test]
+    fn fold_row_matches_fold_polynomial() {
+        let mut rng = ChaCha20Rng::seed_from_u64(0);
+        let log_domain_size = 6;
+        for log_factor in 1..4 {
+            let coeffs: Vec<EF> = (0..1 << (log_domain_size - 1)).map(|_| rng.gen()).collect();
+            let alpha: EF = rng.gen();
+            let folded = fold_polynomial(&coeffs, log_factor, alpha);
+            for row in 0..1 << (log_domain_size - log_factor) {
+                let start = row_start::<F>(log_domain_size, log_factor, row);
+                let evals = row_points(start, log_factor)
+                    .into_iter()
+                    .map(|x| eval_polynomial(&coeffs, EF::from_base(x)))
+                    .collect_vec();
+                assert_eq!(
+                    fold_row(start, log_factor, alpha, &evals),
+                    eval_polynomial(&folded, EF::from_base(start.exp_power_of_2(log_factor)))
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn quotient_and_degree_correction() {
+        let mut rng = ChaCha20Rng::seed_from_u64(1);
+        let quotient: Vec<EF> = (0..11).map(|_| rng.gen()).collect();
+        let remainder: Vec<EF> = (0..5).map(|_| rng.gen()).collect();
+        let points: Vec<EF> = (0..5).map(|_| rng.gen()).collect();
+
+        // Build `quotient * prod (X - point) + remainder`.
+        let mut coeffs = quotient.clone();
+        for &point in &points {
+            coeffs.insert(0, EF::ZERO);
+            for i in 0..coeffs.len() - 1 {
+                let shifted = coeffs[i + 1];
+                coeffs[i] -= point * shifted;
+            }
+        }
+        for (coeff, &r) in coeffs.iter_mut().zip(&remainder) {
+            *coeff += r;
+        }
+        assert_eq!(divide_by_vanishing(coeffs, &points), quotient);
+
+        let comb: EF = rng.gen();
+        let corrected = degree_correct(&quotient, comb, points.len(), 16);
+        let x: EF = rng.gen();
+        assert_eq!(
+            eval_polynomial(&corrected, x),
+            eval_polynomial(&quotient, x) * degree_correction_factor(comb * x, points.len())
+        );
+        assert_eq!(
+            degree_correction_factor(EF::ONE, 3),
+            EF::from_canonical_usize(4)
+        );
+    }
+}
@@ -0,0 +1,178 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_commit::MultiOpeningMmcs;
+use p3_field::{CanonicalEncoding, DecodingError, Field};
+use p3_fri::{FriSizeReport, VerifierHashCount};
+use serde::{Deserialize, Serialize};
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "Witness: Serialize, InputProof: Serialize",
+    deserialize = "Witness: Deserialize<'de>, InputProof: Deserialize<'de>"
+))]
+pub struct StirProof<F: Field, M: MultiOpeningMmcs<F>, Witness, InputProof> {
+    /// The commitment to the evaluations of the tested function, in rows of the first round's
+    /// folding factor.
+    pub initial_commit: M::Commitment,
+    /// Every round but the final one.
+    pub rounds: Vec<StirRoundProof<F, M, Witness>>,
+    /// The function folded by the final round, which has degree less than `final_poly.len()`.
+    pub final_poly: Vec<F>,
+    pub final_pow_witness: Witness,
+    /// The openings of the last committed oracle at the final round's queries.
+    pub final_queries: StirQueryProof<F, M>,
+    /// The openings of the inputs at the first round's queries.
+    pub input_proof: InputProof,
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+#[serde(bound(
+    serialize = "Witness: Serialize",
+    deserialize = "Witness: Deserialize<'de>"
+))]
+pub struct StirRoundProof<F: Field, M: MultiOpeningMmcs<F>, Witness> {
+    /// The commitment to the evaluations of the round's folded function on the next domain, in
+    /// rows of the next round's folding factor.
+    pub commit: M::Commitment,
+    /// The folded function's value at the round's out-of-domain point.
+    pub ood_answer: F,
+    pub pow_witness: Witness,
+    /// The openings of the round's oracle at its queries.
+    pub queries: StirQueryProof<F, M>,
+}
+
+#[derive(Debug, Serialize, Deserialize, Clone)]
+#[serde(bound = "")]
+pub struct StirQueryProof<F: Field, M: MultiOpeningMmcs<F>> {
+    /// For each query, the opened row of the oracle.
+    pub opened_rows: Vec<Vec<F>>,
+    /// A proof of the queried rows, in which the Merkle paths of different queries share their
+    /// common nodes.
+    pub opening_proof: M::MultiProof,
+}
+
+impl<F, M, Witness, InputProof> CanonicalEncoding for StirProof<F, M, Witness, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::Commitment: CanonicalEncoding,
+    M::MultiProof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+    InputProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.initial_commit.encode(out);
+        self.rounds.encode(out);
+        self.final_poly.encode(out);
+        self.final_pow_witness.encode(out);
+        self.final_queries.encode(out);
+        self.input_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            initial_commit: CanonicalEncoding::decode(input)?,
+            rounds: CanonicalEncoding::decode(input)?,
+            final_poly: CanonicalEncoding::decode(input)?,
+            final_pow_witness: CanonicalEncoding::decode(input)?,
+            final_queries: CanonicalEncoding::decode(input)?,
+            input_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<F, M, Witness> CanonicalEncoding for StirRoundProof<F, M, Witness>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::Commitment: CanonicalEncoding,
+    M::MultiProof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.commit.encode(out);
+        self.ood_answer.encode(out);
+        self.pow_witness.encode(out);
+        self.queries.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            commit: CanonicalEncoding::decode(input)?,
+            ood_answer: CanonicalEncoding::decode(input)?,
+            pow_witness: CanonicalEncoding::decode(input)?,
+            queries: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<F, M> CanonicalEncoding for StirQueryProof<F, M>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::MultiProof: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.opened_rows.encode(out);
+        self.opening_proof.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            opened_rows: CanonicalEncoding::decode(input)?,
+            opening_proof: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<F, M, Witness, InputProof> StirProof<F, M, Witness, InputProof>
+where
+    F: Field + CanonicalEncoding,
+    M: MultiOpeningMmcs<F>,
+    M::Commitment: CanonicalEncoding,
+    M::MultiProof: CanonicalEncoding,
+    Witness: CanonicalEncoding,
+    InputProof: CanonicalEncoding + VerifierHashCount,
+{
+    /// Breaks down the size of this proof in its canonical encoding by component, and counts the
+    /// hashing its verifier does. The openings of each round's oracle are reported as those of a
+    /// FRI commit phase round, and the out-of-domain answers are counted with the commitments.
+    pub fn size_report(&self) -> FriSizeReport {
+        let mut report = FriSizeReport {
+            commit_phase_commits: self.initial_commit.to_bytes().len()
+                + self
+                    .rounds
+                    .iter()
+                    .map(|round| round.commit.to_bytes().len() + round.ood_answer.to_bytes().len())
+                    .sum::<usize>(),
+            commit_pow_witnesses: self
+                .rounds
+                .iter()
+                .map(|round| round.pow_witness.to_bytes().len())
+                .sum(),
+            commit_phase_openings: vec![0; self.rounds.len() + 1],
+            final_poly: self.final_poly.to_bytes().len(),
+            pow_witness: self.final_pow_witness.to_bytes().len(),
+            total: self.to_bytes().len(),
+            ..FriSizeReport::default()
+        };
+        report.add_query(
+            &self.input_proof,
+            self.rounds
+                .iter()
+                .map(|round| &round.queries)
+                .chain([&self.final_queries])
+                .map(|queries| {
+                    (
+                        queries.to_bytes().len(),
+                        M::verify_batch_many_hash_count(
+                            queries.opened_rows.len(),
+                            &queries.opening_proof,
+                        ),
+                    )
+                }),
+        );
+        report
+    }
+}
@@ -0,0 +1,164 @@
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::Itertools;
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::MultiOpeningMmcs;
+use p3_dft::{Radix2Dit, TwoAdicSubgroupDft};
+use p3_field::{ExtensionField, Field, TwoAdicField};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_util::{log2_strict_usize, reverse_slice_index_bits};
+use tracing::{debug_span, info_span, instrument};
+
+use crate::config::assert_disjoint_domains;
+use crate::{
+    degree_correct, divide_by_vanishing, eval_polynomial, fold_polynomial, row_start, StirConfig,
+    StirProof, StirQueryProof, StirRound, StirRoundProof,
+};
+
+/// Proves that `input`, the bit-reversed evaluations of a function on `Val::GENERATOR H`, has
+/// degree less than `input.len() >> config.log_blowup`. `open_input` opens the inputs from which
+/// `input` was computed at the first round's queries, which index `input`.
+#[instrument(name = "STIR prover", skip_all)]
+pub fn prove<Val, Challenge, M, Challenger, InputProof>(
+    config: &StirConfig<M>,
+    input: Vec<Challenge>,
+    challenger: &mut Challenger,
+    open_input: impl FnOnce(&[usize]) -> InputProof,
+) -> StirProof<Challenge, M, Challenger::Witness, InputProof>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val> + TwoAdicField,
+    M: MultiOpeningMmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    let rounds = config.rounds(log2_strict_usize(input.len()));
+    assert_disjoint_domains::<Val>(&rounds);
+    let dft = Radix2Dit::default();
+    let shift = Challenge::from_base(Val::GENERATOR);
+
+    let mut coeffs = debug_span!("idft input").in_scope(|| {
+        let mut evals = input.clone();
+        reverse_slice_index_bits(&mut evals);
+        dft.coset_idft(evals, shift)
+    });
+    // The domain is blown up relative to the degree, so all higher coefficients should be zero.
+    debug_assert!(
+        coeffs[1 << rounds[0].log_degree..]
+            .iter()
+            .all(|c| c.is_zero()),
+        "The input has too large a degree"
+    );
+    coeffs.truncate(1 << rounds[0].log_degree);
+
+    let (initial_commit, mut oracle) = config.mmcs.commit_matrix(RowMajorMatrix::new(
+        input,
+        1 << rounds[0].log_folding_factor,
+    ));
+    challenger.observe(initial_commit.clone());
+
+    let mut input_proof = None;
+    let mut open_input = Some(open_input);
+    let mut sample_queries = |challenger: &mut Challenger, round: &StirRound| {
+        let indices = iter::repeat_with(|| challenger.sample_bits(round.log_domain_size))
+            .take(round.num_queries)
+            .collect_vec();
+        // The first round's queries are those at which the inputs are opened.
+        if let Some(open_input) = open_input.take() {
+            input_proof = Some(open_input(&indices));
+        }
+        indices
+            .into_iter()
+            .map(|index| index >> round.log_folding_factor)
+            .collect_vec()
+    };
+
+    let mut round_proofs = Vec::with_capacity(rounds.len() - 1);
+    for (round, next) in rounds.iter().tuple_windows() {
+        let _span = info_span!("round", log_degree = round.log_degree).entered();
+        let alpha: Challenge = challenger.sample_ext_element();
+        let folded = fold_polynomial(&coeffs, round.log_folding_factor, alpha);
+
+        // Commit to the folded function on the next domain, which is half the size.
+        let mut evals = folded.clone();
+        evals.resize(1 << next.log_domain_size, Challenge::ZERO);
+        let mut evals = debug_span!("dft folded").in_scope(|| dft.coset_dft(evals, shift));
+        reverse_slice_index_bits(&mut evals);
+        let (commit, next_oracle) = config
+            .mmcs
+            .commit_matrix(RowMajorMatrix::new(evals, 1 << next.log_folding_factor));
+        challenger.observe(commit.clone());
+
+        let ood_point: Challenge = challenger.sample_ext_element();
+        let ood_answer = eval_polynomial(&folded, ood_point);
+        challenger.observe_ext_element(ood_answer);
+
+        let pow_witness = challenger.grind(config.proof_of_work_bits);
+        let rows = sample_queries(challenger, round);
+        let queries = open_rows(config, &oracle, &rows);
+        let comb: Challenge = challenger.sample_ext_element();
+
+        // The next function is the quotient of the folded one by its answers at the out-of-domain
+        // point and the queried points, with its degree bound corrected back to that of the folded
+        // function.
+        let points = iter::once(ood_point)
+            .chain(rows.into_iter().unique().map(|row| {
+                Challenge::from_base(
+                    row_start::<Val>(round.log_domain_size, round.log_folding_factor, row)
+                        .exp_power_of_2(round.log_folding_factor),
+                )
+            }))
+            .collect_vec();
+        let quotient = divide_by_vanishing(folded, &points);
+        coeffs = degree_correct(&quotient, comb, points.len(), 1 << next.log_degree);
+
+        oracle = next_oracle;
+        round_proofs.push(StirRoundProof {
+            commit,
+            ood_answer,
+            pow_witness,
+            queries,
+        });
+    }
+
+    let final_round = rounds.last().unwrap();
+    let alpha: Challenge = challenger.sample_ext_element();
+    let final_poly = fold_polynomial(&coeffs, final_round.log_folding_factor, alpha);
+    for &x in &final_poly {
+        challenger.observe_ext_element(x);
+    }
+    let final_pow_witness = challenger.grind(config.proof_of_work_bits);
+    let rows = sample_queries(challenger, final_round);
+    let final_queries = open_rows(config, &oracle, &rows);
+
+    StirProof {
+        initial_commit,
+        rounds: round_proofs,
+        final_poly,
+        final_pow_witness,
+        final_queries,
+        input_proof: input_proof.unwrap(),
+    }
+}
+
+fn open_rows<F, M>(
+    config: &StirConfig<M>,
+    oracle: &M::ProverData<RowMajorMatrix<F>>,
+    rows: &[usize],
+) -> StirQueryProof<F, M>
+where
+    F: Field,
+    M: MultiOpeningMmcs<F>,
+{
+    let (opened_rows, opening_proof) = config.mmcs.open_batch_many(rows, oracle);
+    StirQueryProof {
+        opened_rows: opened_rows
+            .into_iter()
+            .map(|mut opened_rows| {
+                assert_eq!(opened_rows.len(), 1);
+                opened_rows.pop().unwrap()
+            })
+            .collect(),
+        opening_proof,
+    }
+}
@@ -0,0 +1,251 @@
+use alloc::vec;
+use alloc::vec::Vec;
+use core::iter;
+
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, FieldChallenger, GrindingChallenger};
+use p3_commit::MultiOpeningMmcs;
+use p3_field::{batch_multiplicative_inverse, ExtensionField, Field, TwoAdicField};
+use p3_matrix::Dimensions;
+
+use crate::config::assert_disjoint_domains;
+use crate::{
+    degree_correction_factor, eval_polynomial, fold_row, row_points, row_start, StirConfig,
+    StirProof, StirQueryProof, StirRound,
+};
+
+#[derive(Debug)]
+pub enum StirError<OracleMmcsErr, InputError> {
+    InvalidProofShape,
+    OracleMmcsError(OracleMmcsErr),
+    InputError(InputError),
+    /// The initial oracle doesn't agree with the inputs at a queried location.
+    InputMismatch,
+    FinalPolyMismatch,
+    InvalidPowWitness,
+}
+
+/// The quotient and degree correction which turn a round's folded function into the next round's.
+struct RoundQuotient<F> {
+    /// The out-of-domain point and the round's queried points.
+    points: Vec<F>,
+    /// The folded function's value at each point, times its barycentric weight.
+    weighted_answers: Vec<F>,
+    comb: F,
+}
+
+impl<F: Field> RoundQuotient<F> {
+    fn new(points: Vec<F>, answers: Vec<F>, comb: F) -> Self {
+        let weights = batch_multiplicative_inverse(
+            &points
+                .iter()
+                .enumerate()
+                .map(|(i, &a)| {
+                    points
+                        .iter()
+                        .enumerate()
+                        .filter(|&(j, _)| j != i)
+                        .map(|(_, &b)| a - b)
+                        .product()
+                })
+                .collect_vec(),
+        );
+        Self {
+            weighted_answers: izip!(weights, answers).map(|(w, ans)| w * ans).collect(),
+            points,
+            comb,
+        }
+    }
+
+    /// The next round's function at `x`, given the folded function's value there.
+    fn apply<Val: Field>(&self, x: Val, folded_at_x: F) -> F
+    where
+        F: ExtensionField<Val>,
+    {
+        let inv_diffs =
+            batch_multiplicative_inverse(&self.points.iter().map(|&a| -a + x).collect_vec());
+        let inv_vanishing: F = inv_diffs.iter().copied().product();
+        // `(g(x) - R(x)) / V(x)`, for `R` the interpolant of the answers and `V` the vanishing
+        // polynomial of the points, where `R(x) / V(x) = sum_a w_a ans_a / (x - a)`.
+        let interpolant_quotient: F = izip!(&self.weighted_answers, inv_diffs)
+            .map(|(&weighted_answer, inv_diff)| weighted_answer * inv_diff)
+            .sum();
+        (folded_at_x * inv_vanishing - interpolant_quotient)
+            * degree_correction_factor(self.comb * x, self.points.len())
+    }
+}
+
+/// Verifies a proof that the function, given by the bit-reversed evaluations on
+/// `Val::GENERATOR H` from which `open_input` computes the values at the first round's queries,
+/// has degree less than `|H| >> config.log_blowup`, for `H` of log size `log_domain_size`.
+pub fn verify<Val, Challenge, M, Challenger, InputProof, InputError>(
+    config: &StirConfig<M>,
+    log_domain_size: usize,
+    proof: &StirProof<Challenge, M, Challenger::Witness, InputProof>,
+    challenger: &mut Challenger,
+    open_input: impl FnOnce(&[usize], &InputProof) -> Result<Vec<Challenge>, InputError>,
+) -> Result<(), StirError<M::Error, InputError>>
+where
+    Val: TwoAdicField,
+    Challenge: ExtensionField<Val> + TwoAdicField,
+    M: MultiOpeningMmcs<Challenge>,
+    Challenger: FieldChallenger<Val> + GrindingChallenger + CanObserve<M::Commitment>,
+{
+    let rounds = config.rounds(log_domain_size);
+    assert_disjoint_domains::<Val>(&rounds);
+    let final_round = rounds.last().unwrap();
+
+    let queries = proof
+        .rounds
+        .iter()
+        .map(|round| &round.queries)
+        .chain([&proof.final_queries])
+        .collect_vec();
+    if proof.rounds.len() + 1 != rounds.len()
+        || proof.final_poly.len() != 1 << (final_round.log_degree - final_round.log_folding_factor)
+        || izip!(&queries, &rounds).any(|(queries, round)| {
+            queries.opened_rows.len() != round.num_queries
+                || queries
+                    .opened_rows
+                    .iter()
+                    .any(|row| row.len() != 1 << round.log_folding_factor)
+        })
+    {
+        return Err(StirError::InvalidProofShape);
+    }
+
+    // Replay the transcript, collecting each round's challenges.
+    challenger.observe(proof.initial_commit.clone());
+    let commits = iter::once(&proof.initial_commit)
+        .chain(proof.rounds.iter().map(|round| &round.commit))
+        .collect_vec();
+    let mut input_indices = None;
+    let mut sample_queries = |challenger: &mut Challenger, round: &StirRound| {
+        let indices = iter::repeat_with(|| challenger.sample_bits(round.log_domain_size))
+            .take(round.num_queries)
+            .collect_vec();
+        let rows = indices
+            .iter()
+            .map(|index| index >> round.log_folding_factor)
+            .collect_vec();
+        input_indices.get_or_insert(indices);
+        rows
+    };
+
+    let mut alphas = Vec::with_capacity(rounds.len());
+    let mut rows = Vec::with_capacity(rounds.len());
+    let mut ood = Vec::with_capacity(rounds.len() - 1);
+    for (round, round_proof) in izip!(&rounds, &proof.rounds) {
+        alphas.push(challenger.sample_ext_element::<Challenge>());
+        challenger.observe(round_proof.commit.clone());
+        let ood_point: Challenge = challenger.sample_ext_element();
+        challenger.observe_ext_element(round_proof.ood_answer);
+        if !challenger.check_witness(config.proof_of_work_bits, round_proof.pow_witness) {
+            return Err(StirError::InvalidPowWitness);
+        }
+        rows.push(sample_queries(challenger, round));
+        let comb: Challenge = challenger.sample_ext_element();
+        ood.push((ood_point, round_proof.ood_answer, comb));
+    }
+    alphas.push(challenger.sample_ext_element());
+    for &x in &proof.final_poly {
+        challenger.observe_ext_element(x);
+    }
+    if !challenger.check_witness(config.proof_of_work_bits, proof.final_pow_witness) {
+        return Err(StirError::InvalidPowWitness);
+    }
+    rows.push(sample_queries(challenger, final_round));
+
+    // The initial oracle must agree with the inputs at the first round's queries.
+    let input_indices = input_indices.unwrap();
+    let input_values =
+        open_input(&input_indices, &proof.input_proof).map_err(StirError::InputError)?;
+    if input_values.len() != input_indices.len() {
+        return Err(StirError::InvalidProofShape);
+    }
+    let position_mask = (1 << rounds[0].log_folding_factor) - 1;
+    for (index, value, row) in izip!(&input_indices, input_values, &queries[0].opened_rows) {
+        if row[index & position_mask] != value {
+            return Err(StirError::InputMismatch);
+        }
+    }
+
+    let mut quotient: Option<RoundQuotient<Challenge>> = None;
+    for (i, round) in rounds.iter().enumerate() {
+        verify_queries(config, commits[i], round, &rows[i], queries[i])
+            .map_err(StirError::OracleMmcsError)?;
+
+        // Fold the round's function at each queried row.
+        let folded = izip!(&rows[i], &queries[i].opened_rows)
+            .map(|(&row, opened_row)| {
+                let start = row_start::<Val>(round.log_domain_size, round.log_folding_factor, row);
+                let evals = match &quotient {
+                    None => opened_row.clone(),
+                    Some(quotient) => {
+                        izip!(row_points(start, round.log_folding_factor), opened_row)
+                            .map(|(x, &eval)| quotient.apply(x, eval))
+                            .collect()
+                    }
+                };
+                (
+                    start.exp_power_of_2(round.log_folding_factor),
+                    fold_row(start, round.log_folding_factor, alphas[i], &evals),
+                )
+            })
+            .collect_vec();
+
+        if let Some(&(ood_point, ood_answer, comb)) = ood.get(i) {
+            let mut points = vec![ood_point];
+            let mut answers = vec![ood_answer];
+            let mut distinct_rows = vec![];
+            for (&row, (y, folded_at_y)) in izip!(&rows[i], folded) {
+                match distinct_rows.iter().position(|&other| other == row) {
+                    // A repeated query opens the same row, which must fold to the same value.
+                    Some(j) if answers[j + 1] != folded_at_y => {
+                        return Err(StirError::InvalidProofShape)
+                    }
+                    Some(_) => {}
+                    None => {
+                        distinct_rows.push(row);
+                        points.push(Challenge::from_base(y));
+                        answers.push(folded_at_y);
+                    }
+                }
+            }
+            quotient = Some(RoundQuotient::new(points, answers, comb));
+        } else {
+            for (y, folded_at_y) in folded {
+                if eval_polynomial(&proof.final_poly, Challenge::from_base(y)) != folded_at_y {
+                    return Err(StirError::FinalPolyMismatch);
+                }
+            }
+        }
+    }
+
+    Ok(())
+}
+
+fn verify_queries<F, M>(
+    config: &StirConfig<M>,
+    commit: &M::Commitment,
+    round: &StirRound,
+    rows: &[usize],
+    queries: &StirQueryProof<F, M>,
+) -> Result<(), M::Error>
+where
+    F: Field,
+    M: MultiOpeningMmcs<F>,
+{
+    let dims = [Dimensions {
+        width: 1 << round.log_folding_factor,
+        height: 1 << (round.log_domain_size - round.log_folding_factor),
+    }];
+    let opened_values = queries
+        .opened_rows
+        .iter()
+        .map(|row| vec![row.clone()])
+        .collect_vec();
+    config
+        .mmcs
+        .verify_batch_many(commit, &dims, rows, &opened_values, &queries.opening_proof)
+}
@@ -0,0 +1,297 @@
+use itertools::{izip, Itertools};
+use p3_challenger::{CanObserve, DuplexChallenger, FieldChallenger};
+use p3_commit::{ExtensionMmcs, Pcs, PolynomialSpace};
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::{ExtensionField, Field};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_stir::{StirConfig, TwoAdicStirPcs, TwoAdicStirPcsProof};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use rand::distributions::{Distribution, Standard};
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha20Rng;
+
+fn seeded_rng() -> impl Rng {
+    ChaCha20Rng::seed_from_u64(0)
+}
+
+/// Commits to random polynomials of the given degrees and opens them at a random point, then
+/// verifies the opening proof after passing it through `tamper`.
+fn do_test_stir_pcs<Val, Challenge, Challenger, P>(
+    (pcs, challenger): &(P, Challenger),
+    log_degrees_by_round: &[&[usize]],
+    tamper: impl FnOnce(&mut P::Proof),
+) -> Result<(), P::Error>
+where
+    P: Pcs<Challenge, Challenger>,
+    P::Domain: PolynomialSpace<Val = Val>,
+    Val: Field,
+    Standard: Distribution<Val>,
+    Challenge: ExtensionField<Val>,
+    Challenger: Clone + CanObserve<P::Commitment> + FieldChallenger<Val>,
+{
+    let mut rng = seeded_rng();
+    let mut p_challenger = challenger.clone();
+
+    let domains_and_polys_by_round = log_degrees_by_round
+        .iter()
+        .map(|log_degrees| {
+            log_degrees
+                .iter()
+                .map(|&log_degree| {
+                    let d = 1 << log_degree;
+                    // random width 5-15
+                    let width = 5 + rng.gen_range(0..=10);
+                    (
+                        pcs.natural_domain_for_degree(d),
+                        RowMajorMatrix::<Val>::rand(&mut rng, d, width),
+                    )
+                })
+                .collect_vec()
+        })
+        .collect_vec();
+
+    let (commits_by_round, data_by_round): (Vec<_>, Vec<_>) = domains_and_polys_by_round
+        .iter()
+        .map(|domains_and_polys| pcs.commit(domains_and_polys.clone()))
+        .unzip();
+    p_challenger.observe_slice(&commits_by_round);
+
+    let zeta: Challenge = p_challenger.sample_ext_element();
+
+    let points_by_round = log_degrees_by_round
+        .iter()
+        .map(|log_degrees| vec![vec![zeta]; log_degrees.len()])
+        .collect_vec();
+    let data_and_points = data_by_round.iter().zip(points_by_round).collect();
+    let (opening_by_round, mut proof) = pcs.open(data_and_points, &mut p_challenger);
+    assert_eq!(opening_by_round.len(), log_degrees_by_round.len());
+    tamper(&mut proof);
+
+    let mut v_challenger = challenger.clone();
+    v_challenger.observe_slice(&commits_by_round);
+    let verifier_zeta: Challenge = v_challenger.sample_ext_element();
+    assert_eq!(verifier_zeta, zeta);
+
+    let commits_and_claims_by_round = izip!(
+        commits_by_round,
+        domains_and_polys_by_round,
+        opening_by_round
+    )
+    .map(|(commit, domains_and_polys, openings)| {
+        let claims = domains_and_polys
+            .iter()
+            .zip(openings)
+            .map(|((domain, _), mat_openings)| (*domain, vec![(zeta, mat_openings[0].clone())]))
+            .collect_vec();
+        (commit, claims)
+    })
+    .collect_vec();
+
+    pcs.verify(commits_and_claims_by_round, &proof, &mut v_challenger)
+}
+
+// Set it up so we create tests inside a module for each pcs, so we get nice error reports
+// specific to a failing PCS.
+macro_rules! make_tests_for_pcs {
+    ($p:expr) => {
+        fn verify(p: &PcsAndChallenger, log_degrees_by_round: &[&[usize]]) {
+            $crate::do_test_stir_pcs(p, log_degrees_by_round, |_| {}).unwrap();
+        }
+
+        #[test]
+        fn single() {
+            let p = $p;
+            for i in 2..10 {
+                verify(&p, &[&[i]]);
+            }
+        }
+
+        #[test]
+        fn many_equal() {
+            let p = $p;
+            for i in 5..8 {
+                verify(&p, &[&[i; 5]]);
+            }
+        }
+
+        #[test]
+        fn many_different() {
+            let p = $p;
+            for i in 3..8 {
+                let degrees = (3..3 + i).collect::<Vec<_>>();
+                verify(&p, &[&degrees]);
+            }
+        }
+
+        #[test]
+        fn many_different_rev() {
+            let p = $p;
+            for i in 3..8 {
+                let degrees = (3..3 + i).rev().collect::<Vec<_>>();
+                verify(&p, &[&degrees]);
+            }
+        }
+
+        #[test]
+        fn multiple_rounds() {
+            let p = $p;
+            verify(&p, &[&[3], &[3]]);
+            verify(&p, &[&[3], &[2]]);
+            verify(&p, &[&[2], &[3]]);
+            verify(&p, &[&[3, 4], &[3, 4]]);
+            verify(&p, &[&[4, 2], &[4, 2]]);
+            verify(&p, &[&[2], &[3, 3]]);
+            verify(&p, &[&[8, 5], &[6]]);
+        }
+
+        #[test]
+        fn tampered_proofs_fail() {
+            let p = $p;
+            let log_degrees: &[&[usize]] = &[&[9, 6]];
+            assert!(
+                $crate::do_test_stir_pcs(&p, log_degrees, |proof: &mut Proof| {
+                    proof[0].final_poly[0] += Challenge::ONE;
+                })
+                .is_err()
+            );
+            assert!(
+                $crate::do_test_stir_pcs(&p, log_degrees, |proof: &mut Proof| {
+                    proof[0].rounds[0].ood_answer += Challenge::ONE;
+                })
+                .is_err()
+            );
+            assert!(
+                $crate::do_test_stir_pcs(&p, log_degrees, |proof: &mut Proof| {
+                    proof[0].rounds[0].queries.opened_rows[0][0] += Challenge::ONE;
+                })
+                .is_err()
+            );
+            assert!(
+                $crate::do_test_stir_pcs(&p, log_degrees, |proof: &mut Proof| {
+                    proof[1].input_proof[0].opened_values[0][1][0] += Val::ONE;
+                })
+                .is_err()
+            );
+            assert!(
+                $crate::do_test_stir_pcs(&p, log_degrees, |proof: &mut Proof| {
+                    proof.swap(0, 1);
+                })
+                .is_err()
+            );
+        }
+    };
+}
+
+mod babybear_stir_pcs {
+    use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+    use p3_field::FieldAlgebra;
+
+    use super::*;
+
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    type Dft = Radix2DitParallel<Val>;
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    type MyPcs = TwoAdicStirPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    type PcsAndChallenger = (MyPcs, Challenger);
+    type Proof = TwoAdicStirPcsProof<Val, Challenge, ValMmcs, ChallengeMmcs>;
+
+    fn get_pcs(
+        log_blowup: usize,
+        log_folding_factor: usize,
+        log_final_poly_len: usize,
+    ) -> PcsAndChallenger {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm.clone());
+
+        let val_mmcs = ValMmcs::new(hash, compress);
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+        let stir_config = StirConfig {
+            log_blowup,
+            log_final_poly_len,
+            log_folding_factor,
+            num_queries: 10,
+            proof_of_work_bits: 8,
+            mmcs: challenge_mmcs,
+        };
+
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, stir_config);
+        (pcs, Challenger::new(perm))
+    }
+
+    mod blowup_1_factor_4 {
+        use super::*;
+        make_tests_for_pcs!(get_pcs(1, 2, 0));
+    }
+    mod blowup_2_factor_16 {
+        use super::*;
+        make_tests_for_pcs!(get_pcs(2, 4, 0));
+    }
+    mod factor_2_final_poly {
+        use super::*;
+        make_tests_for_pcs!(get_pcs(1, 1, 2));
+    }
+}
+
+mod koalabear_stir_pcs {
+    use p3_field::FieldAlgebra;
+    use p3_koala_bear::{KoalaBear, Poseidon2KoalaBear};
+
+    use super::*;
+
+    type Val = KoalaBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2KoalaBear<16>;
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+
+    type Dft = Radix2DitParallel<Val>;
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+    type MyPcs = TwoAdicStirPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    type PcsAndChallenger = (MyPcs, Challenger);
+    type Proof = TwoAdicStirPcsProof<Val, Challenge, ValMmcs, ChallengeMmcs>;
+
+    fn get_pcs(log_blowup: usize, log_folding_factor: usize) -> PcsAndChallenger {
+        let perm = Perm::new_from_rng_128(&mut seeded_rng());
+        let hash = MyHash::new(perm.clone());
+        let compress = MyCompress::new(perm.clone());
+
+        let val_mmcs = ValMmcs::new(hash, compress);
+        let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+        let stir_config = StirConfig {
+            log_blowup,
+            log_final_poly_len: 0,
+            log_folding_factor,
+            num_queries: 10,
+            proof_of_work_bits: 8,
+            mmcs: challenge_mmcs,
+        };
+
+        let pcs = MyPcs::new(Dft::default(), val_mmcs, stir_config);
+        (pcs, Challenger::new(perm))
+    }
+
+    mod blowup_1_factor_8 {
+        use super::*;
+        make_tests_for_pcs!(get_pcs(1, 3));
+    }
+}
@@ -1,6 +1,8 @@
+use alloc::vec::Vec;
 use core::borrow::Borrow;
 use core::marker::PhantomData;
 
+use p3_field::{CanonicalEncoding, DecodingError};
 use serde::{Deserialize, Serialize};
 
 /// A wrapper around an array digest, with a phantom type parameter to ensure that the digest is
@@ -56,3 +58,93 @@ impl<F, W, const DIGEST_ELEMS: usize> AsRef<[W; DIGEST_ELEMS]> for Hash<F, W, DI
         &self.value
     }
 }
+
+impl<F, W: CanonicalEncoding, const DIGEST_ELEMS: usize> CanonicalEncoding
+    for Hash<F, W, DIGEST_ELEMS>
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.value.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        <[W; DIGEST_ELEMS]>::decode(input).map(Self::from)
+    }
+}
+
+/// The cap of a Merkle tree: the layer of `2^cap_height` digests `cap_height` layers below its
+/// root, in order. A cap of height zero is the root itself.
+///
+/// Committing to a cap rather than a root makes each opening proof `cap_height` digests shorter.
+#[derive(Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(bound(serialize = "[W; DIGEST_ELEMS]: Serialize"))]
+#[serde(bound(deserialize = "[W; DIGEST_ELEMS]: Deserialize<'de>"))]
+pub struct MerkleCap<F, W, const DIGEST_ELEMS: usize> {
+    digests: Vec<[W; DIGEST_ELEMS]>,
+    _marker: PhantomData<F>,
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> MerkleCap<F, W, DIGEST_ELEMS> {
+    pub fn digests(&self) -> &[[W; DIGEST_ELEMS]] {
+        &self.digests
+    }
+
+    pub fn len(&self) -> usize {
+        self.digests.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.digests.is_empty()
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> From<Vec<[W; DIGEST_ELEMS]>>
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn from(digests: Vec<[W; DIGEST_ELEMS]>) -> Self {
+        Self {
+            digests,
+            _marker: PhantomData,
+        }
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> From<Hash<F, W, DIGEST_ELEMS>>
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn from(root: Hash<F, W, DIGEST_ELEMS>) -> Self {
+        Self::from(alloc::vec![root.value])
+    }
+}
+
+/// A cap equals a digest if it is a single root with that value.
+impl<F, W: PartialEq, const DIGEST_ELEMS: usize> PartialEq<[W; DIGEST_ELEMS]>
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn eq(&self, other: &[W; DIGEST_ELEMS]) -> bool {
+        self.digests.len() == 1 && self.digests[0] == *other
+    }
+}
+
+impl<F, W, const DIGEST_ELEMS: usize> IntoIterator for MerkleCap<F, W, DIGEST_ELEMS> {
+    type Item = Hash<F, W, DIGEST_ELEMS>;
+    type IntoIter = core::iter::Map<
+        alloc::vec::IntoIter<[W; DIGEST_ELEMS]>,
+        fn([W; DIGEST_ELEMS]) -> Hash<F, W, DIGEST_ELEMS>,
+    >;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.digests.into_iter().map(Hash::from)
+    }
+}
+
+impl<F, W: CanonicalEncoding, const DIGEST_ELEMS: usize> CanonicalEncoding
+    for MerkleCap<F, W, DIGEST_ELEMS>
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.digests.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Vec::<[W; DIGEST_ELEMS]>::decode(input).map(Self::from)
+    }
+}
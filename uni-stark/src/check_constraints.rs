@@ -1,63 +1,269 @@
 use alloc::vec::Vec;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues};
-use p3_field::Field;
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, ExtensionBuilder, MultiTableAirBuilder,
+    PairBuilder, PeriodicAirBuilder, PermutationAirBuilder,
+};
+use p3_field::{ExtensionField, Field};
 use p3_matrix::dense::{RowMajorMatrix, RowMajorMatrixView};
-use p3_matrix::stack::VerticalPair;
 use p3_matrix::Matrix;
 use tracing::instrument;
 
+use crate::{get_all_symbolic_constraints, SymbolicAirBuilder, SymbolicExpression};
+
+/// A constraint which doesn't vanish on some row of a trace, as found by
+/// `find_constraint_failures`.
+#[derive(Clone, Debug)]
+pub struct ConstraintFailure<F, EF> {
+    /// The index of the row on which the constraint was evaluated, as its local row.
+    pub row: usize,
+    /// The index of the constraint, in the order in which the AIR asserts its constraints.
+    pub constraint_index: usize,
+    /// The failing constraint. Base field constraints are lifted to the extension field.
+    pub constraint: SymbolicExpression<EF>,
+    /// The constraint's nonzero value on this row.
+    pub value: EF,
+    /// The rows of the main trace in the constraint's window, starting with the local row, and
+    /// wrapping around the end of the trace.
+    pub window: Vec<Vec<F>>,
+}
+
+/// Evaluates every constraint of `air` on every row of `main`, and returns the constraints which
+/// don't vanish, ordered by row and then by constraint. Unlike the checks made by the prover in
+/// debug builds, this works in any build profile and doesn't stop at the first failure, unless
+/// `max_failures` is reached.
+///
+/// The AIR's permutation trace, if any, is generated from `main` and `permutation_challenges`, as
+/// the prover would.
+#[instrument(name = "find constraint failures", skip_all)]
+pub fn find_constraint_failures<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    public_values: &[F],
+    permutation_challenges: &[EF],
+    max_failures: Option<usize>,
+) -> Vec<ConstraintFailure<F, EF>>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: Air<SymbolicAirBuilder<F, EF>> + for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let permutation = (air.permutation_width() > 0).then(|| {
+        air.permutation_trace(main, permutation_challenges)
+            .expect("AIR has a nonzero permutation width but no permutation trace")
+    });
+    let cumulative_sum = permutation
+        .as_ref()
+        .filter(|_| air.has_cumulative_sum())
+        .map_or(EF::ZERO, |permutation| *permutation.values.last().unwrap());
+
+    let failures = evaluate_constraints(
+        air,
+        main,
+        permutation.as_ref(),
+        permutation_challenges,
+        cumulative_sum,
+        public_values,
+        max_failures,
+    );
+    if failures.is_empty() {
+        return Vec::new();
+    }
+
+    let window_size = <A as BaseAir<F>>::window_size(air);
+    let preprocessed_width = air.preprocessed_trace().map_or(0, |p| p.width());
+    let (constraints, extension_constraints) =
+        get_all_symbolic_constraints::<F, EF, A>(air, preprocessed_width, public_values.len());
+    failures
+        .into_iter()
+        .map(|failure| {
+            let constraint = match failure.constraint {
+                ConstraintRef::Base(i) => constraints[i].lift(),
+                ConstraintRef::Extension(i) => extension_constraints[i].clone(),
+            };
+            ConstraintFailure {
+                row: failure.row,
+                constraint_index: failure.constraint_index,
+                constraint,
+                value: failure.value,
+                window: (0..window_size)
+                    .map(|offset| {
+                        main.row_slice((failure.row + offset) % main.height())
+                            .to_vec()
+                    })
+                    .collect(),
+            }
+        })
+        .collect()
+}
+
+#[cfg(debug_assertions)]
 #[instrument(name = "check constraints", skip_all)]
-pub(crate) fn check_constraints<F, A>(air: &A, main: &RowMajorMatrix<F>, public_values: &Vec<F>)
+pub(crate) fn check_constraints<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    permutation: Option<&RowMajorMatrix<EF>>,
+    permutation_challenges: &[EF],
+    cumulative_sum: EF,
+    public_values: &Vec<F>,
+) where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
+{
+    let failures = evaluate_constraints(
+        air,
+        main,
+        permutation,
+        permutation_challenges,
+        cumulative_sum,
+        public_values,
+        Some(1),
+    );
+    if let Some(failure) = failures.first() {
+        assert_eq!(
+            failure.value,
+            EF::ZERO,
+            "constraints had nonzero value on row {}",
+            failure.row
+        );
+    }
+}
+
+/// Which list returned by `get_all_symbolic_constraints` a constraint belongs to, and its index
+/// there.
+#[derive(Copy, Clone, Debug)]
+enum ConstraintRef {
+    Base(usize),
+    Extension(usize),
+}
+
+#[derive(Debug)]
+struct RawConstraintFailure<EF> {
+    row: usize,
+    constraint_index: usize,
+    constraint: ConstraintRef,
+    value: EF,
+}
+
+fn evaluate_constraints<F, EF, A>(
+    air: &A,
+    main: &RowMajorMatrix<F>,
+    permutation: Option<&RowMajorMatrix<EF>>,
+    permutation_challenges: &[EF],
+    cumulative_sum: EF,
+    public_values: &[F],
+    max_failures: Option<usize>,
+) -> Vec<RawConstraintFailure<EF>>
 where
     F: Field,
-    A: for<'a> Air<DebugConstraintBuilder<'a, F>>,
+    EF: ExtensionField<F>,
+    A: for<'a> Air<DebugConstraintBuilder<'a, F, EF>>,
 {
     let height = main.height();
+    let preprocessed = air.preprocessed_trace();
+    let max_failures = max_failures.unwrap_or(usize::MAX);
+    let mut failures = Vec::new();
 
-    (0..height).for_each(|i| {
-        let i_next = (i + 1) % height;
+    let window_size = <A as BaseAir<F>>::window_size(air);
+    let periodic_columns = <A as BaseAir<F>>::periodic_columns(air);
+    let preprocessed_width = preprocessed.as_ref().map_or(0, |p| p.width());
+    let permutation_width = permutation.map_or(0, |p| p.width());
 
-        let local = main.row_slice(i);
-        let next = main.row_slice(i_next);
-        let main = VerticalPair::new(
-            RowMajorMatrixView::new_row(&*local),
-            RowMajorMatrixView::new_row(&*next),
-        );
+    for i in 0..height {
+        if failures.len() >= max_failures {
+            break;
+        }
+        let rows = (0..window_size).map(|offset| (i + offset) % height);
+
+        let preprocessed_window = preprocessed.as_ref().map_or_else(Vec::new, |preprocessed| {
+            rows.clone()
+                .flat_map(|r| preprocessed.row_slice(r).to_vec())
+                .collect()
+        });
+        let main_window: Vec<F> = rows
+            .clone()
+            .flat_map(|r| main.row_slice(r).to_vec())
+            .collect();
+        let permutation_window = permutation.map_or_else(Vec::new, |permutation| {
+            rows.flat_map(|r| permutation.row_slice(r).to_vec())
+                .collect()
+        });
+
+        let periodic_values = periodic_columns
+            .iter()
+            .map(|column| column[i % column.len()])
+            .collect::<Vec<_>>();
 
         let mut builder = DebugConstraintBuilder {
             row_index: i,
-            main,
+            height,
+            window_size,
+            preprocessed: RowMajorMatrixView::new(&preprocessed_window, preprocessed_width),
+            main: RowMajorMatrixView::new(&main_window, main.width()),
+            permutation: RowMajorMatrixView::new(&permutation_window, permutation_width),
+            periodic_values: &periodic_values,
+            permutation_challenges,
+            cumulative_sum,
             public_values,
             is_first_row: F::from_bool(i == 0),
             is_last_row: F::from_bool(i == height - 1),
-            is_transition: F::from_bool(i != height - 1),
+            num_constraints: 0,
+            num_extension_constraints: 0,
+            failures: Vec::new(),
         };
 
         air.eval(&mut builder);
-    });
+        failures.extend(builder.failures);
+    }
+
+    failures.truncate(max_failures);
+    failures
 }
 
-/// An `AirBuilder` which asserts that each constraint is zero, allowing any failed constraints to
-/// be detected early.
+/// An `AirBuilder` which evaluates constraints on a single row of a trace, and records those which
+/// don't vanish, allowing any failed constraints to be detected before proving.
 #[derive(Debug)]
-pub struct DebugConstraintBuilder<'a, F: Field> {
+pub struct DebugConstraintBuilder<'a, F: Field, EF: ExtensionField<F> = F> {
     row_index: usize,
-    main: VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>,
+    height: usize,
+    window_size: usize,
+    preprocessed: RowMajorMatrixView<'a, F>,
+    main: RowMajorMatrixView<'a, F>,
+    permutation: RowMajorMatrixView<'a, EF>,
+    periodic_values: &'a [F],
+    permutation_challenges: &'a [EF],
+    cumulative_sum: EF,
     public_values: &'a [F],
     is_first_row: F,
     is_last_row: F,
-    is_transition: F,
+    num_constraints: usize,
+    num_extension_constraints: usize,
+    failures: Vec<RawConstraintFailure<EF>>,
+}
+
+impl<F: Field, EF: ExtensionField<F>> DebugConstraintBuilder<'_, F, EF> {
+    fn record(&mut self, constraint: ConstraintRef, value: EF) {
+        if !value.is_zero() {
+            self.failures.push(RawConstraintFailure {
+                row: self.row_index,
+                constraint_index: self.num_constraints + self.num_extension_constraints,
+                constraint,
+                value,
+            });
+        }
+    }
 }
 
-impl<'a, F> AirBuilder for DebugConstraintBuilder<'a, F>
+impl<'a, F, EF> AirBuilder for DebugConstraintBuilder<'a, F, EF>
 where
     F: Field,
+    EF: ExtensionField<F>,
 {
     type F = F;
     type Expr = F;
     type Var = F;
-    type M = VerticalPair<RowMajorMatrixView<'a, F>, RowMajorMatrixView<'a, F>>;
+    type M = RowMajorMatrixView<'a, F>;
 
     fn main(&self) -> Self::M {
         self.main
@@ -72,37 +278,83 @@ where
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            self.is_transition
-        } else {
-            panic!("only supports a window size of 2")
-        }
-    }
-
-    fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
-        assert_eq!(
-            x.into(),
-            F::ZERO,
-            "constraints had nonzero value on row {}",
-            self.row_index
+        assert!(
+            (2..=self.window_size).contains(&size),
+            "window size must be between 2 and the AIR's window size of {}",
+            self.window_size
         );
+        F::from_bool(self.row_index + size <= self.height)
     }
 
-    fn assert_eq<I1: Into<Self::Expr>, I2: Into<Self::Expr>>(&mut self, x: I1, y: I2) {
-        let x = x.into();
-        let y = y.into();
-        assert_eq!(
-            x, y,
-            "values didn't match on row {}: {} != {}",
-            self.row_index, x, y
+    fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
+        self.record(
+            ConstraintRef::Base(self.num_constraints),
+            EF::from_base(x.into()),
         );
+        self.num_constraints += 1;
     }
 }
 
-impl<F: Field> AirBuilderWithPublicValues for DebugConstraintBuilder<'_, F> {
+impl<F: Field, EF: ExtensionField<F>> AirBuilderWithPublicValues
+    for DebugConstraintBuilder<'_, F, EF>
+{
     type PublicVar = Self::F;
 
     fn public_values(&self) -> &[Self::F] {
         self.public_values
     }
 }
+
+impl<F: Field, EF: ExtensionField<F>> PeriodicAirBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type PeriodicVar = Self::F;
+
+    fn periodic_values(&self) -> &[Self::F] {
+        self.periodic_values
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> PairBuilder for DebugConstraintBuilder<'_, F, EF> {
+    fn preprocessed(&self) -> Self::M {
+        self.preprocessed
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> ExtensionBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type EF = EF;
+    type ExprEF = EF;
+    type VarEF = EF;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.record(
+            ConstraintRef::Extension(self.num_extension_constraints),
+            x.into(),
+        );
+        self.num_extension_constraints += 1;
+    }
+}
+
+impl<'a, F: Field, EF: ExtensionField<F>> PermutationAirBuilder
+    for DebugConstraintBuilder<'a, F, EF>
+{
+    type MP = RowMajorMatrixView<'a, EF>;
+    type RandomVar = EF;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        self.permutation_challenges
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> MultiTableAirBuilder for DebugConstraintBuilder<'_, F, EF> {
+    type Sum = EF;
+
+    fn cumulative_sum(&self) -> Self::Sum {
+        self.cumulative_sum
+    }
+}
@@ -1,8 +1,9 @@
+use alloc::vec::Vec;
 use core::marker::PhantomData;
 
 use p3_challenger::{CanObserve, CanSample, FieldChallenger};
-use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::{ExtensionField, Field};
+use p3_commit::{Pcs, PeriodicPolynomialSpace, PolynomialSpace};
+use p3_field::{CanonicalEncoding, ExtensionField, Field};
 
 pub type PcsError<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
@@ -14,6 +15,11 @@ pub type Domain<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenger,
 >>::Domain;
 
+pub type PcsProverData<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+    <SC as StarkGenericConfig>::Challenge,
+    <SC as StarkGenericConfig>::Challenger,
+>>::ProverData;
+
 pub type Val<SC> = <Domain<SC> as PolynomialSpace>::Val;
 
 pub type PackedVal<SC> = <Val<SC> as Field>::Packing;
@@ -22,8 +28,14 @@ pub type PackedChallenge<SC> =
     <<SC as StarkGenericConfig>::Challenge as ExtensionField<Val<SC>>>::ExtensionPacking;
 
 pub trait StarkGenericConfig {
-    /// The PCS used to commit to trace polynomials.
-    type Pcs: Pcs<Self::Challenge, Self::Challenger>;
+    /// The PCS used to commit to trace polynomials, over domains which support the periodic
+    /// columns of AIRs. The domains' field must have a canonical encoding, with which AIR digests
+    /// are computed.
+    type Pcs: Pcs<
+        Self::Challenge,
+        Self::Challenger,
+        Domain: PeriodicPolynomialSpace<Val: CanonicalEncoding>,
+    >;
 
     /// The field from which most random challenges are drawn.
     type Challenge: ExtensionField<Val<Self>>;
@@ -34,11 +46,23 @@ pub trait StarkGenericConfig {
         + CanSample<Self::Challenge>;
 
     fn pcs(&self) -> &Self::Pcs;
+
+    /// Whether proofs are zero-knowledge, which is the case when the PCS is hiding.
+    fn is_zk(&self) -> bool {
+        <Self::Pcs as Pcs<Self::Challenge, Self::Challenger>>::ZK
+    }
+
+    /// A tag absorbed into the transcript before anything else, to separate the proofs of
+    /// different applications or protocol versions which would otherwise share an AIR.
+    fn domain_separator(&self) -> &[u8] {
+        &[]
+    }
 }
 
 #[derive(Debug)]
 pub struct StarkConfig<Pcs, Challenge, Challenger> {
     pcs: Pcs,
+    domain_separator: Vec<u8>,
     _phantom: PhantomData<(Challenge, Challenger)>,
 }
 
@@ -46,15 +70,24 @@ impl<Pcs, Challenge, Challenger> StarkConfig<Pcs, Challenge, Challenger> {
     pub const fn new(pcs: Pcs) -> Self {
         Self {
             pcs,
+            domain_separator: Vec::new(),
             _phantom: PhantomData,
         }
     }
+
+    /// Sets the domain separation tag which provers and verifiers absorb into the transcript.
+    pub fn with_domain_separator(mut self, domain_separator: &[u8]) -> Self {
+        self.domain_separator = domain_separator.to_vec();
+        self
+    }
 }
 
 impl<Pcs, Challenge, Challenger> StarkGenericConfig for StarkConfig<Pcs, Challenge, Challenger>
 where
     Challenge: ExtensionField<<Pcs::Domain as PolynomialSpace>::Val>,
     Pcs: p3_commit::Pcs<Challenge, Challenger>,
+    Pcs::Domain: PeriodicPolynomialSpace,
+    <Pcs::Domain as PolynomialSpace>::Val: CanonicalEncoding,
     Challenger: FieldChallenger<<Pcs::Domain as PolynomialSpace>::Val>
         + CanObserve<<Pcs as p3_commit::Pcs<Challenge, Challenger>>::Commitment>
         + CanSample<Challenge>,
@@ -66,4 +99,8 @@ where
     fn pcs(&self) -> &Self::Pcs {
         &self.pcs
     }
+
+    fn domain_separator(&self) -> &[u8] {
+        &self.domain_separator
+    }
 }
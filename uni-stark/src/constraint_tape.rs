@@ -0,0 +1,1058 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::Itertools;
+use p3_field::{ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra};
+
+use crate::symbolic_builder::SymbolicConstraint;
+use crate::{Entry, SymbolicExpression};
+
+type Packed<F> = <F as Field>::Packing;
+type PackedExt<F, EF> = <EF as ExtensionField<F>>::ExtensionPacking;
+
+/// A node of an optimized constraint DAG, referring to its operands by their indices.
+///
+/// Nodes are deduplicated structurally, so that every distinct subexpression of the constraints is
+/// computed once. The variants up to `Mul` are base field nodes, and the rest are extension field
+/// nodes. Mixed operations take their extension field operand first.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, PartialOrd, Ord)]
+enum Node {
+    Preprocessed(usize),
+    Main(usize),
+    Periodic(usize),
+    Public(usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    Constant(usize),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Neg(usize),
+    Mul(usize, usize),
+    Permutation(usize),
+    Challenge(usize),
+    CumulativeSum,
+    ConstantExt(usize),
+    AddExt(usize, usize),
+    SubExt(usize, usize),
+    NegExt(usize),
+    MulExt(usize, usize),
+    AddBase(usize, usize),
+    SubBase(usize, usize),
+    MulBase(usize, usize),
+}
+
+impl Node {
+    const fn is_ext(&self) -> bool {
+        matches!(
+            self,
+            Self::Permutation(_)
+                | Self::Challenge(_)
+                | Self::CumulativeSum
+                | Self::ConstantExt(_)
+                | Self::AddExt(..)
+                | Self::SubExt(..)
+                | Self::NegExt(_)
+                | Self::MulExt(..)
+                | Self::AddBase(..)
+                | Self::SubBase(..)
+                | Self::MulBase(..)
+        )
+    }
+
+    fn operands(&self) -> Vec<usize> {
+        match *self {
+            Self::Add(x, y)
+            | Self::Sub(x, y)
+            | Self::Mul(x, y)
+            | Self::AddExt(x, y)
+            | Self::SubExt(x, y)
+            | Self::MulExt(x, y)
+            | Self::AddBase(x, y)
+            | Self::SubBase(x, y)
+            | Self::MulBase(x, y) => vec![x, y],
+            Self::Neg(x) | Self::NegExt(x) => vec![x],
+            _ => vec![],
+        }
+    }
+}
+
+/// An instruction of a `ConstraintTape`.
+///
+/// Base and extension field values live in separate register files; `dst` and the operands `x`
+/// and `y` index into the register file of their type.
+#[derive(Copy, Clone, Debug)]
+enum Instruction<F, EF> {
+    Preprocessed {
+        dst: usize,
+        index: usize,
+    },
+    Main {
+        dst: usize,
+        index: usize,
+    },
+    Periodic {
+        dst: usize,
+        index: usize,
+    },
+    Public {
+        dst: usize,
+        index: usize,
+    },
+    IsFirstRow {
+        dst: usize,
+    },
+    IsLastRow {
+        dst: usize,
+    },
+    IsTransition {
+        dst: usize,
+    },
+    IsTransitionWindow {
+        dst: usize,
+        index: usize,
+    },
+    Constant {
+        dst: usize,
+        value: F,
+    },
+    Add {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    Sub {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    Neg {
+        dst: usize,
+        x: usize,
+    },
+    Mul {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    Permutation {
+        dst: usize,
+        index: usize,
+    },
+    Challenge {
+        dst: usize,
+        index: usize,
+    },
+    CumulativeSum {
+        dst: usize,
+    },
+    ConstantExt {
+        dst: usize,
+        value: EF,
+    },
+    AddExt {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    SubExt {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    NegExt {
+        dst: usize,
+        x: usize,
+    },
+    MulExt {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    AddBase {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    SubBase {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    MulBase {
+        dst: usize,
+        x: usize,
+        y: usize,
+    },
+    /// Add a base field register, times the given power of `alpha`, to the accumulator.
+    Accumulate {
+        x: usize,
+        power: usize,
+    },
+    /// Add an extension field register, times the given power of `alpha`, to the accumulator.
+    AccumulateExt {
+        x: usize,
+        power: usize,
+    },
+}
+
+/// The values which constraints are evaluated on, for a packed batch of rows.
+///
+/// Trace values are given row by row, from the local row to the last row of the AIR's window.
+pub(crate) struct ConstraintInputs<'a, F: Field, EF: ExtensionField<F>> {
+    pub(crate) preprocessed: &'a [Packed<F>],
+    pub(crate) main: &'a [Packed<F>],
+    /// The values of the AIR's periodic columns on the local row.
+    pub(crate) periodic: &'a [Packed<F>],
+    pub(crate) permutation: &'a [PackedExt<F, EF>],
+    pub(crate) public_values: &'a [F],
+    pub(crate) permutation_challenges: &'a [PackedExt<F, EF>],
+    pub(crate) cumulative_sum: PackedExt<F, EF>,
+    pub(crate) is_first_row: Packed<F>,
+    pub(crate) is_last_row: Packed<F>,
+    pub(crate) is_transition: Packed<F>,
+    /// The selectors of `is_transition_window` for windows of three or more rows, starting with
+    /// three.
+    pub(crate) is_transition_windows: &'a [Packed<F>],
+}
+
+/// An AIR's constraints compiled into a straight-line program, which computes their random linear
+/// combination by powers of `alpha`.
+///
+/// Compared to evaluating the AIR through a constraint folder, this evaluates each distinct
+/// subexpression once, folds constants ahead of time, keeps base field subexpressions in the base
+/// field, and reuses registers once their values are dead, all without the overhead of a generic
+/// `AirBuilder`.
+#[derive(Clone, Debug)]
+pub(crate) struct ConstraintTape<F, EF> {
+    instructions: Vec<Instruction<F, EF>>,
+    num_registers: usize,
+    num_ext_registers: usize,
+    num_constraints: usize,
+}
+
+impl<F: Field, EF: ExtensionField<F>> ConstraintTape<F, EF> {
+    /// Compiles the given constraints, which are in the order they were asserted, as returned by
+    /// `get_ordered_symbolic_constraints`.
+    pub(crate) fn new(
+        constraints: &[SymbolicConstraint<F, EF>],
+        preprocessed_width: usize,
+        width: usize,
+        permutation_width: usize,
+    ) -> Self {
+        let mut compiler = Compiler::<F, EF> {
+            preprocessed_width,
+            width,
+            permutation_width,
+            nodes: Vec::new(),
+            node_ids: BTreeMap::new(),
+            constants: Vec::new(),
+            ext_constants: Vec::new(),
+        };
+        let mut base_memo = BTreeMap::new();
+        let mut ext_memo = BTreeMap::new();
+        let roots = constraints
+            .iter()
+            .map(|constraint| match constraint {
+                SymbolicConstraint::Base(expr) => compiler.lower(expr, &mut base_memo),
+                SymbolicConstraint::Extension(expr) => compiler.lower(expr, &mut ext_memo),
+            })
+            .collect_vec();
+        compiler.emit(&roots)
+    }
+
+    pub(crate) const fn num_constraints(&self) -> usize {
+        self.num_constraints
+    }
+
+    /// Evaluates the random linear combination of the constraints, where `alpha_powers[i]` is
+    /// the coefficient of the `i`th constraint.
+    pub(crate) fn eval(
+        &self,
+        inputs: &ConstraintInputs<'_, F, EF>,
+        alpha_powers: &[EF],
+    ) -> PackedExt<F, EF> {
+        let mut base = vec![Packed::<F>::ZERO; self.num_registers];
+        let mut ext = vec![PackedExt::<F, EF>::ZERO; self.num_ext_registers];
+        let mut accumulator = PackedExt::<F, EF>::ZERO;
+        for instruction in &self.instructions {
+            match *instruction {
+                Instruction::Preprocessed { dst, index } => base[dst] = inputs.preprocessed[index],
+                Instruction::Main { dst, index } => base[dst] = inputs.main[index],
+                Instruction::Periodic { dst, index } => base[dst] = inputs.periodic[index],
+                Instruction::Public { dst, index } => {
+                    base[dst] = Packed::<F>::from_f(inputs.public_values[index]);
+                }
+                Instruction::IsFirstRow { dst } => base[dst] = inputs.is_first_row,
+                Instruction::IsLastRow { dst } => base[dst] = inputs.is_last_row,
+                Instruction::IsTransition { dst } => base[dst] = inputs.is_transition,
+                Instruction::IsTransitionWindow { dst, index } => {
+                    base[dst] = inputs.is_transition_windows[index]
+                }
+                Instruction::Constant { dst, value } => base[dst] = Packed::<F>::from_f(value),
+                Instruction::Add { dst, x, y } => base[dst] = base[x] + base[y],
+                Instruction::Sub { dst, x, y } => base[dst] = base[x] - base[y],
+                Instruction::Neg { dst, x } => base[dst] = -base[x],
+                Instruction::Mul { dst, x, y } => base[dst] = base[x] * base[y],
+                Instruction::Permutation { dst, index } => ext[dst] = inputs.permutation[index],
+                Instruction::Challenge { dst, index } => {
+                    ext[dst] = inputs.permutation_challenges[index];
+                }
+                Instruction::CumulativeSum { dst } => ext[dst] = inputs.cumulative_sum,
+                Instruction::ConstantExt { dst, value } => {
+                    ext[dst] = PackedExt::<F, EF>::from_f(value);
+                }
+                Instruction::AddExt { dst, x, y } => ext[dst] = ext[x] + ext[y],
+                Instruction::SubExt { dst, x, y } => ext[dst] = ext[x] - ext[y],
+                Instruction::NegExt { dst, x } => ext[dst] = -ext[x],
+                Instruction::MulExt { dst, x, y } => ext[dst] = ext[x] * ext[y],
+                Instruction::AddBase { dst, x, y } => ext[dst] = ext[x] + base[y],
+                Instruction::SubBase { dst, x, y } => ext[dst] = ext[x] - base[y],
+                Instruction::MulBase { dst, x, y } => ext[dst] = ext[x] * base[y],
+                Instruction::Accumulate { x, power } => {
+                    accumulator += PackedExt::<F, EF>::from_f(alpha_powers[power]) * base[x];
+                }
+                Instruction::AccumulateExt { x, power } => {
+                    accumulator += PackedExt::<F, EF>::from_f(alpha_powers[power]) * ext[x];
+                }
+            }
+        }
+        accumulator
+    }
+}
+
+struct Compiler<F, EF> {
+    preprocessed_width: usize,
+    width: usize,
+    permutation_width: usize,
+    nodes: Vec<Node>,
+    node_ids: BTreeMap<Node, usize>,
+    constants: Vec<F>,
+    ext_constants: Vec<EF>,
+}
+
+impl<F: Field, EF: ExtensionField<F>> Compiler<F, EF> {
+    fn push(&mut self, node: Node) -> usize {
+        if let Some(&id) = self.node_ids.get(&node) {
+            return id;
+        }
+        self.nodes.push(node);
+        self.node_ids.insert(node, self.nodes.len() - 1);
+        self.nodes.len() - 1
+    }
+
+    fn constant(&mut self, value: F) -> usize {
+        let index = self
+            .constants
+            .iter()
+            .position(|&c| c == value)
+            .unwrap_or_else(|| {
+                self.constants.push(value);
+                self.constants.len() - 1
+            });
+        self.push(Node::Constant(index))
+    }
+
+    /// Extension field constants which lie in the base field are treated as base field constants.
+    fn constant_ext(&mut self, value: EF) -> usize {
+        if let Some(value) = <EF as ExtensionField<F>>::as_base(&value) {
+            return self.constant(value);
+        }
+        let index = self
+            .ext_constants
+            .iter()
+            .position(|&c| c == value)
+            .unwrap_or_else(|| {
+                self.ext_constants.push(value);
+                self.ext_constants.len() - 1
+            });
+        self.push(Node::ConstantExt(index))
+    }
+
+    fn value(&self, id: usize) -> Option<EF> {
+        match self.nodes[id] {
+            Node::Constant(index) => Some(<EF as FieldExtensionAlgebra<F>>::from_base(
+                self.constants[index],
+            )),
+            Node::ConstantExt(index) => Some(self.ext_constants[index]),
+            _ => None,
+        }
+    }
+
+    fn is_ext(&self, id: usize) -> bool {
+        self.nodes[id].is_ext()
+    }
+
+    fn add(&mut self, x: usize, y: usize) -> usize {
+        match (self.value(x), self.value(y)) {
+            (Some(a), Some(b)) => return self.constant_ext(a + b),
+            (Some(a), _) if a.is_zero() => return y,
+            (_, Some(b)) if b.is_zero() => return x,
+            _ => {}
+        }
+        match (self.is_ext(x), self.is_ext(y)) {
+            (false, false) => self.push(Node::Add(x.min(y), x.max(y))),
+            (true, true) => self.push(Node::AddExt(x.min(y), x.max(y))),
+            (true, false) => self.push(Node::AddBase(x, y)),
+            (false, true) => self.push(Node::AddBase(y, x)),
+        }
+    }
+
+    fn sub(&mut self, x: usize, y: usize) -> usize {
+        if x == y {
+            return self.constant(F::ZERO);
+        }
+        match (self.value(x), self.value(y)) {
+            (Some(a), Some(b)) => return self.constant_ext(a - b),
+            (Some(a), _) if a.is_zero() => return self.neg(y),
+            (_, Some(b)) if b.is_zero() => return x,
+            _ => {}
+        }
+        match (self.is_ext(x), self.is_ext(y)) {
+            (false, false) => self.push(Node::Sub(x, y)),
+            (true, true) => self.push(Node::SubExt(x, y)),
+            (true, false) => self.push(Node::SubBase(x, y)),
+            (false, true) => {
+                let difference = self.push(Node::SubBase(y, x));
+                self.neg(difference)
+            }
+        }
+    }
+
+    fn neg(&mut self, x: usize) -> usize {
+        if let Some(a) = self.value(x) {
+            return self.constant_ext(-a);
+        }
+        match self.nodes[x] {
+            Node::Neg(y) | Node::NegExt(y) => y,
+            _ if self.is_ext(x) => self.push(Node::NegExt(x)),
+            _ => self.push(Node::Neg(x)),
+        }
+    }
+
+    fn mul(&mut self, x: usize, y: usize) -> usize {
+        match (self.value(x), self.value(y)) {
+            (Some(a), Some(b)) => return self.constant_ext(a * b),
+            (Some(a), _) | (_, Some(a)) if a.is_zero() => return self.constant(F::ZERO),
+            (Some(a), _) if a.is_one() => return y,
+            (_, Some(b)) if b.is_one() => return x,
+            (Some(a), _) if (-a).is_one() => return self.neg(y),
+            (_, Some(b)) if (-b).is_one() => return self.neg(x),
+            _ => {}
+        }
+        match (self.is_ext(x), self.is_ext(y)) {
+            (false, false) => self.push(Node::Mul(x.min(y), x.max(y))),
+            (true, true) => self.push(Node::MulExt(x.min(y), x.max(y))),
+            (true, false) => self.push(Node::MulBase(x, y)),
+            (false, true) => self.push(Node::MulBase(y, x)),
+        }
+    }
+
+    /// Lowers a symbolic expression over `F` or `EF` into nodes, returning the root's index.
+    ///
+    /// `memo` maps already lowered subexpressions, which may be shared through `Rc`s, to their
+    /// nodes.
+    fn lower<E: Field>(
+        &mut self,
+        expr: &SymbolicExpression<E>,
+        memo: &mut BTreeMap<*const SymbolicExpression<E>, usize>,
+    ) -> usize
+    where
+        EF: ExtensionField<E>,
+    {
+        let mut lower_rc = |compiler: &mut Self, x: &Rc<SymbolicExpression<E>>| {
+            let ptr = Rc::as_ptr(x);
+            if let Some(&id) = memo.get(&ptr) {
+                return id;
+            }
+            let id = compiler.lower(x, memo);
+            memo.insert(ptr, id);
+            id
+        };
+        match expr {
+            SymbolicExpression::Variable(v) => match v.entry {
+                Entry::Preprocessed { offset } => self.push(Node::Preprocessed(
+                    offset * self.preprocessed_width + v.index,
+                )),
+                Entry::Main { offset } => self.push(Node::Main(offset * self.width + v.index)),
+                Entry::Permutation { offset } => {
+                    self.push(Node::Permutation(offset * self.permutation_width + v.index))
+                }
+                Entry::Periodic => self.push(Node::Periodic(v.index)),
+                Entry::Public => self.push(Node::Public(v.index)),
+                Entry::Challenge => self.push(Node::Challenge(v.index)),
+                Entry::CumulativeSum => self.push(Node::CumulativeSum),
+            },
+            SymbolicExpression::IsFirstRow => self.push(Node::IsFirstRow),
+            SymbolicExpression::IsLastRow => self.push(Node::IsLastRow),
+            SymbolicExpression::IsTransition => self.push(Node::IsTransition),
+            SymbolicExpression::IsTransitionWindow(size) => {
+                self.push(Node::IsTransitionWindow(*size))
+            }
+            SymbolicExpression::Constant(c) => {
+                self.constant_ext(<EF as FieldExtensionAlgebra<E>>::from_base(*c))
+            }
+            SymbolicExpression::Add { x, y, .. } => {
+                let (x, y) = (lower_rc(self, x), lower_rc(self, y));
+                self.add(x, y)
+            }
+            SymbolicExpression::Sub { x, y, .. } => {
+                let (x, y) = (lower_rc(self, x), lower_rc(self, y));
+                self.sub(x, y)
+            }
+            SymbolicExpression::Neg { x, .. } => {
+                let x = lower_rc(self, x);
+                self.neg(x)
+            }
+            SymbolicExpression::Mul { x, y, .. } => {
+                let (x, y) = (lower_rc(self, x), lower_rc(self, y));
+                self.mul(x, y)
+            }
+        }
+    }
+
+    /// Emits instructions for the nodes which the constraint roots depend on, in topological
+    /// order, allocating registers as they become free.
+    fn emit(self, roots: &[usize]) -> ConstraintTape<F, EF> {
+        let num_nodes = self.nodes.len();
+
+        let num_constraints = roots.len();
+
+        // Constraints which fold to zero can be dropped.
+        let roots = roots
+            .iter()
+            .enumerate()
+            .filter(|&(_, &root)| !self.value(root).is_some_and(|value| value.is_zero()))
+            .map(|(power, &root)| (root, power))
+            .collect::<Vec<_>>();
+
+        // Operands always precede the nodes using them, so a reverse sweep finds all live nodes
+        // and a forward sweep finds the last use of each.
+        let mut live = vec![false; num_nodes];
+        for &(root, _) in &roots {
+            live[root] = true;
+        }
+        for id in (0..num_nodes).rev() {
+            if live[id] {
+                for operand in self.nodes[id].operands() {
+                    live[operand] = true;
+                }
+            }
+        }
+        let mut last_use = (0..num_nodes).collect::<Vec<_>>();
+        for id in (0..num_nodes).filter(|&id| live[id]) {
+            for operand in self.nodes[id].operands() {
+                last_use[operand] = id;
+            }
+        }
+        let mut roots_of = vec![Vec::new(); num_nodes];
+        for &(root, power) in &roots {
+            roots_of[root].push(power);
+        }
+
+        let mut registers = vec![0; num_nodes];
+        let mut free = Vec::new();
+        let mut free_ext = Vec::new();
+        let mut num_registers = 0;
+        let mut num_ext_registers = 0;
+        let mut instructions = Vec::new();
+
+        for id in (0..num_nodes).filter(|&id| live[id]) {
+            let node = self.nodes[id];
+            let mut operands = node.operands();
+            operands.dedup();
+            for &operand in &operands {
+                if last_use[operand] == id {
+                    if self.nodes[operand].is_ext() {
+                        free_ext.push(registers[operand]);
+                    } else {
+                        free.push(registers[operand]);
+                    }
+                }
+            }
+
+            let dst = if node.is_ext() {
+                free_ext.pop().unwrap_or_else(|| {
+                    num_ext_registers += 1;
+                    num_ext_registers - 1
+                })
+            } else {
+                free.pop().unwrap_or_else(|| {
+                    num_registers += 1;
+                    num_registers - 1
+                })
+            };
+            registers[id] = dst;
+
+            let r = |operand: usize| registers[operand];
+            instructions.push(match node {
+                Node::Preprocessed(index) => Instruction::Preprocessed { dst, index },
+                Node::Main(index) => Instruction::Main { dst, index },
+                Node::Periodic(index) => Instruction::Periodic { dst, index },
+                Node::Public(index) => Instruction::Public { dst, index },
+                Node::IsFirstRow => Instruction::IsFirstRow { dst },
+                Node::IsLastRow => Instruction::IsLastRow { dst },
+                Node::IsTransition => Instruction::IsTransition { dst },
+                Node::IsTransitionWindow(size) => Instruction::IsTransitionWindow {
+                    dst,
+                    index: size - 3,
+                },
+                Node::Constant(index) => Instruction::Constant {
+                    dst,
+                    value: self.constants[index],
+                },
+                Node::Add(x, y) => Instruction::Add {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::Sub(x, y) => Instruction::Sub {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::Neg(x) => Instruction::Neg { dst, x: r(x) },
+                Node::Mul(x, y) => Instruction::Mul {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::Permutation(index) => Instruction::Permutation { dst, index },
+                Node::Challenge(index) => Instruction::Challenge { dst, index },
+                Node::CumulativeSum => Instruction::CumulativeSum { dst },
+                Node::ConstantExt(index) => Instruction::ConstantExt {
+                    dst,
+                    value: self.ext_constants[index],
+                },
+                Node::AddExt(x, y) => Instruction::AddExt {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::SubExt(x, y) => Instruction::SubExt {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::NegExt(x) => Instruction::NegExt { dst, x: r(x) },
+                Node::MulExt(x, y) => Instruction::MulExt {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::AddBase(x, y) => Instruction::AddBase {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::SubBase(x, y) => Instruction::SubBase {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+                Node::MulBase(x, y) => Instruction::MulBase {
+                    dst,
+                    x: r(x),
+                    y: r(y),
+                },
+            });
+
+            for &power in &roots_of[id] {
+                instructions.push(if node.is_ext() {
+                    Instruction::AccumulateExt { x: dst, power }
+                } else {
+                    Instruction::Accumulate { x: dst, power }
+                });
+            }
+            if last_use[id] == id {
+                if node.is_ext() {
+                    free_ext.push(dst);
+                } else {
+                    free.push(dst);
+                }
+            }
+        }
+
+        ConstraintTape {
+            instructions,
+            num_registers,
+            num_ext_registers,
+            num_constraints,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use p3_air::{
+        Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, ExtensionBuilder,
+        MultiTableAirBuilder, PairBuilder, PeriodicAirBuilder, PermutationAirBuilder,
+    };
+    use p3_baby_bear::BabyBear;
+    use p3_field::extension::BinomialExtensionField;
+    use p3_matrix::Matrix;
+    use rand::distributions::{Distribution, Standard};
+    use rand::rngs::StdRng;
+    use rand::{Rng, SeedableRng};
+
+    use super::*;
+    use crate::symbolic_builder::get_ordered_symbolic_constraints;
+    use crate::{SymbolicAirBuilder, SymbolicExpressionExt};
+
+    type F = BabyBear;
+    type EF = BinomialExtensionField<F, 4>;
+
+    const NUM_PUBLIC_VALUES: usize = 2;
+
+    /// An AIR of the given shape, whose constraints are asserted by `eval`.
+    struct TestAir {
+        preprocessed_width: usize,
+        width: usize,
+        window_size: usize,
+        num_periodic_columns: usize,
+        permutation_width: usize,
+        num_challenges: usize,
+        eval: fn(&mut SymbolicAirBuilder<F, EF>),
+    }
+
+    impl TestAir {
+        const fn new(width: usize, eval: fn(&mut SymbolicAirBuilder<F, EF>)) -> Self {
+            Self {
+                preprocessed_width: 0,
+                width,
+                window_size: 2,
+                num_periodic_columns: 0,
+                permutation_width: 0,
+                num_challenges: 0,
+                eval,
+            }
+        }
+
+        fn constraints(&self) -> Vec<SymbolicConstraint<F, EF>> {
+            get_ordered_symbolic_constraints(self, self.preprocessed_width, NUM_PUBLIC_VALUES)
+        }
+
+        fn tape(&self) -> ConstraintTape<F, EF> {
+            ConstraintTape::new(
+                &self.constraints(),
+                self.preprocessed_width,
+                self.width,
+                self.permutation_width,
+            )
+        }
+    }
+
+    impl BaseAir<F> for TestAir {
+        fn width(&self) -> usize {
+            self.width
+        }
+
+        fn window_size(&self) -> usize {
+            self.window_size
+        }
+
+        fn periodic_columns(&self) -> Vec<Vec<F>> {
+            vec![vec![F::ZERO]; self.num_periodic_columns]
+        }
+
+        fn permutation_width(&self) -> usize {
+            self.permutation_width
+        }
+
+        fn num_permutation_challenges(&self) -> usize {
+            self.num_challenges
+        }
+    }
+
+    impl Air<SymbolicAirBuilder<F, EF>> for TestAir {
+        fn eval(&self, builder: &mut SymbolicAirBuilder<F, EF>) {
+            (self.eval)(builder);
+        }
+    }
+
+    /// The values of every input of an AIR's constraints at a single row, laid out as in
+    /// `ConstraintInputs`.
+    struct Inputs {
+        preprocessed: Vec<F>,
+        main: Vec<F>,
+        periodic: Vec<F>,
+        permutation: Vec<EF>,
+        public_values: Vec<F>,
+        permutation_challenges: Vec<EF>,
+        cumulative_sum: EF,
+        is_first_row: F,
+        is_last_row: F,
+        is_transition: F,
+        is_transition_windows: Vec<F>,
+    }
+
+    impl Inputs {
+        fn random(rng: &mut impl Rng, air: &TestAir) -> Self {
+            Self {
+                preprocessed: random_vec(rng, air.window_size * air.preprocessed_width),
+                main: random_vec(rng, air.window_size * air.width),
+                periodic: random_vec(rng, air.num_periodic_columns),
+                permutation: random_vec(rng, air.window_size * air.permutation_width),
+                public_values: random_vec(rng, NUM_PUBLIC_VALUES),
+                permutation_challenges: random_vec(rng, air.num_challenges),
+                cumulative_sum: rng.gen(),
+                is_first_row: rng.gen(),
+                is_last_row: rng.gen(),
+                is_transition: rng.gen(),
+                is_transition_windows: random_vec(rng, air.window_size - 2),
+            }
+        }
+
+        /// Evaluates `expr` by recursing over its tree, as a folder would.
+        fn eval_directly<E: Field>(&self, expr: &SymbolicExpression<E>, air: &TestAir) -> EF
+        where
+            EF: ExtensionField<E>,
+        {
+            let base = |x: F| <EF as FieldExtensionAlgebra<F>>::from_base(x);
+            match expr {
+                SymbolicExpression::Variable(v) => match v.entry {
+                    Entry::Preprocessed { offset } => {
+                        base(self.preprocessed[offset * air.preprocessed_width + v.index])
+                    }
+                    Entry::Main { offset } => base(self.main[offset * air.width + v.index]),
+                    Entry::Permutation { offset } => {
+                        self.permutation[offset * air.permutation_width + v.index]
+                    }
+                    Entry::Periodic => base(self.periodic[v.index]),
+                    Entry::Public => base(self.public_values[v.index]),
+                    Entry::Challenge => self.permutation_challenges[v.index],
+                    Entry::CumulativeSum => self.cumulative_sum,
+                },
+                SymbolicExpression::IsFirstRow => base(self.is_first_row),
+                SymbolicExpression::IsLastRow => base(self.is_last_row),
+                SymbolicExpression::IsTransition => base(self.is_transition),
+                SymbolicExpression::IsTransitionWindow(size) => {
+                    base(self.is_transition_windows[size - 3])
+                }
+                SymbolicExpression::Constant(c) => <EF as FieldExtensionAlgebra<E>>::from_base(*c),
+                SymbolicExpression::Add { x, y, .. } => {
+                    self.eval_directly(x, air) + self.eval_directly(y, air)
+                }
+                SymbolicExpression::Sub { x, y, .. } => {
+                    self.eval_directly(x, air) - self.eval_directly(y, air)
+                }
+                SymbolicExpression::Neg { x, .. } => -self.eval_directly(x, air),
+                SymbolicExpression::Mul { x, y, .. } => {
+                    self.eval_directly(x, air) * self.eval_directly(y, air)
+                }
+            }
+        }
+
+        fn eval_tape(&self, tape: &ConstraintTape<F, EF>, alpha_powers: &[EF]) -> PackedExt<F, EF> {
+            let pack = |values: &[F]| values.iter().map(|&x| Packed::<F>::from_f(x)).collect_vec();
+            let pack_ext = |values: &[EF]| {
+                values
+                    .iter()
+                    .map(|&x| PackedExt::<F, EF>::from_f(x))
+                    .collect_vec()
+            };
+            tape.eval(
+                &ConstraintInputs {
+                    preprocessed: &pack(&self.preprocessed),
+                    main: &pack(&self.main),
+                    periodic: &pack(&self.periodic),
+                    permutation: &pack_ext(&self.permutation),
+                    public_values: &self.public_values,
+                    permutation_challenges: &pack_ext(&self.permutation_challenges),
+                    cumulative_sum: PackedExt::<F, EF>::from_f(self.cumulative_sum),
+                    is_first_row: Packed::<F>::from_f(self.is_first_row),
+                    is_last_row: Packed::<F>::from_f(self.is_last_row),
+                    is_transition: Packed::<F>::from_f(self.is_transition),
+                    is_transition_windows: &pack(&self.is_transition_windows),
+                },
+                alpha_powers,
+            )
+        }
+    }
+
+    fn random_vec<T>(rng: &mut impl Rng, len: usize) -> Vec<T>
+    where
+        Standard: Distribution<T>,
+    {
+        (0..len).map(|_| rng.gen()).collect()
+    }
+
+    /// Checks that the tape of `air`'s constraints computes the same random linear combination as
+    /// evaluating the constraints directly, on random inputs.
+    fn check_against_direct_evaluation(air: &TestAir) {
+        let mut rng = StdRng::seed_from_u64(0);
+        let constraints = air.constraints();
+        let tape = air.tape();
+        assert_eq!(tape.num_constraints(), constraints.len());
+
+        for _ in 0..10 {
+            let inputs = Inputs::random(&mut rng, air);
+            let alpha: EF = rng.gen();
+            let alpha_powers = alpha.powers().take(constraints.len()).collect_vec();
+            let expected: EF = constraints
+                .iter()
+                .zip(&alpha_powers)
+                .map(|(constraint, &alpha_power)| {
+                    alpha_power
+                        * match constraint {
+                            SymbolicConstraint::Base(expr) => inputs.eval_directly(expr, air),
+                            SymbolicConstraint::Extension(expr) => inputs.eval_directly(expr, air),
+                        }
+                })
+                .sum();
+            assert_eq!(
+                inputs.eval_tape(&tape, &alpha_powers),
+                PackedExt::<F, EF>::from_f(expected)
+            );
+        }
+    }
+
+    fn count(tape: &ConstraintTape<F, EF>, f: impl Fn(&Instruction<F, EF>) -> bool) -> usize {
+        tape.instructions.iter().filter(|&i| f(i)).count()
+    }
+
+    /// Constraints on every kind of input, over a window of three rows.
+    fn eval_mixed(builder: &mut SymbolicAirBuilder<F, EF>) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+        let permutation = builder.permutation();
+        let periodic = builder.periodic_values()[0];
+        let public = builder.public_values()[0];
+        let r: SymbolicExpressionExt<F, EF> = builder.permutation_randomness()[0].into();
+        let cumulative_sum = builder.cumulative_sum();
+        let (local, next, last) = (main.row_slice(0), main.row_slice(1), main.row_slice(2));
+        let prep = preprocessed.row_slice(0);
+        let (phi, phi_next) = (permutation.row_slice(0)[0], permutation.row_slice(1)[0]);
+
+        let product = local[0] * local[1];
+        builder.assert_zero(product.clone() - next[2] + periodic);
+        builder.when_first_row().assert_eq(local[2], public);
+        builder
+            .when_last_row()
+            .assert_zero(product.clone() * prep[0] - F::TWO);
+        builder
+            .when_transition()
+            .assert_eq(next[0], local[0] + local[1]);
+        builder
+            .when_transition_window(3)
+            .assert_eq(last[1], -product.square());
+
+        builder.when_transition().assert_eq_ext(
+            SymbolicExpressionExt::<F, EF>::from(phi_next) - phi,
+            r.clone() * product - SymbolicExpression::<F>::from(local[2]),
+        );
+        builder.when_last_row().assert_eq_ext(phi, cumulative_sum);
+        let ext_constant = EF::from_base_slice(&[F::ONE, F::TWO, F::ZERO, F::ZERO]);
+        builder.assert_zero_ext(
+            r * SymbolicExpressionExt::<F, EF>::from_f(ext_constant)
+                + SymbolicExpressionExt::<F, EF>::from_f(EF::TWO)
+                    * SymbolicExpression::<F>::from(prep[1]),
+        );
+    }
+
+    #[test]
+    fn matches_direct_evaluation() {
+        check_against_direct_evaluation(&TestAir {
+            preprocessed_width: 2,
+            width: 3,
+            window_size: 3,
+            num_periodic_columns: 1,
+            permutation_width: 1,
+            num_challenges: 1,
+            eval: eval_mixed,
+        });
+    }
+
+    #[test]
+    fn shares_common_subexpressions() {
+        // The product is built twice, with its operands swapped.
+        let air = TestAir::new(3, |builder| {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            builder.assert_zero(local[0] * local[1] + local[2]);
+            builder.assert_zero(local[1] * local[0] - local[2]);
+        });
+        check_against_direct_evaluation(&air);
+
+        let tape = air.tape();
+        assert_eq!(count(&tape, |i| matches!(i, Instruction::Mul { .. })), 1);
+        assert_eq!(count(&tape, |i| matches!(i, Instruction::Main { .. })), 3);
+    }
+
+    #[test]
+    fn folds_constants() {
+        let air = TestAir::new(3, |builder| {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            let two = SymbolicExpression::<F>::ONE + SymbolicExpression::ONE;
+            builder.assert_zero(local[0] * two - local[0] * F::TWO);
+            builder.assert_zero(local[0] * F::ZERO + local[1] * F::ONE - local[1]);
+            builder.assert_zero((local[0] - local[0]) * local[1] + local[2]);
+        });
+        check_against_direct_evaluation(&air);
+
+        // The first two constraints fold to zero and are dropped, and the third to a column.
+        let tape = air.tape();
+        assert_eq!(tape.num_constraints(), 3);
+        assert!(matches!(
+            tape.instructions[..],
+            [
+                Instruction::Main { index: 2, .. },
+                Instruction::Accumulate { power: 2, .. }
+            ]
+        ));
+    }
+
+    #[test]
+    fn reuses_registers() {
+        let air = TestAir::new(16, |builder| {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            builder.assert_zero(local.iter().copied().sum::<SymbolicExpression<F>>());
+        });
+        check_against_direct_evaluation(&air);
+
+        // Each partial sum is dead once the next column is added to it, so the running sum and
+        // the next column are all that's ever live.
+        let tape = air.tape();
+        assert_eq!(count(&tape, |i| matches!(i, Instruction::Add { .. })), 15);
+        assert_eq!(tape.num_registers, 2);
+        assert_eq!(tape.num_ext_registers, 0);
+    }
+
+    #[test]
+    fn keeps_base_subexpressions_in_the_base_field() {
+        let air = TestAir {
+            num_challenges: 1,
+            ..TestAir::new(2, |builder| {
+                let main = builder.main();
+                let local = main.row_slice(0);
+                let r: SymbolicExpressionExt<F, EF> = builder.permutation_randomness()[0].into();
+                builder.assert_zero_ext(r * (local[0] * local[1] + F::ONE));
+            })
+        };
+        check_against_direct_evaluation(&air);
+
+        let tape = air.tape();
+        assert_eq!(count(&tape, |i| matches!(i, Instruction::Mul { .. })), 1);
+        assert_eq!(count(&tape, |i| matches!(i, Instruction::Add { .. })), 1);
+        assert_eq!(
+            count(&tape, |i| matches!(i, Instruction::MulBase { .. })),
+            1
+        );
+        assert_eq!(
+            count(&tape, |i| matches!(
+                i,
+                Instruction::ConstantExt { .. }
+                    | Instruction::AddExt { .. }
+                    | Instruction::MulExt { .. }
+            )),
+            0
+        );
+        // The challenge's register is free once it's multiplied by the base field sum.
+        assert_eq!(tape.num_ext_registers, 1);
+    }
+}
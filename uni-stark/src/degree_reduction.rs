@@ -0,0 +1,438 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, PairBuilder, PeriodicAirBuilder,
+};
+use p3_field::Field;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_maybe_rayon::prelude::*;
+use tracing::instrument;
+
+use crate::{get_symbolic_constraints, Entry, SymbolicAirBuilder, SymbolicExpression};
+
+/// A node of a constraint DAG, referring to its operands by their indices.
+///
+/// Unlike `SymbolicExpression`, which shares subexpressions through `Rc`s, this can be held by an
+/// AIR, which must be `Sync`.
+#[derive(Copy, Clone, Debug)]
+enum Node<F> {
+    Variable(Entry, usize),
+    IsFirstRow,
+    IsLastRow,
+    IsTransition,
+    IsTransitionWindow(usize),
+    Constant(F),
+    Add(usize, usize),
+    Sub(usize, usize),
+    Neg(usize),
+    Mul(usize, usize),
+}
+
+impl<F> Node<F> {
+    fn operands(&self) -> Vec<usize> {
+        match *self {
+            Self::Add(x, y) | Self::Sub(x, y) | Self::Mul(x, y) => vec![x, y],
+            Self::Neg(x) => vec![x],
+            _ => vec![],
+        }
+    }
+}
+
+/// Wraps an AIR, replacing subterms of its constraints with intermediate columns until no
+/// constraint's degree exceeds `max_degree`, which keeps the quotient degree down at the cost of a
+/// wider trace.
+///
+/// The intermediate columns are appended to the main trace, after the inner AIR's columns, and each
+/// comes with a constraint equating it to the subterm it replaces. Use `generate_trace` to compute
+/// them from the inner AIR's trace.
+///
+/// Subterms involving selectors such as `is_first_row` can't be replaced, as their values depend on
+/// the PCS's domain, so constraints must keep selectors out of their high degree factors, as
+/// `when_first_row().assert_eq(...)` and the like do. AIRs with a permutation trace aren't
+/// supported.
+#[derive(Debug)]
+pub struct DegreeReducedAir<F, A> {
+    inner: A,
+    max_degree: usize,
+    num_public_values: usize,
+    nodes: Vec<Node<F>>,
+    /// The roots of the rewritten constraints of the inner AIR.
+    constraints: Vec<usize>,
+    /// The roots of the subterms defining each intermediate column.
+    definitions: Vec<usize>,
+}
+
+impl<F: Field, A: BaseAir<F>> DegreeReducedAir<F, A> {
+    /// Reduces the constraints of `inner` to at most `max_degree`, which must be at least two.
+    ///
+    /// # Panics
+    ///
+    /// Panics if `inner` has a permutation trace, or if some constraint can't be reduced because a
+    /// product involving selectors is of too high a degree.
+    pub fn new(inner: A, max_degree: usize, num_public_values: usize) -> Self
+    where
+        A: Air<SymbolicAirBuilder<F>>,
+    {
+        assert!(max_degree >= 2, "the maximum degree must be at least two");
+        assert_eq!(
+            inner.permutation_width(),
+            0,
+            "degree reduction doesn't support AIRs with a permutation trace"
+        );
+        let preprocessed_width = inner.preprocessed_trace().map_or(0, |p| p.width());
+        let symbolic_constraints =
+            get_symbolic_constraints(&inner, preprocessed_width, num_public_values);
+
+        let mut reducer = Reducer {
+            width: inner.width(),
+            max_degree,
+            nodes: Vec::new(),
+            degrees: Vec::new(),
+            has_selector: Vec::new(),
+            reduced: BTreeMap::new(),
+            columns: BTreeMap::new(),
+            definitions: Vec::new(),
+        };
+        let mut memo = BTreeMap::new();
+        let roots = symbolic_constraints
+            .iter()
+            .map(|constraint| {
+                let root = reducer.lower(constraint, &mut memo);
+                reducer.reduce(root, max_degree)
+            })
+            .collect::<Vec<_>>();
+
+        // Keep only the nodes which the constraints and definitions depend on.
+        let Reducer {
+            nodes, definitions, ..
+        } = reducer;
+        let mut live = vec![false; nodes.len()];
+        for &root in roots.iter().chain(&definitions) {
+            live[root] = true;
+        }
+        for id in (0..nodes.len()).rev() {
+            if live[id] {
+                for operand in nodes[id].operands() {
+                    live[operand] = true;
+                }
+            }
+        }
+        let mut new_ids = vec![0; nodes.len()];
+        let mut live_nodes = Vec::new();
+        for id in (0..nodes.len()).filter(|&id| live[id]) {
+            let new_id = |x: usize| new_ids[x];
+            live_nodes.push(match nodes[id] {
+                Node::Add(x, y) => Node::Add(new_id(x), new_id(y)),
+                Node::Sub(x, y) => Node::Sub(new_id(x), new_id(y)),
+                Node::Neg(x) => Node::Neg(new_id(x)),
+                Node::Mul(x, y) => Node::Mul(new_id(x), new_id(y)),
+                node => node,
+            });
+            new_ids[id] = live_nodes.len() - 1;
+        }
+
+        Self {
+            inner,
+            max_degree,
+            num_public_values,
+            nodes: live_nodes,
+            constraints: roots.iter().map(|&root| new_ids[root]).collect(),
+            definitions: definitions.iter().map(|&root| new_ids[root]).collect(),
+        }
+    }
+}
+
+impl<F: Field, A: BaseAir<F>> DegreeReducedAir<F, A> {
+    pub const fn inner(&self) -> &A {
+        &self.inner
+    }
+
+    pub const fn max_degree(&self) -> usize {
+        self.max_degree
+    }
+
+    /// The number of intermediate columns appended to the inner AIR's main trace.
+    pub fn num_intermediate_columns(&self) -> usize {
+        self.definitions.len()
+    }
+
+    /// Extends a main trace of the inner AIR with the intermediate columns.
+    ///
+    /// Traces which need padding should be padded first, with `pad_trace` and the inner AIR, as
+    /// padding rows wouldn't satisfy the intermediate columns' constraints otherwise.
+    #[instrument(name = "generate intermediate columns", skip_all)]
+    pub fn generate_trace(
+        &self,
+        main: &RowMajorMatrix<F>,
+        public_values: &[F],
+    ) -> RowMajorMatrix<F> {
+        assert_eq!(main.width(), self.inner.width(), "wrong main trace width");
+        assert_eq!(
+            public_values.len(),
+            self.num_public_values,
+            "wrong number of public values"
+        );
+        let height = main.height();
+        let inner_width = self.inner.width();
+        let preprocessed = self.inner.preprocessed_trace();
+        let periodic_columns = self.inner.periodic_columns();
+
+        // Only the nodes which the definitions depend on need to be evaluated.
+        let mut needed = vec![false; self.nodes.len()];
+        for &root in &self.definitions {
+            needed[root] = true;
+        }
+        for id in (0..self.nodes.len()).rev() {
+            if needed[id] {
+                for operand in self.nodes[id].operands() {
+                    needed[operand] = true;
+                }
+            }
+        }
+
+        let mut trace = RowMajorMatrix::new(F::zero_vec(height * self.width()), self.width());
+        trace.par_rows_mut().enumerate().for_each(|(i, row)| {
+            let mut values = vec![F::ZERO; self.nodes.len()];
+            for id in (0..self.nodes.len()).filter(|&id| needed[id]) {
+                values[id] = match self.nodes[id] {
+                    Node::Variable(Entry::Main { offset }, index) if index < inner_width => {
+                        main.get((i + offset) % height, index)
+                    }
+                    // Definitions only refer to the local row of earlier intermediate columns.
+                    Node::Variable(Entry::Main { .. }, index) => {
+                        values[self.definitions[index - inner_width]]
+                    }
+                    Node::Variable(Entry::Preprocessed { offset }, index) => preprocessed
+                        .as_ref()
+                        .expect("AIR refers to a preprocessed trace it doesn't have")
+                        .get((i + offset) % height, index),
+                    Node::Variable(Entry::Periodic, index) => {
+                        let column = &periodic_columns[index];
+                        column[i % column.len()]
+                    }
+                    Node::Variable(Entry::Public, index) => public_values[index],
+                    Node::Constant(c) => c,
+                    Node::Add(x, y) => values[x] + values[y],
+                    Node::Sub(x, y) => values[x] - values[y],
+                    Node::Neg(x) => -values[x],
+                    Node::Mul(x, y) => values[x] * values[y],
+                    node => unreachable!("definitions can't depend on {node:?}"),
+                };
+            }
+            row[..inner_width].copy_from_slice(&main.row_slice(i));
+            for (value, &root) in row[inner_width..].iter_mut().zip(&self.definitions) {
+                *value = values[root];
+            }
+        });
+        trace
+    }
+}
+
+impl<F: Field, A: BaseAir<F>> BaseAir<F> for DegreeReducedAir<F, A> {
+    fn width(&self) -> usize {
+        self.inner.width() + self.definitions.len()
+    }
+
+    fn window_size(&self) -> usize {
+        self.inner.window_size()
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        self.inner.preprocessed_trace()
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        self.inner.periodic_columns()
+    }
+}
+
+impl<F, A, AB> Air<AB> for DegreeReducedAir<F, A>
+where
+    F: Field,
+    A: BaseAir<F>,
+    AB: PairBuilder<F = F> + AirBuilderWithPublicValues + PeriodicAirBuilder,
+{
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+        let mut values: Vec<AB::Expr> = Vec::with_capacity(self.nodes.len());
+        for node in &self.nodes {
+            let value = match *node {
+                Node::Variable(Entry::Main { offset }, index) => main.get(offset, index).into(),
+                Node::Variable(Entry::Preprocessed { offset }, index) => {
+                    preprocessed.get(offset, index).into()
+                }
+                Node::Variable(Entry::Periodic, index) => builder.periodic_values()[index].into(),
+                Node::Variable(Entry::Public, index) => builder.public_values()[index].into(),
+                Node::Variable(entry, _) => unreachable!("unsupported entry {entry:?}"),
+                Node::IsFirstRow => builder.is_first_row(),
+                Node::IsLastRow => builder.is_last_row(),
+                Node::IsTransition => builder.is_transition(),
+                Node::IsTransitionWindow(size) => builder.is_transition_window(size),
+                Node::Constant(c) => c.into(),
+                Node::Add(x, y) => values[x].clone() + values[y].clone(),
+                Node::Sub(x, y) => values[x].clone() - values[y].clone(),
+                Node::Neg(x) => -values[x].clone(),
+                Node::Mul(x, y) => values[x].clone() * values[y].clone(),
+            };
+            values.push(value);
+        }
+
+        for &root in &self.constraints {
+            builder.assert_zero(values[root].clone());
+        }
+        let inner_width = self.inner.width();
+        for (k, &root) in self.definitions.iter().enumerate() {
+            builder.assert_eq(main.get(0, inner_width + k), values[root].clone());
+        }
+    }
+}
+
+struct Reducer<F> {
+    width: usize,
+    max_degree: usize,
+    nodes: Vec<Node<F>>,
+    /// The degree multiple of each node, as given by `SymbolicExpression::degree_multiple`.
+    degrees: Vec<usize>,
+    /// Whether each node depends on a selector, which rules out replacing it with a column.
+    has_selector: Vec<bool>,
+    /// Maps a node and a degree bound to an equivalent node within the bound.
+    reduced: BTreeMap<(usize, usize), usize>,
+    /// Maps a node to the variable of the intermediate column replacing it.
+    columns: BTreeMap<usize, usize>,
+    definitions: Vec<usize>,
+}
+
+impl<F: Field> Reducer<F> {
+    fn push(&mut self, node: Node<F>) -> usize {
+        let (degree, has_selector) = match node {
+            Node::Variable(entry, _) => (
+                match entry {
+                    Entry::Public => 0,
+                    _ => 1,
+                },
+                false,
+            ),
+            Node::IsFirstRow | Node::IsLastRow | Node::IsTransitionWindow(_) => (1, true),
+            Node::IsTransition => (0, true),
+            Node::Constant(_) => (0, false),
+            Node::Add(x, y) | Node::Sub(x, y) => (
+                self.degrees[x].max(self.degrees[y]),
+                self.has_selector[x] || self.has_selector[y],
+            ),
+            Node::Neg(x) => (self.degrees[x], self.has_selector[x]),
+            Node::Mul(x, y) => (
+                self.degrees[x] + self.degrees[y],
+                self.has_selector[x] || self.has_selector[y],
+            ),
+        };
+        self.nodes.push(node);
+        self.degrees.push(degree);
+        self.has_selector.push(has_selector);
+        self.nodes.len() - 1
+    }
+
+    fn lower(
+        &mut self,
+        expr: &SymbolicExpression<F>,
+        memo: &mut BTreeMap<*const SymbolicExpression<F>, usize>,
+    ) -> usize {
+        let mut lower_rc = |reducer: &mut Self, x: &Rc<SymbolicExpression<F>>| {
+            let ptr = Rc::as_ptr(x);
+            if let Some(&id) = memo.get(&ptr) {
+                return id;
+            }
+            let id = reducer.lower(x, memo);
+            memo.insert(ptr, id);
+            id
+        };
+        let node = match expr {
+            SymbolicExpression::Variable(v) => match v.entry {
+                Entry::Preprocessed { .. }
+                | Entry::Main { .. }
+                | Entry::Periodic
+                | Entry::Public => Node::Variable(v.entry, v.index),
+                Entry::Permutation { .. } | Entry::Challenge | Entry::CumulativeSum => {
+                    panic!("degree reduction doesn't support AIRs with a permutation trace")
+                }
+            },
+            SymbolicExpression::IsFirstRow => Node::IsFirstRow,
+            SymbolicExpression::IsLastRow => Node::IsLastRow,
+            SymbolicExpression::IsTransition => Node::IsTransition,
+            SymbolicExpression::IsTransitionWindow(size) => Node::IsTransitionWindow(*size),
+            SymbolicExpression::Constant(c) => Node::Constant(*c),
+            SymbolicExpression::Add { x, y, .. } => Node::Add(lower_rc(self, x), lower_rc(self, y)),
+            SymbolicExpression::Sub { x, y, .. } => Node::Sub(lower_rc(self, x), lower_rc(self, y)),
+            SymbolicExpression::Neg { x, .. } => Node::Neg(lower_rc(self, x)),
+            SymbolicExpression::Mul { x, y, .. } => Node::Mul(lower_rc(self, x), lower_rc(self, y)),
+        };
+        self.push(node)
+    }
+
+    /// Returns a node equal to `id` on the trace, whose degree is at most `bound`, which is only
+    /// zero for nodes of degree zero.
+    fn reduce(&mut self, id: usize, bound: usize) -> usize {
+        if self.degrees[id] <= bound {
+            return id;
+        }
+        if let Some(&reduced) = self.reduced.get(&(id, bound)) {
+            return reduced;
+        }
+        let reduced = match self.nodes[id] {
+            _ if bound == 1 => {
+                assert!(
+                    !self.has_selector[id],
+                    "unable to reduce a constraint to degree {}, as a product involving selectors \
+                     has too high a degree",
+                    self.max_degree
+                );
+                let definition = self.reduce(id, self.max_degree);
+                self.column(definition)
+            }
+            Node::Add(x, y) => {
+                let (x, y) = (self.reduce(x, bound), self.reduce(y, bound));
+                self.push(Node::Add(x, y))
+            }
+            Node::Sub(x, y) => {
+                let (x, y) = (self.reduce(x, bound), self.reduce(y, bound));
+                self.push(Node::Sub(x, y))
+            }
+            Node::Neg(x) => {
+                let x = self.reduce(x, bound);
+                self.push(Node::Neg(x))
+            }
+            Node::Mul(x, y) => {
+                // The factor of lower degree keeps as much of its degree as possible, and the other
+                // factor makes do with the rest.
+                let (x_bound, y_bound) = if self.degrees[x] >= self.degrees[y] {
+                    let y_bound = self.degrees[y].min(bound - 1);
+                    (bound - y_bound, y_bound)
+                } else {
+                    let x_bound = self.degrees[x].min(bound - 1);
+                    (x_bound, bound - x_bound)
+                };
+                let (x, y) = (self.reduce(x, x_bound), self.reduce(y, y_bound));
+                self.push(Node::Mul(x, y))
+            }
+            _ => unreachable!("nodes without operands have degree at most one"),
+        };
+        self.reduced.insert((id, bound), reduced);
+        reduced
+    }
+
+    /// Returns the variable of an intermediate column equal to `definition`.
+    fn column(&mut self, definition: usize) -> usize {
+        if let Some(&variable) = self.columns.get(&definition) {
+            return variable;
+        }
+        let index = self.width + self.definitions.len();
+        self.definitions.push(definition);
+        let variable = self.push(Node::Variable(Entry::Main { offset: 0 }, index));
+        self.columns.insert(definition, variable);
+        variable
+    }
+}
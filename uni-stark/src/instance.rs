@@ -0,0 +1,167 @@
+use alloc::collections::BTreeMap;
+use alloc::rc::Rc;
+use alloc::vec;
+use alloc::vec::Vec;
+
+use p3_air::{Air, BaseAir};
+use p3_challenger::FieldChallenger;
+use p3_field::{CanonicalEncoding, ExtensionField, Field, FieldAlgebra, FieldExtensionAlgebra};
+use p3_sha256::Sha256;
+use p3_symmetric::CryptographicHasher;
+use tracing::instrument;
+
+use crate::symbolic_builder::{
+    get_all_symbolic_constraints, log_quotient_degree, SymbolicAirBuilder,
+};
+use crate::{Entry, StarkGenericConfig, SymbolicExpression, Val};
+
+/// A digest of an AIR instance, as computed by `get_air_digest`.
+pub type AirDigest = [u8; 32];
+
+/// Computes a digest of an AIR instance.
+///
+/// The digest is a SHA-256 hash of the AIR's trace widths and window size, its periodic columns,
+/// its numbers of public values and permutation challenges, its log quotient degree and the
+/// structure of all of its symbolic constraints. It depends only on how the AIR evaluates its
+/// constraints, so it is stable across runs and platforms, and callers proving or verifying many
+/// statements about the same AIR can compute it once. Provers and verifiers absorb it into the
+/// transcript before sampling any challenge, which prevents a proof for one AIR from being
+/// replayed as a proof for another.
+#[instrument(name = "compute AIR digest", skip_all, level = "debug")]
+pub fn get_air_digest<F, EF, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+    is_zk: bool,
+) -> AirDigest
+where
+    F: Field + CanonicalEncoding,
+    EF: ExtensionField<F>,
+    A: Air<SymbolicAirBuilder<F, EF>>,
+{
+    let (constraints, extension_constraints) =
+        get_all_symbolic_constraints::<F, EF, A>(air, preprocessed_width, num_public_values);
+    let constraint_degree = constraints
+        .iter()
+        .map(SymbolicExpression::degree_multiple)
+        .chain(
+            extension_constraints
+                .iter()
+                .map(SymbolicExpression::degree_multiple),
+        )
+        .max()
+        .unwrap_or(0);
+
+    let periodic_columns = <A as BaseAir<F>>::periodic_columns(air);
+    let mut encoding = [
+        <A as BaseAir<F>>::width(air),
+        <A as BaseAir<F>>::window_size(air),
+        preprocessed_width,
+        <A as BaseAir<F>>::permutation_width(air),
+        <A as BaseAir<F>>::num_permutation_challenges(air),
+        <A as BaseAir<F>>::has_cumulative_sum(air) as usize,
+        periodic_columns.len(),
+        num_public_values,
+        log_quotient_degree(constraint_degree, is_zk),
+        constraints.len(),
+        extension_constraints.len(),
+    ]
+    .map(F::from_canonical_usize)
+    .to_vec();
+    for column in &periodic_columns {
+        encoding.push(F::from_canonical_usize(column.len()));
+        encoding.extend_from_slice(column);
+    }
+    encode_constraints(&constraints, &mut encoding);
+    encode_constraints(&extension_constraints, &mut encoding);
+    Sha256.hash_iter(encoding.to_bytes())
+}
+
+/// Absorbs the configuration's domain separation tag, followed by the digest of each AIR being
+/// proven, into the transcript.
+pub(crate) fn observe_air_digests<SC: StarkGenericConfig>(
+    config: &SC,
+    challenger: &mut SC::Challenger,
+    air_digests: &[AirDigest],
+) {
+    let domain_separator = config.domain_separator();
+    challenger.observe(Val::<SC>::from_canonical_usize(domain_separator.len()));
+    for &byte in domain_separator {
+        challenger.observe(Val::<SC>::from_canonical_u8(byte));
+    }
+    for air_digest in air_digests {
+        for &byte in air_digest {
+            challenger.observe(Val::<SC>::from_canonical_u8(byte));
+        }
+    }
+}
+
+/// Appends an encoding of the given constraints to `out`.
+///
+/// Constraints are directed acyclic graphs whose nodes may be shared through `Rc`s, so each node
+/// is encoded once, in post-order, and referred to by its index afterwards. The root of each
+/// constraint is encoded last, so the constraint is identified by the index of its final node.
+fn encode_constraints<F: Field, EF: ExtensionField<F>>(
+    constraints: &[SymbolicExpression<EF>],
+    out: &mut Vec<F>,
+) {
+    let mut indices = BTreeMap::new();
+    let mut num_nodes = 0;
+    for constraint in constraints {
+        encode_node(constraint, &mut indices, &mut num_nodes, out);
+    }
+}
+
+/// Encodes `expr` after any of its children that haven't been encoded yet, returning its index.
+fn encode_node<F: Field, EF: ExtensionField<F>>(
+    expr: &SymbolicExpression<EF>,
+    indices: &mut BTreeMap<*const SymbolicExpression<EF>, usize>,
+    num_nodes: &mut usize,
+    out: &mut Vec<F>,
+) -> usize {
+    let mut encode_child = |x: &Rc<SymbolicExpression<EF>>, out: &mut Vec<F>| {
+        if let Some(&index) = indices.get(&Rc::as_ptr(x)) {
+            return index;
+        }
+        let index = encode_node(x, indices, num_nodes, out);
+        indices.insert(Rc::as_ptr(x), index);
+        index
+    };
+    let encoding: Vec<usize> = match expr {
+        SymbolicExpression::Variable(v) => {
+            let (kind, offset) = match v.entry {
+                Entry::Preprocessed { offset } => (0, offset),
+                Entry::Main { offset } => (1, offset),
+                Entry::Permutation { offset } => (2, offset),
+                Entry::Public => (3, 0),
+                Entry::Challenge => (4, 0),
+                Entry::CumulativeSum => (5, 0),
+                Entry::Periodic => (6, 0),
+            };
+            vec![0, kind, offset, v.index]
+        }
+        SymbolicExpression::IsFirstRow => vec![1],
+        SymbolicExpression::IsLastRow => vec![2],
+        SymbolicExpression::IsTransition => vec![3],
+        SymbolicExpression::IsTransitionWindow(size) => vec![9, *size],
+        SymbolicExpression::Constant(c) => {
+            out.push(F::from_canonical_usize(4));
+            out.extend_from_slice(<EF as FieldExtensionAlgebra<F>>::as_base_slice(c));
+            *num_nodes += 1;
+            return *num_nodes - 1;
+        }
+        SymbolicExpression::Add { x, y, .. } => {
+            vec![5, encode_child(x, out), encode_child(y, out)]
+        }
+        SymbolicExpression::Sub { x, y, .. } => {
+            vec![6, encode_child(x, out), encode_child(y, out)]
+        }
+        SymbolicExpression::Neg { x, .. } => vec![7, encode_child(x, out)],
+        SymbolicExpression::Mul { x, y, .. } => {
+            vec![8, encode_child(x, out), encode_child(y, out)]
+        }
+    };
+    out.extend(encoding.into_iter().map(F::from_canonical_usize));
+    *num_nodes += 1;
+    *num_nodes - 1
+}
@@ -4,27 +4,39 @@
 
 extern crate alloc;
 
+mod check_constraints;
 mod config;
+mod constraint_tape;
+mod degree_reduction;
 mod folder;
+mod instance;
+mod multi_prover;
+mod multi_verifier;
+mod preprocessed;
 mod proof;
 mod prover;
+mod soundness;
 mod symbolic_builder;
 mod symbolic_expression;
+mod symbolic_expression_ext;
 mod symbolic_variable;
 mod verifier;
 mod zerofier_coset;
 
-#[cfg(debug_assertions)]
-mod check_constraints;
-
-#[cfg(debug_assertions)]
 pub use check_constraints::*;
 pub use config::*;
+pub use degree_reduction::*;
 pub use folder::*;
+pub use instance::*;
+pub use multi_prover::*;
+pub use multi_verifier::*;
+pub use preprocessed::*;
 pub use proof::*;
 pub use prover::*;
+pub use soundness::*;
 pub use symbolic_builder::*;
 pub use symbolic_expression::*;
+pub use symbolic_expression_ext::*;
 pub use symbolic_variable::*;
 pub use verifier::*;
 pub use zerofier_coset::*;
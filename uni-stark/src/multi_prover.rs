@@ -0,0 +1,414 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{izip, Itertools};
+use p3_air::{Air, BaseAir};
+use p3_challenger::{CanObserve, CanSample, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::FieldAlgebra;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_util::log2_strict_usize;
+use tracing::{info_span, instrument};
+
+use crate::constraint_tape::ConstraintTape;
+use crate::instance::observe_air_digests;
+use crate::prover::{quotient_values, split_window};
+use crate::symbolic_builder::{
+    get_ordered_symbolic_constraints, log_quotient_degree, SymbolicConstraint,
+};
+use crate::verifier::window_points;
+use crate::{
+    get_air_digest, pad_trace, Commitments, MultiProof, OpenedValues, ProverError,
+    StarkGenericConfig, SymbolicAirBuilder, Val,
+};
+
+/// Prove several AIR instances ("tables") at once.
+///
+/// Each instance is given as `(air, trace, public_values)`. Traces may have different heights. All
+/// traces are committed in a single PCS commitment, all permutation traces in another, all quotient
+/// chunks in a third, and everything is opened with a single PCS opening argument.
+///
+/// All tables share the same permutation challenges, so tables can take part in a common argument
+/// such as a LogUp bus; see `LogUpAir`. The cumulative sums of all tables must add up to zero.
+///
+/// Tables of different kinds can be combined by wrapping their AIRs in an enum which implements
+/// `Air` by dispatching to the wrapped AIR. Preprocessed traces and zero-knowledge mode are not
+/// supported here.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_multi<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    instances: Vec<(&A, RowMajorMatrix<Val<SC>>, &Vec<Val<SC>>)>,
+    challenger: &mut SC::Challenger,
+) -> MultiProof<SC>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>,
+{
+    assert!(!instances.is_empty(), "No AIR instances given?");
+    assert!(
+        !config.is_zk(),
+        "zero-knowledge mode is not supported for multi proofs"
+    );
+    assert!(
+        instances
+            .iter()
+            .all(|&(air, _, _)| <A as BaseAir<Val<SC>>>::preprocessed_trace(air).is_none()),
+        "preprocessed traces are not supported for multi proofs"
+    );
+
+    let (airs, traces, public_values): (Vec<_>, Vec<_>, Vec<_>) =
+        instances.into_iter().multiunzip();
+
+    let degrees = traces.iter().map(|trace| trace.height()).collect_vec();
+    let degree_bits = degrees
+        .iter()
+        .map(|&degree| log2_strict_usize(degree))
+        .collect_vec();
+    let permutation_widths = airs
+        .iter()
+        .map(|&air| <A as BaseAir<Val<SC>>>::permutation_width(air))
+        .collect_vec();
+    let window_sizes = airs
+        .iter()
+        .map(|&air| <A as BaseAir<Val<SC>>>::window_size(air))
+        .collect_vec();
+    assert!(
+        izip!(&airs, &degrees).all(|(&air, &degree)| {
+            <A as BaseAir<Val<SC>>>::periodic_columns(air)
+                .iter()
+                .all(|column| column.len().is_power_of_two() && column.len() <= degree)
+        }),
+        "periodic columns must have power of two periods no larger than the trace height"
+    );
+
+    let (constraint_tapes, log_quotient_degrees): (Vec<_>, Vec<_>) =
+        izip!(&airs, &public_values, &permutation_widths)
+            .map(|(&air, public_values, &permutation_width)| {
+                let constraints = get_ordered_symbolic_constraints::<Val<SC>, SC::Challenge, A>(
+                    air,
+                    0,
+                    public_values.len(),
+                );
+                let constraint_degree = constraints
+                    .iter()
+                    .map(SymbolicConstraint::degree_multiple)
+                    .max()
+                    .unwrap_or(0);
+                let constraint_tape = ConstraintTape::new(
+                    &constraints,
+                    0,
+                    <A as BaseAir<Val<SC>>>::width(air),
+                    permutation_width,
+                );
+                (
+                    constraint_tape,
+                    log_quotient_degree(constraint_degree, false),
+                )
+            })
+            .unzip();
+
+    let pcs = config.pcs();
+    let trace_domains = degrees
+        .iter()
+        .map(|&degree| pcs.natural_domain_for_degree(degree))
+        .collect_vec();
+
+    // Permutation traces are generated from the main traces after the latter have been committed,
+    // so we hold on to copies of them. Debug builds also need them to check the constraints.
+    let mains = izip!(&traces, &permutation_widths)
+        .map(|(trace, &permutation_width)| {
+            (permutation_width > 0 || cfg!(debug_assertions)).then(|| trace.clone())
+        })
+        .collect_vec();
+
+    let (trace_commit, trace_data) = info_span!("commit to trace data")
+        .in_scope(|| pcs.commit(izip!(trace_domains.iter().copied(), traces).collect_vec()));
+
+    // Observe the instance.
+    let air_digests = izip!(&airs, &public_values)
+        .map(|(&air, public_values)| {
+            get_air_digest::<Val<SC>, SC::Challenge, A>(air, 0, public_values.len(), false)
+        })
+        .collect_vec();
+    observe_air_digests(config, challenger, &air_digests);
+    challenger.observe(Val::<SC>::from_canonical_usize(airs.len()));
+    for &bits in &degree_bits {
+        challenger.observe(Val::<SC>::from_canonical_usize(bits));
+    }
+
+    challenger.observe(trace_commit.clone());
+    for public_values in &public_values {
+        challenger.observe_slice(public_values);
+    }
+
+    // The permutation challenges are shared by all tables.
+    let num_permutation_challenges = airs
+        .iter()
+        .map(|&air| <A as BaseAir<Val<SC>>>::num_permutation_challenges(air))
+        .max()
+        .unwrap_or(0);
+    let permutation_challenges: Vec<SC::Challenge> = (0..num_permutation_challenges)
+        .map(|_| challenger.sample_ext_element())
+        .collect();
+    let permutations = izip!(&airs, &mains, &permutation_widths, &degrees)
+        .map(|(&air, main, &permutation_width, &degree)| {
+            (permutation_width > 0).then(|| {
+                let num_challenges = <A as BaseAir<Val<SC>>>::num_permutation_challenges(air);
+                let permutation = <A as BaseAir<Val<SC>>>::permutation_trace(
+                    air,
+                    main.as_ref().unwrap(),
+                    &permutation_challenges[..num_challenges],
+                )
+                .expect("AIR has a nonzero permutation width but no permutation trace");
+                assert_eq!(permutation.width(), permutation_width);
+                assert_eq!(permutation.height(), degree);
+                permutation
+            })
+        })
+        .collect_vec();
+    let cumulative_sums = izip!(&airs, &permutations)
+        .map(|(&air, permutation)| {
+            <A as BaseAir<Val<SC>>>::has_cumulative_sum(air).then(|| {
+                let permutation = permutation
+                    .as_ref()
+                    .expect("an AIR with a cumulative sum must have a permutation trace");
+                *permutation.values.last().unwrap()
+            })
+        })
+        .collect_vec();
+
+    #[cfg(debug_assertions)]
+    for (&air, main, permutation, cumulative_sum, public_values) in izip!(
+        &airs,
+        &mains,
+        &permutations,
+        &cumulative_sums,
+        &public_values
+    ) {
+        let num_challenges = <A as BaseAir<Val<SC>>>::num_permutation_challenges(air);
+        crate::check_constraints::check_constraints(
+            air,
+            main.as_ref().unwrap(),
+            permutation.as_ref(),
+            &permutation_challenges[..num_challenges],
+            cumulative_sum.unwrap_or_default(),
+            public_values,
+        );
+    }
+
+    let permutation_traces = izip!(trace_domains.iter().copied(), permutations)
+        .filter_map(|(trace_domain, permutation)| {
+            permutation.map(|permutation| (trace_domain, permutation.flatten_to_base()))
+        })
+        .collect_vec();
+    let permutation_commit_and_data = (!permutation_traces.is_empty()).then(|| {
+        info_span!("commit to permutation trace data").in_scope(|| pcs.commit(permutation_traces))
+    });
+    if let Some((permutation_commit, _)) = &permutation_commit_and_data {
+        challenger.observe(permutation_commit.clone());
+    }
+    for cumulative_sum in cumulative_sums.iter().flatten() {
+        challenger.observe_ext_element(*cumulative_sum);
+    }
+
+    let alpha: SC::Challenge = challenger.sample_ext_element();
+
+    // The index of each table's permutation trace within the permutation commitment.
+    let permutation_indices = permutation_widths
+        .iter()
+        .scan(0, |next_index, &permutation_width| {
+            Some((permutation_width > 0).then(|| {
+                *next_index += 1;
+                *next_index - 1
+            }))
+        })
+        .collect_vec();
+
+    let quotient_domains = izip!(&trace_domains, &degree_bits, &log_quotient_degrees)
+        .map(|(trace_domain, &bits, &log_quotient_degree)| {
+            trace_domain.create_disjoint_domain(1 << (bits + log_quotient_degree))
+        })
+        .collect_vec();
+
+    let quotient_chunks = izip!(
+        0..,
+        &airs,
+        &window_sizes,
+        &public_values,
+        &trace_domains,
+        &quotient_domains,
+        &log_quotient_degrees,
+        &constraint_tapes,
+        &permutation_indices,
+        &cumulative_sums,
+    )
+    .flat_map(
+        |(
+            i,
+            &air,
+            &window_size,
+            public_values,
+            &trace_domain,
+            &quotient_domain,
+            &log_quotient_degree,
+            constraint_tape,
+            &permutation_index,
+            &cumulative_sum,
+        )| {
+            let trace_on_quotient_domain =
+                pcs.get_evaluations_on_domain(&trace_data, i, quotient_domain);
+            let permutation_on_quotient_domain = permutation_index.map(|permutation_index| {
+                let (_, permutation_data) = permutation_commit_and_data.as_ref().unwrap();
+                pcs.get_evaluations_on_domain(permutation_data, permutation_index, quotient_domain)
+            });
+            let num_challenges = <A as BaseAir<Val<SC>>>::num_permutation_challenges(air);
+            let quotient_values = quotient_values::<SC, _>(
+                constraint_tape,
+                window_size,
+                &<A as BaseAir<Val<SC>>>::periodic_columns(air),
+                public_values,
+                trace_domain,
+                quotient_domain,
+                None,
+                trace_on_quotient_domain,
+                permutation_on_quotient_domain,
+                &permutation_challenges[..num_challenges],
+                cumulative_sum.unwrap_or_default(),
+                alpha,
+            );
+            let quotient_degree = 1 << log_quotient_degree;
+            let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
+            let chunks = quotient_domain.split_evals(quotient_degree, quotient_flat);
+            let qc_domains = quotient_domain.split_domains(quotient_degree);
+            izip!(qc_domains, chunks)
+        },
+    )
+    .collect_vec();
+
+    let (quotient_commit, quotient_data) =
+        info_span!("commit to quotient poly chunks").in_scope(|| pcs.commit(quotient_chunks));
+    challenger.observe(quotient_commit.clone());
+
+    let (permutation_commit, permutation_data) = permutation_commit_and_data.unzip();
+    let commitments = Commitments {
+        trace: trace_commit,
+        permutation: permutation_commit,
+        quotient_chunks: quotient_commit,
+        random: None,
+    };
+
+    let zeta: SC::Challenge = challenger.sample();
+
+    let trace_points = izip!(&trace_domains, &window_sizes)
+        .map(|(&trace_domain, &window_size)| window_points::<SC>(trace_domain, zeta, window_size))
+        .collect_vec();
+    let quotient_points = log_quotient_degrees
+        .iter()
+        // open every chunk at zeta
+        .flat_map(|&log_quotient_degree| (0..1 << log_quotient_degree).map(|_| vec![zeta]))
+        .collect_vec();
+    let permutation_points = izip!(&trace_points, &permutation_widths)
+        .filter(|(_, &permutation_width)| permutation_width > 0)
+        .map(|(points, _)| points.clone())
+        .collect_vec();
+
+    let mut rounds = vec![
+        (&trace_data, trace_points),
+        (&quotient_data, quotient_points),
+    ];
+    if let Some(permutation_data) = &permutation_data {
+        rounds.push((permutation_data, permutation_points));
+    }
+    let (opened_values, opening_proof) =
+        info_span!("open").in_scope(|| pcs.open(rounds, challenger));
+
+    let mut quotient_openings = opened_values[1].iter();
+    let mut permutation_openings = opened_values.get(2).into_iter().flatten();
+    let opened_values = izip!(
+        &opened_values[0],
+        &window_sizes,
+        &log_quotient_degrees,
+        &permutation_widths,
+        cumulative_sums
+    )
+    .map(
+        |(
+            trace_openings,
+            &window_size,
+            &log_quotient_degree,
+            &permutation_width,
+            cumulative_sum,
+        )| {
+            let (trace_local, trace_next, trace_lookahead) = split_window(trace_openings.clone());
+            let (permutation_local, permutation_next, permutation_lookahead) =
+                if permutation_width > 0 {
+                    split_window(permutation_openings.next().unwrap().clone())
+                } else {
+                    (vec![], vec![], vec![vec![]; window_size - 2])
+                };
+            OpenedValues {
+                preprocessed_local: vec![],
+                preprocessed_next: vec![],
+                preprocessed_lookahead: vec![vec![]; window_size - 2],
+                trace_local,
+                trace_next,
+                trace_lookahead,
+                permutation_local,
+                permutation_next,
+                permutation_lookahead,
+                cumulative_sum,
+                quotient_chunks: quotient_openings
+                    .by_ref()
+                    .take(1 << log_quotient_degree)
+                    .map(|v| v[0].clone())
+                    .collect(),
+                random: None,
+            }
+        },
+    )
+    .collect();
+
+    MultiProof {
+        commitments,
+        opened_values,
+        opening_proof,
+        degree_bits,
+    }
+}
+
+/// Like `prove_multi`, but accepts traces of any nonzero height, padding them with `pad_trace`, and
+/// returns an error rather than panicking if a trace doesn't fit its AIR or an AIR has a
+/// preprocessed trace.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn try_prove_multi<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    instances: Vec<(&A, RowMajorMatrix<Val<SC>>, &Vec<Val<SC>>)>,
+    challenger: &mut SC::Challenger,
+) -> Result<MultiProof<SC>, ProverError>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>,
+{
+    if instances
+        .iter()
+        .any(|&(air, _, _)| <A as BaseAir<Val<SC>>>::preprocessed_trace(air).is_some())
+    {
+        return Err(ProverError::PreprocessedTraceUnsupported);
+    }
+    let instances = instances
+        .into_iter()
+        .map(|(air, trace, public_values)| Ok((air, pad_trace(air, trace)?, public_values)))
+        .collect::<Result<Vec<_>, _>>()?;
+    Ok(prove_multi(config, instances, challenger))
+}
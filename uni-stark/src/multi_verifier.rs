@@ -0,0 +1,236 @@
+use alloc::vec;
+use alloc::vec::Vec;
+
+use itertools::{izip, Itertools};
+use p3_air::{Air, BaseAir};
+use p3_challenger::{CanObserve, CanSample, FieldChallenger};
+use p3_commit::{Pcs, PolynomialSpace};
+use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra};
+use tracing::instrument;
+
+use crate::instance::observe_air_digests;
+use crate::symbolic_builder::{get_log_quotient_degree, SymbolicAirBuilder};
+use crate::verifier::{verify_constraints, window_claims, window_points};
+use crate::{
+    get_air_digest, MultiProof, PcsError, StarkGenericConfig, Val, VerificationError,
+    VerifierConstraintFolder,
+};
+
+/// Verify a proof produced by `prove_multi`.
+///
+/// Each instance is given as `(air, public_values)`, in the same order as was given to the prover.
+#[instrument(skip_all)]
+pub fn verify_multi<SC, A>(
+    config: &SC,
+    instances: Vec<(&A, &Vec<Val<SC>>)>,
+    challenger: &mut SC::Challenger,
+    proof: &MultiProof<SC>,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    let MultiProof {
+        commitments,
+        opened_values,
+        opening_proof,
+        degree_bits,
+    } = proof;
+
+    if instances
+        .iter()
+        .any(|&(air, _)| <A as BaseAir<Val<SC>>>::preprocessed_trace(air).is_some())
+    {
+        return Err(VerificationError::PreprocessedTraceUnsupported);
+    }
+
+    let permutation_widths = instances
+        .iter()
+        .map(|&(air, _)| {
+            <A as BaseAir<Val<SC>>>::permutation_width(air)
+                * <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D
+        })
+        .collect_vec();
+    if instances.is_empty()
+        || commitments.random.is_some()
+        || commitments.permutation.is_some() != permutation_widths.iter().any(|&width| width > 0)
+        || degree_bits.len() != instances.len()
+        || opened_values.len() != instances.len()
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
+    let log_quotient_degrees = instances
+        .iter()
+        .map(|&(air, public_values)| {
+            get_log_quotient_degree::<Val<SC>, SC::Challenge, A>(air, 0, public_values.len(), false)
+        })
+        .collect_vec();
+
+    let pcs = config.pcs();
+    let trace_domains = degree_bits
+        .iter()
+        .map(|&bits| pcs.natural_domain_for_degree(1 << bits))
+        .collect_vec();
+    let quotient_chunks_domains = izip!(&trace_domains, degree_bits, &log_quotient_degrees)
+        .map(|(trace_domain, &bits, &log_quotient_degree)| {
+            trace_domain
+                .create_disjoint_domain(1 << (bits + log_quotient_degree))
+                .split_domains(1 << log_quotient_degree)
+        })
+        .collect_vec();
+
+    for ((air, _), opened_values, &log_quotient_degree, &permutation_width) in izip!(
+        &instances,
+        opened_values,
+        &log_quotient_degrees,
+        &permutation_widths
+    ) {
+        let valid_shape = opened_values.has_window_shape(
+            0,
+            <A as BaseAir<Val<SC>>>::width(*air),
+            permutation_width,
+            <A as BaseAir<Val<SC>>>::window_size(*air),
+        ) && opened_values.cumulative_sum.is_some()
+            == <A as BaseAir<Val<SC>>>::has_cumulative_sum(*air)
+            && opened_values.random.is_none()
+            && opened_values.quotient_chunks.len() == 1 << log_quotient_degree
+            && opened_values
+                .quotient_chunks
+                .iter()
+                .all(|qc| qc.len() == <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D);
+        if !valid_shape {
+            return Err(VerificationError::InvalidProofShape);
+        }
+    }
+
+    // The running sums of all tables must balance together.
+    let total_cumulative_sum: SC::Challenge = opened_values
+        .iter()
+        .filter_map(|opened_values| opened_values.cumulative_sum)
+        .sum();
+    if !total_cumulative_sum.is_zero() {
+        return Err(VerificationError::UnbalancedCumulativeSums);
+    }
+
+    // Observe the instance.
+    let air_digests = instances
+        .iter()
+        .map(|&(air, public_values)| {
+            get_air_digest::<Val<SC>, SC::Challenge, A>(air, 0, public_values.len(), false)
+        })
+        .collect_vec();
+    observe_air_digests(config, challenger, &air_digests);
+    challenger.observe(Val::<SC>::from_canonical_usize(instances.len()));
+    for &bits in degree_bits {
+        challenger.observe(Val::<SC>::from_canonical_usize(bits));
+    }
+
+    challenger.observe(commitments.trace.clone());
+    for (_, public_values) in &instances {
+        challenger.observe_slice(public_values);
+    }
+
+    let num_permutation_challenges = instances
+        .iter()
+        .map(|&(air, _)| <A as BaseAir<Val<SC>>>::num_permutation_challenges(air))
+        .max()
+        .unwrap_or(0);
+    let permutation_challenges: Vec<SC::Challenge> = (0..num_permutation_challenges)
+        .map(|_| challenger.sample_ext_element())
+        .collect();
+    if let Some(permutation_commit) = &commitments.permutation {
+        challenger.observe(permutation_commit.clone());
+    }
+    for cumulative_sum in opened_values
+        .iter()
+        .filter_map(|opened_values| opened_values.cumulative_sum)
+    {
+        challenger.observe_ext_element(cumulative_sum);
+    }
+
+    let alpha: SC::Challenge = challenger.sample_ext_element();
+    challenger.observe(commitments.quotient_chunks.clone());
+
+    let zeta: SC::Challenge = challenger.sample();
+    let window_points = izip!(&instances, &trace_domains)
+        .map(|(&(air, _), &trace_domain)| {
+            window_points::<SC>(
+                trace_domain,
+                zeta,
+                <A as BaseAir<Val<SC>>>::window_size(air),
+            )
+        })
+        .collect_vec();
+
+    let trace_round = izip!(&trace_domains, &window_points, opened_values)
+        .map(|(&trace_domain, points, opened_values)| {
+            (
+                trace_domain,
+                window_claims(
+                    points,
+                    &opened_values.trace_local,
+                    &opened_values.trace_next,
+                    &opened_values.trace_lookahead,
+                ),
+            )
+        })
+        .collect_vec();
+    let quotient_round = izip!(&quotient_chunks_domains, opened_values)
+        .flat_map(|(domains, opened_values)| {
+            izip!(domains, &opened_values.quotient_chunks)
+                .map(|(&domain, values)| (domain, vec![(zeta, values.clone())]))
+        })
+        .collect_vec();
+
+    let mut rounds = vec![
+        (commitments.trace.clone(), trace_round),
+        (commitments.quotient_chunks.clone(), quotient_round),
+    ];
+    if let Some(permutation_commit) = &commitments.permutation {
+        let permutation_round = izip!(
+            &trace_domains,
+            &window_points,
+            opened_values,
+            &permutation_widths
+        )
+        .filter(|(_, _, _, &permutation_width)| permutation_width > 0)
+        .map(|(&trace_domain, points, opened_values, _)| {
+            (
+                trace_domain,
+                window_claims(
+                    points,
+                    &opened_values.permutation_local,
+                    &opened_values.permutation_next,
+                    &opened_values.permutation_lookahead,
+                ),
+            )
+        })
+        .collect_vec();
+        rounds.push((permutation_commit.clone(), permutation_round));
+    }
+
+    pcs.verify(rounds, opening_proof, challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    for ((air, public_values), opened_values, &trace_domain, quotient_chunks_domains) in izip!(
+        &instances,
+        opened_values,
+        &trace_domains,
+        &quotient_chunks_domains
+    ) {
+        verify_constraints::<SC, A, _>(
+            *air,
+            opened_values,
+            trace_domain,
+            quotient_chunks_domains,
+            &permutation_challenges[..<A as BaseAir<Val<SC>>>::num_permutation_challenges(*air)],
+            zeta,
+            alpha,
+            public_values,
+        )?;
+    }
+
+    Ok(())
+}
@@ -0,0 +1,98 @@
+use alloc::vec;
+
+use p3_air::BaseAir;
+use p3_commit::Pcs;
+use p3_matrix::Matrix;
+use p3_util::log2_strict_usize;
+use serde::{Deserialize, Serialize};
+use tracing::{info_span, instrument};
+
+use crate::{Com, PcsProverData, StarkGenericConfig, Val};
+
+/// Prover-side data for an AIR's preprocessed (fixed) trace.
+///
+/// This is produced once by `setup_preprocessed` and can be reused for every proof of the same
+/// AIR at the same trace height.
+pub struct PreprocessedProverData<SC: StarkGenericConfig> {
+    /// The number of preprocessed columns.
+    pub width: usize,
+    /// The log of the height of the preprocessed trace, which must match the main trace.
+    pub degree_bits: usize,
+    /// The commitment to the preprocessed trace.
+    pub commitment: Com<SC>,
+    /// The PCS data needed to open the preprocessed trace.
+    pub prover_data: PcsProverData<SC>,
+}
+
+/// Verifier-side data for an AIR's preprocessed (fixed) trace.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct PreprocessedVerifierKey<SC: StarkGenericConfig> {
+    /// The number of preprocessed columns.
+    pub width: usize,
+    /// The log of the height of the preprocessed trace, which must match the main trace.
+    pub degree_bits: usize,
+    /// The commitment to the preprocessed trace.
+    pub commitment: Com<SC>,
+}
+
+impl<SC: StarkGenericConfig> Clone for PreprocessedVerifierKey<SC> {
+    fn clone(&self) -> Self {
+        Self {
+            width: self.width,
+            degree_bits: self.degree_bits,
+            commitment: self.commitment.clone(),
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> PreprocessedProverData<SC> {
+    /// Returns the verifier key corresponding to this preprocessed data.
+    pub fn verifier_key(&self) -> PreprocessedVerifierKey<SC> {
+        PreprocessedVerifierKey {
+            width: self.width,
+            degree_bits: self.degree_bits,
+            commitment: self.commitment.clone(),
+        }
+    }
+}
+
+/// Commit to the preprocessed trace of `air`, if it has one.
+///
+/// Returns `None` if the AIR has no preprocessed trace, or if its preprocessed trace is empty.
+///
+/// The preprocessed trace is public, so it is committed to with `Pcs::commit_public`, which doesn't
+/// randomize it. A hiding PCS still salts its commitments though, so with one the verifier must be
+/// given the key produced alongside the prover's data rather than running this itself.
+#[instrument(skip_all)]
+pub fn setup_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+) -> Option<(PreprocessedProverData<SC>, PreprocessedVerifierKey<SC>)>
+where
+    SC: StarkGenericConfig,
+    A: BaseAir<Val<SC>>,
+{
+    let preprocessed = air.preprocessed_trace()?;
+    let width = preprocessed.width();
+    if width == 0 {
+        return None;
+    }
+
+    let degree = preprocessed.height();
+    let degree_bits = log2_strict_usize(degree);
+
+    let pcs = config.pcs();
+    let domain = pcs.natural_domain_for_degree(degree);
+    let (commitment, prover_data) = info_span!("commit to preprocessed trace")
+        .in_scope(|| pcs.commit_public(vec![(domain, preprocessed)]));
+
+    let prover_data = PreprocessedProverData {
+        width,
+        degree_bits,
+        commitment,
+        prover_data,
+    };
+    let verifier_key = prover_data.verifier_key();
+    Some((prover_data, verifier_key))
+}
@@ -1,11 +1,12 @@
 use alloc::vec::Vec;
 
 use p3_commit::Pcs;
+use p3_field::{CanonicalEncoding, DecodingError, Field, FieldExtensionAlgebra};
 use serde::{Deserialize, Serialize};
 
-use crate::StarkGenericConfig;
+use crate::{StarkGenericConfig, Val};
 
-type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
+pub type Com<SC> = <<SC as StarkGenericConfig>::Pcs as Pcs<
     <SC as StarkGenericConfig>::Challenge,
     <SC as StarkGenericConfig>::Challenger,
 >>::Commitment;
@@ -23,15 +24,426 @@ pub struct Proof<SC: StarkGenericConfig> {
     pub(crate) degree_bits: usize,
 }
 
+/// A proof of several AIR instances ("tables") of possibly different heights, which share a
+/// single transcript and a single PCS opening argument.
+#[derive(Serialize, Deserialize)]
+#[serde(bound = "")]
+pub struct MultiProof<SC: StarkGenericConfig> {
+    /// Commitments to all traces, and to all quotient chunks, each in a single batch.
+    pub(crate) commitments: Commitments<Com<SC>>,
+    /// The opened values of each table, in the order the tables were given to the prover.
+    pub(crate) opened_values: Vec<OpenedValues<SC::Challenge>>,
+    pub(crate) opening_proof: PcsProof<SC>,
+    pub(crate) degree_bits: Vec<usize>,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct Commitments<Com> {
     pub(crate) trace: Com,
+    /// The commitment to the permutation trace, if the AIR has one.
+    pub(crate) permutation: Option<Com>,
     pub(crate) quotient_chunks: Com,
+    /// The commitment to the randomizer polynomial, in zero-knowledge mode.
+    pub(crate) random: Option<Com>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct OpenedValues<Challenge> {
+    pub(crate) preprocessed_local: Vec<Challenge>,
+    pub(crate) preprocessed_next: Vec<Challenge>,
+    /// The rows of the preprocessed trace's window after the next row, for AIRs whose window size
+    /// is larger than two.
+    pub(crate) preprocessed_lookahead: Vec<Vec<Challenge>>,
     pub(crate) trace_local: Vec<Challenge>,
     pub(crate) trace_next: Vec<Challenge>,
+    /// The rows of the trace's window after the next row. See `BaseAir::window_size`.
+    pub(crate) trace_lookahead: Vec<Vec<Challenge>>,
+    /// The permutation trace is committed as base field columns, so each of its extension field
+    /// columns is opened as `D` consecutive values.
+    pub(crate) permutation_local: Vec<Challenge>,
+    pub(crate) permutation_next: Vec<Challenge>,
+    pub(crate) permutation_lookahead: Vec<Vec<Challenge>>,
+    /// The claimed final value of the permutation trace's running sum, for AIRs which have one.
+    /// See `BaseAir::has_cumulative_sum`.
+    pub(crate) cumulative_sum: Option<Challenge>,
     pub(crate) quotient_chunks: Vec<Vec<Challenge>>,
+    /// The opened randomizer polynomial, in zero-knowledge mode. Like the permutation trace, it is
+    /// committed as `D` base field columns.
+    pub(crate) random: Option<Vec<Challenge>>,
+}
+
+impl<SC: StarkGenericConfig> Proof<SC> {
+    pub const fn commitments(&self) -> &Commitments<Com<SC>> {
+        &self.commitments
+    }
+
+    pub const fn opened_values(&self) -> &OpenedValues<SC::Challenge> {
+        &self.opened_values
+    }
+
+    pub const fn opening_proof(&self) -> &PcsProof<SC> {
+        &self.opening_proof
+    }
+
+    /// The log of the height of the trace.
+    pub const fn degree_bits(&self) -> usize {
+        self.degree_bits
+    }
+}
+
+impl<SC: StarkGenericConfig> MultiProof<SC> {
+    pub const fn commitments(&self) -> &Commitments<Com<SC>> {
+        &self.commitments
+    }
+
+    /// The opened values of each table, in the order the tables were given to the prover.
+    pub fn opened_values(&self) -> &[OpenedValues<SC::Challenge>] {
+        &self.opened_values
+    }
+
+    pub const fn opening_proof(&self) -> &PcsProof<SC> {
+        &self.opening_proof
+    }
+
+    /// The log of the height of each table's trace.
+    pub fn degree_bits(&self) -> &[usize] {
+        &self.degree_bits
+    }
+}
+
+impl<Com> Commitments<Com> {
+    pub const fn trace(&self) -> &Com {
+        &self.trace
+    }
+
+    pub const fn permutation(&self) -> Option<&Com> {
+        self.permutation.as_ref()
+    }
+
+    pub const fn quotient_chunks(&self) -> &Com {
+        &self.quotient_chunks
+    }
+
+    pub const fn random(&self) -> Option<&Com> {
+        self.random.as_ref()
+    }
+}
+
+impl<Challenge> OpenedValues<Challenge> {
+    pub fn preprocessed_local(&self) -> &[Challenge] {
+        &self.preprocessed_local
+    }
+
+    pub fn preprocessed_next(&self) -> &[Challenge] {
+        &self.preprocessed_next
+    }
+
+    pub fn preprocessed_lookahead(&self) -> &[Vec<Challenge>] {
+        &self.preprocessed_lookahead
+    }
+
+    pub fn trace_local(&self) -> &[Challenge] {
+        &self.trace_local
+    }
+
+    pub fn trace_next(&self) -> &[Challenge] {
+        &self.trace_next
+    }
+
+    pub fn trace_lookahead(&self) -> &[Vec<Challenge>] {
+        &self.trace_lookahead
+    }
+
+    pub fn permutation_local(&self) -> &[Challenge] {
+        &self.permutation_local
+    }
+
+    pub fn permutation_next(&self) -> &[Challenge] {
+        &self.permutation_next
+    }
+
+    pub fn permutation_lookahead(&self) -> &[Vec<Challenge>] {
+        &self.permutation_lookahead
+    }
+
+    pub const fn cumulative_sum(&self) -> Option<&Challenge> {
+        self.cumulative_sum.as_ref()
+    }
+
+    pub fn quotient_chunks(&self) -> &[Vec<Challenge>] {
+        &self.quotient_chunks
+    }
+
+    pub fn random(&self) -> Option<&[Challenge]> {
+        self.random.as_deref()
+    }
+
+    /// Whether the opened windows of the preprocessed, main and permutation traces have the given
+    /// widths, with `window_size` rows each. Permutation widths are counted in base field columns.
+    pub(crate) fn has_window_shape(
+        &self,
+        preprocessed_width: usize,
+        width: usize,
+        permutation_width: usize,
+        window_size: usize,
+    ) -> bool {
+        let is_window =
+            |local: &[Challenge], next: &[Challenge], lookahead: &[Vec<Challenge>], width| {
+                local.len() == width
+                    && next.len() == width
+                    && lookahead.len() == window_size - 2
+                    && lookahead.iter().all(|row| row.len() == width)
+            };
+        is_window(
+            &self.preprocessed_local,
+            &self.preprocessed_next,
+            &self.preprocessed_lookahead,
+            preprocessed_width,
+        ) && is_window(
+            &self.trace_local,
+            &self.trace_next,
+            &self.trace_lookahead,
+            width,
+        ) && is_window(
+            &self.permutation_local,
+            &self.permutation_next,
+            &self.permutation_lookahead,
+            permutation_width,
+        )
+    }
+}
+
+/// The version of the binary proof format written by `Proof::to_versioned_bytes` and
+/// `MultiProof::to_versioned_bytes`. It changes whenever the encoding of any proof changes.
+pub const PROOF_FORMAT_VERSION: u32 = 3;
+
+/// An error in decoding a proof with `Proof::from_versioned_bytes` or
+/// `MultiProof::from_versioned_bytes`.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum ProofDecodingError {
+    /// The proof was written in a format version this library can't read.
+    UnsupportedVersion(u32),
+    /// The proof is over a different field, or a different extension of it.
+    FieldMismatch,
+    /// The proof was made with different PCS parameters, zero-knowledge mode or domain separator.
+    ConfigMismatch,
+    /// The proof's body isn't a canonical encoding.
+    Format(DecodingError),
+}
+
+impl From<DecodingError> for ProofDecodingError {
+    fn from(error: DecodingError) -> Self {
+        Self::Format(error)
+    }
+}
+
+/// Identifies the base field, by its order, and the degree of the challenge field over it.
+fn field_id<SC: StarkGenericConfig>() -> (Vec<u8>, usize) {
+    (
+        Val::<SC>::order().to_bytes_le(),
+        <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D,
+    )
+}
+
+/// Identifies the parameters of `config` which affect proofs.
+fn config_fingerprint<SC: StarkGenericConfig>(config: &SC) -> Vec<u8> {
+    (
+        config.pcs().fingerprint(),
+        (config.is_zk(), config.domain_separator().to_vec()),
+    )
+        .to_bytes()
+}
+
+/// Encodes `body` after a header with the format version, the field id and the fingerprint of
+/// `config`.
+fn encode_versioned<SC: StarkGenericConfig, T: CanonicalEncoding>(
+    config: &SC,
+    body: &T,
+) -> Vec<u8> {
+    let mut out = Vec::new();
+    PROOF_FORMAT_VERSION.encode(&mut out);
+    field_id::<SC>().encode(&mut out);
+    config_fingerprint(config).encode(&mut out);
+    body.encode(&mut out);
+    out
+}
+
+/// Checks the header written by `encode_versioned` against `config`, then decodes the body.
+fn decode_versioned<SC: StarkGenericConfig, T: CanonicalEncoding>(
+    config: &SC,
+    mut input: &[u8],
+) -> Result<T, ProofDecodingError> {
+    let version = u32::decode(&mut input)?;
+    if version != PROOF_FORMAT_VERSION {
+        return Err(ProofDecodingError::UnsupportedVersion(version));
+    }
+    if <(Vec<u8>, usize)>::decode(&mut input)? != field_id::<SC>() {
+        return Err(ProofDecodingError::FieldMismatch);
+    }
+    if Vec::<u8>::decode(&mut input)? != config_fingerprint(config) {
+        return Err(ProofDecodingError::ConfigMismatch);
+    }
+    Ok(T::from_bytes(input)?)
+}
+
+impl<SC: StarkGenericConfig> Proof<SC>
+where
+    Self: CanonicalEncoding,
+{
+    /// Encodes this proof in the versioned binary format, in which a header identifying the format
+    /// version, the field and the configuration precedes the canonical encoding of the proof. See
+    /// `CanonicalEncoding` for the details of the encoding.
+    pub fn to_versioned_bytes(&self, config: &SC) -> Vec<u8> {
+        encode_versioned(config, self)
+    }
+
+    /// Decodes a proof written by `to_versioned_bytes` with an equivalent `config`. Anything else,
+    /// including a non-canonical field element or trailing bytes, is rejected.
+    pub fn from_versioned_bytes(config: &SC, bytes: &[u8]) -> Result<Self, ProofDecodingError> {
+        decode_versioned(config, bytes)
+    }
+}
+
+impl<SC: StarkGenericConfig> MultiProof<SC>
+where
+    Self: CanonicalEncoding,
+{
+    /// Like `Proof::to_versioned_bytes`.
+    pub fn to_versioned_bytes(&self, config: &SC) -> Vec<u8> {
+        encode_versioned(config, self)
+    }
+
+    /// Like `Proof::from_versioned_bytes`.
+    pub fn from_versioned_bytes(config: &SC, bytes: &[u8]) -> Result<Self, ProofDecodingError> {
+        decode_versioned(config, bytes)
+    }
+}
+
+/// A breakdown of the size in bytes of a proof in its canonical encoding. See `Proof::size_report`.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct ProofSizeReport {
+    pub commitments: usize,
+    pub opened_values: usize,
+    /// The PCS opening argument. For FRI based PCSs, `FriProof::size_report` breaks this down
+    /// further, and counts the hashing done by the verifier.
+    pub opening_proof: usize,
+    pub degree_bits: usize,
+    /// The size of the whole proof, without the header added by `to_versioned_bytes`.
+    pub total: usize,
+}
+
+impl<SC: StarkGenericConfig> Proof<SC>
+where
+    Com<SC>: CanonicalEncoding,
+    PcsProof<SC>: CanonicalEncoding,
+    SC::Challenge: CanonicalEncoding,
+{
+    /// Breaks down the size of this proof in its canonical encoding by component.
+    pub fn size_report(&self) -> ProofSizeReport {
+        ProofSizeReport {
+            commitments: self.commitments.to_bytes().len(),
+            opened_values: self.opened_values.to_bytes().len(),
+            opening_proof: self.opening_proof.to_bytes().len(),
+            degree_bits: self.degree_bits.to_bytes().len(),
+            total: self.to_bytes().len(),
+        }
+    }
+}
+
+impl<SC: StarkGenericConfig> CanonicalEncoding for Proof<SC>
+where
+    Com<SC>: CanonicalEncoding,
+    PcsProof<SC>: CanonicalEncoding,
+    SC::Challenge: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.commitments.encode(out);
+        self.opened_values.encode(out);
+        self.opening_proof.encode(out);
+        self.degree_bits.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            commitments: CanonicalEncoding::decode(input)?,
+            opened_values: CanonicalEncoding::decode(input)?,
+            opening_proof: CanonicalEncoding::decode(input)?,
+            degree_bits: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<SC: StarkGenericConfig> CanonicalEncoding for MultiProof<SC>
+where
+    Com<SC>: CanonicalEncoding,
+    PcsProof<SC>: CanonicalEncoding,
+    SC::Challenge: CanonicalEncoding,
+{
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.commitments.encode(out);
+        self.opened_values.encode(out);
+        self.opening_proof.encode(out);
+        self.degree_bits.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            commitments: CanonicalEncoding::decode(input)?,
+            opened_values: CanonicalEncoding::decode(input)?,
+            opening_proof: CanonicalEncoding::decode(input)?,
+            degree_bits: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<Com: CanonicalEncoding> CanonicalEncoding for Commitments<Com> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.trace.encode(out);
+        self.permutation.encode(out);
+        self.quotient_chunks.encode(out);
+        self.random.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            trace: CanonicalEncoding::decode(input)?,
+            permutation: CanonicalEncoding::decode(input)?,
+            quotient_chunks: CanonicalEncoding::decode(input)?,
+            random: CanonicalEncoding::decode(input)?,
+        })
+    }
+}
+
+impl<Challenge: Field + CanonicalEncoding> CanonicalEncoding for OpenedValues<Challenge> {
+    fn encode(&self, out: &mut Vec<u8>) {
+        self.preprocessed_local.encode(out);
+        self.preprocessed_next.encode(out);
+        self.preprocessed_lookahead.encode(out);
+        self.trace_local.encode(out);
+        self.trace_next.encode(out);
+        self.trace_lookahead.encode(out);
+        self.permutation_local.encode(out);
+        self.permutation_next.encode(out);
+        self.permutation_lookahead.encode(out);
+        self.cumulative_sum.encode(out);
+        self.quotient_chunks.encode(out);
+        self.random.encode(out);
+    }
+
+    fn decode(input: &mut &[u8]) -> Result<Self, DecodingError> {
+        Ok(Self {
+            preprocessed_local: CanonicalEncoding::decode(input)?,
+            preprocessed_next: CanonicalEncoding::decode(input)?,
+            preprocessed_lookahead: CanonicalEncoding::decode(input)?,
+            trace_local: CanonicalEncoding::decode(input)?,
+            trace_next: CanonicalEncoding::decode(input)?,
+            trace_lookahead: CanonicalEncoding::decode(input)?,
+            permutation_local: CanonicalEncoding::decode(input)?,
+            permutation_next: CanonicalEncoding::decode(input)?,
+            permutation_lookahead: CanonicalEncoding::decode(input)?,
+            cumulative_sum: CanonicalEncoding::decode(input)?,
+            quotient_chunks: CanonicalEncoding::decode(input)?,
+            random: CanonicalEncoding::decode(input)?,
+        })
+    }
 }
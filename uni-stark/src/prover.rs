@@ -2,26 +2,33 @@ use alloc::vec;
 use alloc::vec::Vec;
 
 use itertools::{izip, Itertools};
-use p3_air::Air;
+use p3_air::{Air, BaseAir, Padding};
 use p3_challenger::{CanObserve, CanSample, FieldChallenger};
-use p3_commit::{Pcs, PolynomialSpace};
-use p3_field::{FieldAlgebra, FieldExtensionAlgebra, PackedValue};
+use p3_commit::{Pcs, PeriodicPolynomialSpace, PolynomialSpace};
+use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra, PackedValue};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 use p3_maybe_rayon::prelude::*;
-use p3_util::{log2_ceil_usize, log2_strict_usize};
+use p3_util::log2_strict_usize;
 use tracing::{info_span, instrument};
 
+use crate::constraint_tape::{ConstraintInputs, ConstraintTape};
+use crate::instance::observe_air_digests;
+use crate::symbolic_builder::{
+    get_ordered_symbolic_constraints, log_quotient_degree, SymbolicConstraint,
+};
+use crate::verifier::window_points;
 use crate::{
-    get_symbolic_constraints, Commitments, Domain, OpenedValues, PackedChallenge, PackedVal, Proof,
-    ProverConstraintFolder, StarkGenericConfig, SymbolicAirBuilder, SymbolicExpression, Val,
+    get_air_digest, setup_preprocessed, AirDigest, Commitments, Domain, OpenedValues,
+    PackedChallenge, PackedVal, PreprocessedProverData, Proof, StarkGenericConfig,
+    SymbolicAirBuilder, Val,
 };
 
 #[instrument(skip_all)]
 #[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
 pub fn prove<
     SC,
-    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>>>,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
     #[cfg(not(debug_assertions))] A,
 >(
     config: &SC,
@@ -32,88 +39,388 @@ pub fn prove<
 ) -> Proof<SC>
 where
     SC: StarkGenericConfig,
-    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<ProverConstraintFolder<'a, SC>>,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>,
 {
-    #[cfg(debug_assertions)]
-    crate::check_constraints::check_constraints(air, &trace, public_values);
+    // Commit to the preprocessed trace and compute the AIR digest on the fly. Callers proving many
+    // statements about the same AIR should call `setup_preprocessed` and `get_air_digest` once and
+    // use `prove_with_preprocessed` instead.
+    let preprocessed = setup_preprocessed(config, air);
+    let air_digest = get_air_digest::<Val<SC>, SC::Challenge, A>(
+        air,
+        preprocessed
+            .as_ref()
+            .map_or(0, |(prover_data, _)| prover_data.width),
+        public_values.len(),
+        config.is_zk(),
+    );
+    prove_with_preprocessed(
+        config,
+        air,
+        challenger,
+        trace,
+        public_values,
+        preprocessed.as_ref().map(|(prover_data, _)| prover_data),
+        &air_digest,
+    )
+}
 
+/// An error in the statement given to `try_prove` or `try_prove_multi`.
+#[derive(Debug, PartialEq, Eq)]
+pub enum ProverError {
+    /// The trace has no rows.
+    EmptyTrace,
+    /// The trace's width doesn't match the AIR's.
+    WrongTraceWidth { expected: usize, actual: usize },
+    /// The trace's height isn't a power of two, and the AIR's `Padding` is `Padding::None`.
+    HeightNotPowerOfTwo(usize),
+    /// The AIR's `Padding::Row` doesn't have the AIR's width.
+    WrongPaddingRowWidth { expected: usize, actual: usize },
+    /// The AIR's `BaseAir::real_row_selector` isn't one of its columns.
+    RealRowSelectorOutOfBounds { column: usize, width: usize },
+    /// The AIR's preprocessed trace doesn't have the same height as the padded trace.
+    PreprocessedHeightMismatch { expected: usize, actual: usize },
+    /// The AIR has a preprocessed trace, which `try_prove_multi` doesn't support.
+    PreprocessedTraceUnsupported,
+}
+
+/// Pads `trace` to the next power of two height according to `BaseAir::padding`, and fills in the
+/// AIR's `BaseAir::real_row_selector` column, if any.
+pub fn pad_trace<F: Field, A: BaseAir<F>>(
+    air: &A,
+    mut trace: RowMajorMatrix<F>,
+) -> Result<RowMajorMatrix<F>, ProverError> {
+    let width = air.width();
+    if trace.width() != width {
+        return Err(ProverError::WrongTraceWidth {
+            expected: width,
+            actual: trace.width(),
+        });
+    }
+    let height = trace.height();
+    if height == 0 {
+        return Err(ProverError::EmptyTrace);
+    }
+    let real_row_selector = air.real_row_selector();
+    if let Some(column) = real_row_selector.filter(|&column| column >= width) {
+        return Err(ProverError::RealRowSelectorOutOfBounds { column, width });
+    }
+
+    let num_padding_rows = height.next_power_of_two() - height;
+    if num_padding_rows > 0 {
+        let padding_row = match air.padding() {
+            Padding::None => return Err(ProverError::HeightNotPowerOfTwo(height)),
+            Padding::Zeros => F::zero_vec(width),
+            Padding::RepeatLastRow => trace.row_slice(height - 1).to_vec(),
+            Padding::Row(row) if row.len() == width => row,
+            Padding::Row(row) => {
+                return Err(ProverError::WrongPaddingRowWidth {
+                    expected: width,
+                    actual: row.len(),
+                })
+            }
+        };
+        trace.values.reserve(num_padding_rows * width);
+        for _ in 0..num_padding_rows {
+            trace.values.extend_from_slice(&padding_row);
+        }
+    }
+
+    if let Some(column) = real_row_selector {
+        for (i, row) in trace.rows_mut().enumerate() {
+            row[column] = F::from_bool(i < height);
+        }
+    }
+    Ok(trace)
+}
+
+/// Like `prove`, but accepts traces of any nonzero height, padding them with `pad_trace`, and
+/// returns an error rather than panicking if the trace doesn't fit the AIR.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn try_prove<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+) -> Result<Proof<SC>, ProverError>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>,
+{
+    let trace = pad_trace(air, trace)?;
+    let preprocessed = setup_preprocessed(config, air);
+    if let Some((prover_data, _)) = &preprocessed {
+        if 1 << prover_data.degree_bits != trace.height() {
+            return Err(ProverError::PreprocessedHeightMismatch {
+                expected: trace.height(),
+                actual: 1 << prover_data.degree_bits,
+            });
+        }
+    }
+    let air_digest = get_air_digest::<Val<SC>, SC::Challenge, A>(
+        air,
+        preprocessed
+            .as_ref()
+            .map_or(0, |(prover_data, _)| prover_data.width),
+        public_values.len(),
+        config.is_zk(),
+    );
+    Ok(prove_with_preprocessed(
+        config,
+        air,
+        challenger,
+        trace,
+        public_values,
+        preprocessed.as_ref().map(|(prover_data, _)| prover_data),
+        &air_digest,
+    ))
+}
+
+/// Like `prove`, but with the AIR's preprocessed data and digest computed ahead of time, by
+/// `setup_preprocessed` and `get_air_digest`, so that they can be reused across proofs.
+#[instrument(skip_all)]
+#[allow(clippy::multiple_bound_locations)] // cfg not supported in where clauses?
+pub fn prove_with_preprocessed<
+    SC,
+    #[cfg(debug_assertions)] A: for<'a> Air<crate::check_constraints::DebugConstraintBuilder<'a, Val<SC>, SC::Challenge>>,
+    #[cfg(not(debug_assertions))] A,
+>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    trace: RowMajorMatrix<Val<SC>>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedProverData<SC>>,
+    air_digest: &AirDigest,
+) -> Proof<SC>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>,
+{
     let degree = trace.height();
     let log_degree = log2_strict_usize(degree);
 
-    let symbolic_constraints = get_symbolic_constraints::<Val<SC>, A>(air, 0, public_values.len());
-    let constraint_count = symbolic_constraints.len();
-    let constraint_degree = symbolic_constraints
+    let preprocessed_width = preprocessed.map_or(0, |preprocessed| preprocessed.width);
+    if let Some(preprocessed) = preprocessed {
+        assert_eq!(
+            preprocessed.degree_bits, log_degree,
+            "preprocessed trace height must match the main trace height"
+        );
+    }
+    let permutation_width = <A as BaseAir<Val<SC>>>::permutation_width(air);
+    let window_size = <A as BaseAir<Val<SC>>>::window_size(air);
+    let periodic_columns = <A as BaseAir<Val<SC>>>::periodic_columns(air);
+    assert!(
+        periodic_columns
+            .iter()
+            .all(|column| column.len().is_power_of_two() && column.len() <= degree),
+        "periodic columns must have power of two periods no larger than the trace height"
+    );
+
+    let constraints = get_ordered_symbolic_constraints::<Val<SC>, SC::Challenge, A>(
+        air,
+        preprocessed_width,
+        public_values.len(),
+    );
+    let constraint_degree = constraints
         .iter()
-        .map(SymbolicExpression::degree_multiple)
+        .map(SymbolicConstraint::degree_multiple)
         .max()
         .unwrap_or(0);
-    let log_quotient_degree = log2_ceil_usize(constraint_degree - 1);
+    let constraint_tape = info_span!("compile constraints").in_scope(|| {
+        ConstraintTape::new(
+            &constraints,
+            preprocessed_width,
+            <A as BaseAir<Val<SC>>>::width(air),
+            permutation_width,
+        )
+    });
+    let is_zk = config.is_zk();
+    let log_quotient_degree = log_quotient_degree(constraint_degree, is_zk);
     let quotient_degree = 1 << log_quotient_degree;
 
     let pcs = config.pcs();
     let trace_domain = pcs.natural_domain_for_degree(degree);
+    // In zero-knowledge mode, committed traces are randomized to twice their degree.
+    let ext_trace_domain = pcs.natural_domain_for_degree(degree << is_zk as usize);
 
-    let (trace_commit, trace_data) =
-        info_span!("commit to trace data").in_scope(|| pcs.commit(vec![(trace_domain, trace)]));
+    // The permutation trace is generated from the main trace after the latter has been committed,
+    // so we hold on to a copy of it. Debug builds also need it to check the constraints.
+    let main = (permutation_width > 0 || cfg!(debug_assertions)).then(|| trace.clone());
+
+    let (trace_commit, trace_data) = info_span!("commit to trace data")
+        .in_scope(|| pcs.commit_randomized(vec![(trace_domain, trace)]));
 
     // Observe the instance.
+    observe_air_digests(config, challenger, &[*air_digest]);
     challenger.observe(Val::<SC>::from_canonical_usize(log_degree));
-    // TODO: Might be best practice to include other instance data here; see verifier comment.
+    if let Some(preprocessed) = preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
 
     challenger.observe(trace_commit.clone());
     challenger.observe_slice(public_values);
+
+    let num_permutation_challenges = <A as BaseAir<Val<SC>>>::num_permutation_challenges(air);
+    let permutation_challenges: Vec<SC::Challenge> = (0..num_permutation_challenges)
+        .map(|_| challenger.sample_ext_element())
+        .collect();
+    let permutation = (permutation_width > 0).then(|| {
+        let permutation = info_span!("generate permutation trace").in_scope(|| {
+            <A as BaseAir<Val<SC>>>::permutation_trace(
+                air,
+                main.as_ref().unwrap(),
+                &permutation_challenges,
+            )
+            .expect("AIR has a nonzero permutation width but no permutation trace")
+        });
+        assert_eq!(permutation.width(), permutation_width);
+        assert_eq!(permutation.height(), degree);
+        permutation
+    });
+    let cumulative_sum = <A as BaseAir<Val<SC>>>::has_cumulative_sum(air).then(|| {
+        let permutation = permutation
+            .as_ref()
+            .expect("an AIR with a cumulative sum must have a permutation trace");
+        *permutation.values.last().unwrap()
+    });
+
+    #[cfg(debug_assertions)]
+    crate::check_constraints::check_constraints(
+        air,
+        main.as_ref().unwrap(),
+        permutation.as_ref(),
+        &permutation_challenges,
+        cumulative_sum.unwrap_or_default(),
+        public_values,
+    );
+
+    let permutation_commit_and_data = permutation.map(|permutation| {
+        info_span!("commit to permutation trace data")
+            .in_scope(|| pcs.commit_randomized(vec![(trace_domain, permutation.flatten_to_base())]))
+    });
+    if let Some((permutation_commit, _)) = &permutation_commit_and_data {
+        challenger.observe(permutation_commit.clone());
+    }
+    if let Some(cumulative_sum) = cumulative_sum {
+        challenger.observe_ext_element(cumulative_sum);
+    }
+
     let alpha: SC::Challenge = challenger.sample_ext_element();
 
     let quotient_domain =
         trace_domain.create_disjoint_domain(1 << (log_degree + log_quotient_degree));
 
     let trace_on_quotient_domain = pcs.get_evaluations_on_domain(&trace_data, 0, quotient_domain);
+    let preprocessed_on_quotient_domain = preprocessed.map(|preprocessed| {
+        pcs.get_evaluations_on_domain(&preprocessed.prover_data, 0, quotient_domain)
+    });
+    let permutation_on_quotient_domain =
+        permutation_commit_and_data
+            .as_ref()
+            .map(|(_, permutation_data)| {
+                pcs.get_evaluations_on_domain(permutation_data, 0, quotient_domain)
+            });
 
     let quotient_values = quotient_values(
-        air,
+        &constraint_tape,
+        window_size,
+        &periodic_columns,
         public_values,
         trace_domain,
         quotient_domain,
+        preprocessed_on_quotient_domain,
         trace_on_quotient_domain,
+        permutation_on_quotient_domain,
+        &permutation_challenges,
+        cumulative_sum.unwrap_or_default(),
         alpha,
-        constraint_count,
     );
     let quotient_flat = RowMajorMatrix::new_col(quotient_values).flatten_to_base();
     let quotient_chunks = quotient_domain.split_evals(quotient_degree, quotient_flat);
     let qc_domains = quotient_domain.split_domains(quotient_degree);
 
     let (quotient_commit, quotient_data) = info_span!("commit to quotient poly chunks")
-        .in_scope(|| pcs.commit(izip!(qc_domains, quotient_chunks).collect_vec()));
+        .in_scope(|| pcs.commit_quotient(izip!(qc_domains, quotient_chunks).collect_vec()));
     challenger.observe(quotient_commit.clone());
 
+    // In zero-knowledge mode, a random polynomial is also committed and opened, to mask the other
+    // polynomials in the opening argument.
+    let (random_commit, random_data) = pcs.commit_randomizer(ext_trace_domain).unzip();
+    if let Some(random_commit) = &random_commit {
+        challenger.observe(random_commit.clone());
+    }
+
+    let (permutation_commit, permutation_data) = permutation_commit_and_data.unzip();
     let commitments = Commitments {
         trace: trace_commit,
+        permutation: permutation_commit,
         quotient_chunks: quotient_commit,
+        random: random_commit,
     };
 
     let zeta: SC::Challenge = challenger.sample();
-    let zeta_next = trace_domain.next_point(zeta).unwrap();
-
-    let (opened_values, opening_proof) = info_span!("open").in_scope(|| {
-        pcs.open(
-            vec![
-                (&trace_data, vec![vec![zeta, zeta_next]]),
-                (
-                    &quotient_data,
-                    // open every chunk at zeta
-                    (0..quotient_degree).map(|_| vec![zeta]).collect_vec(),
-                ),
-            ],
-            challenger,
-        )
-    });
-    let trace_local = opened_values[0][0][0].clone();
-    let trace_next = opened_values[0][0][1].clone();
-    let quotient_chunks = opened_values[1].iter().map(|v| v[0].clone()).collect_vec();
+    let window_points = window_points::<SC>(trace_domain, zeta, window_size);
+
+    let mut rounds = vec![
+        (&trace_data, vec![window_points.clone()]),
+        (
+            &quotient_data,
+            // open every chunk at zeta
+            (0..quotient_degree).map(|_| vec![zeta]).collect_vec(),
+        ),
+    ];
+    if let Some(preprocessed) = preprocessed {
+        rounds.push((&preprocessed.prover_data, vec![window_points.clone()]));
+    }
+    if let Some(permutation_data) = &permutation_data {
+        rounds.push((permutation_data, vec![window_points.clone()]));
+    }
+    if let Some(random_data) = &random_data {
+        rounds.push((random_data, vec![vec![zeta]]));
+    }
+
+    let (opened_values, opening_proof) =
+        info_span!("open").in_scope(|| pcs.open(rounds, challenger));
+    let mut opened_values = opened_values.into_iter();
+    let (trace_local, trace_next, trace_lookahead) =
+        split_window(opened_values.next().unwrap().remove(0));
+    let quotient_chunks = opened_values
+        .next()
+        .unwrap()
+        .into_iter()
+        .map(|mut v| v.remove(0))
+        .collect_vec();
+    let mut next_round_window = |present: bool| {
+        if present {
+            split_window(opened_values.next().unwrap().remove(0))
+        } else {
+            (vec![], vec![], vec![vec![]; window_size - 2])
+        }
+    };
+    let (preprocessed_local, preprocessed_next, preprocessed_lookahead) =
+        next_round_window(preprocessed.is_some());
+    let (permutation_local, permutation_next, permutation_lookahead) =
+        next_round_window(permutation_data.is_some());
+    let random = random_data.map(|_| opened_values.next().unwrap().remove(0).remove(0));
     let opened_values = OpenedValues {
+        preprocessed_local,
+        preprocessed_next,
+        preprocessed_lookahead,
         trace_local,
         trace_next,
+        trace_lookahead,
+        permutation_local,
+        permutation_next,
+        permutation_lookahead,
+        cumulative_sum,
         quotient_chunks,
+        random,
     };
     Proof {
         commitments,
@@ -123,28 +430,57 @@ where
     }
 }
 
+/// Splits the openings of a matrix at the points of a window into its local row, its next row and
+/// any further rows.
+pub(crate) fn split_window<T>(openings: Vec<Vec<T>>) -> (Vec<T>, Vec<T>, Vec<Vec<T>>) {
+    let mut openings = openings.into_iter();
+    let local = openings.next().unwrap();
+    let next = openings.next().unwrap();
+    (local, next, openings.collect())
+}
+
 #[instrument(name = "compute quotient polynomial", skip_all)]
-fn quotient_values<SC, A, Mat>(
-    air: &A,
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn quotient_values<SC, Mat>(
+    constraint_tape: &ConstraintTape<Val<SC>, SC::Challenge>,
+    window_size: usize,
+    periodic_columns: &[Vec<Val<SC>>],
     public_values: &Vec<Val<SC>>,
     trace_domain: Domain<SC>,
     quotient_domain: Domain<SC>,
+    preprocessed_on_quotient_domain: Option<Mat>,
     trace_on_quotient_domain: Mat,
+    permutation_on_quotient_domain: Option<Mat>,
+    permutation_challenges: &[SC::Challenge],
+    cumulative_sum: SC::Challenge,
     alpha: SC::Challenge,
-    constraint_count: usize,
 ) -> Vec<SC::Challenge>
 where
     SC: StarkGenericConfig,
-    A: for<'a> Air<ProverConstraintFolder<'a, SC>>,
     Mat: Matrix<Val<SC>> + Sync,
 {
     let quotient_size = quotient_domain.size();
-    let width = trace_on_quotient_domain.width();
     let mut sels = trace_domain.selectors_on_coset(quotient_domain);
 
     let qdb = log2_strict_usize(quotient_domain.size()) - log2_strict_usize(trace_domain.size());
     let next_step = 1 << qdb;
 
+    // The selector of a window of `k + 1` rows is that of a window of `k` rows times the transition
+    // selector shifted by `k - 1` rows.
+    let mut is_transition_windows: Vec<Vec<Val<SC>>> = Vec::new();
+    for k in 2..window_size {
+        let previous = is_transition_windows.last().unwrap_or(&sels.is_transition);
+        let window = (0..quotient_size)
+            .map(|i| previous[i] * sels.is_transition[(i + (k - 1) * next_step) % quotient_size])
+            .collect();
+        is_transition_windows.push(window);
+    }
+
+    let mut periodic_on_quotient_domain = periodic_columns
+        .iter()
+        .map(|column| trace_domain.periodic_column_on_coset(column, quotient_domain))
+        .collect_vec();
+
     // We take PackedVal::<SC>::WIDTH worth of values at a time from a quotient_size slice, so we need to
     // pad with default values in the case where quotient_size is smaller than PackedVal::<SC>::WIDTH.
     for _ in quotient_size..PackedVal::<SC>::WIDTH {
@@ -152,11 +488,26 @@ where
         sels.is_last_row.push(Val::<SC>::default());
         sels.is_transition.push(Val::<SC>::default());
         sels.inv_zeroifier.push(Val::<SC>::default());
+        for window in &mut is_transition_windows {
+            window.push(Val::<SC>::default());
+        }
+        for column in &mut periodic_on_quotient_domain {
+            column.push(Val::<SC>::default());
+        }
     }
 
-    let mut alpha_powers = alpha.powers().take(constraint_count).collect_vec();
+    let mut alpha_powers = alpha
+        .powers()
+        .take(constraint_tape.num_constraints())
+        .collect_vec();
     alpha_powers.reverse();
 
+    let permutation_challenges = permutation_challenges
+        .iter()
+        .map(|&challenge| PackedChallenge::<SC>::from_f(challenge))
+        .collect_vec();
+    let cumulative_sum = PackedChallenge::<SC>::from_f(cumulative_sum);
+
     (0..quotient_size)
         .into_par_iter()
         .step_by(PackedVal::<SC>::WIDTH)
@@ -167,27 +518,60 @@ where
             let is_last_row = *PackedVal::<SC>::from_slice(&sels.is_last_row[i_range.clone()]);
             let is_transition = *PackedVal::<SC>::from_slice(&sels.is_transition[i_range.clone()]);
             let inv_zeroifier = *PackedVal::<SC>::from_slice(&sels.inv_zeroifier[i_range.clone()]);
+            let is_transition_windows = is_transition_windows
+                .iter()
+                .map(|window| *PackedVal::<SC>::from_slice(&window[i_range.clone()]))
+                .collect_vec();
+            let periodic = periodic_on_quotient_domain
+                .iter()
+                .map(|column| *PackedVal::<SC>::from_slice(&column[i_range.clone()]))
+                .collect_vec();
 
-            let main = RowMajorMatrix::new(
-                trace_on_quotient_domain.vertically_packed_row_pair(i_start, next_step),
-                width,
+            let preprocessed = preprocessed_on_quotient_domain
+                .as_ref()
+                .map_or_else(Vec::new, |preprocessed| {
+                    preprocessed.vertically_packed_row_window(i_start, next_step, window_size)
+                });
+            let main = trace_on_quotient_domain.vertically_packed_row_window(
+                i_start,
+                next_step,
+                window_size,
             );
+            // The permutation trace was committed as base field columns; reassemble each group of
+            // `D` of them into an extension field column.
+            let permutation = permutation_on_quotient_domain
+                .as_ref()
+                .map_or_else(Vec::new, |permutation| {
+                    permutation
+                        .vertically_packed_row_window::<PackedVal<SC>>(
+                            i_start,
+                            next_step,
+                            window_size,
+                        )
+                        .chunks_exact(<SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D)
+                        .map(
+                            <PackedChallenge<SC> as FieldExtensionAlgebra<PackedVal<SC>>>::from_base_slice,
+                        )
+                        .collect()
+                });
 
-            let accumulator = PackedChallenge::<SC>::ZERO;
-            let mut folder = ProverConstraintFolder {
-                main: main.as_view(),
+            let inputs = ConstraintInputs {
+                preprocessed: &preprocessed,
+                main: &main,
+                periodic: &periodic,
+                permutation: &permutation,
                 public_values,
+                permutation_challenges: &permutation_challenges,
+                cumulative_sum,
                 is_first_row,
                 is_last_row,
                 is_transition,
-                alpha_powers: &alpha_powers,
-                accumulator,
-                constraint_index: 0,
+                is_transition_windows: &is_transition_windows,
             };
-            air.eval(&mut folder);
+            let constraints = constraint_tape.eval(&inputs, &alpha_powers);
 
             // quotient(x) = constraints(x) / Z_H(x)
-            let quotient = folder.accumulator * inv_zeroifier;
+            let quotient = constraints * inv_zeroifier;
 
             // "Transpose" D packed base coefficients into WIDTH scalar extension coefficients.
             (0..core::cmp::min(quotient_size, PackedVal::<SC>::WIDTH)).map(move |idx_in_packing| {
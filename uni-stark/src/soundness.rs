@@ -0,0 +1,110 @@
+use p3_air::{Air, BaseAir};
+use p3_field::{log2_field_order, ExtensionField};
+use p3_util::log2_f64;
+
+use crate::{
+    get_all_symbolic_constraints, log_quotient_degree, StarkGenericConfig, SymbolicAirBuilder, Val,
+};
+
+/// Proven round-by-round soundness, in bits, of the DEEP-ALI rounds reducing a STARK for an AIR to
+/// a batched low-degree test.
+///
+/// These are the bounds of the [ethSTARK](https://eprint.iacr.org/2021/582) documentation, in the
+/// round-by-round form of Block et al., "Fiat-Shamir Security of FRI and Related SNARKs". The
+/// soundness of the whole STARK is the least of these and of the low-degree test's, e.g.
+/// `p3_fri::FriConfig::proven_soundness` with `num_batched_functions` functions.
+#[derive(Clone, Debug, PartialEq)]
+pub struct DeepAliSoundness {
+    /// The round which samples `alpha`, combining the constraints into a single quotient.
+    pub ali_bits: f64,
+    /// The round which samples the out-of-domain point `zeta`.
+    pub deep_bits: f64,
+    /// The number of functions the PCS batches into its low-degree test: each column of the
+    /// preprocessed, main and permutation traces at each row of the window, and each column of
+    /// the quotient chunks and of the randomizer at `zeta`, counting base field columns.
+    pub num_batched_functions: usize,
+}
+
+impl DeepAliSoundness {
+    /// The soundness of the weakest of the two rounds.
+    pub fn bits(&self) -> f64 {
+        self.ali_bits.min(self.deep_bits)
+    }
+}
+
+/// Returns the proven soundness of the DEEP-ALI rounds of proving `air` for traces of height
+/// `2^log_degree`, where `list_size` bounds the number of codewords close to any committed
+/// function at the PCS's proximity parameter, e.g. `p3_fri::SoundnessRegime::list_size`. An
+/// infinite `list_size` gives zero bits for both rounds.
+pub fn deep_ali_soundness<SC, A>(
+    config: &SC,
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+    log_degree: usize,
+    list_size: f64,
+) -> DeepAliSoundness
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>,
+{
+    let (constraints, extension_constraints) = get_all_symbolic_constraints::<
+        Val<SC>,
+        SC::Challenge,
+        A,
+    >(air, preprocessed_width, num_public_values);
+    let num_constraints = constraints.len() + extension_constraints.len();
+    let constraint_degree = constraints
+        .iter()
+        .map(|c| c.degree_multiple())
+        .chain(extension_constraints.iter().map(|c| c.degree_multiple()))
+        .max()
+        .unwrap_or(0);
+
+    let log_field_size = log2_field_order::<SC::Challenge>();
+    // With no bound on the list size, neither round has any proven soundness.
+    let log_list_size = if list_size.is_finite() {
+        log2_f64(list_size)
+    } else {
+        f64::INFINITY
+    };
+
+    // Each codeword in the list fails the combination of constraints by `alpha`'s powers for at
+    // most `num_constraints - 1` values of `alpha`.
+    let ali_bits = if list_size.is_infinite() {
+        0.0
+    } else if num_constraints > 1 {
+        bits(log_list_size + log2_f64((num_constraints - 1) as f64) - log_field_size)
+    } else {
+        f64::INFINITY
+    };
+
+    // The trace polynomials have degree below `2^log_degree`, or twice that in zero-knowledge
+    // mode, and are opened at each row of the window. Each pair of codewords in the list agrees at
+    // `zeta` with probability bounded by the degree of their difference's composition, over
+    // `|F|`, ignoring the negligible points of the trace and evaluation domains.
+    let is_zk = config.is_zk();
+    let trace_len = (1u64 << (log_degree + is_zk as usize)) as f64;
+    let window_size = <A as BaseAir<Val<SC>>>::window_size(air) as f64;
+    let deep_degree =
+        constraint_degree.max(1) as f64 * (trace_len + window_size - 1.0) + (trace_len - 1.0);
+    let deep_bits = bits(2.0 * log_list_size + log2_f64(deep_degree) - log_field_size);
+
+    let extension_degree = <SC::Challenge as ExtensionField<Val<SC>>>::D;
+    let trace_width = preprocessed_width
+        + <A as BaseAir<Val<SC>>>::width(air)
+        + <A as BaseAir<Val<SC>>>::permutation_width(air) * extension_degree;
+    let quotient_degree = 1 << log_quotient_degree(constraint_degree, is_zk);
+    let num_batched_functions = trace_width * <A as BaseAir<Val<SC>>>::window_size(air)
+        + (quotient_degree + is_zk as usize) * extension_degree;
+
+    DeepAliSoundness {
+        ali_bits,
+        deep_bits,
+        num_batched_functions,
+    }
+}
+
+fn bits(log_error: f64) -> f64 {
+    (-log_error).max(0.0)
+}
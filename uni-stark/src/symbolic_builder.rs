@@ -1,49 +1,71 @@
 use alloc::vec;
 use alloc::vec::Vec;
 
-use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, PairBuilder};
-use p3_field::Field;
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, ExtensionBuilder, MultiTableAirBuilder,
+    PairBuilder, PeriodicAirBuilder, PermutationAirBuilder,
+};
+use p3_field::{ExtensionField, Field};
 use p3_matrix::dense::RowMajorMatrix;
 use p3_util::log2_ceil_usize;
 use tracing::instrument;
 
 use crate::symbolic_expression::SymbolicExpression;
+use crate::symbolic_expression_ext::SymbolicExpressionExt;
 use crate::symbolic_variable::SymbolicVariable;
 use crate::Entry;
 
 #[instrument(name = "infer log of constraint degree", skip_all)]
-pub fn get_log_quotient_degree<F, A>(
+pub fn get_log_quotient_degree<F, EF, A>(
     air: &A,
     preprocessed_width: usize,
     num_public_values: usize,
+    is_zk: bool,
 ) -> usize
 where
     F: Field,
-    A: Air<SymbolicAirBuilder<F>>,
+    EF: ExtensionField<F>,
+    A: Air<SymbolicAirBuilder<F, EF>>,
 {
+    let constraint_degree = get_max_constraint_degree(air, preprocessed_width, num_public_values);
+    log_quotient_degree(constraint_degree, is_zk)
+}
+
+/// The log of the number of quotient chunks, each the size of the trace domain, needed for
+/// constraints of the given degree.
+pub(crate) fn log_quotient_degree(constraint_degree: usize, is_zk: bool) -> usize {
     // We pad to at least degree 2, since a quotient argument doesn't make sense with smaller degrees.
-    let constraint_degree =
-        get_max_constraint_degree(air, preprocessed_width, num_public_values).max(2);
+    let constraint_degree = constraint_degree.max(2);
 
     // The quotient's actual degree is approximately (max_constraint_degree - 1) n,
-    // where subtracting 1 comes from division by the zerofier.
+    // where subtracting 1 comes from division by the zerofier. In zero-knowledge mode the
+    // randomized trace polynomials have degree below 2n, so the quotient's degree is below
+    // (2 max_constraint_degree - 1) n instead.
     // But we pad it to a power of two so that we can efficiently decompose the quotient.
-    log2_ceil_usize(constraint_degree - 1)
+    if is_zk {
+        log2_ceil_usize(2 * constraint_degree - 1)
+    } else {
+        log2_ceil_usize(constraint_degree - 1)
+    }
 }
 
 #[instrument(name = "infer constraint degree", skip_all, level = "debug")]
-pub fn get_max_constraint_degree<F, A>(
+pub fn get_max_constraint_degree<F, EF, A>(
     air: &A,
     preprocessed_width: usize,
     num_public_values: usize,
 ) -> usize
 where
     F: Field,
-    A: Air<SymbolicAirBuilder<F>>,
+    EF: ExtensionField<F>,
+    A: Air<SymbolicAirBuilder<F, EF>>,
 {
-    get_symbolic_constraints(air, preprocessed_width, num_public_values)
+    let (constraints, extension_constraints) =
+        get_all_symbolic_constraints(air, preprocessed_width, num_public_values);
+    constraints
         .iter()
         .map(|c| c.degree_multiple())
+        .chain(extension_constraints.iter().map(|c| c.degree_multiple()))
         .max()
         .unwrap_or(0)
 }
@@ -58,52 +80,194 @@ where
     F: Field,
     A: Air<SymbolicAirBuilder<F>>,
 {
-    let mut builder = SymbolicAirBuilder::new(preprocessed_width, air.width(), num_public_values);
+    let mut builder = SymbolicAirBuilder::new(
+        preprocessed_width,
+        air.width(),
+        air.window_size(),
+        air.periodic_columns().len(),
+        num_public_values,
+    );
     air.eval(&mut builder);
     builder.constraints()
 }
 
+/// Evaluates the constraints of `air` symbolically, including those over the extension field `EF`
+/// (such as constraints on the permutation trace).
+///
+/// Returns the base field constraints and the extension field constraints separately.
+#[instrument(name = "evaluate constraints symbolically", skip_all, level = "debug")]
+pub fn get_all_symbolic_constraints<F, EF, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> (Vec<SymbolicExpression<F>>, Vec<SymbolicExpression<EF>>)
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: Air<SymbolicAirBuilder<F, EF>>,
+{
+    let mut builder = SymbolicAirBuilder::new_with_permutation(
+        preprocessed_width,
+        air.width(),
+        air.window_size(),
+        air.periodic_columns().len(),
+        num_public_values,
+        air.permutation_width(),
+        air.num_permutation_challenges(),
+    );
+    air.eval(&mut builder);
+    (builder.constraints, builder.extension_constraints)
+}
+
+/// A constraint over either the base field or the extension field.
+#[derive(Clone, Debug)]
+pub(crate) enum SymbolicConstraint<F, EF> {
+    Base(SymbolicExpression<F>),
+    Extension(SymbolicExpression<EF>),
+}
+
+impl<F: Field, EF: Field> SymbolicConstraint<F, EF> {
+    pub(crate) const fn degree_multiple(&self) -> usize {
+        match self {
+            Self::Base(expr) => expr.degree_multiple(),
+            Self::Extension(expr) => expr.degree_multiple(),
+        }
+    }
+}
+
+/// Like `get_all_symbolic_constraints`, but returns the constraints in the order in which the AIR
+/// asserts them, which is the order in which they're combined with powers of `alpha`.
+pub(crate) fn get_ordered_symbolic_constraints<F, EF, A>(
+    air: &A,
+    preprocessed_width: usize,
+    num_public_values: usize,
+) -> Vec<SymbolicConstraint<F, EF>>
+where
+    F: Field,
+    EF: ExtensionField<F>,
+    A: Air<SymbolicAirBuilder<F, EF>>,
+{
+    let mut builder = SymbolicAirBuilder::new_with_permutation(
+        preprocessed_width,
+        air.width(),
+        air.window_size(),
+        air.periodic_columns().len(),
+        num_public_values,
+        air.permutation_width(),
+        air.num_permutation_challenges(),
+    );
+    air.eval(&mut builder);
+    let mut constraints = builder.constraints.into_iter();
+    let mut extension_constraints = builder.extension_constraints.into_iter();
+    builder
+        .is_extension
+        .into_iter()
+        .map(|is_extension| {
+            if is_extension {
+                SymbolicConstraint::Extension(extension_constraints.next().unwrap())
+            } else {
+                SymbolicConstraint::Base(constraints.next().unwrap())
+            }
+        })
+        .collect()
+}
+
 /// An `AirBuilder` for evaluating constraints symbolically, and recording them for later use.
+///
+/// Constraints over the extension field `EF`, which arise from the permutation trace and its
+/// challenges, are recorded separately from those over `F`.
 #[derive(Debug)]
-pub struct SymbolicAirBuilder<F: Field> {
+pub struct SymbolicAirBuilder<F: Field, EF: ExtensionField<F> = F> {
     preprocessed: RowMajorMatrix<SymbolicVariable<F>>,
     main: RowMajorMatrix<SymbolicVariable<F>>,
+    periodic_values: Vec<SymbolicVariable<F>>,
     public_values: Vec<SymbolicVariable<F>>,
+    permutation: RowMajorMatrix<SymbolicVariable<EF>>,
+    permutation_challenges: Vec<SymbolicVariable<EF>>,
     constraints: Vec<SymbolicExpression<F>>,
+    extension_constraints: Vec<SymbolicExpression<EF>>,
+    /// For each constraint in the order it was asserted, whether it's an extension constraint.
+    is_extension: Vec<bool>,
+    window_size: usize,
 }
 
 impl<F: Field> SymbolicAirBuilder<F> {
-    pub(crate) fn new(preprocessed_width: usize, width: usize, num_public_values: usize) -> Self {
-        let prep_values = [0, 1]
-            .into_iter()
+    pub(crate) fn new(
+        preprocessed_width: usize,
+        width: usize,
+        window_size: usize,
+        num_periodic_columns: usize,
+        num_public_values: usize,
+    ) -> Self {
+        Self::new_with_permutation(
+            preprocessed_width,
+            width,
+            window_size,
+            num_periodic_columns,
+            num_public_values,
+            0,
+            0,
+        )
+    }
+
+    pub(crate) fn constraints(self) -> Vec<SymbolicExpression<F>> {
+        self.constraints
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> SymbolicAirBuilder<F, EF> {
+    pub(crate) fn new_with_permutation(
+        preprocessed_width: usize,
+        width: usize,
+        window_size: usize,
+        num_periodic_columns: usize,
+        num_public_values: usize,
+        permutation_width: usize,
+        num_permutation_challenges: usize,
+    ) -> Self {
+        assert!(window_size >= 2, "the window size must be at least two");
+        let prep_values = (0..window_size)
             .flat_map(|offset| {
                 (0..preprocessed_width)
                     .map(move |index| SymbolicVariable::new(Entry::Preprocessed { offset }, index))
             })
             .collect();
-        let main_values = [0, 1]
-            .into_iter()
+        let main_values = (0..window_size)
             .flat_map(|offset| {
                 (0..width).map(move |index| SymbolicVariable::new(Entry::Main { offset }, index))
             })
             .collect();
+        let periodic_values = (0..num_periodic_columns)
+            .map(move |index| SymbolicVariable::new(Entry::Periodic, index))
+            .collect();
         let public_values = (0..num_public_values)
             .map(move |index| SymbolicVariable::new(Entry::Public, index))
             .collect();
+        let permutation_values = (0..window_size)
+            .flat_map(|offset| {
+                (0..permutation_width)
+                    .map(move |index| SymbolicVariable::new(Entry::Permutation { offset }, index))
+            })
+            .collect();
+        let permutation_challenges = (0..num_permutation_challenges)
+            .map(move |index| SymbolicVariable::new(Entry::Challenge, index))
+            .collect();
         Self {
             preprocessed: RowMajorMatrix::new(prep_values, preprocessed_width),
             main: RowMajorMatrix::new(main_values, width),
+            periodic_values,
             public_values,
+            permutation: RowMajorMatrix::new(permutation_values, permutation_width),
+            permutation_challenges,
             constraints: vec![],
+            extension_constraints: vec![],
+            is_extension: vec![],
+            window_size,
         }
     }
-
-    pub(crate) fn constraints(self) -> Vec<SymbolicExpression<F>> {
-        self.constraints
-    }
 }
 
-impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
+impl<F: Field, EF: ExtensionField<F>> AirBuilder for SymbolicAirBuilder<F, EF> {
     type F = F;
     type Expr = SymbolicExpression<F>;
     type Var = SymbolicVariable<F>;
@@ -122,27 +286,75 @@ impl<F: Field> AirBuilder for SymbolicAirBuilder<F> {
     }
 
     fn is_transition_window(&self, size: usize) -> Self::Expr {
-        if size == 2 {
-            SymbolicExpression::IsTransition
-        } else {
-            panic!("uni-stark only supports a window size of 2")
+        match size {
+            2 => SymbolicExpression::IsTransition,
+            _ if size > 2 && size <= self.window_size => {
+                SymbolicExpression::IsTransitionWindow(size)
+            }
+            _ => panic!(
+                "window size must be between 2 and the AIR's window size of {}",
+                self.window_size
+            ),
         }
     }
 
     fn assert_zero<I: Into<Self::Expr>>(&mut self, x: I) {
         self.constraints.push(x.into());
+        self.is_extension.push(false);
     }
 }
 
-impl<F: Field> AirBuilderWithPublicValues for SymbolicAirBuilder<F> {
+impl<F: Field, EF: ExtensionField<F>> AirBuilderWithPublicValues for SymbolicAirBuilder<F, EF> {
     type PublicVar = SymbolicVariable<F>;
     fn public_values(&self) -> &[Self::PublicVar] {
         &self.public_values
     }
 }
 
-impl<F: Field> PairBuilder for SymbolicAirBuilder<F> {
+impl<F: Field, EF: ExtensionField<F>> PeriodicAirBuilder for SymbolicAirBuilder<F, EF> {
+    type PeriodicVar = SymbolicVariable<F>;
+    fn periodic_values(&self) -> &[Self::PeriodicVar] {
+        &self.periodic_values
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> PairBuilder for SymbolicAirBuilder<F, EF> {
     fn preprocessed(&self) -> Self::M {
         self.preprocessed.clone()
     }
 }
+
+impl<F: Field, EF: ExtensionField<F>> ExtensionBuilder for SymbolicAirBuilder<F, EF> {
+    type EF = EF;
+    type ExprEF = SymbolicExpressionExt<F, EF>;
+    type VarEF = SymbolicVariable<EF>;
+
+    fn assert_zero_ext<I>(&mut self, x: I)
+    where
+        I: Into<Self::ExprEF>,
+    {
+        self.extension_constraints.push(x.into().into_inner());
+        self.is_extension.push(true);
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> PermutationAirBuilder for SymbolicAirBuilder<F, EF> {
+    type MP = RowMajorMatrix<Self::VarEF>;
+    type RandomVar = SymbolicVariable<EF>;
+
+    fn permutation(&self) -> Self::MP {
+        self.permutation.clone()
+    }
+
+    fn permutation_randomness(&self) -> &[Self::RandomVar] {
+        &self.permutation_challenges
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> MultiTableAirBuilder for SymbolicAirBuilder<F, EF> {
+    type Sum = SymbolicVariable<EF>;
+
+    fn cumulative_sum(&self) -> Self::Sum {
+        SymbolicVariable::new(Entry::CumulativeSum, 0)
+    }
+}
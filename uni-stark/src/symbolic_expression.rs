@@ -1,10 +1,11 @@
+use alloc::collections::BTreeMap;
 use alloc::rc::Rc;
 use core::cmp;
 use core::fmt::Debug;
 use core::iter::{Product, Sum};
 use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
 
-use p3_field::{Field, FieldAlgebra};
+use p3_field::{ExtensionField, Field, FieldAlgebra};
 
 use crate::symbolic_variable::SymbolicVariable;
 
@@ -15,6 +16,9 @@ pub enum SymbolicExpression<F> {
     IsFirstRow,
     IsLastRow,
     IsTransition,
+    /// The selector returned by `AirBuilder::is_transition_window` for windows of more than two
+    /// rows, which vanishes on the last `size - 1` rows.
+    IsTransitionWindow(usize),
     Constant(F),
     Add {
         x: Rc<Self>,
@@ -45,6 +49,8 @@ impl<F> SymbolicExpression<F> {
             SymbolicExpression::IsFirstRow => 1,
             SymbolicExpression::IsLastRow => 1,
             SymbolicExpression::IsTransition => 0,
+            // A product of `size - 1` linear factors, so unlike `IsTransition` it can't be ignored.
+            SymbolicExpression::IsTransitionWindow(_) => 1,
             SymbolicExpression::Constant(_) => 0,
             SymbolicExpression::Add {
                 degree_multiple, ..
@@ -62,6 +68,70 @@ impl<F> SymbolicExpression<F> {
     }
 }
 
+impl<F: Field> SymbolicExpression<F> {
+    /// Maps this expression over `F` to the same expression over an extension field `EF`.
+    ///
+    /// Shared subexpressions stay shared, so the result has the same size as `self`.
+    pub fn lift<EF: ExtensionField<F>>(&self) -> SymbolicExpression<EF> {
+        self.lift_with_cache(&mut BTreeMap::new())
+    }
+
+    fn lift_with_cache<EF: ExtensionField<F>>(
+        &self,
+        cache: &mut BTreeMap<*const Self, Rc<SymbolicExpression<EF>>>,
+    ) -> SymbolicExpression<EF> {
+        let mut lift_rc = |x: &Rc<Self>| {
+            if let Some(lifted) = cache.get(&Rc::as_ptr(x)) {
+                return lifted.clone();
+            }
+            let lifted = Rc::new(x.lift_with_cache(cache));
+            cache.insert(Rc::as_ptr(x), lifted.clone());
+            lifted
+        };
+        match self {
+            Self::Variable(v) => {
+                SymbolicExpression::Variable(SymbolicVariable::new(v.entry, v.index))
+            }
+            Self::IsFirstRow => SymbolicExpression::IsFirstRow,
+            Self::IsLastRow => SymbolicExpression::IsLastRow,
+            Self::IsTransition => SymbolicExpression::IsTransition,
+            Self::IsTransitionWindow(size) => SymbolicExpression::IsTransitionWindow(*size),
+            Self::Constant(c) => SymbolicExpression::Constant(EF::from_base(*c)),
+            Self::Add {
+                x,
+                y,
+                degree_multiple,
+            } => SymbolicExpression::Add {
+                x: lift_rc(x),
+                y: lift_rc(y),
+                degree_multiple: *degree_multiple,
+            },
+            Self::Sub {
+                x,
+                y,
+                degree_multiple,
+            } => SymbolicExpression::Sub {
+                x: lift_rc(x),
+                y: lift_rc(y),
+                degree_multiple: *degree_multiple,
+            },
+            Self::Neg { x, degree_multiple } => SymbolicExpression::Neg {
+                x: lift_rc(x),
+                degree_multiple: *degree_multiple,
+            },
+            Self::Mul {
+                x,
+                y,
+                degree_multiple,
+            } => SymbolicExpression::Mul {
+                x: lift_rc(x),
+                y: lift_rc(y),
+                degree_multiple: *degree_multiple,
+            },
+        }
+    }
+}
+
 impl<F: Field> Default for SymbolicExpression<F> {
     fn default() -> Self {
         Self::Constant(F::ZERO)
@@ -0,0 +1,187 @@
+use core::fmt::Debug;
+use core::iter::{Product, Sum};
+use core::marker::PhantomData;
+use core::ops::{Add, AddAssign, Mul, MulAssign, Neg, Sub, SubAssign};
+
+use p3_field::{ExtensionField, Field, FieldAlgebra};
+
+use crate::symbolic_expression::SymbolicExpression;
+use crate::symbolic_variable::SymbolicVariable;
+
+/// A symbolic expression over an extension field `EF` of `F`.
+///
+/// This wraps a `SymbolicExpression<EF>` so that expressions over `F` can be mixed in, which is
+/// what `ExtensionBuilder` requires of its `ExprEF` type; see `p3_air::ExtensionExpr`. Base field
+/// expressions are lifted with `SymbolicExpression::lift`.
+#[derive(Clone, Debug)]
+pub struct SymbolicExpressionExt<F, EF> {
+    pub expr: SymbolicExpression<EF>,
+    _phantom: PhantomData<F>,
+}
+
+impl<F, EF> SymbolicExpressionExt<F, EF> {
+    pub const fn new(expr: SymbolicExpression<EF>) -> Self {
+        Self {
+            expr,
+            _phantom: PhantomData,
+        }
+    }
+
+    pub fn into_inner(self) -> SymbolicExpression<EF> {
+        self.expr
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> Default for SymbolicExpressionExt<F, EF> {
+    fn default() -> Self {
+        Self::ZERO
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> From<SymbolicExpression<F>> for SymbolicExpressionExt<F, EF> {
+    fn from(value: SymbolicExpression<F>) -> Self {
+        Self::new(value.lift())
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> From<SymbolicVariable<EF>> for SymbolicExpressionExt<F, EF> {
+    fn from(value: SymbolicVariable<EF>) -> Self {
+        Self::new(SymbolicExpression::Variable(value))
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> FieldAlgebra for SymbolicExpressionExt<F, EF> {
+    type F = EF;
+
+    const ZERO: Self = Self::new(SymbolicExpression::Constant(EF::ZERO));
+    const ONE: Self = Self::new(SymbolicExpression::Constant(EF::ONE));
+    const TWO: Self = Self::new(SymbolicExpression::Constant(EF::TWO));
+    const NEG_ONE: Self = Self::new(SymbolicExpression::Constant(EF::NEG_ONE));
+
+    #[inline]
+    fn from_f(f: Self::F) -> Self {
+        Self::new(SymbolicExpression::Constant(f))
+    }
+
+    fn from_bool(b: bool) -> Self {
+        Self::from_f(EF::from_bool(b))
+    }
+
+    fn from_canonical_u8(n: u8) -> Self {
+        Self::from_f(EF::from_canonical_u8(n))
+    }
+
+    fn from_canonical_u16(n: u16) -> Self {
+        Self::from_f(EF::from_canonical_u16(n))
+    }
+
+    fn from_canonical_u32(n: u32) -> Self {
+        Self::from_f(EF::from_canonical_u32(n))
+    }
+
+    fn from_canonical_u64(n: u64) -> Self {
+        Self::from_f(EF::from_canonical_u64(n))
+    }
+
+    fn from_canonical_usize(n: usize) -> Self {
+        Self::from_f(EF::from_canonical_usize(n))
+    }
+
+    fn from_wrapped_u32(n: u32) -> Self {
+        Self::from_f(EF::from_wrapped_u32(n))
+    }
+
+    fn from_wrapped_u64(n: u64) -> Self {
+        Self::from_f(EF::from_wrapped_u64(n))
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> Add<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    type Output = Self;
+
+    fn add(self, rhs: T) -> Self {
+        Self::new(self.expr + rhs.into().expr)
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> AddAssign<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    fn add_assign(&mut self, rhs: T) {
+        self.expr += rhs.into().expr;
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> Sum<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    fn sum<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.map(Into::into)
+            .reduce(|x, y| x + y)
+            .unwrap_or(Self::ZERO)
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> Sub<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    type Output = Self;
+
+    fn sub(self, rhs: T) -> Self {
+        Self::new(self.expr - rhs.into().expr)
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> SubAssign<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    fn sub_assign(&mut self, rhs: T) {
+        self.expr -= rhs.into().expr;
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>> Neg for SymbolicExpressionExt<F, EF> {
+    type Output = Self;
+
+    fn neg(self) -> Self {
+        Self::new(-self.expr)
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> Mul<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    type Output = Self;
+
+    fn mul(self, rhs: T) -> Self {
+        Self::new(self.expr * rhs.into().expr)
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> MulAssign<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    fn mul_assign(&mut self, rhs: T) {
+        self.expr *= rhs.into().expr;
+    }
+}
+
+impl<F: Field, EF: ExtensionField<F>, T> Product<T> for SymbolicExpressionExt<F, EF>
+where
+    T: Into<Self>,
+{
+    fn product<I: Iterator<Item = T>>(iter: I) -> Self {
+        iter.map(Into::into)
+            .reduce(|x, y| x * y)
+            .unwrap_or(Self::ONE)
+    }
+}
@@ -10,8 +10,10 @@ pub enum Entry {
     Preprocessed { offset: usize },
     Main { offset: usize },
     Permutation { offset: usize },
+    Periodic,
     Public,
     Challenge,
+    CumulativeSum,
 }
 
 /// A variable within the evaluation window, i.e. a column in either the local or next row.
@@ -33,8 +35,11 @@ impl<F> SymbolicVariable<F> {
 
     pub const fn degree_multiple(&self) -> usize {
         match self.entry {
-            Entry::Preprocessed { .. } | Entry::Main { .. } | Entry::Permutation { .. } => 1,
-            Entry::Public | Entry::Challenge => 0,
+            Entry::Preprocessed { .. }
+            | Entry::Main { .. }
+            | Entry::Permutation { .. }
+            | Entry::Periodic => 1,
+            Entry::Public | Entry::Challenge | Entry::CumulativeSum => 0,
         }
     }
 }
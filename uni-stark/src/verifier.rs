@@ -1,17 +1,22 @@
 use alloc::vec;
 use alloc::vec::Vec;
+use core::iter;
 
-use itertools::Itertools;
+use itertools::{izip, Itertools};
 use p3_air::{Air, BaseAir};
 use p3_challenger::{CanObserve, CanSample, FieldChallenger};
-use p3_commit::{Pcs, PolynomialSpace};
+use p3_commit::{Pcs, PeriodicPolynomialSpace, PolynomialSpace};
 use p3_field::{Field, FieldAlgebra, FieldExtensionAlgebra};
-use p3_matrix::dense::RowMajorMatrixView;
-use p3_matrix::stack::VerticalPair;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
 use tracing::instrument;
 
+use crate::instance::observe_air_digests;
 use crate::symbolic_builder::{get_log_quotient_degree, SymbolicAirBuilder};
-use crate::{PcsError, Proof, StarkGenericConfig, Val, VerifierConstraintFolder};
+use crate::{
+    get_air_digest, setup_preprocessed, AirDigest, Domain, OpenedValues, PcsError,
+    PreprocessedVerifierKey, Proof, StarkGenericConfig, Val, VerifierConstraintFolder,
+};
 
 #[instrument(skip_all)]
 pub fn verify<SC, A>(
@@ -23,7 +28,55 @@ pub fn verify<SC, A>(
 ) -> Result<(), VerificationError<PcsError<SC>>>
 where
     SC: StarkGenericConfig,
-    A: Air<SymbolicAirBuilder<Val<SC>>> + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
+    // A hiding PCS salts its commitments, so the preprocessed commitment can't be recomputed.
+    if config.is_zk()
+        && <A as BaseAir<Val<SC>>>::preprocessed_trace(air).is_some_and(|p| p.width() > 0)
+    {
+        return Err(VerificationError::PreprocessedKeyRequired);
+    }
+
+    // Recompute the preprocessed commitment and the AIR digest on the fly. Callers verifying many
+    // proofs of the same AIR should call `setup_preprocessed` and `get_air_digest` once and use
+    // `verify_with_preprocessed` instead.
+    let preprocessed = setup_preprocessed(config, air).map(|(_, verifier_key)| verifier_key);
+    let air_digest = get_air_digest::<Val<SC>, SC::Challenge, A>(
+        air,
+        preprocessed
+            .as_ref()
+            .map_or(0, |verifier_key| verifier_key.width),
+        public_values.len(),
+        config.is_zk(),
+    );
+    verify_with_preprocessed(
+        config,
+        air,
+        challenger,
+        proof,
+        public_values,
+        preprocessed.as_ref(),
+        &air_digest,
+    )
+}
+
+/// Like `verify`, but with the AIR's preprocessed verifier key and digest computed ahead of time,
+/// by `setup_preprocessed` and `get_air_digest`, so that they can be reused across proofs.
+#[instrument(skip_all)]
+pub fn verify_with_preprocessed<SC, A>(
+    config: &SC,
+    air: &A,
+    challenger: &mut SC::Challenger,
+    proof: &Proof<SC>,
+    public_values: &Vec<Val<SC>>,
+    preprocessed: Option<&PreprocessedVerifierKey<SC>>,
+    air_digest: &AirDigest,
+) -> Result<(), VerificationError<PcsError<SC>>>
+where
+    SC: StarkGenericConfig,
+    A: Air<SymbolicAirBuilder<Val<SC>, SC::Challenge>>
+        + for<'a> Air<VerifierConstraintFolder<'a, SC>>,
 {
     let Proof {
         commitments,
@@ -32,8 +85,18 @@ where
         degree_bits,
     } = proof;
 
+    let preprocessed_width = preprocessed.map_or(0, |preprocessed| preprocessed.width);
+    if preprocessed.is_some_and(|preprocessed| preprocessed.degree_bits != *degree_bits) {
+        return Err(VerificationError::InvalidProofShape);
+    }
+
     let degree = 1 << degree_bits;
-    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, A>(air, 0, public_values.len());
+    let log_quotient_degree = get_log_quotient_degree::<Val<SC>, SC::Challenge, A>(
+        air,
+        preprocessed_width,
+        public_values.len(),
+        config.is_zk(),
+    );
     let quotient_degree = 1 << log_quotient_degree;
 
     let pcs = config.pcs();
@@ -42,60 +105,170 @@ where
         trace_domain.create_disjoint_domain(1 << (degree_bits + log_quotient_degree));
     let quotient_chunks_domains = quotient_domain.split_domains(quotient_degree);
 
+    // In zero-knowledge mode, the committed polynomials are randomized to twice the degree of the
+    // domains they were given over, so the opening argument is checked against larger domains.
+    let is_zk = config.is_zk() as usize;
+    let ext_trace_domain = pcs.natural_domain_for_degree(degree << is_zk);
+    let ext_quotient_chunks_domains = quotient_chunks_domains
+        .iter()
+        .map(|domain| pcs.natural_domain_for_degree(domain.size() << is_zk))
+        .collect_vec();
+
     let air_width = <A as BaseAir<Val<SC>>>::width(air);
-    let valid_shape = opened_values.trace_local.len() == air_width
-        && opened_values.trace_next.len() == air_width
+    let window_size = <A as BaseAir<Val<SC>>>::window_size(air);
+    let permutation_width = <A as BaseAir<Val<SC>>>::permutation_width(air)
+        * <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D;
+    let valid_shape = opened_values.has_window_shape(
+        preprocessed_width,
+        air_width,
+        permutation_width,
+        window_size,
+    ) && commitments.permutation.is_some() == (permutation_width > 0)
+        && opened_values.cumulative_sum.is_some()
+            == <A as BaseAir<Val<SC>>>::has_cumulative_sum(air)
         && opened_values.quotient_chunks.len() == quotient_degree
         && opened_values
             .quotient_chunks
             .iter()
-            .all(|qc| qc.len() == <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D);
+            .all(|qc| qc.len() == <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D)
+        && commitments.random.is_some() == config.is_zk()
+        && opened_values
+            .random
+            .as_ref()
+            .map_or(!config.is_zk(), |random| {
+                random.len() == <SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D
+            });
     if !valid_shape {
         return Err(VerificationError::InvalidProofShape);
     }
+    // With a single table, any running sum must balance on its own.
+    if opened_values
+        .cumulative_sum
+        .is_some_and(|cumulative_sum| !cumulative_sum.is_zero())
+    {
+        return Err(VerificationError::UnbalancedCumulativeSums);
+    }
 
     // Observe the instance.
+    observe_air_digests(config, challenger, &[*air_digest]);
     challenger.observe(Val::<SC>::from_canonical_usize(proof.degree_bits));
-    // TODO: Might be best practice to include other instance data here in the transcript, like some
-    // encoding of the AIR. This protects against transcript collisions between distinct instances.
-    // Practically speaking though, the only related known attack is from failing to include public
-    // values. It's not clear if failing to include other instance data could enable a transcript
-    // collision, since most such changes would completely change the set of satisfying witnesses.
+    if let Some(preprocessed) = preprocessed {
+        challenger.observe(preprocessed.commitment.clone());
+    }
 
     challenger.observe(commitments.trace.clone());
     challenger.observe_slice(public_values);
+
+    let num_permutation_challenges = <A as BaseAir<Val<SC>>>::num_permutation_challenges(air);
+    let permutation_challenges: Vec<SC::Challenge> = (0..num_permutation_challenges)
+        .map(|_| challenger.sample_ext_element())
+        .collect();
+    if let Some(permutation_commit) = &commitments.permutation {
+        challenger.observe(permutation_commit.clone());
+    }
+    if let Some(cumulative_sum) = opened_values.cumulative_sum {
+        challenger.observe_ext_element(cumulative_sum);
+    }
+
     let alpha: SC::Challenge = challenger.sample_ext_element();
     challenger.observe(commitments.quotient_chunks.clone());
+    if let Some(random_commit) = &commitments.random {
+        challenger.observe(random_commit.clone());
+    }
 
     let zeta: SC::Challenge = challenger.sample();
-    let zeta_next = trace_domain.next_point(zeta).unwrap();
-
-    pcs.verify(
-        vec![
-            (
-                commitments.trace.clone(),
-                vec![(
-                    trace_domain,
-                    vec![
-                        (zeta, opened_values.trace_local.clone()),
-                        (zeta_next, opened_values.trace_next.clone()),
-                    ],
-                )],
-            ),
-            (
-                commitments.quotient_chunks.clone(),
-                quotient_chunks_domains
-                    .iter()
-                    .zip(&opened_values.quotient_chunks)
-                    .map(|(domain, values)| (*domain, vec![(zeta, values.clone())]))
-                    .collect_vec(),
-            ),
-        ],
-        opening_proof,
-        challenger,
+    let window_points = window_points::<SC>(trace_domain, zeta, window_size);
+
+    let mut rounds = vec![
+        (
+            commitments.trace.clone(),
+            vec![(
+                ext_trace_domain,
+                window_claims(
+                    &window_points,
+                    &opened_values.trace_local,
+                    &opened_values.trace_next,
+                    &opened_values.trace_lookahead,
+                ),
+            )],
+        ),
+        (
+            commitments.quotient_chunks.clone(),
+            ext_quotient_chunks_domains
+                .iter()
+                .zip(&opened_values.quotient_chunks)
+                .map(|(domain, values)| (*domain, vec![(zeta, values.clone())]))
+                .collect_vec(),
+        ),
+    ];
+    if let Some(preprocessed) = preprocessed {
+        rounds.push((
+            preprocessed.commitment.clone(),
+            vec![(
+                ext_trace_domain,
+                window_claims(
+                    &window_points,
+                    &opened_values.preprocessed_local,
+                    &opened_values.preprocessed_next,
+                    &opened_values.preprocessed_lookahead,
+                ),
+            )],
+        ));
+    }
+    if let Some(permutation_commit) = &commitments.permutation {
+        rounds.push((
+            permutation_commit.clone(),
+            vec![(
+                ext_trace_domain,
+                window_claims(
+                    &window_points,
+                    &opened_values.permutation_local,
+                    &opened_values.permutation_next,
+                    &opened_values.permutation_lookahead,
+                ),
+            )],
+        ));
+    }
+
+    if let (Some(random_commit), Some(random)) = (&commitments.random, &opened_values.random) {
+        rounds.push((
+            random_commit.clone(),
+            vec![(ext_trace_domain, vec![(zeta, random.clone())])],
+        ));
+    }
+
+    pcs.verify(rounds, opening_proof, challenger)
+        .map_err(VerificationError::InvalidOpeningArgument)?;
+
+    verify_constraints::<SC, A, _>(
+        air,
+        opened_values,
+        trace_domain,
+        &quotient_chunks_domains,
+        &permutation_challenges,
+        zeta,
+        alpha,
+        public_values,
     )
-    .map_err(VerificationError::InvalidOpeningArgument)?;
+}
 
+/// Checks that the constraints of `air`, folded with powers of `alpha` and evaluated at `zeta` using
+/// the opened trace values, agree with the quotient reconstructed from the opened chunks.
+#[allow(clippy::too_many_arguments)]
+pub(crate) fn verify_constraints<SC, A, PcsErr>(
+    air: &A,
+    opened_values: &OpenedValues<SC::Challenge>,
+    trace_domain: Domain<SC>,
+    quotient_chunks_domains: &[Domain<SC>],
+    permutation_challenges: &[SC::Challenge],
+    zeta: SC::Challenge,
+    alpha: SC::Challenge,
+    public_values: &Vec<Val<SC>>,
+) -> Result<(), VerificationError<PcsErr>>
+where
+    SC: StarkGenericConfig,
+    A: for<'a> Air<VerifierConstraintFolder<'a, SC>>,
+{
     let zps = quotient_chunks_domains
         .iter()
         .enumerate()
@@ -126,17 +299,87 @@ where
 
     let sels = trace_domain.selectors_at_point(zeta);
 
-    let main = VerticalPair::new(
-        RowMajorMatrixView::new_row(&opened_values.trace_local),
-        RowMajorMatrixView::new_row(&opened_values.trace_next),
+    // Periodic columns aren't committed to, so we evaluate them at `zeta` ourselves.
+    let periodic_columns = <A as BaseAir<Val<SC>>>::periodic_columns(air);
+    if !periodic_columns
+        .iter()
+        .all(|column| column.len().is_power_of_two() && column.len() <= trace_domain.size())
+    {
+        return Err(VerificationError::InvalidProofShape);
+    }
+    let periodic_values = periodic_columns
+        .iter()
+        .map(|column| trace_domain.periodic_column_at_point(column, zeta))
+        .collect_vec();
+
+    // The selector of a window of `k + 1` rows is that of a window of `k` rows times the transition
+    // selector at the window's `k`th row.
+    let window_size = <A as BaseAir<Val<SC>>>::window_size(air);
+    let window_points = window_points::<SC>(trace_domain, zeta, window_size);
+    let is_transition_windows = window_points[1..window_size - 1]
+        .iter()
+        .scan(sels.is_transition, |selector, &point| {
+            *selector *= trace_domain.selectors_at_point(point).is_transition;
+            Some(*selector)
+        })
+        .collect_vec();
+
+    let window =
+        |local: &[SC::Challenge], next: &[SC::Challenge], lookahead: &[Vec<SC::Challenge>]| {
+            let values = [local, next]
+                .into_iter()
+                .chain(lookahead.iter().map(Vec::as_slice))
+                .flatten()
+                .copied()
+                .collect_vec();
+            RowMajorMatrix::new(values, local.len())
+        };
+    let preprocessed = window(
+        &opened_values.preprocessed_local,
+        &opened_values.preprocessed_next,
+        &opened_values.preprocessed_lookahead,
+    );
+    let main = window(
+        &opened_values.trace_local,
+        &opened_values.trace_next,
+        &opened_values.trace_lookahead,
+    );
+    // The permutation trace was committed as base field columns, so we recombine each group of `D`
+    // opened values into a single extension field value.
+    let recombine = |values: &[SC::Challenge]| {
+        values
+            .chunks_exact(<SC::Challenge as FieldExtensionAlgebra<Val<SC>>>::D)
+            .map(|chunk| {
+                chunk
+                    .iter()
+                    .enumerate()
+                    .map(|(e_i, &c)| SC::Challenge::monomial(e_i) * c)
+                    .sum::<SC::Challenge>()
+            })
+            .collect_vec()
+    };
+    let permutation = window(
+        &recombine(&opened_values.permutation_local),
+        &recombine(&opened_values.permutation_next),
+        &opened_values
+            .permutation_lookahead
+            .iter()
+            .map(|row| recombine(row))
+            .collect_vec(),
     );
 
     let mut folder = VerifierConstraintFolder {
-        main,
+        preprocessed: preprocessed.as_view(),
+        main: main.as_view(),
+        permutation: permutation.as_view(),
+        periodic_values: &periodic_values,
+        permutation_challenges,
+        cumulative_sum: opened_values.cumulative_sum.unwrap_or_default(),
         public_values,
         is_first_row: sels.is_first_row,
         is_last_row: sels.is_last_row,
         is_transition: sels.is_transition,
+        is_transition_windows: &is_transition_windows,
         alpha,
         accumulator: SC::Challenge::ZERO,
     };
@@ -152,6 +395,32 @@ where
     Ok(())
 }
 
+/// The points at which the rows of a window starting at `zeta` are opened, i.e. `zeta` followed by
+/// its successors in `trace_domain`.
+pub(crate) fn window_points<SC: StarkGenericConfig>(
+    trace_domain: Domain<SC>,
+    zeta: SC::Challenge,
+    window_size: usize,
+) -> Vec<SC::Challenge> {
+    iter::successors(Some(zeta), |&point| trace_domain.next_point(point))
+        .take(window_size)
+        .collect()
+}
+
+/// Pairs the opened rows of a window with the points they were opened at.
+pub(crate) fn window_claims<EF: Copy, T: Clone>(
+    points: &[EF],
+    local: &T,
+    next: &T,
+    lookahead: &[T],
+) -> Vec<(EF, T)> {
+    izip!(
+        points.iter().copied(),
+        [local, next].into_iter().chain(lookahead).cloned()
+    )
+    .collect()
+}
+
 #[derive(Debug)]
 pub enum VerificationError<PcsErr> {
     InvalidProofShape,
@@ -160,4 +429,12 @@ pub enum VerificationError<PcsErr> {
     /// Out-of-domain evaluation mismatch, i.e. `constraints(zeta)` did not match
     /// `quotient(zeta) Z_H(zeta)`.
     OodEvaluationMismatch,
+    /// The cumulative sums of the tables' running sums did not add up to zero, i.e. some bus
+    /// interactions were not matched.
+    UnbalancedCumulativeSums,
+    /// The AIR has a preprocessed trace but the PCS is hiding, so `verify` can't recompute its
+    /// commitment. Use `verify_with_preprocessed` with the key from `setup_preprocessed` instead.
+    PreprocessedKeyRequired,
+    /// The AIR has a preprocessed trace, which `verify_multi` doesn't support.
+    PreprocessedTraceUnsupported,
 }
@@ -0,0 +1,91 @@
+//! The configuration shared by the uni-stark integration tests: BabyBear with a degree four
+//! extension, Poseidon2 Merkle trees and FRI.
+
+// Each test crate uses a different subset of these.
+#![allow(dead_code)]
+
+use p3_baby_bear::{BabyBear, Poseidon2BabyBear};
+use p3_challenger::DuplexChallenger;
+use p3_commit::ExtensionMmcs;
+use p3_dft::Radix2DitParallel;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::Field;
+use p3_fri::{create_test_fri_config, FriConfig, HidingFriPcs, TwoAdicFriPcs};
+use p3_merkle_tree::{MerkleTreeHidingMmcs, MerkleTreeMmcs};
+use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
+use p3_uni_stark::StarkConfig;
+use rand::rngs::StdRng;
+use rand::SeedableRng;
+
+pub type Val = BabyBear;
+pub type Perm = Poseidon2BabyBear<16>;
+pub type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+pub type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+pub type ValMmcs =
+    MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+pub type Challenge = BinomialExtensionField<Val, 4>;
+pub type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+pub type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+pub type Dft = Radix2DitParallel<Val>;
+pub type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+pub type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+pub type HidingValMmcs = MerkleTreeHidingMmcs<
+    <Val as Field>::Packing,
+    <Val as Field>::Packing,
+    MyHash,
+    MyCompress,
+    StdRng,
+    8,
+    4,
+>;
+pub type HidingChallengeMmcs = ExtensionMmcs<Val, Challenge, HidingValMmcs>;
+pub type HidingPcs = HidingFriPcs<Val, Dft, HidingValMmcs, HidingChallengeMmcs, StdRng>;
+pub type ZkConfig = StarkConfig<HidingPcs, Challenge, Challenger>;
+
+pub fn make_mmcs(perm: &Perm) -> (ValMmcs, ChallengeMmcs) {
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = ValMmcs::new(hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    (val_mmcs, challenge_mmcs)
+}
+
+/// A config whose FRI parameters are given by `fri_config` for the challenge MMCS.
+pub fn make_config_with_fri(
+    perm: &Perm,
+    fri_config: impl FnOnce(ChallengeMmcs) -> FriConfig<ChallengeMmcs>,
+) -> MyConfig {
+    let (val_mmcs, challenge_mmcs) = make_mmcs(perm);
+    let pcs = Pcs::new(Dft::default(), val_mmcs, fri_config(challenge_mmcs));
+    MyConfig::new(pcs)
+}
+
+pub fn make_config(perm: &Perm) -> MyConfig {
+    make_config_with_fri(perm, create_test_fri_config)
+}
+
+/// A config with a hiding PCS, for proving in zero-knowledge mode, whose randomness is seeded by
+/// `seed` and whose FRI parameters are given by `fri_config` for the challenge MMCS.
+pub fn make_zk_config_with_fri(
+    perm: &Perm,
+    seed: u64,
+    fri_config: impl FnOnce(HidingChallengeMmcs) -> FriConfig<HidingChallengeMmcs>,
+) -> ZkConfig {
+    let hash = MyHash::new(perm.clone());
+    let compress = MyCompress::new(perm.clone());
+    let val_mmcs = HidingValMmcs::new(hash, compress, StdRng::seed_from_u64(seed));
+    let challenge_mmcs = HidingChallengeMmcs::new(val_mmcs.clone());
+    let pcs = HidingPcs::new(
+        Dft::default(),
+        val_mmcs,
+        fri_config(challenge_mmcs),
+        4,
+        StdRng::seed_from_u64(seed + 1),
+    );
+    ZkConfig::new(pcs)
+}
+
+pub fn make_zk_config(perm: &Perm, seed: u64) -> ZkConfig {
+    make_zk_config_with_fri(perm, seed, create_test_fri_config)
+}
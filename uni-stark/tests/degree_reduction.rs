@@ -0,0 +1,107 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    find_constraint_failures, get_log_quotient_degree, get_max_constraint_degree, prove, verify,
+    DegreeReducedAir,
+};
+use rand::thread_rng;
+
+use common::*;
+
+/// A single column `x` with `x' = x^9 + 1`, starting from the first public value, and whose last
+/// value raised to the fourth power is the second public value.
+pub struct PowerAir;
+
+impl<F> BaseAir<F> for PowerAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for PowerAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.get(0, 0), main.get(1, 0));
+        let pis = builder.public_values();
+        let (first, last) = (pis[0], pis[1]);
+
+        let power = |n: usize| (0..n).map(|_| local.into()).product::<AB::Expr>();
+
+        builder.when_first_row().assert_eq(local, first);
+        builder
+            .when_transition()
+            .assert_eq(next, power(9) + AB::Expr::ONE);
+        builder.when_last_row().assert_eq(power(4), last);
+    }
+}
+
+fn generate_trace<F: Field>(n: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+    let mut values = vec![F::TWO];
+    for i in 1..n {
+        values.push(values[i - 1].exp_u64(9) + F::ONE);
+    }
+    let pis = vec![values[0], values[n - 1].exp_u64(4)];
+    (RowMajorMatrix::new_col(values), pis)
+}
+
+#[test]
+fn test_reduced_degree() {
+    assert_eq!(get_max_constraint_degree::<Val, Val, _>(&PowerAir, 0, 2), 9);
+    assert_eq!(
+        get_log_quotient_degree::<Val, Val, _>(&PowerAir, 0, 2, false),
+        3
+    );
+
+    for max_degree in [2, 3, 5] {
+        let air = DegreeReducedAir::<Val, _>::new(PowerAir, max_degree, 2);
+        assert!(air.num_intermediate_columns() > 0);
+        assert_eq!(air.width(), 1 + air.num_intermediate_columns());
+        assert!(get_max_constraint_degree::<Val, Val, _>(&air, 0, 2) <= max_degree);
+
+        let (main, pis) = generate_trace::<Val>(1 << 4);
+        let trace = air.generate_trace(&main, &pis);
+        let failures = find_constraint_failures::<_, Val, _>(&air, &trace, &pis, &[], None);
+        assert!(failures.is_empty());
+    }
+
+    // Constraints which are already low enough are left alone.
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 9, 2);
+    assert_eq!(air.num_intermediate_columns(), 0);
+}
+
+#[test]
+fn test_prove_reduced() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 3, 2);
+    assert_eq!(
+        get_log_quotient_degree::<Val, Challenge, _>(&air, 0, 2, false),
+        1
+    );
+    let (main, pis) = generate_trace::<Val>(1 << 6);
+    let trace = air.generate_trace(&main, &pis);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, trace, &pis);
+
+    let mut challenger = Challenger::new(perm);
+    verify(&config, &air, &mut challenger, &proof, &pis).expect("verification failed");
+}
+
+#[test]
+fn test_bad_intermediate_column() {
+    let air = DegreeReducedAir::<Val, _>::new(PowerAir, 3, 2);
+    let (main, pis) = generate_trace::<Val>(1 << 4);
+    let mut trace = air.generate_trace(&main, &pis);
+
+    // Corrupting an intermediate column breaks its defining constraint, even though the inner
+    // AIR's own column is untouched.
+    trace.values[5 * air.width() + 1] += Val::ONE;
+    let failures = find_constraint_failures::<_, Val, _>(&air, &trace, &pis, &[], None);
+    assert!(failures.iter().any(|failure| failure.row == 5));
+}
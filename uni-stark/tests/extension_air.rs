@@ -0,0 +1,138 @@
+mod common;
+
+use p3_air::{
+    Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, ExtensionBuilder, PermutationAirBuilder,
+};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    find_constraint_failures, get_all_symbolic_constraints, get_max_constraint_degree, prove,
+    verify,
+};
+use rand::thread_rng;
+
+use common::*;
+
+/// An AIR with columns `a` and `b = a^2`, and `a' = a b + 1`, all of whose constraints are random
+/// linear combinations over the challenge field. It has a challenge `r` but no permutation trace.
+///
+/// The first row's `a` and `b` are the public values, which are checked together as
+/// `a + r b = pis[0] + r pis[1]`.
+pub struct CubeAir;
+
+impl<F> BaseAir<F> for CubeAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: PermutationAirBuilder + AirBuilderWithPublicValues> Air<AB> for CubeAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let a: AB::Expr = main.get(0, 0).into();
+        let b: AB::Expr = main.get(0, 1).into();
+        let next_a: AB::Expr = main.get(1, 0).into();
+        let pis = builder.public_values();
+        let (first_a, first_b): (AB::Expr, AB::Expr) = (pis[0].into(), pis[1].into());
+        let r: AB::ExprEF = builder.permutation_randomness()[0].into();
+
+        builder.assert_zero_ext(r.clone() * (b.clone() - a.clone() * a.clone()));
+        builder
+            .when_transition()
+            .assert_zero_ext(r.clone() * (next_a - a.clone() * b.clone() - AB::Expr::ONE));
+        builder.when_first_row().assert_eq_ext(
+            AB::ExprEF::from(a) + r.clone() * b,
+            AB::ExprEF::from(first_a) + r * first_b,
+        );
+    }
+}
+
+fn generate_trace<F: Field>(n: usize) -> (RowMajorMatrix<F>, Vec<F>) {
+    let mut values = Vec::with_capacity(2 * n);
+    let mut a = F::TWO;
+    for _ in 0..n {
+        values.extend([a, a.square()]);
+        a = a.cube() + F::ONE;
+    }
+    let pis = vec![values[0], values[1]];
+    (RowMajorMatrix::new(values, 2), pis)
+}
+
+#[test]
+fn test_extension_constraint_degree() {
+    let (constraints, extension_constraints) =
+        get_all_symbolic_constraints::<Val, Challenge, _>(&CubeAir, 0, 2);
+    assert!(constraints.is_empty());
+    assert_eq!(extension_constraints.len(), 3);
+
+    // The challenge has degree zero, so the degrees are those of the trace polynomials.
+    let degrees = extension_constraints
+        .iter()
+        .map(|c| c.degree_multiple())
+        .collect::<Vec<_>>();
+    assert_eq!(degrees, [2, 2, 2]);
+    assert_eq!(
+        get_max_constraint_degree::<Val, Challenge, _>(&CubeAir, 0, 2),
+        2
+    );
+}
+
+#[test]
+fn test_prove_extension_constraints() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+
+    let (trace, pis) = generate_trace::<Val>(1 << 5);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &CubeAir, &mut challenger, trace, &pis);
+    assert!(proof.commitments().permutation().is_none());
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify(&config, &CubeAir, &mut challenger, &proof, &pis).expect("verification failed");
+
+    // The public values are absorbed into the transcript, so a wrong one changes every challenge
+    // and the opening proof no longer verifies.
+    let wrong_pis = vec![pis[0], pis[1] + Val::ONE];
+    let mut challenger = Challenger::new(perm);
+    assert!(verify(&config, &CubeAir, &mut challenger, &proof, &wrong_pis).is_err());
+}
+
+#[test]
+fn test_extension_constraint_failures() {
+    let (mut trace, pis) = generate_trace::<Val>(1 << 4);
+    trace.values[5 * 2 + 1] += Val::ONE;
+    let challenges = [Challenge::from_canonical_u32(7)];
+
+    let failures =
+        find_constraint_failures::<_, Challenge, _>(&CubeAir, &trace, &pis, &challenges, None);
+    let rows_and_constraints = failures
+        .iter()
+        .map(|failure| (failure.row, failure.constraint_index))
+        .collect::<Vec<_>>();
+    // `b = a^2` fails on row 5, and `a' = a b + 1` fails going from row 5 to row 6.
+    assert_eq!(rows_and_constraints, [(5, 0), (5, 1)]);
+    assert_eq!(failures[0].value, challenges[0]);
+
+    // A wrong public value is caught by the combined first row constraint.
+    let (trace, pis) = generate_trace::<Val>(1 << 4);
+    let wrong_pis = vec![pis[0], pis[1] + Val::ONE];
+    let failures = find_constraint_failures::<_, Challenge, _>(
+        &CubeAir,
+        &trace,
+        &wrong_pis,
+        &challenges,
+        None,
+    );
+    let rows_and_constraints = failures
+        .iter()
+        .map(|failure| (failure.row, failure.constraint_index))
+        .collect::<Vec<_>>();
+    assert_eq!(rows_and_constraints, [(0, 2)]);
+    assert_eq!(failures[0].value, -challenges[0]);
+}
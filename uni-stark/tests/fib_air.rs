@@ -12,7 +12,7 @@ use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_symmetric::{PaddingFreeSponge, TruncatedPermutation};
-use p3_uni_stark::{prove, verify, StarkConfig};
+use p3_uni_stark::{find_constraint_failures, prove, verify, StarkConfig};
 use rand::thread_rng;
 
 /// For testing the public values feature
@@ -166,3 +166,43 @@ fn test_incorrect_public_value() {
     ];
     prove(&config, &FibonacciAir {}, &mut challenger, trace, &pis);
 }
+
+#[test]
+fn test_find_constraint_failures() {
+    let pis = vec![
+        BabyBear::from_canonical_u64(0),
+        BabyBear::from_canonical_u64(1),
+        BabyBear::from_canonical_u64(21),
+    ];
+    let mut trace = generate_trace_rows::<Val>(0, 1, 1 << 3);
+    let failures =
+        find_constraint_failures::<_, Challenge, _>(&FibonacciAir {}, &trace, &pis, &[], None);
+    assert!(failures.is_empty());
+
+    // Corrupt the right column of row 3, which breaks both transition constraints from row 3 and
+    // the second one from row 2.
+    trace.values[3 * NUM_FIBONACCI_COLS + 1] = BabyBear::from_canonical_u64(4);
+    let failures =
+        find_constraint_failures::<_, Challenge, _>(&FibonacciAir {}, &trace, &pis, &[], None);
+    let rows_and_indices = failures
+        .iter()
+        .map(|failure| (failure.row, failure.constraint_index))
+        .collect::<Vec<_>>();
+    assert_eq!(rows_and_indices, vec![(2, 3), (3, 2), (3, 3)]);
+    assert_eq!(
+        failures[0].value,
+        Challenge::from_canonical_u64(3) - Challenge::from_canonical_u64(4)
+    );
+    assert_eq!(failures[0].window.len(), 2);
+    assert_eq!(
+        failures[0].window[1],
+        vec![
+            BabyBear::from_canonical_u64(2),
+            BabyBear::from_canonical_u64(4)
+        ]
+    );
+
+    let failures =
+        find_constraint_failures::<_, Challenge, _>(&FibonacciAir {}, &trace, &pis, &[], Some(2));
+    assert_eq!(failures.len(), 2);
+}
@@ -0,0 +1,104 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{get_air_digest, prove, verify};
+use rand::thread_rng;
+
+use common::*;
+
+/// An AIR with columns `(a, b, c)` asserting `a * b = c` on every row, or `b * a = c` if
+/// `commuted`. Both versions accept exactly the same traces.
+pub struct MulAir {
+    commuted: bool,
+}
+
+impl<F> BaseAir<F> for MulAir {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for MulAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        if self.commuted {
+            builder.assert_eq(local[1] * local[0], local[2]);
+        } else {
+            builder.assert_eq(local[0] * local[1], local[2]);
+        }
+    }
+}
+
+fn generate_trace<F: Field>(log_height: usize) -> RowMajorMatrix<F> {
+    let values = (0..1u64 << log_height)
+        .flat_map(|i| [i, i + 3, i * (i + 3)].map(F::from_canonical_u64))
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+#[test]
+fn test_air_digest() {
+    let air = MulAir { commuted: false };
+    let digest = get_air_digest::<Val, Challenge, _>(&air, 0, 0, false);
+    assert_eq!(
+        digest,
+        get_air_digest::<Val, Challenge, _>(&air, 0, 0, false)
+    );
+
+    let commuted_air = MulAir { commuted: true };
+    assert_ne!(
+        digest,
+        get_air_digest::<Val, Challenge, _>(&commuted_air, 0, 0, false)
+    );
+    assert_ne!(
+        digest,
+        get_air_digest::<Val, Challenge, _>(&air, 0, 1, false)
+    );
+    assert_ne!(
+        digest,
+        get_air_digest::<Val, Challenge, _>(&air, 0, 0, true)
+    );
+}
+
+#[test]
+fn test_proof_bound_to_air() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let air = MulAir { commuted: false };
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, generate_trace(6), &vec![]);
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+    // The commuted AIR accepts the same trace, but the proof was made for a different instance.
+    let mut challenger = Challenger::new(perm);
+    let commuted_air = MulAir { commuted: true };
+    assert!(verify(&config, &commuted_air, &mut challenger, &proof, &vec![]).is_err());
+}
+
+#[test]
+fn test_proof_bound_to_domain_separator() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm).with_domain_separator(b"application-a");
+    let air = MulAir { commuted: false };
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, generate_trace(6), &vec![]);
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+
+    let other_config = make_config(&perm).with_domain_separator(b"application-b");
+    let mut challenger = Challenger::new(perm.clone());
+    assert!(verify(&other_config, &air, &mut challenger, &proof, &vec![]).is_err());
+
+    let untagged_config = make_config(&perm);
+    let mut challenger = Challenger::new(perm);
+    assert!(verify(&untagged_config, &air, &mut challenger, &proof, &vec![]).is_err());
+}
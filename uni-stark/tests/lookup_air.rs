@@ -0,0 +1,153 @@
+mod common;
+
+use p3_air::{
+    Air, AirBuilder, BaseAir, Bus, Interaction, InteractionAir, LogUpAir, VirtualPairCol,
+};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove_multi, verify_multi, MultiProof, PcsError, VerificationError};
+use rand::thread_rng;
+
+use common::*;
+
+const RANGE_BUS: Bus = Bus::new("range");
+
+/// A toy range check, split over two tables which interact on a bus.
+pub enum LookupTable {
+    /// Two columns `(value, multiplicity)`, where `value` counts up from zero. Receives each value
+    /// with its multiplicity.
+    Range,
+    /// A single column of values, each of which is sent on the given bus to be range checked.
+    User(Bus),
+}
+
+impl<F: Field> BaseAir<F> for LookupTable {
+    fn width(&self) -> usize {
+        match self {
+            LookupTable::Range => 2,
+            LookupTable::User(_) => 1,
+        }
+    }
+}
+
+impl<F: Field> InteractionAir<F> for LookupTable {
+    fn sends(&self) -> Vec<Interaction<F>> {
+        match self {
+            LookupTable::Range => vec![],
+            LookupTable::User(bus) => vec![Interaction::new(
+                vec![VirtualPairCol::single_main(0)],
+                VirtualPairCol::ONE,
+                *bus,
+            )],
+        }
+    }
+
+    fn receives(&self) -> Vec<Interaction<F>> {
+        match self {
+            LookupTable::Range => vec![Interaction::new(
+                vec![VirtualPairCol::single_main(0)],
+                VirtualPairCol::single_main(1),
+                RANGE_BUS,
+            )],
+            LookupTable::User(_) => vec![],
+        }
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for LookupTable {
+    fn eval(&self, builder: &mut AB) {
+        if let LookupTable::Range = self {
+            let main = builder.main();
+            let local = main.row_slice(0);
+            let next = main.row_slice(1);
+            builder.when_first_row().assert_zero(local[0]);
+            builder
+                .when_transition()
+                .assert_eq(next[0], local[0] + AB::Expr::ONE);
+        }
+    }
+}
+
+fn generate_traces<F: Field>(
+    log_range: usize,
+    values: &[u64],
+) -> (RowMajorMatrix<F>, RowMajorMatrix<F>) {
+    let mut multiplicities = vec![0; 1 << log_range];
+    for &value in values {
+        if let Some(multiplicity) = multiplicities.get_mut(value as usize) {
+            *multiplicity += 1;
+        }
+    }
+    let range_trace = RowMajorMatrix::new(
+        multiplicities
+            .into_iter()
+            .enumerate()
+            .flat_map(|(i, m)| [F::from_canonical_usize(i), F::from_canonical_u64(m)])
+            .collect(),
+        2,
+    );
+    let user_trace =
+        RowMajorMatrix::new_col(values.iter().map(|&v| F::from_canonical_u64(v)).collect());
+    (range_trace, user_trace)
+}
+
+fn prove_and_verify(
+    values: &[u64],
+    user_bus: Bus,
+) -> Result<(), VerificationError<PcsError<MyConfig>>> {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+
+    let range_air = LogUpAir::<Val, _>::new(LookupTable::Range);
+    let user_air = LogUpAir::<Val, _>::new(LookupTable::User(user_bus));
+    let (range_trace, user_trace) = generate_traces::<Val>(3, values);
+    let pis = vec![];
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(
+        &config,
+        vec![
+            (&range_air, range_trace, &pis),
+            (&user_air, user_trace, &pis),
+        ],
+        &mut challenger,
+    );
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof: MultiProof<MyConfig> =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut challenger = Challenger::new(perm);
+    verify_multi(
+        &config,
+        vec![(&range_air, &pis), (&user_air, &pis)],
+        &mut challenger,
+        &proof,
+    )
+}
+
+#[test]
+fn test_range_check() {
+    let values = [3, 1, 4, 1, 5, 2, 6, 5, 3, 5, 0, 7, 7, 1, 3, 2];
+    prove_and_verify(&values, RANGE_BUS).expect("verification failed");
+}
+
+#[test]
+fn test_range_check_out_of_range() {
+    let values = [3, 1, 4, 1, 5, 2, 6, 5, 3, 5, 0, 7, 8, 1, 3, 2];
+    assert!(matches!(
+        prove_and_verify(&values, RANGE_BUS),
+        Err(VerificationError::UnbalancedCumulativeSums)
+    ));
+}
+
+#[test]
+fn test_range_check_wrong_bus() {
+    // The values are in range, but sent on a bus which nothing receives on.
+    let values = [3, 1, 4, 1, 5, 2, 6, 5, 3, 5, 0, 7, 7, 1, 3, 2];
+    assert!(matches!(
+        prove_and_verify(&values, Bus::new("other")),
+        Err(VerificationError::UnbalancedCumulativeSums)
+    ));
+}
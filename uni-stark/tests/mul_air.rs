@@ -17,6 +17,7 @@ use p3_matrix::dense::RowMajorMatrix;
 use p3_matrix::Matrix;
 use p3_merkle_tree::MerkleTreeMmcs;
 use p3_mersenne_31::Mersenne31;
+use p3_stir::{StirConfig, TwoAdicStirPcs};
 use p3_symmetric::{
     CompressionFunctionFromHasher, PaddingFreeSponge, SerializingHasher32, TruncatedPermutation,
 };
@@ -217,8 +218,10 @@ fn do_test_bb_twoadic(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
     let fri_config = FriConfig {
         log_blowup,
         log_final_poly_len: 5,
+        log_folding_arity: 1,
         num_queries: 40,
         proof_of_work_bits: 8,
+        commit_proof_of_work_bits: 0,
         mmcs: challenge_mmcs,
     };
     type Pcs = TwoAdicFriPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
@@ -255,6 +258,68 @@ fn prove_bb_twoadic_deg5() -> Result<(), impl Debug> {
     do_test_bb_twoadic(2, 5, 6)
 }
 
+fn do_test_bb_stir(
+    log_blowup: usize,
+    log_folding_factor: usize,
+    degree: u64,
+    log_n: usize,
+) -> Result<(), impl Debug> {
+    type Val = BabyBear;
+    type Challenge = BinomialExtensionField<Val, 4>;
+
+    type Perm = Poseidon2BabyBear<16>;
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+
+    type MyHash = PaddingFreeSponge<Perm, 16, 8, 8>;
+    let hash = MyHash::new(perm.clone());
+
+    type MyCompress = TruncatedPermutation<Perm, 2, 8, 16>;
+    let compress = MyCompress::new(perm.clone());
+
+    type ValMmcs =
+        MerkleTreeMmcs<<Val as Field>::Packing, <Val as Field>::Packing, MyHash, MyCompress, 8>;
+    let val_mmcs = ValMmcs::new(hash, compress);
+
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+
+    type Dft = Radix2DitParallel<Val>;
+    let dft = Dft::default();
+
+    type Challenger = DuplexChallenger<Val, Perm, 16, 8>;
+
+    let stir_config = StirConfig {
+        log_blowup,
+        log_final_poly_len: 2,
+        log_folding_factor,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        mmcs: challenge_mmcs,
+    };
+    type Pcs = TwoAdicStirPcs<Val, Dft, ValMmcs, ChallengeMmcs>;
+    let pcs = Pcs::new(dft, val_mmcs, stir_config);
+
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+    let config = MyConfig::new(pcs);
+
+    let air = MulAir {
+        degree,
+        ..Default::default()
+    };
+
+    do_test(config, air, 1 << log_n, Challenger::new(perm))
+}
+
+#[test]
+fn prove_bb_stir_deg2() -> Result<(), impl Debug> {
+    do_test_bb_stir(1, 2, 2, 8)
+}
+
+#[test]
+fn prove_bb_stir_deg4() -> Result<(), impl Debug> {
+    do_test_bb_stir(2, 4, 4, 7)
+}
+
 fn do_test_m31_circle(log_blowup: usize, degree: u64, log_n: usize) -> Result<(), impl Debug> {
     type Val = Mersenne31;
     type Challenge = BinomialExtensionField<Val, 3>;
@@ -278,8 +343,10 @@ fn do_test_m31_circle(log_blowup: usize, degree: u64, log_n: usize) -> Result<()
     let fri_config = FriConfig {
         log_blowup,
         log_final_poly_len: 0,
+        log_folding_arity: 1,
         num_queries: 40,
         proof_of_work_bits: 8,
+        commit_proof_of_work_bits: 0,
         mmcs: challenge_mmcs,
     };
 
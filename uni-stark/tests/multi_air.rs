@@ -0,0 +1,147 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_fri::{create_test_fri_config, FriConfig};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove_multi, verify_multi, MultiProof, VerificationError};
+use rand::thread_rng;
+
+use common::*;
+
+/// The tables of a toy VM. Each variant is a different AIR; the enum dispatches to them.
+pub enum TableAir {
+    /// A single column counting up from a public starting value.
+    Counter,
+    /// Two columns `(x, x^3)`.
+    Cube,
+}
+
+impl TableAir {
+    fn generate_trace<F: Field>(&self, log_height: usize, start: u64) -> RowMajorMatrix<F> {
+        let xs = (0..1 << log_height).map(|i| F::from_canonical_u64(start + i));
+        match self {
+            TableAir::Counter => RowMajorMatrix::new_col(xs.collect()),
+            TableAir::Cube => RowMajorMatrix::new(xs.flat_map(|x| [x, x.cube()]).collect(), 2),
+        }
+    }
+}
+
+impl<F> BaseAir<F> for TableAir {
+    fn width(&self) -> usize {
+        match self {
+            TableAir::Counter => 1,
+            TableAir::Cube => 2,
+        }
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for TableAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+
+        match self {
+            TableAir::Counter => {
+                let start = builder.public_values()[0];
+                builder.when_first_row().assert_eq(local[0], start);
+                builder
+                    .when_transition()
+                    .assert_eq(next[0], local[0] + AB::Expr::ONE);
+            }
+            TableAir::Cube => {
+                builder.assert_eq(local[1], local[0].into().cube());
+            }
+        }
+    }
+}
+
+fn make_config(perm: &Perm) -> MyConfig {
+    // The cube constraint has degree 3, so its quotient needs a blowup of at least 2.
+    make_config_with_fri(perm, |mmcs| FriConfig {
+        log_blowup: 2,
+        ..create_test_fri_config(mmcs)
+    })
+}
+
+fn do_test(counter_log_height: usize, cube_log_height: usize) {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+
+    let counter_pis = vec![Val::from_canonical_u64(5)];
+    let cube_pis = vec![];
+    let counter_trace = TableAir::Counter.generate_trace(counter_log_height, 5);
+    let cube_trace = TableAir::Cube.generate_trace(cube_log_height, 17);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(
+        &config,
+        vec![
+            (&TableAir::Counter, counter_trace, &counter_pis),
+            (&TableAir::Cube, cube_trace, &cube_pis),
+        ],
+        &mut challenger,
+    );
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof: MultiProof<MyConfig> =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut challenger = Challenger::new(perm);
+    verify_multi(
+        &config,
+        vec![
+            (&TableAir::Counter, &counter_pis),
+            (&TableAir::Cube, &cube_pis),
+        ],
+        &mut challenger,
+        &proof,
+    )
+    .expect("verification failed");
+}
+
+#[test]
+fn test_multi_same_height() {
+    do_test(4, 4);
+}
+
+#[test]
+fn test_multi_taller_first() {
+    do_test(6, 3);
+}
+
+#[test]
+fn test_multi_taller_last() {
+    do_test(2, 5);
+}
+
+#[test]
+fn test_multi_wrong_instance_count() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+
+    let counter_pis = vec![Val::ZERO];
+    let counter_trace = TableAir::Counter.generate_trace(3, 0);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(
+        &config,
+        vec![(&TableAir::Counter, counter_trace, &counter_pis)],
+        &mut challenger,
+    );
+
+    let cube_pis = vec![];
+    let mut challenger = Challenger::new(perm);
+    let result = verify_multi(
+        &config,
+        vec![
+            (&TableAir::Counter, &counter_pis),
+            (&TableAir::Cube, &cube_pis),
+        ],
+        &mut challenger,
+        &proof,
+    );
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
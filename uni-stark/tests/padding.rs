@@ -0,0 +1,226 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir, Padding};
+use p3_field::FieldAlgebra;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{pad_trace, try_prove, try_prove_multi, verify, verify_multi, ProverError};
+use rand::thread_rng;
+
+use common::*;
+
+/// A counter `x`, with a column `is_real` marking the rows which count. The counter only advances
+/// into real rows, and its final value is public.
+pub struct CounterAir {
+    padding: Padding<Val>,
+}
+
+impl BaseAir<Val> for CounterAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn padding(&self) -> Padding<Val> {
+        self.padding.clone()
+    }
+
+    fn real_row_selector(&self) -> Option<usize> {
+        Some(1)
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues<F = Val>> Air<AB> for CounterAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (x, is_real) = (main.get(0, 0), main.get(0, 1));
+        let (next_x, next_is_real) = (main.get(1, 0), main.get(1, 1));
+        let last = builder.public_values()[0];
+
+        builder.assert_bool(is_real);
+        builder.when_first_row().assert_zero(x);
+        builder.when_first_row().assert_one(is_real);
+
+        let mut when_transition = builder.when_transition();
+        when_transition.assert_eq(next_x, x + next_is_real);
+        // Real rows can't follow padding rows.
+        when_transition.assert_zero(next_is_real * (AB::Expr::ONE - is_real.into()));
+
+        builder.when_last_row().assert_eq(x, last);
+    }
+}
+
+/// An AIR whose real row selector is past its last column.
+pub struct SelectorOutOfBoundsAir;
+
+impl BaseAir<Val> for SelectorOutOfBoundsAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn real_row_selector(&self) -> Option<usize> {
+        Some(2)
+    }
+}
+
+fn generate_trace(n: usize) -> RowMajorMatrix<Val> {
+    let values = (0..n)
+        .flat_map(|i| [Val::from_canonical_usize(i), Val::ZERO])
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+#[test]
+fn test_pad_trace() {
+    let trace = generate_trace(5);
+
+    let air = CounterAir {
+        padding: Padding::RepeatLastRow,
+    };
+    let padded = pad_trace(&air, trace.clone()).unwrap();
+    assert_eq!(padded.height(), 8);
+    let column = |i| {
+        padded
+            .values
+            .iter()
+            .skip(i)
+            .step_by(2)
+            .copied()
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(
+        column(0),
+        [0, 1, 2, 3, 4, 4, 4, 4].map(Val::from_canonical_u32)
+    );
+    assert_eq!(
+        column(1),
+        [1, 1, 1, 1, 1, 0, 0, 0].map(Val::from_canonical_u32)
+    );
+
+    let air = CounterAir {
+        padding: Padding::Zeros,
+    };
+    let padded = pad_trace(&air, trace.clone()).unwrap();
+    assert_eq!(padded.row_slice(7).to_vec(), vec![Val::ZERO; 2]);
+
+    let air = CounterAir {
+        padding: Padding::Row(vec![Val::TWO, Val::TWO]),
+    };
+    let padded = pad_trace(&air, trace).unwrap();
+    assert_eq!(padded.row_slice(7).to_vec(), vec![Val::TWO, Val::ZERO]);
+
+    // Power of two heights aren't padded, but the selector is still filled in.
+    let air = CounterAir {
+        padding: Padding::None,
+    };
+    let padded = pad_trace(&air, generate_trace(4)).unwrap();
+    assert_eq!(padded.height(), 4);
+    assert!(padded
+        .values
+        .iter()
+        .skip(1)
+        .step_by(2)
+        .all(|&v| v == Val::ONE));
+}
+
+#[test]
+fn test_pad_trace_errors() {
+    let air = CounterAir {
+        padding: Padding::None,
+    };
+    assert_eq!(
+        pad_trace(&air, generate_trace(5)).unwrap_err(),
+        ProverError::HeightNotPowerOfTwo(5)
+    );
+    assert_eq!(
+        pad_trace(&air, generate_trace(0)).unwrap_err(),
+        ProverError::EmptyTrace
+    );
+    assert_eq!(
+        pad_trace(&air, RowMajorMatrix::new_col(vec![Val::ZERO; 4])).unwrap_err(),
+        ProverError::WrongTraceWidth {
+            expected: 2,
+            actual: 1
+        }
+    );
+
+    let air = CounterAir {
+        padding: Padding::Row(vec![Val::ZERO]),
+    };
+    assert_eq!(
+        pad_trace(&air, generate_trace(5)).unwrap_err(),
+        ProverError::WrongPaddingRowWidth {
+            expected: 2,
+            actual: 1
+        }
+    );
+
+    assert_eq!(
+        pad_trace(&SelectorOutOfBoundsAir, generate_trace(4)).unwrap_err(),
+        ProverError::RealRowSelectorOutOfBounds {
+            column: 2,
+            width: 2
+        }
+    );
+
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let mut challenger = Challenger::new(perm);
+    let air = CounterAir {
+        padding: Padding::None,
+    };
+    let pis = vec![Val::from_canonical_u32(10)];
+    assert!(matches!(
+        try_prove(&config, &air, &mut challenger, generate_trace(11), &pis),
+        Err(ProverError::HeightNotPowerOfTwo(11))
+    ));
+}
+
+#[test]
+fn test_prove_padded() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let air = CounterAir {
+        padding: Padding::RepeatLastRow,
+    };
+    let pis = vec![Val::from_canonical_u32(10)];
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = try_prove(&config, &air, &mut challenger, generate_trace(11), &pis)
+        .expect("failed to pad the trace");
+
+    let mut challenger = Challenger::new(perm);
+    verify(&config, &air, &mut challenger, &proof, &pis).expect("verification failed");
+}
+
+#[test]
+fn test_prove_multi_padded() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let air = CounterAir {
+        padding: Padding::RepeatLastRow,
+    };
+    let pis = [
+        vec![Val::from_canonical_u32(2)],
+        vec![Val::from_canonical_u32(20)],
+    ];
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = try_prove_multi(
+        &config,
+        vec![
+            (&air, generate_trace(3), &pis[0]),
+            (&air, generate_trace(21), &pis[1]),
+        ],
+        &mut challenger,
+    )
+    .expect("failed to pad the traces");
+
+    let mut challenger = Challenger::new(perm);
+    verify_multi(
+        &config,
+        vec![(&air, &pis[0]), (&air, &pis[1])],
+        &mut challenger,
+        &proof,
+    )
+    .expect("verification failed");
+}
@@ -0,0 +1,135 @@
+mod common;
+
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+use p3_air::{Air, AirBuilder, BaseAir, PeriodicAirBuilder};
+use p3_challenger::{HashChallenger, SerializingChallenger32};
+use p3_circle::CirclePcs;
+use p3_commit::ExtensionMmcs;
+use p3_field::extension::BinomialExtensionField;
+use p3_field::Field;
+use p3_fri::FriConfig;
+use p3_keccak::Keccak256Hash;
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_merkle_tree::MerkleTreeMmcs;
+use p3_mersenne_31::Mersenne31;
+use p3_symmetric::{CompressionFunctionFromHasher, SerializingHasher32};
+use p3_uni_stark::{prove, verify, StarkConfig, StarkGenericConfig};
+use rand::thread_rng;
+
+/// An AIR with a single column `x`, updated as `x' = x + c + f * x`, where `c` is a periodic
+/// column of round constants with period 4 and `f` is a periodic flag set once every 8 rows.
+pub struct PeriodicAir {
+    constants: [u64; 4],
+}
+
+impl<F: Field> BaseAir<F> for PeriodicAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn periodic_columns(&self) -> Vec<Vec<F>> {
+        let flags = (0..8).map(|i| F::from_bool(i == 0)).collect();
+        vec![self.constants.map(F::from_canonical_u64).to_vec(), flags]
+    }
+}
+
+impl<AB: PeriodicAirBuilder> Air<AB> for PeriodicAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (local, next) = (main.get(0, 0), main.get(1, 0));
+        let periodic = builder.periodic_values();
+        let constant: AB::Expr = periodic[0].into();
+        let flag: AB::Expr = periodic[1].into();
+
+        builder.when_first_row().assert_one(local);
+        builder
+            .when_transition()
+            .assert_eq(next, local + constant + flag * local);
+    }
+}
+
+impl PeriodicAir {
+    fn generate_trace<F: Field>(&self, n: usize) -> RowMajorMatrix<F> {
+        let periodic = <Self as BaseAir<F>>::periodic_columns(self);
+        let mut values = vec![F::ONE];
+        for i in 0..n - 1 {
+            let x = values[i];
+            values.push(x + periodic[0][i % 4] + periodic[1][i % 8] * x);
+        }
+        RowMajorMatrix::new_col(values)
+    }
+}
+
+fn do_test<SC: StarkGenericConfig>(
+    config: SC,
+    log_height: usize,
+    challenger: SC::Challenger,
+) -> Result<(), impl Debug>
+where
+    SC::Challenger: Clone,
+{
+    let air = PeriodicAir {
+        constants: [3, 1, 4, 1],
+    };
+    let trace = air.generate_trace(1 << log_height);
+
+    let mut p_challenger = challenger.clone();
+    let proof = prove(&config, &air, &mut p_challenger, trace, &vec![]);
+
+    // The periodic columns are part of the statement.
+    let other_air = PeriodicAir {
+        constants: [2, 7, 1, 8],
+    };
+    let mut v_challenger = challenger.clone();
+    assert!(verify(&config, &other_air, &mut v_challenger, &proof, &vec![]).is_err());
+
+    let mut v_challenger = challenger;
+    verify(&config, &air, &mut v_challenger, &proof, &vec![])
+}
+
+#[test]
+fn prove_bb_periodic() -> Result<(), impl Debug> {
+    let perm = common::Perm::new_from_rng_128(&mut thread_rng());
+    let config = common::make_config(&perm);
+    do_test(config, 6, common::Challenger::new(perm))
+}
+
+#[test]
+fn prove_m31_circle_periodic() -> Result<(), impl Debug> {
+    type Val = Mersenne31;
+    type Challenge = BinomialExtensionField<Val, 3>;
+    type ByteHash = Keccak256Hash;
+    type FieldHash = SerializingHasher32<ByteHash>;
+    type MyCompress = CompressionFunctionFromHasher<ByteHash, 2, 32>;
+    type ValMmcs = MerkleTreeMmcs<Val, u8, FieldHash, MyCompress, 32>;
+    type ChallengeMmcs = ExtensionMmcs<Val, Challenge, ValMmcs>;
+    type Challenger = SerializingChallenger32<Val, HashChallenger<u8, ByteHash, 32>>;
+    type Pcs = CirclePcs<Val, ValMmcs, ChallengeMmcs>;
+    type MyConfig = StarkConfig<Pcs, Challenge, Challenger>;
+
+    let byte_hash = ByteHash {};
+    let field_hash = FieldHash::new(byte_hash);
+    let compress = MyCompress::new(byte_hash);
+    let val_mmcs = ValMmcs::new(field_hash, compress);
+    let challenge_mmcs = ChallengeMmcs::new(val_mmcs.clone());
+    let fri_config = FriConfig {
+        log_blowup: 1,
+        log_final_poly_len: 0,
+        log_folding_arity: 1,
+        num_queries: 40,
+        proof_of_work_bits: 8,
+        commit_proof_of_work_bits: 0,
+        mmcs: challenge_mmcs,
+    };
+    let pcs = Pcs {
+        mmcs: val_mmcs,
+        fri_config,
+        _phantom: PhantomData,
+    };
+    let config = MyConfig::new(pcs);
+
+    do_test(config, 6, Challenger::from_hasher(vec![], byte_hash))
+}
@@ -0,0 +1,138 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, BaseAir, ExtensionBuilder, PermutationAirBuilder};
+use p3_field::{ExtensionField, Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove, verify, Proof};
+use rand::thread_rng;
+
+use common::*;
+
+/// An AIR with two main columns `a` and `b`, which proves that `b` is a permutation of `a` with a
+/// running product argument.
+///
+/// Given a challenge `gamma`, the permutation column `z` holds the running product of
+/// `(gamma - a) / (gamma - b)`, which must end at one.
+pub struct PermutationCheckAir;
+
+impl<F: Field> BaseAir<F> for PermutationCheckAir {
+    fn width(&self) -> usize {
+        2
+    }
+
+    fn permutation_width(&self) -> usize {
+        1
+    }
+
+    fn num_permutation_challenges(&self) -> usize {
+        1
+    }
+
+    fn permutation_trace<EF>(
+        &self,
+        main: &RowMajorMatrix<F>,
+        challenges: &[EF],
+    ) -> Option<RowMajorMatrix<EF>>
+    where
+        F: Field,
+        EF: ExtensionField<F>,
+    {
+        let gamma = challenges[0];
+        let mut z = EF::ONE;
+        let values = (0..main.height())
+            .map(|i| {
+                let row = main.row_slice(i);
+                z *= (gamma - row[0]) / (gamma - row[1]);
+                z
+            })
+            .collect();
+        Some(RowMajorMatrix::new_col(values))
+    }
+}
+
+impl<AB: PermutationAirBuilder> Air<AB> for PermutationCheckAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        let next = main.row_slice(1);
+        let permutation = builder.permutation();
+        let z_local: AB::ExprEF = permutation.row_slice(0)[0].into();
+        let z_next: AB::ExprEF = permutation.row_slice(1)[0].into();
+        let gamma: AB::ExprEF = builder.permutation_randomness()[0].into();
+
+        let ext = |x: AB::Var| -> AB::ExprEF {
+            let x: AB::Expr = x.into();
+            x.into()
+        };
+
+        builder.when_first_row().assert_eq_ext(
+            z_local.clone() * (gamma.clone() - ext(local[1])),
+            gamma.clone() - ext(local[0]),
+        );
+        builder.when_transition().assert_eq_ext(
+            z_next * (gamma.clone() - ext(next[1])),
+            z_local.clone() * (gamma - ext(next[0])),
+        );
+        builder.when_last_row().assert_one_ext(z_local);
+    }
+}
+
+fn generate_trace<F: Field>(log_height: usize, permuted: bool) -> RowMajorMatrix<F> {
+    let n = 1 << log_height;
+    let values = (0..n)
+        .flat_map(|i| {
+            let b = if permuted { n - 1 - i } else { i + 1 };
+            [F::from_canonical_usize(i), F::from_canonical_usize(b)]
+        })
+        .collect();
+    RowMajorMatrix::new(values, 2)
+}
+
+#[test]
+fn test_permutation_check() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let trace = generate_trace::<Val>(5, true);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(
+        &config,
+        &PermutationCheckAir,
+        &mut challenger,
+        trace,
+        &vec![],
+    );
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof: Proof<MyConfig> =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut challenger = Challenger::new(perm);
+    verify(
+        &config,
+        &PermutationCheckAir,
+        &mut challenger,
+        &proof,
+        &vec![],
+    )
+    .expect("verification failed");
+}
+
+#[cfg(debug_assertions)]
+#[test]
+#[should_panic(expected = "assertion `left == right` failed: constraints had nonzero value")]
+fn test_not_a_permutation() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let trace = generate_trace::<Val>(5, false);
+
+    let mut challenger = Challenger::new(perm);
+    prove(
+        &config,
+        &PermutationCheckAir,
+        &mut challenger,
+        trace,
+        &vec![],
+    );
+}
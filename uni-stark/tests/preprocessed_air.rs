@@ -0,0 +1,238 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, BaseAir, PairBuilder};
+use p3_field::{Field, FieldAlgebra};
+use p3_fri::{create_test_fri_config, FriConfig};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{
+    get_air_digest, prove, prove_multi, prove_with_preprocessed, setup_preprocessed,
+    try_prove_multi, verify, verify_multi, verify_with_preprocessed, ProverError,
+    StarkGenericConfig, VerificationError,
+};
+use rand::thread_rng;
+
+use common::*;
+
+/// An AIR whose preprocessed trace holds a row counter and a boolean selector, and whose single
+/// main column must equal the square of the counter on selected rows and zero elsewhere.
+pub struct SquaresAir {
+    log_height: usize,
+}
+
+impl SquaresAir {
+    fn generate_trace<F: Field>(&self) -> RowMajorMatrix<F> {
+        let values = (0..1 << self.log_height)
+            .map(|i| {
+                if i % 3 == 0 {
+                    F::from_canonical_usize(i * i)
+                } else {
+                    F::ZERO
+                }
+            })
+            .collect();
+        RowMajorMatrix::new_col(values)
+    }
+}
+
+impl<F: Field> BaseAir<F> for SquaresAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn preprocessed_trace(&self) -> Option<RowMajorMatrix<F>> {
+        let values = (0..1 << self.log_height)
+            .flat_map(|i| [F::from_canonical_usize(i), F::from_bool(i % 3 == 0)])
+            .collect();
+        Some(RowMajorMatrix::new(values, 2))
+    }
+}
+
+impl<AB: PairBuilder> Air<AB> for SquaresAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let preprocessed = builder.preprocessed();
+
+        let local = main.row_slice(0);
+        let prep_local = preprocessed.row_slice(0);
+        let prep_next = preprocessed.row_slice(1);
+
+        let counter = prep_local[0];
+        let selector = prep_local[1];
+
+        builder.assert_bool(selector);
+        builder.assert_eq(local[0], selector * counter * counter);
+        builder
+            .when_transition()
+            .assert_eq(prep_next[0], counter + AB::Expr::ONE);
+    }
+}
+
+/// A single column of zeros, with no preprocessed trace.
+pub struct ZeroAir;
+
+impl<F> BaseAir<F> for ZeroAir {
+    fn width(&self) -> usize {
+        1
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for ZeroAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_zero(local[0]);
+    }
+}
+
+#[test]
+fn test_preprocessed_on_the_fly() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let air = SquaresAir { log_height: 4 };
+    let trace = air.generate_trace::<Val>();
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+    let mut challenger = Challenger::new(perm);
+    verify(&config, &air, &mut challenger, &proof, &vec![]).expect("verification failed");
+}
+
+#[test]
+fn test_preprocessed_reused_key() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let air = SquaresAir { log_height: 5 };
+    let (prover_data, verifier_key) =
+        setup_preprocessed(&config, &air).expect("AIR has a preprocessed trace");
+    let air_digest =
+        get_air_digest::<Val, Challenge, _>(&air, prover_data.width, 0, config.is_zk());
+
+    for _ in 0..2 {
+        let trace = air.generate_trace::<Val>();
+        let mut challenger = Challenger::new(perm.clone());
+        let proof = prove_with_preprocessed(
+            &config,
+            &air,
+            &mut challenger,
+            trace,
+            &vec![],
+            Some(&prover_data),
+            &air_digest,
+        );
+
+        let mut challenger = Challenger::new(perm.clone());
+        verify_with_preprocessed(
+            &config,
+            &air,
+            &mut challenger,
+            &proof,
+            &vec![],
+            Some(&verifier_key),
+            &air_digest,
+        )
+        .expect("verification failed");
+    }
+}
+
+#[test]
+fn test_preprocessed_height_mismatch() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let air = SquaresAir { log_height: 4 };
+    let trace = air.generate_trace::<Val>();
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &air, &mut challenger, trace, &vec![]);
+
+    // A verifier key for a taller preprocessed trace must be rejected.
+    let (_, other_key) = setup_preprocessed(&config, &SquaresAir { log_height: 5 })
+        .expect("AIR has a preprocessed trace");
+    let air_digest = get_air_digest::<Val, Challenge, _>(&air, other_key.width, 0, config.is_zk());
+    let mut challenger = Challenger::new(perm);
+    let result = verify_with_preprocessed(
+        &config,
+        &air,
+        &mut challenger,
+        &proof,
+        &vec![],
+        Some(&other_key),
+        &air_digest,
+    );
+    assert!(matches!(result, Err(VerificationError::InvalidProofShape)));
+}
+
+#[test]
+fn test_preprocessed_zk() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    // In zero-knowledge mode the cubic constraint's quotient has degree below 5n, which needs a
+    // blowup of 4 over the randomized trace of degree below 2n.
+    let config = make_zk_config_with_fri(&perm, 0, |mmcs| FriConfig {
+        log_blowup: 2,
+        ..create_test_fri_config(mmcs)
+    });
+    let air = SquaresAir { log_height: 4 };
+    let (prover_data, verifier_key) =
+        setup_preprocessed(&config, &air).expect("AIR has a preprocessed trace");
+    let air_digest =
+        get_air_digest::<Val, Challenge, _>(&air, prover_data.width, 0, config.is_zk());
+
+    let trace = air.generate_trace::<Val>();
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_with_preprocessed(
+        &config,
+        &air,
+        &mut challenger,
+        trace,
+        &vec![],
+        Some(&prover_data),
+        &air_digest,
+    );
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify_with_preprocessed(
+        &config,
+        &air,
+        &mut challenger,
+        &proof,
+        &vec![],
+        Some(&verifier_key),
+        &air_digest,
+    )
+    .expect("verification failed");
+
+    // The hiding PCS salts the preprocessed commitment, so the verifier can't recompute it.
+    let mut challenger = Challenger::new(perm);
+    let result = verify(&config, &air, &mut challenger, &proof, &vec![]);
+    assert!(matches!(
+        result,
+        Err(VerificationError::PreprocessedKeyRequired)
+    ));
+}
+
+#[test]
+fn test_preprocessed_multi_unsupported() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let air = SquaresAir { log_height: 4 };
+    let trace = air.generate_trace::<Val>();
+
+    let mut challenger = Challenger::new(perm.clone());
+    let result = try_prove_multi(&config, vec![(&air, trace, &vec![])], &mut challenger);
+    assert_eq!(
+        result.err(),
+        Some(ProverError::PreprocessedTraceUnsupported)
+    );
+
+    // The verifier rejects the AIR before looking at the proof.
+    let zeros = RowMajorMatrix::new_col(vec![Val::ZERO; 1 << 4]);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(&config, vec![(&ZeroAir, zeros, &vec![])], &mut challenger);
+    let mut challenger = Challenger::new(perm);
+    let result = verify_multi(&config, vec![(&air, &vec![])], &mut challenger, &proof);
+    assert!(matches!(
+        result,
+        Err(VerificationError::PreprocessedTraceUnsupported)
+    ));
+}
@@ -0,0 +1,197 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{CanonicalEncoding, DecodingError, FieldAlgebra, PrimeField32};
+use p3_fri::{create_test_fri_config, FriConfig};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_uni_stark::{prove, verify, Proof, ProofDecodingError, PROOF_FORMAT_VERSION};
+use rand::thread_rng;
+
+use common::*;
+
+/// The Fibonacci sequence in two columns, starting from `0, 1`, whose last value is public.
+pub struct FibAir;
+
+impl<F> BaseAir<F> for FibAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for FibAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (a, b) = (main.get(0, 0), main.get(0, 1));
+        let (next_a, next_b) = (main.get(1, 0), main.get(1, 1));
+        let last = builder.public_values()[0];
+
+        builder.when_first_row().assert_zero(a);
+        builder.when_first_row().assert_one(b);
+
+        let mut when_transition = builder.when_transition();
+        when_transition.assert_eq(next_a, b);
+        when_transition.assert_eq(next_b, a + b);
+
+        builder.when_last_row().assert_eq(b, last);
+    }
+}
+
+fn generate_trace(n: usize) -> (RowMajorMatrix<Val>, Vec<Val>) {
+    let mut values = vec![Val::ZERO, Val::ONE];
+    for i in 1..n {
+        let (a, b) = (values[2 * i - 2], values[2 * i - 1]);
+        values.extend([b, a + b]);
+    }
+    let last = values[2 * n - 1];
+    (RowMajorMatrix::new(values, 2), vec![last])
+}
+
+fn make_proof(perm: &Perm, config: &MyConfig) -> (Proof<MyConfig>, Vec<Val>) {
+    let (trace, pis) = generate_trace(1 << 3);
+    let mut challenger = Challenger::new(perm.clone());
+    (prove(config, &FibAir, &mut challenger, trace, &pis), pis)
+}
+
+#[test]
+fn test_round_trip() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let (proof, pis) = make_proof(&perm, &config);
+
+    let bytes = proof.to_versioned_bytes(&config);
+    assert_eq!(bytes[..4], PROOF_FORMAT_VERSION.to_le_bytes());
+    let decoded = Proof::from_versioned_bytes(&config, &bytes).expect("failed to decode");
+    assert_eq!(decoded.to_versioned_bytes(&config), bytes);
+
+    let mut challenger = Challenger::new(perm);
+    verify(&config, &FibAir, &mut challenger, &decoded, &pis).expect("verification failed");
+}
+
+#[test]
+fn test_size_report() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let (proof, _) = make_proof(&perm, &config);
+
+    let report = proof.size_report();
+    assert_eq!(
+        report.commitments + report.opened_values + report.opening_proof + report.degree_bits,
+        report.total
+    );
+    assert_eq!(report.total, proof.to_bytes().len());
+
+    let fri_report = proof.opening_proof().size_report();
+    assert_eq!(fri_report.total, report.opening_proof);
+    let num_rounds = proof.opening_proof().commit_phase_commits.len();
+    assert_eq!(fri_report.commit_phase_openings.len(), num_rounds);
+    // Each round's Merkle multi-proof is no larger than the last round's, whose tree is taller.
+    assert!(fri_report
+        .commit_phase_openings
+        .windows(2)
+        .all(|sizes| sizes[0] >= sizes[1]));
+    assert!(
+        fri_report.commit_phase_commits
+            + fri_report.commit_pow_witnesses
+            + fri_report.input_proofs
+            + fri_report.commit_phase_openings.iter().sum::<usize>()
+            + fri_report.final_poly
+            + fri_report.pow_witness
+            <= fri_report.total
+    );
+
+    // Each round's openings are verified together, so the queries' Merkle paths share at least
+    // the compression into the root, saving hashing over verifying each query separately.
+    let num_queries = proof.opening_proof().commit_phase_openings[0]
+        .sibling_values
+        .len();
+    let separate_hashes = proof
+        .opening_proof()
+        .commit_phase_openings
+        .iter()
+        .map(|step| num_queries * (1 + step.opening_proof.len()))
+        .sum::<usize>();
+    assert!(fri_report.commit_phase_hashes < separate_hashes);
+    // Each query hashes its opened rows in each committed round of the STARK (the trace and the
+    // quotient chunks).
+    assert!(fri_report.input_hashes >= 2 * num_queries);
+}
+
+#[test]
+fn test_malformed_proofs() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let (proof, _) = make_proof(&perm, &config);
+    let bytes = proof.to_versioned_bytes(&config);
+    let decode = |bytes: &[u8]| Proof::<MyConfig>::from_versioned_bytes(&config, bytes).err();
+
+    let mut trailing = bytes.clone();
+    trailing.push(0);
+    assert_eq!(
+        decode(&trailing),
+        Some(ProofDecodingError::Format(DecodingError::TrailingBytes))
+    );
+    assert_eq!(
+        decode(&bytes[..bytes.len() - 1]),
+        Some(ProofDecodingError::Format(DecodingError::UnexpectedEnd))
+    );
+
+    let mut version = bytes.clone();
+    version[..4].copy_from_slice(&(PROOF_FORMAT_VERSION + 1).to_le_bytes());
+    assert_eq!(
+        decode(&version),
+        Some(ProofDecodingError::UnsupportedVersion(
+            PROOF_FORMAT_VERSION + 1
+        ))
+    );
+
+    // The field id follows the version, as the length-prefixed bytes of the field's order.
+    let mut field = bytes.clone();
+    field[8] ^= 1;
+    assert_eq!(decode(&field), Some(ProofDecodingError::FieldMismatch));
+
+    // The first opened value of the trace follows the header, the commitments and the length
+    // prefixes of the empty preprocessed openings and of the trace's local row.
+    let header_len = bytes.len() - proof.to_bytes().len();
+    let offset = header_len + proof.commitments().to_bytes().len() + 4 * 4;
+    let mut non_canonical = bytes.clone();
+    non_canonical[offset..offset + 4].copy_from_slice(&Val::ORDER_U32.to_le_bytes());
+    assert_eq!(
+        decode(&non_canonical),
+        Some(ProofDecodingError::Format(DecodingError::NonCanonical))
+    );
+}
+
+#[test]
+fn test_config_mismatch() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let (proof, _) = make_proof(&perm, &config);
+    let bytes = proof.to_versioned_bytes(&config);
+
+    let more_queries = make_config_with_fri(&perm, |mmcs| FriConfig {
+        num_queries: 3,
+        ..create_test_fri_config(mmcs)
+    });
+    assert_eq!(
+        Proof::from_versioned_bytes(&more_queries, &bytes).err(),
+        Some(ProofDecodingError::ConfigMismatch)
+    );
+
+    // The shape of the Merkle trees is part of the configuration too.
+    let (val_mmcs, challenge_mmcs) = make_mmcs(&perm);
+    let capped = MyConfig::new(Pcs::new(
+        Dft::default(),
+        val_mmcs.with_cap_height(1),
+        create_test_fri_config(challenge_mmcs),
+    ));
+    assert_eq!(
+        Proof::from_versioned_bytes(&capped, &bytes).err(),
+        Some(ProofDecodingError::ConfigMismatch)
+    );
+
+    let separated = make_config(&perm).with_domain_separator(b"other");
+    assert_eq!(
+        Proof::from_versioned_bytes(&separated, &bytes).err(),
+        Some(ProofDecodingError::ConfigMismatch)
+    );
+}
@@ -0,0 +1,113 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::PrimeField32;
+use p3_fri::{create_benchmark_fri_config, SoundnessRegime};
+use p3_uni_stark::deep_ali_soundness;
+use rand::thread_rng;
+
+use common::*;
+
+/// The Fibonacci sequence in two columns, starting from `0, 1`, whose last value is public.
+pub struct FibAir;
+
+impl<F> BaseAir<F> for FibAir {
+    fn width(&self) -> usize {
+        2
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for FibAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let (a, b) = (main.get(0, 0), main.get(0, 1));
+        let (next_a, next_b) = (main.get(1, 0), main.get(1, 1));
+        let last = builder.public_values()[0];
+
+        builder.when_first_row().assert_zero(a);
+        builder.when_first_row().assert_one(b);
+
+        let mut when_transition = builder.when_transition();
+        when_transition.assert_eq(next_a, b);
+        when_transition.assert_eq(next_b, a + b);
+
+        builder.when_last_row().assert_eq(b, last);
+    }
+}
+
+#[test]
+fn test_unique_decoding() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let log_degree = 10;
+    let soundness = deep_ali_soundness(&config, &FibAir, 0, 1, log_degree, 1.0);
+
+    // Five constraints are combined, so `alpha` has at most four bad values.
+    let log_field_size = 4.0 * p3_util::log2_f64(Val::ORDER_U32 as f64);
+    assert!((soundness.ali_bits - (log_field_size - 2.0)).abs() < 1e-9);
+
+    // The constraints have degree 2, and each column is opened at two points.
+    let trace_len = (1 << log_degree) as f64;
+    let deep_degree = 2.0 * (trace_len + 1.0) + trace_len - 1.0;
+    let deep_bits = log_field_size - p3_util::log2_f64(deep_degree);
+    assert!((soundness.deep_bits - deep_bits).abs() < 1e-9);
+    assert_eq!(soundness.bits(), soundness.deep_bits);
+
+    // Both columns at two points, and a single quotient chunk of four base field columns.
+    assert_eq!(soundness.num_batched_functions, 8);
+}
+
+#[test]
+fn test_unbounded_list_size() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let log_degree = 10;
+
+    // Past the Johnson bound there is no bound on the list size, and nothing is proven.
+    let regime = SoundnessRegime::Johnson { m: 1 << 20 };
+    let list_size = regime.list_size(1, log_degree + 1);
+    assert_eq!(list_size, f64::INFINITY);
+    let soundness = deep_ali_soundness(&config, &FibAir, 0, 1, log_degree, list_size);
+    assert_eq!(soundness.ali_bits, 0.0);
+    assert_eq!(soundness.deep_bits, 0.0);
+    assert_eq!(soundness.bits(), 0.0);
+    assert_eq!(soundness.num_batched_functions, 8);
+}
+
+#[test]
+fn test_johnson() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let (_, challenge_mmcs) = make_mmcs(&perm);
+    let fri_config = create_benchmark_fri_config(challenge_mmcs);
+    let log_degree = 10;
+    let log_height = log_degree + fri_config.log_blowup;
+
+    let unique = deep_ali_soundness(&config, &FibAir, 0, 1, log_degree, 1.0);
+    let regime = SoundnessRegime::Johnson { m: 3 };
+    let list_size = regime.list_size(fri_config.log_blowup, log_height);
+    assert!(list_size > 1.0);
+    let johnson = deep_ali_soundness(&config, &FibAir, 0, 1, log_degree, list_size);
+    assert!(johnson.ali_bits < unique.ali_bits);
+    assert!(johnson.deep_bits < unique.deep_bits);
+    assert_eq!(johnson.num_batched_functions, unique.num_batched_functions);
+
+    // The whole STARK is as sound as its weakest round, which here is FRI's query phase.
+    let fri =
+        fri_config.proven_soundness::<Challenge>(regime, log_degree, johnson.num_batched_functions);
+    let bits = johnson.bits().min(fri.bits());
+    assert_eq!(bits, fri.query_bits);
+    assert!(bits < fri_config.conjectured_soundness_bits() as f64);
+
+    // More queries raise FRI's soundness until its batching round is the weakest, at about 85
+    // bits for this small instance.
+    let num_functions = johnson.num_batched_functions;
+    let num_queries = fri_config
+        .min_num_queries::<Challenge>(regime, log_degree, num_functions, 80.0)
+        .unwrap();
+    assert!(num_queries > fri_config.num_queries);
+    assert_eq!(
+        fri_config.min_num_queries::<Challenge>(regime, log_degree, num_functions, 90.0),
+        None
+    );
+}
@@ -0,0 +1,126 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, AirBuilderWithPublicValues, BaseAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{find_constraint_failures, prove, prove_multi, verify, verify_multi};
+use rand::thread_rng;
+
+use common::*;
+
+/// A single column holding the Tribonacci sequence, whose transition constraint spans four rows.
+/// The public values are the first three terms and the last one.
+pub struct TribonacciAir;
+
+impl<F> BaseAir<F> for TribonacciAir {
+    fn width(&self) -> usize {
+        1
+    }
+
+    fn window_size(&self) -> usize {
+        4
+    }
+}
+
+impl<AB: AirBuilderWithPublicValues> Air<AB> for TribonacciAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let pis = builder.public_values();
+        let (a, b, c, x) = (pis[0], pis[1], pis[2], pis[3]);
+
+        let rows = (0..4).map(|i| main.get(i, 0)).collect::<Vec<_>>();
+
+        let mut when_first_row = builder.when_first_row();
+        when_first_row.assert_eq(rows[0], a);
+        when_first_row.assert_eq(rows[1], b);
+        when_first_row.assert_eq(rows[2], c);
+
+        builder
+            .when_transition_window(4)
+            .assert_eq(rows[0] + rows[1] + rows[2], rows[3]);
+
+        builder.when_last_row().assert_eq(rows[0], x);
+    }
+}
+
+fn generate_trace<F: Field>(n: usize) -> RowMajorMatrix<F> {
+    let mut values = vec![F::ZERO, F::ZERO, F::ONE];
+    for i in 3..n {
+        values.push(values[i - 3] + values[i - 2] + values[i - 1]);
+    }
+    values.truncate(n);
+    RowMajorMatrix::new_col(values)
+}
+
+fn public_values<F: Field>(trace: &RowMajorMatrix<F>) -> Vec<F> {
+    vec![F::ZERO, F::ZERO, F::ONE, trace.get(trace.height() - 1, 0)]
+}
+
+#[test]
+fn test_tribonacci() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let trace = generate_trace::<Val>(1 << 6);
+    let pis = public_values(&trace);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &TribonacciAir, &mut challenger, trace, &pis);
+    assert_eq!(proof.opened_values().trace_lookahead().len(), 2);
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof = postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut challenger = Challenger::new(perm);
+    verify(&config, &TribonacciAir, &mut challenger, &proof, &pis).expect("verification failed");
+}
+
+#[test]
+fn test_tribonacci_multi() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let config = make_config(&perm);
+    let traces = [generate_trace::<Val>(1 << 4), generate_trace::<Val>(1 << 7)];
+    let pis = traces.each_ref().map(public_values);
+
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove_multi(
+        &config,
+        vec![
+            (&TribonacciAir, traces[0].clone(), &pis[0]),
+            (&TribonacciAir, traces[1].clone(), &pis[1]),
+        ],
+        &mut challenger,
+    );
+
+    let mut challenger = Challenger::new(perm);
+    verify_multi(
+        &config,
+        vec![(&TribonacciAir, &pis[0]), (&TribonacciAir, &pis[1])],
+        &mut challenger,
+        &proof,
+    )
+    .expect("verification failed");
+}
+
+#[test]
+fn test_window_constraint_failures() {
+    let mut trace = generate_trace::<Val>(1 << 3);
+    let pis = public_values(&trace);
+
+    // Corrupting row 5 breaks the transition constraint of every window containing it, except the
+    // one starting at row 5 itself, which wraps around the end of the trace.
+    trace.values[5] += Val::ONE;
+    let failures =
+        find_constraint_failures::<_, Challenge, _>(&TribonacciAir, &trace, &pis, &[], None);
+    let rows_and_indices = failures
+        .iter()
+        .map(|failure| (failure.row, failure.constraint_index))
+        .collect::<Vec<_>>();
+    assert_eq!(rows_and_indices, vec![(2, 3), (3, 3), (4, 3)]);
+
+    // The first failure's window reaches the corrupted row.
+    let window = (2..6)
+        .map(|row| trace.row_slice(row).to_vec())
+        .collect::<Vec<_>>();
+    assert_eq!(failures[0].window, window);
+}
@@ -0,0 +1,105 @@
+mod common;
+
+use p3_air::{Air, AirBuilder, BaseAir};
+use p3_field::{Field, FieldAlgebra};
+use p3_matrix::dense::RowMajorMatrix;
+use p3_matrix::Matrix;
+use p3_uni_stark::{prove, verify, Proof};
+use rand::thread_rng;
+
+use common::*;
+
+/// An AIR with columns `(a, b, c)` asserting `a * b = c` on every row.
+pub struct MulAir;
+
+impl<F> BaseAir<F> for MulAir {
+    fn width(&self) -> usize {
+        3
+    }
+}
+
+impl<AB: AirBuilder> Air<AB> for MulAir {
+    fn eval(&self, builder: &mut AB) {
+        let main = builder.main();
+        let local = main.row_slice(0);
+        builder.assert_eq(local[0] * local[1], local[2]);
+    }
+}
+
+fn generate_trace<F: Field>(log_height: usize, zero: bool) -> RowMajorMatrix<F> {
+    let values = (0..1u64 << log_height)
+        .flat_map(|i| {
+            let (a, b) = if zero { (0, 0) } else { (i, i + 3) };
+            [a, b, a * b].map(F::from_canonical_u64)
+        })
+        .collect();
+    RowMajorMatrix::new(values, 3)
+}
+
+fn prove_zk(perm: &Perm, seed: u64, trace: RowMajorMatrix<Val>) -> Proof<ZkConfig> {
+    let config = make_zk_config(perm, seed);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(&config, &MulAir, &mut challenger, trace, &vec![]);
+
+    let serialized_proof = postcard::to_allocvec(&proof).expect("unable to serialize proof");
+    let proof: Proof<ZkConfig> =
+        postcard::from_bytes(&serialized_proof).expect("unable to deserialize proof");
+
+    let mut challenger = Challenger::new(perm.clone());
+    verify(&config, &MulAir, &mut challenger, &proof, &vec![]).expect("verification failed");
+    proof
+}
+
+#[test]
+fn test_zk_mul() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+    let proof = prove_zk(&perm, 0, generate_trace(6, false));
+    assert!(proof.commitments().random().is_some());
+    assert!(proof.opened_values().random().is_some());
+}
+
+#[test]
+fn test_zk_openings_hide_witness() {
+    let perm = Perm::new_from_rng_128(&mut thread_rng());
+
+    // Without zero-knowledge, an all-zero witness is opened as zeros.
+    let config = make_config(&perm);
+    let mut challenger = Challenger::new(perm.clone());
+    let proof = prove(
+        &config,
+        &MulAir,
+        &mut challenger,
+        generate_trace(6, true),
+        &vec![],
+    );
+    let opened_values = proof.opened_values();
+    assert!(opened_values
+        .trace_local()
+        .iter()
+        .chain(opened_values.trace_next())
+        .chain(opened_values.quotient_chunks().iter().flatten())
+        .all(|x| x.is_zero()));
+
+    // With zero-knowledge, the openings of the same witness are random.
+    let proof = prove_zk(&perm, 0, generate_trace(6, true));
+    let opened_values = proof.opened_values();
+    assert!(opened_values.trace_local().iter().all(|x| !x.is_zero()));
+    assert!(opened_values.trace_next().iter().all(|x| !x.is_zero()));
+    assert!(opened_values
+        .quotient_chunks()
+        .iter()
+        .all(|chunk| chunk.iter().any(|x| !x.is_zero())));
+
+    // And they change with the prover's randomness alone.
+    let other_proof = prove_zk(&perm, 2, generate_trace(6, true));
+    let other_opened_values = other_proof.opened_values();
+    assert_ne!(
+        opened_values.trace_local(),
+        other_opened_values.trace_local()
+    );
+    assert_ne!(opened_values.trace_next(), other_opened_values.trace_next());
+    assert_ne!(
+        opened_values.quotient_chunks(),
+        other_opened_values.quotient_chunks()
+    );
+}
@@ -37,6 +37,28 @@ pub fn log2_strict_usize(n: usize) -> usize {
     res as usize
 }
 
+/// Computes `log_2(x)` for a positive, finite `x`, since `core` has no floating point logarithms.
+#[must_use]
+pub fn log2_f64(x: f64) -> f64 {
+    assert!(x.is_normal() && x > 0.0, "log2 of {x} is not supported");
+    let bits = x.to_bits();
+    let exponent = ((bits >> 52) & 0x7ff) as i64 - 1023;
+    // The mantissa, scaled into [1, 2).
+    let mantissa = f64::from_bits((bits & ((1 << 52) - 1)) | (1023 << 52));
+
+    // ln(y) = 2 atanh(t) for t = (y - 1) / (y + 1), which is below 1/3, so the series converges
+    // quickly.
+    let t = (mantissa - 1.0) / (mantissa + 1.0);
+    let t_squared = t * t;
+    let mut power = t;
+    let mut atanh = 0.0;
+    for k in 0..24 {
+        atanh += power / (2 * k + 1) as f64;
+        power *= t_squared;
+    }
+    exponent as f64 + 2.0 * atanh / core::f64::consts::LN_2
+}
+
 /// Returns `[0, ..., N - 1]`.
 #[must_use]
 pub const fn indices_arr<const N: usize>() -> [usize; N] {
@@ -255,6 +277,25 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_log2_f64() {
+        for k in -60..60 {
+            let x = if k >= 0 {
+                (1u64 << k) as f64
+            } else {
+                1.0 / (1u64 << -k) as f64
+            };
+            assert_eq!(log2_f64(x), k as f64);
+        }
+        for (x, log_x) in [
+            (3.0, 1.584962500721156),
+            (5.0, 2.321928094887362),
+            (0.3, -1.736965594166206),
+        ] {
+            assert!((log2_f64(x) - log_x).abs() < 1e-12);
+        }
+    }
+
     #[test]
     fn test_reverse_bits_len() {
         assert_eq!(reverse_bits_len(0b0000000000, 10), 0b0000000000);